@@ -0,0 +1,142 @@
+//! Power adapter the system is running from, Linux and macOS only.
+//!
+//! USB-C adapters are negotiating the voltage and current with the system,
+//! Linux exposes the result as a `USB` power supply (ex. `ucsi-source-psy-*`),
+//! macOS as the `AdapterDetails` of the `AppleSmartBattery` IOKit service.
+
+use std::fs;
+use std::path::Path;
+
+use crate::command;
+
+const POWER_SUPPLY: &str = "/sys/class/power_supply";
+
+/// Online power adapter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Adapter {
+    /// Adapter model if known, power supply name or USB type otherwise
+    pub name: String,
+    /// Negotiated voltage, V
+    pub voltage: Option<f32>,
+    /// Maximum current adapter agreed to provide, A
+    pub current: Option<f32>,
+    /// Rated power, W
+    pub watts: Option<f32>,
+}
+
+/// Adapter the system is powered from right now, if any
+pub fn active() -> Option<Adapter> {
+    if cfg!(target_os = "macos") {
+        ioreg()
+    } else {
+        sysfs()
+    }
+}
+
+/// USB supplies are preferred over the `Mains` ones, as they are reporting the negotiated values
+fn sysfs() -> Option<Adapter> {
+    let mut mains = None;
+    for entry in fs::read_dir(POWER_SUPPLY).ok()?.flatten() {
+        let path = entry.path();
+        if read(&path, "online").as_deref() != Some("1") {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let model = read(&path, "model_name").or_else(|| read(&path, "manufacturer"));
+
+        match read(&path, "type").as_deref() {
+            Some("Mains") => {
+                mains = Some(Adapter {
+                    name: model.unwrap_or(name),
+                    voltage: None,
+                    current: None,
+                    watts: None,
+                })
+            }
+            Some(kind) if kind.starts_with("USB") => {
+                // Active type is in the brackets, ex. `C [PD] PD_PPS`
+                let usb_type = read(&path, "usb_type").and_then(|types| {
+                    types
+                        .split_whitespace()
+                        .find(|kind| kind.starts_with('['))
+                        .map(|kind| format!("USB {}", kind.trim_matches(|c| c == '[' || c == ']')))
+                });
+                // µV and µA
+                let micro = |file| {
+                    read(&path, file)
+                        .and_then(|value| value.parse::<f32>().ok())
+                        .filter(|value| *value > 0.0)
+                        .map(|value| value / 1_000_000.0)
+                };
+                let voltage = micro("voltage_now").or_else(|| micro("voltage_max"));
+                let current = micro("current_max");
+                let watts = match (voltage, current) {
+                    (Some(voltage), Some(current)) => Some(voltage * current),
+                    _ => None,
+                };
+                return Some(Adapter {
+                    name: model.or(usb_type).unwrap_or(name),
+                    voltage,
+                    current,
+                    watts,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    mains
+}
+
+fn read(path: &Path, file: &str) -> Option<String> {
+    let value = fs::read_to_string(path.join(file)).ok()?;
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn ioreg() -> Option<Adapter> {
+    let output = command::new("ioreg")
+        .args(["-r", "-n", "AppleSmartBattery"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    parse_ioreg(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the `AdapterDetails` dictionary, voltage and current are in mV and mA.
+///
+/// ```text
+///   "ExternalConnected" = Yes
+///   "AdapterDetails" = {"Watts"=96,"Voltage"=20000,"Current"=4700,"Name"="96W USB-C Power Adapter"}
+/// ```
+fn parse_ioreg(output: &str) -> Option<Adapter> {
+    if !output.contains("\"ExternalConnected\" = Yes") {
+        return None;
+    }
+    let details = output.split("\"AdapterDetails\" = {").nth(1)?.split('}').next()?;
+    let value = |key: &str| {
+        details
+            .split(',')
+            .filter_map(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                Some((parts.next()?.trim_matches('"'), parts.next()?.trim_matches('"')))
+            })
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value.to_string())
+    };
+    let number = |key: &str| value(key).and_then(|value| value.parse::<f32>().ok());
+
+    Some(Adapter {
+        name: value("Name").unwrap_or_else(|| "Adapter".to_string()),
+        voltage: number("Voltage").map(|voltage| voltage / 1000.0),
+        current: number("Current").map(|current| current / 1000.0),
+        watts: number("Watts"),
+    })
+}
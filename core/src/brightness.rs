@@ -0,0 +1,87 @@
+//! Built-in display backlight level.
+//!
+//! Linux exposes the backlight controllers in the `/sys/class/backlight`, readable by anyone;
+//! macOS reports the brightness of the built-in display in the `IODisplayParameters`
+//! of the `AppleBacklightDisplay` IOKit service, queried with `ioreg`.
+//! Displays with no backlight control, like the external ones, are not reported at all.
+
+#[cfg(target_os = "linux")]
+use std::fs;
+#[cfg(target_os = "linux")]
+use std::path::Path;
+
+#[cfg(target_os = "macos")]
+use crate::command;
+
+#[cfg(target_os = "linux")]
+const BACKLIGHT: &str = "/sys/class/backlight";
+
+/// Backlight level of the built-in display, %
+#[cfg(target_os = "linux")]
+pub fn level() -> Option<f32> {
+    let entries = fs::read_dir(BACKLIGHT).ok()?;
+
+    // Firmware interfaces are the ones laptop hotkeys are changing, raw ones might be not wired at all
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let kind = read(&path, "type").unwrap_or_default();
+            let priority = match kind.as_str() {
+                "firmware" => 0,
+                "platform" => 1,
+                _ => 2,
+            };
+            let brightness = read(&path, "actual_brightness")
+                .or_else(|| read(&path, "brightness"))?
+                .parse::<f32>()
+                .ok()?;
+            let max = read(&path, "max_brightness")?.parse::<f32>().ok()?;
+            if max <= 0.0 {
+                return None;
+            }
+
+            Some((priority, brightness / max * 100.0))
+        })
+        .min_by_key(|(priority, _)| *priority)
+        .map(|(_, level)| level.min(100.0))
+}
+
+#[cfg(target_os = "linux")]
+fn read(path: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(path.join(file))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+/// Backlight level of the built-in display, %.
+///
+/// ```text
+///   "IODisplayParameters" = {"brightness"={"max"=1024,"min"=0,"value"=686},…}
+/// ```
+#[cfg(target_os = "macos")]
+pub fn level() -> Option<f32> {
+    let output = command::new("ioreg")
+        .args(&["-r", "-c", "AppleBacklightDisplay", "-k", "IODisplayParameters"])
+        .output()
+        .ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let start = output.find("\"brightness\"={")? + "\"brightness\"={".len();
+    let parameter = output[start..].split('}').next()?;
+
+    let value = |key: &str| -> Option<f32> {
+        let start = parameter.find(key)? + key.len();
+        parameter[start..].split(',').next()?.trim().parse().ok()
+    };
+    let (min, max, current) = (value("\"min\"=")?, value("\"max\"=")?, value("\"value\"=")?);
+    if max <= min {
+        return None;
+    }
+
+    Some(((current - min) / (max - min) * 100.0).max(0.0).min(100.0))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn level() -> Option<f32> {
+    None
+}
@@ -0,0 +1,51 @@
+//! External programs started by the backends and the frontends, ex. `busctl` or `ssh`.
+//!
+//! Children are inheriting the signal mask of the thread which started them, and on Unix
+//! the signals handled by battop are blocked in all of its threads (see `signals::block`),
+//! so the programs started with `Command::new` could not be stopped with Ctrl+C or `kill`.
+
+use std::ffi::OsStr;
+use std::process::Command;
+
+/// Command for the `program`, started with the signals unblocked
+pub fn new<S: AsRef<OsStr>>(program: S) -> Command {
+    #[allow(unused_mut)]
+    let mut command = Command::new(program);
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+
+        // Set is filled in before the fork, only the async-signal-safe call is left for the child
+        let set = crate::signals::handled();
+        unsafe {
+            command.pre_exec(move || crate::signals::unblock(&set));
+        }
+    }
+
+    command
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::thread;
+
+    use crate::signals;
+
+    #[test]
+    fn signals_are_unblocked_in_children() {
+        // Mask is set for the calling thread only, so it does not leak into the other tests
+        let status = thread::spawn(|| {
+            signals::block().expect("Signals are blocked");
+            super::new("cat").arg("/proc/self/status").output().expect("cat is started")
+        })
+        .join()
+        .expect("Thread is finished");
+
+        let status = String::from_utf8_lossy(&status.stdout);
+        let blocked = status
+            .lines()
+            .find(|line| line.starts_with("SigBlk:"))
+            .expect("Blocked signals are listed");
+        assert_eq!(u64::from_str_radix(blocked["SigBlk:".len()..].trim(), 16), Ok(0));
+    }
+}
@@ -0,0 +1,185 @@
+//! CPU utilization and frequency, Linux only.
+//!
+//! Utilization is the share of the non-idle time of all CPUs in the `/proc/stat` since the previous update,
+//! frequency is the average of the current `cpufreq` frequencies of all CPUs.
+//! Both are shown along with the power draw, so the compute-driven spikes are easy to tell.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::Error;
+
+const PROC_STAT: &str = "/proc/stat";
+const CPUS: &str = "/sys/devices/system/cpu";
+
+/// What is plotted along with the power draw
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum CpuMetric {
+    Load,
+    Frequency,
+}
+
+impl CpuMetric {
+    pub fn arg_variants() -> [&'static str; 2] {
+        ["load", "frequency"]
+    }
+}
+
+impl FromStr for CpuMetric {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match () {
+            _ if s.eq_ignore_ascii_case("load") => Ok(CpuMetric::Load),
+            _ if s.eq_ignore_ascii_case("frequency") => Ok(CpuMetric::Frequency),
+            _ => Err(Error::ParseError),
+        }
+    }
+}
+
+impl fmt::Display for CpuMetric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuMetric::Load => f.write_str("load"),
+            CpuMetric::Frequency => f.write_str("frequency"),
+        }
+    }
+}
+
+/// Reading of the selected metric
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CpuReading {
+    /// Share of the non-idle time, 0–1
+    Load(f64),
+    /// Average current frequency and the maximal one, GHz
+    Frequency { current: f64, max: f64 },
+}
+
+impl CpuReading {
+    /// Reading as the share of its full scale, 0–1
+    pub fn fraction(&self) -> f64 {
+        match *self {
+            CpuReading::Load(load) => load,
+            CpuReading::Frequency {
+                current,
+                max,
+            } if max > 0.0 => (current / max).min(1.0),
+            CpuReading::Frequency {
+                ..
+            } => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for CpuReading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CpuReading::Load(load) => write!(f, "{:.0} %", load * 100.0),
+            CpuReading::Frequency {
+                current, ..
+            } => write!(f, "{:.2} GHz", current),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CpuMeter {
+    metric: CpuMetric,
+    /// Busy and total ticks at the previous update
+    previous: Option<(u64, u64)>,
+}
+
+impl CpuMeter {
+    /// Meter for the selected metric, `None` if platform does not report it
+    pub fn new(metric: CpuMetric) -> Option<CpuMeter> {
+        let available = match metric {
+            CpuMetric::Load => ticks().is_some(),
+            CpuMetric::Frequency => frequency().is_some(),
+        };
+        if !available {
+            warn!("CPU {} is not available on this platform", metric);
+            return None;
+        }
+
+        Some(CpuMeter {
+            metric,
+            previous: None,
+        })
+    }
+
+    /// Load is returned starting from the second update, since there is nothing to compare with before
+    pub fn update(&mut self) -> Option<CpuReading> {
+        match self.metric {
+            CpuMetric::Load => {
+                let (busy, total) = ticks()?;
+                let reading = match self.previous {
+                    Some((busy_before, total_before)) if total > total_before => Some(CpuReading::Load(
+                        busy.saturating_sub(busy_before) as f64 / (total - total_before) as f64,
+                    )),
+                    _ => None,
+                };
+                self.previous = Some((busy, total));
+
+                reading
+            }
+            CpuMetric::Frequency => frequency(),
+        }
+    }
+}
+
+/// Non-idle and total time of all CPUs
+fn ticks() -> Option<(u64, u64)> {
+    let stat = fs::read_to_string(PROC_STAT).ok()?;
+    let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+
+    let values = line
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|value| value.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+    // user nice system idle iowait irq softirq steal ...
+    let total = values.iter().take(8).sum::<u64>();
+    let idle = values.iter().skip(3).take(2).sum::<u64>();
+
+    Some((total.saturating_sub(idle), total))
+}
+
+/// Average current and maximal frequencies of all CPUs with `cpufreq` support
+fn frequency() -> Option<CpuReading> {
+    let entries = fs::read_dir(CPUS).ok()?;
+    let (mut current, mut max, mut count) = (0u64, 0u64, 0u64);
+    for entry in entries.flatten() {
+        let is_cpu = entry
+            .file_name()
+            .to_str()
+            .map(|name| name.len() > 3 && name.starts_with("cpu") && name[3..].chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or(false);
+        if !is_cpu {
+            continue;
+        }
+        let cpufreq = entry.path().join("cpufreq");
+        // kHz
+        if let (Some(cpu_current), Some(cpu_max)) = (
+            read_u64(&cpufreq, "scaling_cur_freq"),
+            read_u64(&cpufreq, "cpuinfo_max_freq"),
+        ) {
+            current += cpu_current;
+            max += cpu_max;
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return None;
+    }
+
+    Some(CpuReading::Frequency {
+        current: current as f64 / count as f64 / 1_000_000.0,
+        max: max as f64 / count as f64 / 1_000_000.0,
+    })
+}
+
+fn read_u64(path: &Path, file: &str) -> Option<u64> {
+    fs::read_to_string(path.join(file)).ok()?.trim().parse().ok()
+}
@@ -0,0 +1,212 @@
+//! Android devices connected over ADB, polled with `adb shell dumpsys battery`.
+//!
+//! Handy for profiling the battery impact of the application being developed.
+
+use std::io;
+use std::time::Duration;
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::watt_hour;
+use battery::units::power::watt;
+use battery::units::ratio::percent;
+use battery::units::thermodynamic_temperature::degree_celsius;
+use battery::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature};
+use battery::{State, Technology};
+
+use super::{Capabilities, Device, DeviceKind, PowerSource, Source};
+use crate::command;
+use crate::Result;
+
+/// Charging current is not a part of the `dumpsys` output, µA
+const CURRENT_NOW: &str = "/sys/class/power_supply/battery/current_now";
+
+#[derive(Debug)]
+pub struct Adb;
+
+impl PowerSource for Adb {
+    fn name(&self) -> &str {
+        "ADB"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let mut devices = Vec::new();
+        // List of devices attached
+        // 0123456789ABCDEF	device
+        for line in adb(&["devices"])?.lines().skip(1) {
+            let mut parts = line.split_whitespace();
+            let serial = match (parts.next(), parts.next()) {
+                (Some(serial), Some("device")) => serial,
+                // Unauthorized and offline devices are not able to answer
+                _ => continue,
+            };
+
+            let mut device = Device::empty(Source::Adb(serial.to_string()), DeviceKind::Phone);
+            device.serial_number = Some(serial.to_string());
+            device.vendor = property(serial, "ro.product.manufacturer");
+            device.model = property(serial, "ro.product.model");
+            device.update_adb(serial)?;
+            devices.push(device);
+        }
+        trace!("ADB reported {} devices", devices.len());
+
+        Ok(devices)
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let serial = match device.source {
+            Source::Adb(ref serial) => serial.clone(),
+            _ => return Ok(()),
+        };
+        device.update_adb(&serial)?;
+
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATTERY
+    }
+
+    /// Each refresh spawns `adb shell`, which keeps the phone CPU awake for a moment
+    fn interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(5))
+    }
+}
+
+fn adb(args: &[&str]) -> io::Result<String> {
+    let output = command::new("adb").args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "adb {} exited with {}",
+            args.join(" "),
+            output.status
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn property(serial: &str, name: &str) -> Option<String> {
+    let value = adb(&["-s", serial, "shell", "getprop", name]).ok()?;
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+impl Device {
+    fn update_adb(&mut self, serial: &str) -> io::Result<()> {
+        let info = adb(&["-s", serial, "shell", "dumpsys", "battery"])?;
+        // Not every kernel exposes it, and it is not worth to fail the whole update
+        let current = adb(&["-s", serial, "shell", "cat", CURRENT_NOW])
+            .ok()
+            .and_then(|current| current.trim().parse::<f32>().ok());
+        self.parse_dumpsys(&info, current);
+
+        Ok(())
+    }
+
+    /// Update device with the `dumpsys battery` output and the charging current in µA.
+    ///
+    /// ```text
+    /// Current Battery Service state:
+    ///   USB powered: true
+    ///   Charge counter: 2856000
+    ///   status: 2
+    ///   level: 85
+    ///   voltage: 4312
+    ///   temperature: 298
+    /// ```
+    fn parse_dumpsys(&mut self, info: &str, current: Option<f32>) {
+        let mut level = None;
+        let mut scale = 100.0;
+        let mut counter = None;
+        let mut plugged = Vec::new();
+        let mut attributes = Vec::new();
+
+        for line in info.lines() {
+            let mut parts = line.trim().splitn(2, ':');
+            let key = parts.next().unwrap_or("").trim();
+            let value = match parts.next() {
+                Some(value) => value.trim(),
+                None => continue,
+            };
+            let number = value.parse::<f32>().ok();
+
+            match key {
+                "AC powered" | "USB powered" | "Wireless powered" if value == "true" => {
+                    plugged.push(key.trim_end_matches(" powered"))
+                }
+                "status" => self.state = parse_status(value),
+                "health" => attributes.push(("Health".to_string(), parse_health(value).to_string())),
+                "level" => level = number,
+                "scale" => scale = number.unwrap_or(100.0),
+                // µA·h
+                "Charge counter" => counter = number.map(|counter| counter / 1_000_000.0),
+                // mV
+                "voltage" => self.voltage = ElectricPotential::new::<volt>(number.unwrap_or(0.0) / 1000.0),
+                // Tenths of °C
+                "temperature" => {
+                    self.temperature = number.map(|temp| ThermodynamicTemperature::new::<degree_celsius>(temp / 10.0))
+                }
+                "technology" => self.technology = parse_technology(value),
+                _ => {}
+            }
+        }
+
+        if let Some(level) = level {
+            if scale > 0.0 {
+                self.state_of_charge = Ratio::new::<percent>(level / scale * 100.0);
+            }
+        }
+        let voltage = self.voltage.get::<volt>();
+        if let Some(counter) = counter {
+            self.energy = Energy::new::<watt_hour>(counter * voltage);
+        }
+        // Sign of the current depends on the device vendor
+        let current = current.map(|current| current.abs() / 1_000_000.0);
+        self.energy_rate = Power::new::<watt>(current.unwrap_or(0.0) * voltage);
+        if let Some(current) = current {
+            attributes.push(("Current".to_string(), format!("{:.0} mA", current * 1000.0)));
+        }
+        let plugged = if plugged.is_empty() {
+            "no".to_string()
+        } else {
+            plugged.join(", ")
+        };
+        attributes.push(("Plugged".to_string(), plugged));
+        self.attributes = attributes;
+    }
+}
+
+/// `BatteryManager.BATTERY_STATUS_*` constants
+fn parse_status(value: &str) -> State {
+    match value {
+        "2" => State::Charging,
+        "3" | "4" => State::Discharging,
+        "5" => State::Full,
+        _ => State::Unknown,
+    }
+}
+
+/// `BatteryManager.BATTERY_HEALTH_*` constants
+fn parse_health(value: &str) -> &'static str {
+    match value {
+        "2" => "Good",
+        "3" => "Overheat",
+        "4" => "Dead",
+        "5" => "Over voltage",
+        "6" => "Failure",
+        "7" => "Cold",
+        _ => "Unknown",
+    }
+}
+
+fn parse_technology(value: &str) -> Technology {
+    match value {
+        "Li-ion" => Technology::LithiumIon,
+        "Li-poly" => Technology::LithiumPolymer,
+        _ => Technology::Unknown,
+    }
+}
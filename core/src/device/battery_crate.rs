@@ -0,0 +1,108 @@
+//! Backend based on the cross-platform `battery` crate.
+//!
+//! Batteries are kept by the backend, devices are referring them by the vendor, model and serial number,
+//! so the devices are plain values which can be copied and sent to the other threads.
+
+use super::{Capabilities, Device, DeviceKind, PowerSource, Source};
+use crate::{Error, Result};
+
+#[derive(Debug)]
+pub struct BatteryCrate {
+    manager: battery::Manager,
+    batteries: Vec<battery::Battery>,
+}
+
+impl BatteryCrate {
+    pub fn new() -> Result<BatteryCrate> {
+        Ok(BatteryCrate {
+            manager: battery::Manager::new()?,
+            batteries: Vec::new(),
+        })
+    }
+}
+
+impl PowerSource for BatteryCrate {
+    fn name(&self) -> &str {
+        "battery"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        self.batteries = self.manager.batteries()?.flatten().collect();
+        let devices = self.batteries.iter().map(Device::from_battery).collect();
+
+        Ok(devices)
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let key = match device.source {
+            Source::Battery(ref key) => key,
+            _ => return Ok(()),
+        };
+        let battery = self
+            .batteries
+            .iter_mut()
+            .find(|battery| battery_key(battery) == *key)
+            .ok_or(Error::NoBatteries)?;
+        self.manager.refresh(battery)?;
+        device.copy_battery(battery);
+
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATTERY
+    }
+
+    /// Batteries are read from sysfs on Linux
+    fn hotplug(&self) -> bool {
+        cfg!(target_os = "linux")
+    }
+}
+
+/// Battery crate does not expose the device path
+fn battery_key(battery: &battery::Battery) -> String {
+    format!(
+        "{}/{}/{}",
+        battery.vendor().unwrap_or(""),
+        battery.model().unwrap_or(""),
+        battery.serial_number().unwrap_or("")
+    )
+}
+
+impl Device {
+    fn from_battery(battery: &battery::Battery) -> Device {
+        let mut device = Device::empty(Source::Battery(battery_key(battery)), DeviceKind::Battery);
+        device.copy_battery(battery);
+        #[cfg(target_os = "windows")]
+        device.load_powercfg_history();
+
+        device
+    }
+
+    /// Copy the values from the refreshed battery
+    fn copy_battery(&mut self, battery: &battery::Battery) {
+        self.vendor = battery.vendor().map(str::to_string);
+        self.model = battery.model().map(str::to_string);
+        self.serial_number = battery.serial_number().map(str::to_string);
+        self.technology = battery.technology();
+        self.state = battery.state();
+        self.state_of_charge = battery.state_of_charge();
+        self.energy = battery.energy();
+        self.energy_full = battery.energy_full();
+        self.energy_full_design = battery.energy_full_design();
+        self.energy_rate = battery.energy_rate();
+        self.voltage = battery.voltage();
+        self.state_of_health = battery.state_of_health();
+        self.temperature = battery.temperature();
+        self.cycle_count = battery.cycle_count();
+        self.time_to_full = battery.time_to_full();
+        self.time_to_empty = battery.time_to_empty();
+
+        #[cfg(target_os = "linux")]
+        self.augment_cros_ec();
+        #[cfg(target_os = "macos")]
+        self.augment_ioreg();
+        #[cfg(target_os = "windows")]
+        self.augment_wmi();
+    }
+}
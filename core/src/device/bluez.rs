@@ -0,0 +1,125 @@
+//! Bluetooth devices battery levels, read from the BlueZ `org.bluez.Battery1` interface over the system D-Bus.
+//!
+//! Headphones, keyboards and controllers are reporting nothing but the percentage,
+//! so that is all there is. Only the connected devices with a battery have that interface.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use battery::units::ratio::percent;
+use battery::units::Ratio;
+use battery::State;
+
+use super::dbus::{self, Value};
+use super::{Capabilities, Device, DeviceKind, PowerSource, Source};
+use crate::Result;
+
+const SERVICE: &str = "org.bluez";
+const DEVICE: &str = "org.bluez.Device1";
+const BATTERY: &str = "org.bluez.Battery1";
+
+#[derive(Debug, Default)]
+pub struct Bluez {
+    /// Object paths of the devices found, by their addresses
+    paths: HashMap<String, String>,
+}
+
+impl PowerSource for Bluez {
+    fn name(&self) -> &str {
+        "Bluetooth"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let mut devices = Vec::new();
+        for path in dbus::objects(SERVICE)? {
+            let address = match parse_address(&path) {
+                Some(address) => address,
+                None => continue,
+            };
+            // Paired devices without a battery or not connected right now are not interesting,
+            // neither are the ones which are gone in the meantime
+            let level = match dbus::property(SERVICE, &path, BATTERY, "Percentage") {
+                Ok(level) => level,
+                Err(e) => {
+                    trace!("Skipping Bluetooth device {}: {}", address, e);
+                    continue;
+                }
+            };
+
+            let mut device = Device::empty(Source::Bluez(address.clone()), DeviceKind::Other);
+            device.serial_number = Some(address.clone());
+            device.model = property(&path, "Alias").and_then(|alias| alias.as_str().map(str::to_string));
+            device.kind = property(&path, "Icon")
+                .as_ref()
+                .and_then(Value::as_str)
+                .map_or(DeviceKind::Other, parse_icon);
+            device.update_bluez(&level);
+            self.paths.insert(address, path);
+            devices.push(device);
+        }
+        trace!("BlueZ reported {} devices with a battery", devices.len());
+
+        Ok(devices)
+    }
+
+    /// Battery interface is gone once the device disconnects, so the refresh fails
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let path = match device.source {
+            Source::Bluez(ref address) => self.paths.get(address).cloned(),
+            _ => return Ok(()),
+        };
+        if let Some(path) = path {
+            device.update_bluez(&dbus::property(SERVICE, &path, BATTERY, "Percentage")?);
+        }
+
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::LEVEL_ONLY
+    }
+
+    /// Headsets are reporting the level in 10 % steps at best, no need to spawn `busctl` each second
+    fn interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(30))
+    }
+}
+
+/// Optional `org.bluez.Device1` property, ex. the icon is not known for every device
+fn property(path: &str, name: &str) -> Option<Value> {
+    dbus::property(SERVICE, path, DEVICE, name)
+        .map_err(|e| trace!("No {} property of {}: {}", name, path, e))
+        .ok()
+}
+
+impl Device {
+    /// Update device with the `Percentage` property of its battery
+    fn update_bluez(&mut self, level: &Value) {
+        if let Some(level) = level.as_f64() {
+            self.state_of_charge = Ratio::new::<percent>(level as f32);
+        }
+        self.state = State::Unknown;
+    }
+}
+
+/// Device address out of its object path, ex. `AA:BB:CC:DD:EE:FF` for `/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF`;
+/// `None` for the adapters and the device services
+fn parse_address(path: &str) -> Option<String> {
+    let parts = path.split('/').collect::<Vec<_>>();
+    match parts.as_slice() {
+        ["", "org", "bluez", adapter, device] if adapter.starts_with("hci") && device.starts_with("dev_") => {
+            Some(device[4..].replace('_', ":"))
+        }
+        _ => None,
+    }
+}
+
+/// Device kind by its freedesktop icon name, ex. `input-mouse`
+fn parse_icon(icon: &str) -> DeviceKind {
+    match icon {
+        "input-mouse" => DeviceKind::Mouse,
+        "input-keyboard" => DeviceKind::Keyboard,
+        "phone" => DeviceKind::Phone,
+        _ => DeviceKind::Other,
+    }
+}
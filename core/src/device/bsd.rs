@@ -0,0 +1,360 @@
+//! Native *BSD backend: `acpiconf -i` on FreeBSD and DragonFly, `hw.sensors.acpibat*` sysctl on OpenBSD.
+//!
+//! The `battery` crate knows very little about these platforms, while both of them
+//! are reporting the energy rate, and OpenBSD is reporting battery temperature as well.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::watt_hour;
+use battery::units::power::watt;
+use battery::units::ratio::{percent, ratio};
+use battery::units::thermodynamic_temperature::degree_celsius;
+use battery::units::time::second;
+use battery::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time};
+use battery::{State, Technology};
+
+use super::{Capabilities, Device, DeviceKind, PowerSource, Source};
+use crate::command;
+use crate::Result;
+
+/// OpenBSD is printing battery model and serial number during the boot only
+const DMESG_BOOT: &str = "/var/run/dmesg.boot";
+
+#[derive(Debug)]
+pub struct Bsd;
+
+impl PowerSource for Bsd {
+    fn name(&self) -> &str {
+        "bsd"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let mut devices = Vec::new();
+        for unit in units()? {
+            let mut device = Device::empty(Source::Bsd(unit.clone()), DeviceKind::Battery);
+            // Empty battery bays are listed too
+            if device.update_bsd(&unit)? {
+                devices.push(device);
+            }
+        }
+        trace!("Found {} ACPI batteries", devices.len());
+
+        Ok(devices)
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let unit = match device.source {
+            Source::Bsd(ref unit) => unit.clone(),
+            _ => return Ok(()),
+        };
+        if device.update_bsd(&unit)? {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::NotFound, format!("Battery {} was removed", unit)).into())
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATTERY
+    }
+}
+
+/// Battery units, `0` for `acpiconf -i 0` or `acpibat0` for the OpenBSD sensors
+fn units() -> io::Result<Vec<String>> {
+    if cfg!(target_os = "openbsd") {
+        let mut units = Vec::new();
+        // hw.sensors.acpibat0.volt0=11.10 VDC (voltage)
+        for line in run("sysctl", &["hw.sensors"])?.lines() {
+            let unit = match line.split('.').nth(2) {
+                Some(unit) if unit.starts_with("acpibat") => unit.to_string(),
+                _ => continue,
+            };
+            if !units.contains(&unit) {
+                units.push(unit);
+            }
+        }
+        Ok(units)
+    } else {
+        let count = run("sysctl", &["-n", "hw.acpi.battery.units"])?
+            .trim()
+            .parse::<usize>()
+            .unwrap_or(0);
+        Ok((0..count).map(|unit| unit.to_string()).collect())
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = command::new(program).args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} {} exited with {}",
+            program,
+            args.join(" "),
+            output.status
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+impl Device {
+    /// Returns `false` if there is no battery in the bay
+    fn update_bsd(&mut self, unit: &str) -> io::Result<bool> {
+        if cfg!(target_os = "openbsd") {
+            let sensors = run("sysctl", &[&format!("hw.sensors.{}", unit)])?;
+            if self.model.is_none() {
+                if let Ok(dmesg) = fs::read_to_string(DMESG_BOOT) {
+                    self.parse_dmesg(unit, &dmesg);
+                }
+            }
+            Ok(self.parse_sensors(&sensors))
+        } else {
+            Ok(self.parse_acpiconf(&run("acpiconf", &["-i", unit])?))
+        }
+    }
+
+    /// Update device with the FreeBSD `acpiconf -i` output, capacities are either in mW·h or mA·h.
+    ///
+    /// ```text
+    /// Design capacity:    57000 mWh
+    /// Last full capacity: 51300 mWh
+    /// Design voltage:     11400 mV
+    /// Model number:       5B10W13930
+    /// State:              discharging
+    /// Remaining capacity: 64%
+    /// Remaining time:     4:10
+    /// Present rate:       7800 mW
+    /// Present voltage:    11820 mV
+    /// ```
+    fn parse_acpiconf(&mut self, info: &str) -> bool {
+        let props = info
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(2, ':');
+                Some((parts.next()?.trim(), parts.next()?.trim()))
+            })
+            .collect::<HashMap<_, _>>();
+        let get = |key: &str| props.get(key).cloned();
+        let state = match get("State") {
+            Some(state) if state != "not present" => state,
+            _ => return false,
+        };
+        // Value in the thousandths and its unit, ex. `(57.0, "mWh")`
+        let milli = |key: &str| {
+            let mut parts = get(key)?.split_whitespace();
+            let value = parts.next()?.parse::<f32>().ok()?;
+            Some((value / 1000.0, parts.next().unwrap_or("")))
+        };
+
+        let design_voltage = milli("Design voltage").map(|(value, _)| value).unwrap_or(0.0);
+        let voltage = milli("Present voltage")
+            .map(|(value, _)| value)
+            .unwrap_or(design_voltage);
+        // Charge-based batteries are converted to energy with the design voltage
+        let energy = |key: &str| match milli(key) {
+            Some((value, "mAh")) => value * design_voltage,
+            Some((value, _)) => value,
+            None => 0.0,
+        };
+        let energy_full = energy("Last full capacity");
+        let energy_full_design = energy("Design capacity");
+        let power = match milli("Present rate") {
+            Some((value, "mA")) => value * voltage,
+            Some((value, _)) => value,
+            None => 0.0,
+        };
+        let level = get("Remaining capacity")
+            .and_then(|value| value.trim_end_matches('%').parse::<f32>().ok())
+            .unwrap_or(0.0);
+
+        self.vendor = get("OEM info").filter(|value| !value.is_empty()).map(str::to_string);
+        self.model = get("Model number")
+            .filter(|value| !value.is_empty())
+            .map(str::to_string);
+        self.serial_number = get("Serial number")
+            .filter(|value| !value.is_empty())
+            .map(str::to_string);
+        self.technology = get("Type").map(parse_technology).unwrap_or(Technology::Unknown);
+        self.identity.chemistry = get("Type").filter(|value| !value.is_empty()).map(str::to_string);
+        self.state = match state {
+            state if state.contains("discharging") => State::Discharging,
+            state if state.contains("charging") => State::Charging,
+            // Neither charging nor discharging
+            _ if level >= 100.0 => State::Full,
+            _ => State::Unknown,
+        };
+        self.state_of_charge = Ratio::new::<percent>(level);
+        self.voltage = ElectricPotential::new::<volt>(voltage);
+        self.energy = Energy::new::<watt_hour>(energy_full * level / 100.0);
+        self.energy_full = Energy::new::<watt_hour>(energy_full);
+        self.energy_full_design = Energy::new::<watt_hour>(energy_full_design);
+        self.energy_rate = Power::new::<watt>(power);
+        self.state_of_health = if energy_full_design > 0.0 {
+            Ratio::new::<ratio>(energy_full / energy_full_design)
+        } else {
+            Ratio::new::<ratio>(0.0)
+        };
+        self.cycle_count = get("Cycle Count")
+            .and_then(|value| value.parse().ok())
+            .filter(|cycles| *cycles > 0);
+        // Remaining time is estimated for discharging only, as `hours:minutes`
+        self.time_to_empty = match (self.state, get("Remaining time")) {
+            (State::Discharging, Some(time)) => parse_time(time),
+            _ => None,
+        };
+        self.time_to_full = None;
+
+        true
+    }
+
+    /// Update device with the OpenBSD sensors, capacities are either in W·h or A·h.
+    ///
+    /// ```text
+    /// hw.sensors.acpibat0.volt0=11.40 VDC (voltage)
+    /// hw.sensors.acpibat0.volt1=11.82 VDC (current voltage)
+    /// hw.sensors.acpibat0.power0=7.80 W (rate)
+    /// hw.sensors.acpibat0.watthour0=51.30 Wh (last full capacity)
+    /// hw.sensors.acpibat0.watthour3=32.83 Wh (remaining capacity), OK
+    /// hw.sensors.acpibat0.watthour4=57.00 Wh (design capacity)
+    /// hw.sensors.acpibat0.raw0=1 (battery discharging), OK
+    /// hw.sensors.acpibat0.temp0=31.50 degC
+    /// ```
+    fn parse_sensors(&mut self, sensors: &str) -> bool {
+        // Sensor values keyed by their description, or by the sensor name if there is none
+        let mut values = HashMap::new();
+        let mut status = None;
+        for line in sensors.lines() {
+            let mut parts = line.splitn(2, '=');
+            let (name, value) = match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => (name.rsplit('.').next().unwrap_or(""), value),
+                _ => continue,
+            };
+            let description = value.split(['(', ')']).nth(1).unwrap_or(name).to_string();
+            if name.starts_with("raw") {
+                status = Some(description);
+                continue;
+            }
+            let mut parts = value.split_whitespace();
+            if let (Some(number), Some(unit)) =
+                (parts.next().and_then(|number| number.parse::<f32>().ok()), parts.next())
+            {
+                values.insert(description, (number, unit.to_string()));
+            }
+        }
+        let status = match status {
+            // Battery bay is empty
+            Some(ref status) if status == "battery absent" => return false,
+            Some(status) => status,
+            None if values.is_empty() => return false,
+            None => String::new(),
+        };
+        let get = |key: &str| values.get(key).map(|(value, unit)| (*value, unit.as_str()));
+
+        let design_voltage = get("voltage").map(|(value, _)| value).unwrap_or(0.0);
+        let voltage = get("current voltage").map(|(value, _)| value).unwrap_or(design_voltage);
+        let energy = |key: &str| match get(key) {
+            Some((value, "Ah")) => value * design_voltage,
+            Some((value, _)) => value,
+            None => 0.0,
+        };
+        let energy_now = energy("remaining capacity");
+        let energy_full = energy("last full capacity");
+        let energy_full_design = energy("design capacity");
+        let power = match get("rate") {
+            Some((value, "A")) => value * voltage,
+            Some((value, _)) => value,
+            None => 0.0,
+        };
+
+        self.state = match status.as_str() {
+            "battery discharging" | "battery critical" => State::Discharging,
+            "battery charging" => State::Charging,
+            "battery idle" if energy_full > 0.0 && energy_now >= energy_full => State::Full,
+            _ => State::Unknown,
+        };
+        self.voltage = ElectricPotential::new::<volt>(voltage);
+        self.energy = Energy::new::<watt_hour>(energy_now);
+        self.energy_full = Energy::new::<watt_hour>(energy_full);
+        self.energy_full_design = Energy::new::<watt_hour>(energy_full_design);
+        self.energy_rate = Power::new::<watt>(power);
+        self.state_of_charge = if energy_full > 0.0 {
+            Ratio::new::<ratio>(energy_now / energy_full)
+        } else {
+            Ratio::new::<ratio>(0.0)
+        };
+        self.state_of_health = if energy_full_design > 0.0 {
+            Ratio::new::<ratio>(energy_full / energy_full_design)
+        } else {
+            Ratio::new::<ratio>(0.0)
+        };
+        self.temperature = values
+            .get("temp0")
+            .map(|(value, _)| ThermodynamicTemperature::new::<degree_celsius>(*value));
+        // OpenBSD does not estimate the time, but it is trivial to do
+        self.time_to_empty = match self.state {
+            State::Discharging if power > 0.0 => Some(Time::new::<second>(energy_now / power * 3600.0)),
+            _ => None,
+        };
+        self.time_to_full = match self.state {
+            State::Charging if power > 0.0 => Some(Time::new::<second>((energy_full - energy_now) / power * 3600.0)),
+            _ => None,
+        };
+
+        true
+    }
+
+    /// Fill battery identity from the boot messages.
+    ///
+    /// ```text
+    /// acpibat0 at acpi0: BAT0 model "5B10W13930" serial 1234 type LiP oem "SMP"
+    /// ```
+    fn parse_dmesg(&mut self, unit: &str, dmesg: &str) {
+        let prefix = format!("{} at ", unit);
+        let line = match dmesg.lines().rev().find(|line| line.starts_with(&prefix)) {
+            Some(line) => line,
+            None => return,
+        };
+
+        // Quoted values might contain spaces
+        let value = |key: &str| {
+            let rest = line.split(&format!(" {} ", key)).nth(1)?;
+            let value = if let Some(quoted) = rest.strip_prefix('"') {
+                quoted.split('"').next()?
+            } else {
+                rest.split_whitespace().next()?
+            };
+            Some(value.to_string()).filter(|value| !value.is_empty())
+        };
+        self.model = value("model");
+        self.serial_number = value("serial");
+        self.vendor = value("oem");
+        self.technology = value("type")
+            .map(|value| parse_technology(&value))
+            .unwrap_or(Technology::Unknown);
+        self.identity.chemistry = value("type");
+    }
+}
+
+/// ACPI battery type string, ex. `LION`
+fn parse_technology(value: &str) -> Technology {
+    match value.to_ascii_lowercase().as_str() {
+        "lion" | "li-ion" | "li" => Technology::LithiumIon,
+        "lip" | "lipo" | "li-poly" => Technology::LithiumPolymer,
+        "pbac" => Technology::LeadAcid,
+        "nicd" => Technology::NickelCadmium,
+        "nimh" => Technology::NickelMetalHydride,
+        _ => Technology::Unknown,
+    }
+}
+
+/// Parse remaining time, ex. `4:10`
+fn parse_time(value: &str) -> Option<Time> {
+    let mut parts = value.splitn(2, ':');
+    let hours = parts.next()?.trim().parse::<f32>().ok()?;
+    let minutes = parts.next()?.trim().parse::<f32>().ok()?;
+
+    Some(Time::new::<second>(hours * 3600.0 + minutes * 60.0))
+}
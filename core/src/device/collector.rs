@@ -0,0 +1,610 @@
+//! Each backend is queried on a thread of its own, so a slow sysfs, ACPI or network read
+//! is blocking neither the caller nor the other backends; refreshed devices are delivered over a channel.
+//!
+//! Backends are started on their threads as well, out of their `Plan`, so they do not have to be `Send`.
+//!
+//! System readings requested with `Options::system` are taken by the same threads, right after the device refresh.
+//!
+//! Backend reads can't be interrupted, so a refresh which hangs, ex. the ACPI one after resume,
+//! is only reported by `Collector::hung`; the caller keeps running and the devices of the other backends
+//! are refreshed as usual, while the requests to the hung backend are dropped until it returns.
+
+use std::panic;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{Device, Options, Provider, Selection, LOCAL_HOST};
+use crate::system::{self, Meters};
+use crate::{Error, Result};
+
+/// Consecutive refresh failures after which the device is considered to be disconnected
+const MAX_FAILURES: u32 = 3;
+
+/// What had changed since the previous `Collector::updates` call
+#[derive(Debug)]
+pub enum Update {
+    /// Latest state of the device
+    Refreshed(Device),
+    /// Device failed to refresh, referred by its id; it is retried until it fails `MAX_FAILURES` times in a row
+    Failed(String, Error),
+    /// Device which can't be refreshed anymore, referred by its id
+    Disconnected(String),
+    /// Device found by the rescan, either a new one or the disconnected one which is back
+    Connected(Device),
+}
+
+#[derive(Debug, Clone)]
+enum Request {
+    Refresh,
+    Rescan,
+    /// Devices to keep, resolved out of the devices of all the backends
+    Select(Selection),
+}
+
+/// Refresh in progress on the collection thread
+#[derive(Debug)]
+struct Busy {
+    id: String,
+    since: Instant,
+    /// Already returned by `Collector::hung`
+    reported: bool,
+}
+
+/// Collection thread of one backend
+#[derive(Debug)]
+struct Handle {
+    requests: mpsc::Sender<Request>,
+    /// Minimal polling interval of the backend, see `PowerSource::interval`
+    interval: Option<Duration>,
+    /// Backend is enumerated again on the uevents only, see `PowerSource::hotplug`
+    hotplug: bool,
+    busy: Arc<Mutex<Option<Busy>>>,
+}
+
+impl Handle {
+    fn request(&self, request: Request) {
+        // Requests are not piling up behind the hung refresh, the next poll after it is enough
+        if let Some(Busy {
+            ref id,
+            reported: true,
+            ..
+        }) = *self.busy.lock().expect("Collection thread had panicked")
+        {
+            trace!("Skipping {:?}, refresh of {} is still hanging", request, id);
+            return;
+        }
+        if let Err(e) = self.requests.send(request) {
+            warn!("Collection thread is not running: {:?}", e);
+        }
+    }
+
+    fn hung(&self, timeout: Duration) -> Option<String> {
+        let mut busy = self.busy.lock().expect("Collection thread had panicked");
+        match *busy {
+            Some(ref mut busy) if !busy.reported && busy.since.elapsed() >= timeout => {
+                busy.reported = true;
+                Some(busy.id.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Handle to the collection threads
+#[derive(Debug)]
+pub struct Collector {
+    /// Threads by the index of their backend, `None` for the backends which failed to start
+    workers: Vec<Option<Handle>>,
+    updates: mpsc::Receiver<Update>,
+}
+
+impl Collector {
+    /// Start the backends on their collection threads and return the devices they had found, in the `options.order`.
+    ///
+    /// `notify` is called from these threads each time new updates are available,
+    /// collection stops if it returns `false`.
+    pub fn spawn<F>(options: Options, notify: F) -> Result<(Collector, Vec<Device>)>
+    where
+        F: Fn() -> bool + Send + 'static,
+    {
+        let plans = Provider::plan(&options);
+        let starts = plans
+            .iter()
+            .cloned()
+            .map(|plan| move |index| Provider::start_planned(index, &plan))
+            .collect();
+        let (collector, started) = Collector::start(starts, options.system.clone(), notify)?;
+
+        let mut devices = Vec::new();
+        for (plan, started) in plans.iter().zip(started) {
+            match started {
+                Ok(found) => devices.extend(found),
+                Err(e) if plan.is_required() => return Err(e),
+                // Hosts which are not available are skipped
+                Err(e) => warn!(
+                    "Unable to get devices of the {} host: {}",
+                    plan.host().unwrap_or(LOCAL_HOST),
+                    e
+                ),
+            }
+        }
+
+        devices.sort_by(|a, b| options.order.compare(a, b));
+        let mut selection = options.selection;
+        selection.resolve(&devices);
+        devices.retain(|device| selection.accepts(device));
+        for handle in collector.handles() {
+            handle.request(Request::Select(selection.clone()));
+        }
+
+        Ok((collector, devices))
+    }
+
+    /// Same as `spawn`, with the `Provider` of each thread made by its `start` out of the backend index,
+    /// ex. `Provider::with_source`
+    pub fn spawn_with<S, F>(starts: Vec<S>, notify: F) -> Result<(Collector, Vec<Device>)>
+    where
+        S: FnOnce(usize) -> Result<(Provider, Vec<Device>)> + Send + 'static,
+        F: Fn() -> bool + Send + 'static,
+    {
+        let (collector, started) = Collector::start(starts, None, notify)?;
+        let mut devices = Vec::new();
+        for found in started {
+            devices.extend(found?);
+        }
+
+        Ok((collector, devices))
+    }
+
+    /// Amount of the backends, devices are referring them by their `origin` index
+    pub fn source_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Minimal polling interval of the backend at `index`, see `PowerSource::interval`
+    pub fn interval(&self, index: usize) -> Option<Duration> {
+        match self.workers.get(index) {
+            Some(Some(handle)) => handle.interval,
+            _ => None,
+        }
+    }
+
+    /// Whether the backend at `index` is enumerated again on the uevents only, see `PowerSource::hotplug`
+    pub fn hotplug(&self, index: usize) -> bool {
+        match self.workers.get(index) {
+            Some(Some(handle)) => handle.hotplug,
+            _ => false,
+        }
+    }
+
+    /// Ask to refresh the devices of the backend at `index`, or all of them if it is `None`
+    pub fn refresh(&self, index: Option<usize>) {
+        self.request(index, Request::Refresh);
+    }
+
+    /// Ask the backend at `index`, or all of them if it is `None`, to enumerate the devices again,
+    /// including the ones connected after the start
+    pub fn rescan(&self, index: Option<usize>) {
+        self.request(index, Request::Rescan);
+    }
+
+    /// Id of the device which refresh is taking longer than the `timeout`, reported once per hang.
+    ///
+    /// Device is refreshed as usual once the backend returns, so the caller has to mark it
+    /// as stale until the next `Update::Refreshed` of it.
+    pub fn hung(&self, timeout: Duration) -> Option<String> {
+        self.handles().filter_map(|handle| handle.hung(timeout)).next()
+    }
+
+    /// Updates collected so far, does not block
+    pub fn updates(&self) -> mpsc::TryIter<'_, Update> {
+        self.updates.try_iter()
+    }
+
+    fn handles(&self) -> impl Iterator<Item = &Handle> + '_ {
+        self.workers.iter().filter_map(Option::as_ref)
+    }
+
+    fn request(&self, index: Option<usize>, request: Request) {
+        match index {
+            Some(index) => {
+                if let Some(Some(handle)) = self.workers.get(index) {
+                    handle.request(request);
+                }
+            }
+            None => {
+                for handle in self.handles() {
+                    handle.request(request.clone());
+                }
+            }
+        }
+    }
+
+    /// Start a collection thread per backend, along with the devices each of them had found or its error
+    fn start<S, F>(
+        starts: Vec<S>,
+        system: Option<system::Options>,
+        notify: F,
+    ) -> Result<(Collector, Vec<Result<Vec<Device>>>)>
+    where
+        S: FnOnce(usize) -> Result<(Provider, Vec<Device>)> + Send + 'static,
+        F: Fn() -> bool + Send + 'static,
+    {
+        let (updates_tx, updates) = mpsc::channel();
+        let notify = Arc::new(Mutex::new(notify));
+
+        let mut threads = Vec::new();
+        for (index, start) in starts.into_iter().enumerate() {
+            let (requests, requests_rx) = mpsc::channel();
+            let (started_tx, started_rx) = mpsc::channel();
+            let busy = Arc::new(Mutex::new(None));
+            let worker_busy = busy.clone();
+            let updates_tx = updates_tx.clone();
+            let notify = notify.clone();
+            let system = system.clone();
+
+            let handle = thread::Builder::new()
+                .name(format!("collector-{}", index))
+                .spawn(move || {
+                    let (provider, devices) = match start(index) {
+                        Ok(started) => started,
+                        Err(e) => {
+                            let _ = started_tx.send(Err(e));
+                            return;
+                        }
+                    };
+                    let started = (provider.interval(index), provider.hotplug(index), devices.clone());
+                    if started_tx.send(Ok(started)).is_err() {
+                        return;
+                    }
+
+                    trace!("Collection thread of the backend {} is started", index);
+                    let mut worker = Worker {
+                        provider,
+                        devices: Vec::new(),
+                        updates: updates_tx,
+                        busy: worker_busy,
+                        system,
+                    };
+                    worker.devices = devices.into_iter().map(|device| worker.track(device)).collect();
+                    for request in requests_rx {
+                        let updated = match request {
+                            Request::Refresh => worker.refresh(),
+                            Request::Rescan => worker.rescan(),
+                            Request::Select(selection) => {
+                                worker.select(selection);
+                                false
+                            }
+                        };
+                        if updated && !(*notify.lock().expect("Collection thread had panicked"))() {
+                            return;
+                        }
+                    }
+                })?;
+            threads.push((handle, started_rx, requests, busy));
+        }
+
+        // Backends are started concurrently, so a slow host is not delaying the rest
+        let mut workers = Vec::new();
+        let mut started = Vec::new();
+        for (thread, started_rx, requests, busy) in threads {
+            match started_rx.recv() {
+                Ok(Ok((interval, hotplug, devices))) => {
+                    workers.push(Some(Handle {
+                        requests,
+                        interval,
+                        hotplug,
+                        busy,
+                    }));
+                    started.push(Ok(devices));
+                }
+                Ok(Err(e)) => {
+                    workers.push(None);
+                    started.push(Err(e));
+                }
+                // Backend had panicked while starting, so should the caller
+                Err(_) => match thread.join() {
+                    Err(payload) => panic::resume_unwind(payload),
+                    Ok(()) => unreachable!("Collection thread exited without starting the backend"),
+                },
+            }
+        }
+
+        let collector = Collector {
+            workers,
+            updates,
+        };
+
+        Ok((collector, started))
+    }
+}
+
+/// Device refreshed by the collection thread
+struct Tracked {
+    device: Device,
+    connected: bool,
+    /// Consecutive refresh failures
+    failures: u32,
+    /// Present if the system readings were requested
+    meters: Option<Meters>,
+}
+
+/// Collection thread state
+struct Worker {
+    provider: Provider,
+    devices: Vec<Tracked>,
+    updates: mpsc::Sender<Update>,
+    busy: Arc<Mutex<Option<Busy>>>,
+    system: Option<system::Options>,
+}
+
+impl Worker {
+    fn track(&self, device: Device) -> Tracked {
+        Tracked {
+            meters: self.system.as_ref().map(|options| Meters::new(options, &device)),
+            device,
+            connected: true,
+            failures: 0,
+        }
+    }
+
+    fn refresh(&mut self) -> bool {
+        let mut updated = false;
+        for tracked in self.devices.iter_mut() {
+            let device = &mut tracked.device;
+            if !tracked.connected {
+                continue;
+            }
+
+            *self.busy.lock().expect("Collector had panicked") = Some(Busy {
+                id: device.id(),
+                since: Instant::now(),
+                reported: false,
+            });
+            let result = self.provider.refresh(device);
+            if let Some(busy) = self.busy.lock().expect("Collector had panicked").take() {
+                if busy.reported {
+                    info!("Refresh of {} returned after {:?}", busy.id, busy.since.elapsed());
+                }
+            }
+
+            let update = match result {
+                Ok(()) => {
+                    tracked.failures = 0;
+                    device.system = tracked.meters.as_mut().map(|meters| meters.read(device));
+                    Update::Refreshed(device.clone())
+                }
+                Err(e) if tracked.failures + 1 < MAX_FAILURES => {
+                    tracked.failures += 1;
+                    debug!("Unable to refresh {} ({} times in a row): {}", device.id(), tracked.failures, e);
+                    Update::Failed(device.id(), e)
+                }
+                Err(e) => {
+                    warn!("Device {} is disconnected: {}", device.id(), e);
+                    tracked.connected = false;
+                    Update::Disconnected(device.id())
+                }
+            };
+            updated = true;
+            if self.updates.send(update).is_err() {
+                return false;
+            }
+        }
+
+        updated
+    }
+
+    fn select(&mut self, selection: Selection) {
+        self.devices.retain(|tracked| selection.accepts(&tracked.device));
+        self.provider.select(selection);
+    }
+
+    fn rescan(&mut self) -> bool {
+        let mut updated = false;
+        for found in self.provider.rescan() {
+            if self
+                .devices
+                .iter()
+                .any(|tracked| tracked.connected && tracked.device.id() == found.id())
+            {
+                continue;
+            }
+            let connected = self.track(found.clone());
+            match self
+                .devices
+                .iter_mut()
+                .find(|tracked| !tracked.connected && tracked.device.is_same(&found))
+            {
+                Some(tracked) => *tracked = connected,
+                None => self.devices.push(connected),
+            }
+            updated = true;
+            if self.updates.send(Update::Connected(found)).is_err() {
+                return false;
+            }
+        }
+
+        updated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::{Collector, Update, MAX_FAILURES};
+    use crate::device::{Capabilities, Device, PowerSource, Provider, Reading, Scripted};
+    use crate::system::{self, Readings};
+    use crate::Result;
+
+    /// Scripted battery which refreshes are taking the `delay`
+    #[derive(Debug)]
+    struct Slow {
+        scripted: Scripted,
+        delay: Duration,
+    }
+
+    impl PowerSource for Slow {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        fn devices(&mut self) -> Result<Vec<Device>> {
+            self.scripted.devices()
+        }
+
+        fn refresh(&mut self, device: &mut Device) -> Result<()> {
+            thread::sleep(self.delay);
+            self.scripted.refresh(device)
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            self.scripted.capabilities()
+        }
+    }
+
+    /// Collector over the scripted battery, with the channel notified on each batch of updates
+    fn collector(steps: Vec<Option<Reading>>) -> (Collector, mpsc::Receiver<()>) {
+        collector_with(steps, Duration::from_secs(0))
+    }
+
+    fn collector_with(steps: Vec<Option<Reading>>, delay: Duration) -> (Collector, mpsc::Receiver<()>) {
+        let (notify_tx, notify_rx) = mpsc::channel();
+        let start = move |index| {
+            let source = Slow {
+                scripted: Scripted::new("Test battery", 50.0, steps),
+                delay,
+            };
+            Ok(Provider::with_source(index, Box::new(source)))
+        };
+        let (collector, devices) = Collector::spawn_with(vec![start], move || notify_tx.send(()).is_ok()).unwrap();
+        assert_eq!(devices.len(), 1);
+
+        (collector, notify_rx)
+    }
+
+    /// Wait for the refresh taking longer than the `timeout` to be reported
+    fn wait_hung(collector: &Collector, timeout: Duration) -> String {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(id) = collector.hung(timeout) {
+                return id;
+            }
+            assert!(Instant::now() < deadline, "Hung refresh is not reported");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn refresh(collector: &Collector, notified: &mpsc::Receiver<()>) -> Vec<Update> {
+        collector.refresh(None);
+        notified.recv_timeout(Duration::from_secs(5)).expect("Refresh is done");
+        collector.updates().collect()
+    }
+
+    #[test]
+    fn refreshed_devices_follow_the_script() {
+        let (collector, notified) = collector(vec![
+            Some(Reading::discharging(0.8, 10.0)),
+            Some(Reading::discharging(0.7, 12.0)),
+        ]);
+
+        match refresh(&collector, &notified).as_slice() {
+            [Update::Refreshed(device)] => {
+                assert_eq!(device.state_of_charge().value, 0.7);
+                assert_eq!(device.host(), Some("scripted"));
+                assert_eq!(device.system(), None);
+            }
+            updates => panic!("Unexpected updates: {:?}", updates),
+        }
+    }
+
+    #[test]
+    fn system_readings_are_delivered_with_the_refresh() {
+        let (notify_tx, notified) = mpsc::channel();
+        let start = |index| {
+            let steps = vec![Some(Reading::discharging(0.8, 10.0)); 2];
+            Ok(Provider::with_source(index, Box::new(Scripted::new("Test battery", 50.0, steps))))
+        };
+        let (collector, _) =
+            Collector::start(vec![start], Some(system::Options::default()), move || notify_tx.send(()).is_ok())
+                .unwrap();
+
+        // Remote battery is not getting the readings of this machine
+        match refresh(&collector, &notified).as_slice() {
+            [Update::Refreshed(device)] => assert_eq!(device.system(), Some(&Readings::default())),
+            updates => panic!("Unexpected updates: {:?}", updates),
+        }
+    }
+
+    #[test]
+    fn device_is_disconnected_after_failures_in_a_row() {
+        let (collector, notified) = collector(vec![Some(Reading::discharging(0.8, 10.0)), None]);
+
+        for _ in 1..MAX_FAILURES {
+            match refresh(&collector, &notified).as_slice() {
+                [Update::Failed(id, _)] => assert_eq!(id, "scripted:test-battery@scripted"),
+                updates => panic!("Unexpected updates: {:?}", updates),
+            }
+        }
+        match refresh(&collector, &notified).as_slice() {
+            [Update::Disconnected(_)] => {}
+            updates => panic!("Unexpected updates: {:?}", updates),
+        }
+
+        // Disconnected device is not refreshed anymore
+        collector.refresh(None);
+        assert!(notified.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn hung_refresh_is_reported_once() {
+        let timeout = Duration::from_millis(50);
+        let (collector, notified) = collector_with(vec![Some(Reading::discharging(0.8, 10.0))], timeout * 6);
+
+        collector.refresh(None);
+        assert_eq!(wait_hung(&collector, timeout), "scripted:test-battery@scripted");
+        assert_eq!(collector.hung(timeout), None);
+
+        // Requested while hanging, so it is dropped and only the first refresh is delivered
+        collector.refresh(None);
+        notified.recv_timeout(Duration::from_secs(5)).expect("Refresh is done");
+        match collector.updates().collect::<Vec<_>>().as_slice() {
+            [Update::Refreshed(_)] => {}
+            updates => panic!("Unexpected updates: {:?}", updates),
+        }
+        assert!(notified.recv_timeout(timeout * 8).is_err());
+    }
+
+    #[test]
+    fn other_backends_are_refreshed_while_one_hangs() {
+        let timeout = Duration::from_millis(50);
+        let (notify_tx, notified) = mpsc::channel();
+        let starts = vec![("Hung battery", timeout * 6), ("Test battery", Duration::from_secs(0))]
+            .into_iter()
+            .map(|(model, delay)| {
+                move |index| {
+                    let source = Slow {
+                        scripted: Scripted::new(model, 50.0, vec![Some(Reading::discharging(0.8, 10.0))]),
+                        delay,
+                    };
+                    Ok(Provider::with_source(index, Box::new(source)))
+                }
+            })
+            .collect();
+        let (collector, devices) = Collector::spawn_with(starts, move || notify_tx.send(()).is_ok()).unwrap();
+        assert_eq!(devices.len(), 2);
+
+        collector.refresh(Some(0));
+        assert_eq!(wait_hung(&collector, timeout), "scripted:hung-battery@scripted");
+
+        collector.refresh(Some(1));
+        notified.recv_timeout(timeout * 3).expect("Refresh is done");
+        match collector.updates().collect::<Vec<_>>().as_slice() {
+            [Update::Refreshed(device)] => assert_eq!(device.id(), "scripted:test-battery@scripted"),
+            updates => panic!("Unexpected updates: {:?}", updates),
+        }
+    }
+}
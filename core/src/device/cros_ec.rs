@@ -0,0 +1,124 @@
+//! ChromeOS Embedded Controller charger state, for Chromebooks running the regular Linux.
+//!
+//! Kernel exposes the battery itself as the usual power supply, but the EC also knows
+//! what the charger is doing: the charge current, the voltage and current the battery asks for
+//! and the charger input limit. These are queried with `ectool chargestate show`,
+//! which talks to the `/dev/cros_ec` and usually requires root.
+
+use std::io;
+use std::path::Path;
+
+use super::{Device, DeviceKind};
+use crate::command;
+
+/// Present if the `cros_ec` driver is loaded
+const CROS_EC: &str = "/sys/class/chromeos/cros_ec";
+
+/// Attribute names set here, so they can be replaced on the next refresh
+const ATTRIBUTES: [&str; 4] = ["Charge current", "Requested", "Charger output", "Input limit"];
+
+/// What the EC charger is doing right now, A and V
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChargerState {
+    /// Current flowing into the battery, negative while discharging
+    pub charge_current: Option<f32>,
+    pub desired_current: Option<f32>,
+    pub desired_voltage: Option<f32>,
+    pub charger_current: Option<f32>,
+    pub charger_voltage: Option<f32>,
+    pub input_current_limit: Option<f32>,
+}
+
+impl Device {
+    /// Add the EC charger state to the local battery, if this is a Chromebook
+    pub fn augment_cros_ec(&mut self) {
+        if self.kind != DeviceKind::Battery || self.host.is_some() || !Path::new(CROS_EC).exists() {
+            return;
+        }
+
+        self.charger = match ectool() {
+            Ok(output) => Some(parse_chargestate(&output)),
+            Err(e) => {
+                trace!("Unable to query the EC charge state: {}", e);
+                None
+            }
+        };
+
+        self.attributes.retain(|(name, _)| !ATTRIBUTES.contains(&name.as_str()));
+        let charger = match self.charger {
+            Some(ref charger) => charger,
+            None => return,
+        };
+        if let Some(current) = charger.charge_current {
+            self.attributes
+                .push((ATTRIBUTES[0].to_string(), format!("{:.2} A", current)));
+        }
+        if let (Some(voltage), Some(current)) = (charger.desired_voltage, charger.desired_current) {
+            self.attributes
+                .push((ATTRIBUTES[1].to_string(), format!("{:.2} V, {:.2} A", voltage, current)));
+        }
+        if let (Some(voltage), Some(current)) = (charger.charger_voltage, charger.charger_current) {
+            self.attributes
+                .push((ATTRIBUTES[2].to_string(), format!("{:.2} V, {:.2} A", voltage, current)));
+        }
+        if let Some(limit) = charger.input_current_limit {
+            self.attributes
+                .push((ATTRIBUTES[3].to_string(), format!("{:.2} A", limit)));
+        }
+    }
+
+    /// EC charger state, Chromebooks only
+    pub fn charger(&self) -> Option<&ChargerState> {
+        self.charger.as_ref()
+    }
+}
+
+fn ectool() -> io::Result<String> {
+    let output = command::new("ectool").args(["chargestate", "show"]).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("ectool exited with {}", output.status)));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse the `ectool chargestate show` output, values are in mV and mA.
+///
+/// ```text
+/// chg.voltage = 8700mV
+/// chg.current = 1024mA
+/// chg.input_current = 3000mA
+/// batt.current = 1018mA
+/// batt.desired_voltage = 8700mV
+/// batt.desired_current = 2500mA
+/// ```
+fn parse_chargestate(output: &str) -> ChargerState {
+    let value = |key: &str| {
+        output
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(name), Some(value)) if name.trim() == key => Some(value.trim()),
+                    _ => None,
+                }
+            })
+            .next()
+            .and_then(|value| {
+                value
+                    .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+                    .parse::<f32>()
+                    .ok()
+            })
+            .map(|milli| milli / 1000.0)
+    };
+
+    ChargerState {
+        charge_current: value("batt.current"),
+        desired_current: value("batt.desired_current"),
+        desired_voltage: value("batt.desired_voltage"),
+        charger_current: value("chg.current"),
+        charger_voltage: value("chg.voltage"),
+        input_current_limit: value("chg.input_current"),
+    }
+}
@@ -0,0 +1,631 @@
+//! Devices shared by the `battop daemon` with any number of clients over a Unix socket.
+//!
+//! Daemon is the only one polling the hardware and keeping the history, clients are asking it
+//! for the latest values, so all of them are showing the same readings. Protocol is line-based text,
+//! so the status bar scripts can talk to the daemon with `socat` or `nc -U` as well:
+//!
+//! ```text
+//! > snapshot
+//! < @sysfs:/sys/class/power_supply/BAT0
+//! < kind=battery
+//! < state=discharging
+//! < state_of_charge=0.81
+//! < energy_rate=8.25
+//! < end
+//! ```
+//!
+//! `snapshot` returns the latest values only, while `devices` adds the energy rate history
+//! the daemon had collected, so the charts of the client attached later are not starting empty.
+//! Values are in W, W·h, V, °C and seconds, ratios are from 0 to 1.
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::watt_hour;
+use battery::units::power::watt;
+use battery::units::ratio::ratio;
+use battery::units::thermodynamic_temperature::degree_celsius;
+use battery::units::time::second;
+use battery::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time};
+use battery::{State, Technology};
+
+use super::identity::Date;
+use super::{Capabilities, Device, DeviceKind, HistoryPoint, Identity, PowerSource, Source};
+use crate::history::History;
+use crate::Result;
+
+/// All devices are refreshed one after another during the same tick,
+/// so one snapshot of the daemon is enough for all of them
+const SNAPSHOT_TTL: Duration = Duration::from_millis(500);
+/// Daemon answers from memory, anything slower means it is stuck
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Energy rate history points sent to the client, as many as `History::open` keeps
+const HISTORY_POINTS: usize = 512;
+/// Line closing each response
+const END: &str = "end";
+const ATTRIBUTE: &str = "attribute.";
+
+/// Properties of the device, in the order they were sent
+type Entry = Vec<(String, String)>;
+/// Devices served and their energy rate histories
+type Published = Vec<(Device, Vec<HistoryPoint>)>;
+
+/// `battop.sock` in the `$XDG_RUNTIME_DIR`, or in the directory of the user in the temporary one
+pub fn default_socket() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(ref dir) if !dir.is_empty() => Path::new(dir).join("battop.sock"),
+        _ => private_dir().join("battop.sock"),
+    }
+}
+
+/// Directory of the default socket when there is no `$XDG_RUNTIME_DIR`,
+/// as the temporary one is writable by everybody
+fn private_dir() -> PathBuf {
+    env::temp_dir().join(format!("battop-{}", unsafe { libc::getuid() }))
+}
+
+/// Create the missing socket directory accessible to the user only,
+/// and make sure the private one was not created by somebody else beforehand
+fn prepare_dir(dir: &Path) -> Result<()> {
+    match fs::metadata(dir) {
+        Ok(metadata) => {
+            if dir == private_dir() && (metadata.uid() != unsafe { libc::getuid() } || metadata.mode() & 0o077 != 0) {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("{} is accessible to the other users", dir.display()),
+                )
+                .into());
+            }
+            Ok(())
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Listening side of the socket, answering with the devices `publish`ed last
+#[derive(Debug)]
+pub struct Server {
+    path: PathBuf,
+    published: Arc<Mutex<Published>>,
+}
+
+impl Server {
+    /// Listen on the `path`; the socket left by the daemon which was killed is replaced,
+    /// while the one of the daemon still running is an error
+    pub fn bind(path: &Path) -> Result<Server> {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("battop daemon is already listening on {}", path.display()),
+            )
+            .into());
+        }
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            prepare_dir(dir)?;
+        }
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            if metadata.file_type().is_socket() {
+                fs::remove_file(path)?;
+            }
+        }
+
+        let listener = UnixListener::bind(path)?;
+        // Devices and their history are not meant for the other users
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        let published = Arc::new(Mutex::new(Vec::new()));
+        let shared = published.clone();
+        thread::Builder::new().name("daemon".to_string()).spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("Unable to accept the daemon client: {}", e);
+                        continue;
+                    }
+                };
+                let shared = shared.clone();
+                let spawned = thread::Builder::new()
+                    .name("daemon-client".to_string())
+                    .spawn(move || match serve(stream, &shared) {
+                        Ok(()) => trace!("Daemon client disconnected"),
+                        Err(e) => debug!("Daemon client dropped: {}", e),
+                    });
+                if let Err(e) = spawned {
+                    warn!("Unable to serve the daemon client: {}", e);
+                }
+            }
+        })?;
+        info!("Daemon is listening on {}", path.display());
+
+        Ok(Server {
+            path: path.to_path_buf(),
+            published,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Devices the clients are getting from now on, along with the histories collected for them
+    pub fn publish<'a, I>(&self, devices: I)
+    where
+        I: IntoIterator<Item = (&'a Device, &'a History)>,
+    {
+        let devices = devices
+            .into_iter()
+            .map(|(device, history)| {
+                let samples = history.samples().iter().rev().take(HISTORY_POINTS).collect::<Vec<_>>();
+                let points = samples
+                    .into_iter()
+                    .rev()
+                    .map(|sample| HistoryPoint {
+                        timestamp: sample.timestamp,
+                        value: sample.energy_rate,
+                    })
+                    .collect();
+                (device.clone(), points)
+            })
+            .collect();
+        *self.published.lock().expect("Daemon client had panicked") = devices;
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Unable to remove the daemon socket {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Answer the requests of one client until it disconnects
+fn serve(stream: UnixStream, published: &Mutex<Published>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for request in BufReader::new(stream).lines() {
+        let request = request?;
+        let response = {
+            let devices = published.lock().expect("Daemon client had panicked");
+            match request.trim() {
+                "" => continue,
+                "snapshot" => devices.iter().map(|(device, _)| encode(device, &[])).collect(),
+                "devices" => devices.iter().map(|(device, history)| encode(device, history)).collect(),
+                other => format!("error unknown request `{}`, expected `snapshot` or `devices`\n", other),
+            }
+        };
+        writer.write_all(response.as_bytes())?;
+        writer.write_all(format!("{}\n", END).as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Devices collected by the daemon, read by the client attached to it
+#[derive(Debug)]
+pub struct Daemon {
+    path: PathBuf,
+    connection: Option<BufReader<UnixStream>>,
+    snapshot: Option<(Instant, Vec<(String, Entry)>)>,
+}
+
+impl Daemon {
+    /// Client of the daemon listening on the `path`, fails if there is none
+    pub fn connect(path: &Path) -> Result<Daemon> {
+        Ok(Daemon {
+            path: path.to_path_buf(),
+            connection: Some(open(path)?),
+            snapshot: None,
+        })
+    }
+
+    fn request(&mut self, request: &str) -> io::Result<Vec<(String, Entry)>> {
+        // Restarted daemon is connected to again
+        if self.connection.is_none() {
+            self.connection = Some(open(&self.path)?);
+        }
+        let result = match self.connection {
+            Some(ref mut connection) => exchange(connection, request),
+            None => unreachable!(),
+        };
+        if result.is_err() {
+            self.connection = None;
+        }
+
+        result.and_then(|output| parse(&output))
+    }
+
+    fn snapshot(&mut self) -> io::Result<&[(String, Entry)]> {
+        let fresh = match self.snapshot {
+            Some((taken, _)) => taken.elapsed() < SNAPSHOT_TTL,
+            None => false,
+        };
+        if !fresh {
+            let entries = self.request("snapshot")?;
+            self.snapshot = Some((Instant::now(), entries));
+        }
+
+        match self.snapshot {
+            Some((_, ref entries)) => Ok(entries),
+            None => unreachable!(),
+        }
+    }
+}
+
+impl PowerSource for Daemon {
+    fn name(&self) -> &str {
+        "daemon"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let devices = self
+            .request("devices")?
+            .into_iter()
+            .map(|(id, entry)| {
+                let kind = get(&entry, "kind").map_or(DeviceKind::Other, parse_kind);
+                let mut device = Device::empty(Source::Daemon(id), kind);
+                device.update_daemon(&entry);
+                device
+            })
+            .collect::<Vec<_>>();
+        trace!("Daemon at {} reported {} devices", self.path.display(), devices.len());
+
+        Ok(devices)
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let id = match device.source {
+            Source::Daemon(ref id) => id.clone(),
+            _ => return Ok(()),
+        };
+        match self.snapshot()?.iter().find(|(known, _)| *known == id) {
+            Some((_, entry)) => {
+                device.update_daemon(entry);
+                Ok(())
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not collected by the daemon anymore", id),
+            )
+            .into()),
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATTERY
+    }
+}
+
+fn open(path: &Path) -> io::Result<BufReader<UnixStream>> {
+    let stream = UnixStream::connect(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("no battop daemon is listening on {}: {}", path.display(), e),
+        )
+    })?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    Ok(BufReader::new(stream))
+}
+
+/// Send the `request` and read the response up to the `end` line
+fn exchange(connection: &mut BufReader<UnixStream>, request: &str) -> io::Result<String> {
+    connection.get_mut().write_all(format!("{}\n", request).as_bytes())?;
+    let mut output = String::new();
+    loop {
+        let mut line = String::new();
+        if connection.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "daemon closed the connection",
+            ));
+        }
+        if line.trim_end() == END {
+            return Ok(output);
+        }
+        output.push_str(&line);
+    }
+}
+
+/// Split the response into the devices ids and their properties
+fn parse(output: &str) -> io::Result<Vec<(String, Entry)>> {
+    let mut entries: Vec<(String, Entry)> = Vec::new();
+    for line in output.lines() {
+        if let Some(error) = line.strip_prefix("error ") {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+        if let Some(id) = line.strip_prefix('@') {
+            entries.push((id.to_string(), Vec::new()));
+            continue;
+        }
+        let mut parts = line.splitn(2, '=');
+        if let (Some(key), Some(value), Some((_, entry))) = (parts.next(), parts.next(), entries.last_mut()) {
+            entry.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    Ok(entries)
+}
+
+fn get<'a>(entry: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    entry
+        .iter()
+        .find(|(known, _)| known == key)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Device with its `@<id>` header, followed by the energy rate `history` points if there are any
+pub fn encode(device: &Device, history: &[HistoryPoint]) -> String {
+    let mut lines = vec![
+        format!("@{}", device.id()),
+        format!("kind={}", kind_name(device.kind)),
+    ];
+    let mut text = |key: &str, value: Option<String>| {
+        if let Some(value) = value {
+            // Values are one line each, whatever the device had reported
+            lines.push(format!("{}={}", key, value.replace(&['\n', '\r'][..], " ")));
+        }
+    };
+    text("host", device.host.clone());
+    text("vendor", device.vendor.clone());
+    text("model", device.model.clone());
+    text("serial_number", device.serial_number.clone());
+    text("technology", Some(technology_name(device.technology).to_string()));
+    text("state", Some(state_name(device.state).to_string()));
+    text("state_of_charge", Some(device.state_of_charge.get::<ratio>().to_string()));
+    text("energy", Some(device.energy.get::<watt_hour>().to_string()));
+    text("energy_full", Some(device.energy_full.get::<watt_hour>().to_string()));
+    text("energy_full_design", Some(device.energy_full_design.get::<watt_hour>().to_string()));
+    text("energy_rate", Some(device.energy_rate.get::<watt>().to_string()));
+    text("voltage", Some(device.voltage.get::<volt>().to_string()));
+    text("state_of_health", Some(device.state_of_health.get::<ratio>().to_string()));
+    text("temperature", device.temperature.map(|temp| temp.get::<degree_celsius>().to_string()));
+    text("cycle_count", device.cycle_count.map(|count| count.to_string()));
+    text("time_to_full", device.time_to_full.map(|time| time.get::<second>().to_string()));
+    text("time_to_empty", device.time_to_empty.map(|time| time.get::<second>().to_string()));
+    if !device.cell_voltages.is_empty() {
+        let cells = device
+            .cell_voltages
+            .iter()
+            .map(|voltage| voltage.to_string())
+            .collect::<Vec<_>>();
+        text("cell_voltages", Some(cells.join(",")));
+    }
+    if device.charging_held {
+        text("charging_held", Some("true".to_string()));
+    }
+    text("manufactured", device.identity.manufactured.map(|date| date.to_string()));
+    text("firmware", device.identity.firmware.clone());
+    text("chemistry", device.identity.chemistry.clone());
+    for (name, value) in device.attributes.iter() {
+        text(&format!("{}{}", ATTRIBUTE, name.replace('=', "-")), Some(value.clone()));
+    }
+    if !history.is_empty() {
+        let points = history
+            .iter()
+            .filter_map(|point| {
+                let timestamp = point.timestamp.duration_since(UNIX_EPOCH).ok()?;
+                Some(format!("{}:{}", timestamp.as_secs(), point.value))
+            })
+            .collect::<Vec<_>>();
+        text("rate_history", Some(points.join(",")));
+    }
+
+    let mut encoded = lines.join("\n");
+    encoded.push('\n');
+    encoded
+}
+
+impl Device {
+    /// Update device with the properties sent by the daemon, see `encode`
+    fn update_daemon(&mut self, entry: &[(String, String)]) {
+        let number = |key: &str| get(entry, key).and_then(|value| value.parse::<f32>().ok());
+        let owned = |key: &str| get(entry, key).map(str::to_string);
+
+        self.host = owned("host");
+        self.vendor = owned("vendor");
+        self.model = owned("model");
+        self.serial_number = owned("serial_number");
+        self.technology = get(entry, "technology").map_or(Technology::Unknown, parse_technology);
+        self.state = get(entry, "state").map_or(State::Unknown, parse_state);
+        self.state_of_charge = Ratio::new::<ratio>(number("state_of_charge").unwrap_or(0.0));
+        self.energy = Energy::new::<watt_hour>(number("energy").unwrap_or(0.0));
+        self.energy_full = Energy::new::<watt_hour>(number("energy_full").unwrap_or(0.0));
+        self.energy_full_design = Energy::new::<watt_hour>(number("energy_full_design").unwrap_or(0.0));
+        self.energy_rate = Power::new::<watt>(number("energy_rate").unwrap_or(0.0));
+        self.voltage = ElectricPotential::new::<volt>(number("voltage").unwrap_or(0.0));
+        self.state_of_health = Ratio::new::<ratio>(number("state_of_health").unwrap_or(0.0));
+        self.temperature = number("temperature").map(ThermodynamicTemperature::new::<degree_celsius>);
+        self.cycle_count = get(entry, "cycle_count").and_then(|value| value.parse().ok());
+        self.time_to_full = number("time_to_full").map(Time::new::<second>);
+        self.time_to_empty = number("time_to_empty").map(Time::new::<second>);
+        self.cell_voltages = get(entry, "cell_voltages")
+            .map(|cells| cells.split(',').filter_map(|cell| cell.parse().ok()).collect())
+            .unwrap_or_default();
+        self.charging_held = get(entry, "charging_held") == Some("true");
+        self.identity = Identity {
+            manufactured: get(entry, "manufactured").and_then(parse_date),
+            firmware: owned("firmware"),
+            chemistry: owned("chemistry"),
+        };
+        self.attributes = entry
+            .iter()
+            .filter(|(key, _)| key.starts_with(ATTRIBUTE))
+            .map(|(key, value)| (key[ATTRIBUTE.len()..].to_string(), value.clone()))
+            .collect();
+        // History is sent on enumeration only, the refreshes are not growing it
+        self.rate_history = get(entry, "rate_history")
+            .map(|points| points.split(',').filter_map(parse_point).collect())
+            .unwrap_or_default();
+    }
+}
+
+fn kind_name(kind: DeviceKind) -> &'static str {
+    match kind {
+        DeviceKind::Battery => "battery",
+        DeviceKind::Ups => "ups",
+        DeviceKind::Mouse => "mouse",
+        DeviceKind::Keyboard => "keyboard",
+        DeviceKind::Phone => "phone",
+        DeviceKind::Other => "other",
+    }
+}
+
+fn parse_kind(value: &str) -> DeviceKind {
+    match value {
+        "battery" => DeviceKind::Battery,
+        "ups" => DeviceKind::Ups,
+        "mouse" => DeviceKind::Mouse,
+        "keyboard" => DeviceKind::Keyboard,
+        "phone" => DeviceKind::Phone,
+        _ => DeviceKind::Other,
+    }
+}
+
+fn state_name(state: State) -> &'static str {
+    match state {
+        State::Charging => "charging",
+        State::Discharging => "discharging",
+        State::Full => "full",
+        State::Empty => "empty",
+        _ => "unknown",
+    }
+}
+
+fn parse_state(value: &str) -> State {
+    match value {
+        "charging" => State::Charging,
+        "discharging" => State::Discharging,
+        "full" => State::Full,
+        "empty" => State::Empty,
+        _ => State::Unknown,
+    }
+}
+
+fn technology_name(technology: Technology) -> &'static str {
+    match technology {
+        Technology::LithiumIon => "li-ion",
+        Technology::LithiumPolymer => "li-poly",
+        Technology::LithiumIronPhosphate => "lifepo4",
+        Technology::LeadAcid => "lead-acid",
+        Technology::NickelMetalHydride => "nimh",
+        Technology::NickelCadmium => "nicd",
+        Technology::NickelZinc => "nizn",
+        Technology::RechargeableAlkalineManganese => "ram",
+        _ => "unknown",
+    }
+}
+
+fn parse_technology(value: &str) -> Technology {
+    match value {
+        "li-ion" => Technology::LithiumIon,
+        "li-poly" => Technology::LithiumPolymer,
+        "lifepo4" => Technology::LithiumIronPhosphate,
+        "lead-acid" => Technology::LeadAcid,
+        "nimh" => Technology::NickelMetalHydride,
+        "nicd" => Technology::NickelCadmium,
+        "nizn" => Technology::NickelZinc,
+        "ram" => Technology::RechargeableAlkalineManganese,
+        _ => Technology::Unknown,
+    }
+}
+
+/// `2019-03-27`
+fn parse_date(value: &str) -> Option<Date> {
+    let mut parts = value.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+
+    Date::new(year, month, day)
+}
+
+/// `<unix seconds>:<W>`
+fn parse_point(value: &str) -> Option<HistoryPoint> {
+    let mut parts = value.splitn(2, ':');
+    let seconds = parts.next()?.parse().ok()?;
+    let value = parts.next()?.parse().ok()?;
+
+    Some(HistoryPoint {
+        timestamp: UNIX_EPOCH + Duration::from_secs(seconds),
+        value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::process;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use battery::units::power::watt;
+    use battery::units::ratio::ratio;
+
+    use super::{encode, parse, Device, Server, Source};
+    use crate::device::{HistoryPoint, PowerSource, Reading, Scripted};
+
+    #[test]
+    fn device_round_trip() {
+        let mut source = Scripted::new("Test battery", 50.0, vec![Some(Reading::discharging(0.5, 10.0))]);
+        let mut device = source.devices().expect("Scripted battery").remove(0);
+        device.cell_voltages = vec![3.9, 3.85, 3.9];
+        device.attributes = vec![("Capacity level".to_string(), "Normal".to_string())];
+        let history = vec![HistoryPoint {
+            timestamp: UNIX_EPOCH + Duration::from_secs(1_560_000_000),
+            value: 9.5,
+        }];
+
+        let entries = parse(&encode(&device, &history)).expect("Daemon response");
+        assert_eq!(entries.len(), 1);
+        let (ref id, ref entry) = entries[0];
+        assert_eq!(*id, device.id());
+        let mut received = Device::empty(Source::Daemon(id.clone()), device.kind());
+        received.update_daemon(entry);
+
+        assert_eq!(received.id(), device.id());
+        assert!(received.is_same(&device));
+        assert_eq!(received.state(), device.state());
+        assert_eq!(received.technology(), device.technology());
+        assert_eq!(received.state_of_charge().get::<ratio>(), 0.5);
+        assert_eq!(received.energy_rate().get::<watt>(), 10.0);
+        assert_eq!(received.temperature(), device.temperature());
+        assert_eq!(received.cell_voltages(), &[3.9, 3.85, 3.9]);
+        assert_eq!(received.attributes(), device.attributes());
+        assert_eq!(received.rate_history(), history.as_slice());
+        assert!(received.is_attached() && !device.is_attached());
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        assert!(parse("error unknown request `status`\n").is_err());
+        assert!(parse("").expect("Empty response").is_empty());
+    }
+
+    #[test]
+    fn socket_is_private() {
+        let dir = env::temp_dir().join(format!("battop-test-{}", process::id())).join("run");
+        let path = dir.join("battop.sock");
+        let server = Server::bind(&path).expect("Daemon socket");
+
+        let mode = |path| fs::metadata(path).expect("Socket metadata").permissions().mode() & 0o777;
+        assert_eq!(mode(&dir), 0o700);
+        assert_eq!(mode(&path), 0o600);
+
+        drop(server);
+        fs::remove_dir_all(dir.parent().expect("Test directory")).expect("Test directory removed");
+    }
+}
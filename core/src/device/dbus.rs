@@ -0,0 +1,208 @@
+//! System D-Bus queried with the `busctl` command line tool, the same way the power profiles are,
+//! so battop is not linking to libdbus and the daemons are asked for the typed values
+//! instead of the output of their own tools meant for humans.
+//!
+//! Replies are parsed out of the `busctl` text form, ex. `a{sv} 2 "Percentage" d 60 "Vendor" s "SMP"`,
+//! which is enough for the basic types the battery daemons are using.
+
+// Each backend is using a part of the calls only
+#![cfg_attr(not(all(feature = "upower", feature = "bluez")), allow(dead_code))]
+
+use std::collections::HashMap;
+use std::io;
+
+use crate::command;
+
+/// Basic D-Bus value, all the numeric types are read as `f64`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Number(f64),
+    /// String or the object path
+    Str(String),
+}
+
+impl Value {
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Number(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::Str(ref value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Call the `method` of the object at `path`, reply is returned as its tokens, starting with the signature
+pub fn call(service: &str, path: &str, interface: &str, method: &str, args: &[&str]) -> io::Result<Vec<String>> {
+    let mut command = vec!["call", service, path, interface, method];
+    command.extend(args);
+    tokens(&busctl(&command)?)
+}
+
+/// All properties of the `interface` of the object at `path`
+pub fn properties(service: &str, path: &str, interface: &str) -> io::Result<HashMap<String, Value>> {
+    let reply = call(
+        service,
+        path,
+        "org.freedesktop.DBus.Properties",
+        "GetAll",
+        &["s", interface],
+    )?;
+    parse_properties(&reply)
+}
+
+/// One property of the `interface` of the object at `path`
+pub fn property(service: &str, path: &str, interface: &str, name: &str) -> io::Result<Value> {
+    let reply = tokens(&busctl(&["get-property", service, path, interface, name])?)?;
+    match reply.as_slice() {
+        [signature, value] => parse_value(signature, value),
+        _ => Err(unexpected(&reply)),
+    }
+}
+
+/// Paths of all the objects of the `service`
+pub fn objects(service: &str) -> io::Result<Vec<String>> {
+    let output = busctl(&["tree", "--list", service])?;
+
+    Ok(output
+        .lines()
+        .map(str::trim)
+        .filter(|path| path.starts_with('/'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Elements of the array reply, ex. `ao 2 "/org/…/battery_BAT0" "/org/…/line_power_AC"`,
+/// each made of as many tokens as the struct has fields
+pub fn array(reply: &[String], fields: usize) -> io::Result<Vec<&[String]>> {
+    let count = match reply.get(1).and_then(|count| count.parse::<usize>().ok()) {
+        Some(count) if reply.len() == 2 + count * fields => count,
+        _ => return Err(unexpected(reply)),
+    };
+
+    Ok(reply[2..].chunks(fields).take(count).collect())
+}
+
+fn busctl(args: &[&str]) -> io::Result<String> {
+    let output = command::new("busctl").arg("--system").args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Split the `busctl` output into the words and the unescaped quoted strings
+fn tokens(output: &str) -> io::Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut chars = output.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c != '"' {
+            let mut token = c.to_string();
+            while let Some(c) = chars.peek().cloned().filter(|c| !c.is_whitespace()) {
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+            continue;
+        }
+
+        let mut token = String::new();
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some('n') => token.push('\n'),
+                    Some('t') => token.push('\t'),
+                    Some(c) => token.push(c),
+                    None => break,
+                },
+                Some(c) => token.push(c),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unterminated string in the busctl output: {}", output.trim()),
+                    ))
+                }
+            }
+        }
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// `a{sv}` reply of the `GetAll` call, values of the types other than the basic ones are not expected
+fn parse_properties(reply: &[String]) -> io::Result<HashMap<String, Value>> {
+    if reply.first().map(String::as_str) != Some("a{sv}") {
+        return Err(unexpected(reply));
+    }
+
+    array(reply, 3)?
+        .into_iter()
+        .map(|property| Ok((property[0].clone(), parse_value(&property[1], &property[2])?)))
+        .collect()
+}
+
+fn parse_value(signature: &str, value: &str) -> io::Result<Value> {
+    let parsed = match signature {
+        "b" => Some(Value::Bool(value == "true")),
+        "s" | "o" | "g" => Some(Value::Str(value.to_string())),
+        "y" | "n" | "q" | "i" | "u" | "x" | "t" | "d" => value.parse().ok().map(Value::Number),
+        _ => None,
+    };
+
+    parsed.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported D-Bus value `{} {}`", signature, value),
+        )
+    })
+}
+
+fn unexpected(reply: &[String]) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected busctl output: {}", reply.join(" ")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{array, parse_properties, tokens, Value};
+
+    #[test]
+    fn properties_are_parsed() {
+        let reply = tokens(r#"a{sv} 4 "Model" s "5B10W13930 \"X1\"" "Percentage" d 81.5 "State" u 2 "IsPresent" b true"#)
+            .expect("Tokens");
+        let properties = parse_properties(&reply).expect("Properties");
+
+        assert_eq!(properties["Model"], Value::Str("5B10W13930 \"X1\"".to_string()));
+        assert_eq!(properties["Percentage"].as_f64(), Some(81.5));
+        assert_eq!(properties["State"].as_f64(), Some(2.0));
+        assert_eq!(properties["IsPresent"], Value::Bool(true));
+        // Arrays are not expected among the properties
+        assert!(parse_properties(&tokens(r#"a{sv} 1 "Names" as 2 "a" "b""#).unwrap()).is_err());
+    }
+
+    #[test]
+    fn arrays_are_split_by_elements() {
+        let reply = tokens("a(udu) 2 1560000000 8.6 2 1560000060 8.5 2").expect("Tokens");
+        let points = array(&reply, 3).expect("History");
+
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1], ["1560000060", "8.5", "2"]);
+        assert!(array(&reply, 2).is_err());
+    }
+}
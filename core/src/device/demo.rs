@@ -0,0 +1,192 @@
+//! Simulated laptop battery, for the screenshots, UI development and machines with no battery at all.
+//!
+//! Battery goes through the endless discharge and charge cycles with a noisy load,
+//! voltage sag and heating; simulated time runs `SPEED` times faster than the real one,
+//! so the whole cycle takes about half an hour.
+
+use std::time::Instant;
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::watt_hour;
+use battery::units::power::watt;
+use battery::units::ratio::ratio;
+use battery::units::thermodynamic_temperature::degree_celsius;
+use battery::units::time::second;
+use battery::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time};
+use battery::{State, Technology};
+
+use super::identity::Date;
+use super::{Capabilities, Device, DeviceKind, PowerSource, Source};
+use crate::Result;
+
+/// How many simulated seconds pass in one real second
+const SPEED: f32 = 20.0;
+const DESIGN_CAPACITY: f32 = 57.0; // W·h
+const FULL_CAPACITY: f32 = 51.3; // W·h
+const CELLS: usize = 3;
+/// Internal resistance of the whole pack, Ω
+const RESISTANCE: f32 = 0.12;
+const AMBIENT_TEMPERATURE: f32 = 24.0; // °C
+/// Charger is plugged in below this state of charge and unplugged when battery is full
+const PLUG_IN_LEVEL: f32 = 0.15;
+const CHARGING_POWER: f32 = 45.0; // W
+/// Charging power is tapering off above this state of charge (constant voltage phase)
+const TAPER_LEVEL: f32 = 0.8;
+
+/// State of the simulated battery between the refreshes
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    updated: Instant,
+    elapsed: f32, // Simulated seconds since the start
+    energy: f32,  // W·h
+    power: f32,   // W, positive while charging
+    charging: bool,
+    temperature: f32,
+    seed: u32,
+}
+
+impl Simulation {
+    fn new() -> Simulation {
+        Simulation {
+            updated: Instant::now(),
+            elapsed: 0.0,
+            energy: FULL_CAPACITY * 0.64,
+            power: 0.0,
+            charging: false,
+            temperature: AMBIENT_TEMPERATURE + 6.0,
+            seed: 0x2545_f491,
+        }
+    }
+
+    /// Pseudo-random value in the `[-1.0, 1.0]` range, xorshift is good enough for the noise
+    fn noise(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+
+        (self.seed as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+
+    /// Power the battery is charged (positive) or discharged (negative) with, W
+    fn next_power(&mut self, level: f32) -> f32 {
+        if self.charging {
+            if level < TAPER_LEVEL {
+                CHARGING_POWER
+            } else {
+                CHARGING_POWER * (1.0 - level) / (1.0 - TAPER_LEVEL) + 1.0
+            }
+        } else {
+            // Slowly changing workload with some short spikes on top of it
+            let load = 8.0 + 4.0 * (self.elapsed / 900.0).sin() + 1.5 * self.noise();
+            let spike = if self.noise() > 0.9 { 15.0 } else { 0.0 };
+            -(load + spike)
+        }
+    }
+
+    /// Move the simulation forward by `seconds` of the simulated time
+    fn advance(&mut self, seconds: f32) {
+        self.elapsed += seconds;
+        let level = self.energy / FULL_CAPACITY;
+        if !self.charging && level <= PLUG_IN_LEVEL {
+            self.charging = true;
+        } else if self.charging && level >= 0.999 {
+            self.charging = false;
+        }
+
+        self.power = self.next_power(level);
+        self.energy = (self.energy + self.power * seconds / 3600.0).clamp(0.0, FULL_CAPACITY);
+
+        // Heating is proportional to the power, cooling to the difference with the surroundings
+        let target = AMBIENT_TEMPERATURE + self.power.abs() * 0.35;
+        let factor = (seconds / 120.0).min(1.0);
+        self.temperature += (target - self.temperature) * factor;
+    }
+}
+
+#[derive(Debug)]
+pub struct Demo;
+
+impl PowerSource for Demo {
+    fn name(&self) -> &str {
+        "demo"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let mut device = Device::empty(Source::Demo(Simulation::new()), DeviceKind::Battery);
+        device.vendor = Some("battop".to_string());
+        device.model = Some("Demo battery".to_string());
+        device.serial_number = Some("0042".to_string());
+        device.technology = Technology::LithiumIon;
+        device.energy_full = Energy::new::<watt_hour>(FULL_CAPACITY);
+        device.energy_full_design = Energy::new::<watt_hour>(DESIGN_CAPACITY);
+        device.state_of_health = Ratio::new::<ratio>(FULL_CAPACITY / DESIGN_CAPACITY);
+        device.cycle_count = Some(212);
+        device.identity.manufactured = Date::new(2019, 3, 27);
+        device.identity.firmware = Some("1.42".to_string());
+        device.identity.chemistry = Some("LION".to_string());
+        device.update_demo(0.0);
+
+        Ok(vec![device])
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let seconds = match device.source {
+            Source::Demo(ref mut simulation) => {
+                let elapsed = simulation.updated.elapsed();
+                simulation.updated = Instant::now();
+                (elapsed.as_secs() as f32 + elapsed.subsec_millis() as f32 / 1000.0) * SPEED
+            }
+            _ => return Ok(()),
+        };
+        device.update_demo(seconds);
+
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATTERY
+    }
+}
+
+impl Device {
+    fn update_demo(&mut self, seconds: f32) {
+        let simulation = match self.source {
+            Source::Demo(ref mut simulation) => simulation,
+            _ => return,
+        };
+        simulation.advance(seconds);
+
+        let level = simulation.energy / FULL_CAPACITY;
+        let power = simulation.power;
+        // Open circuit voltage of the Li-ion cell is roughly linear in the middle of the range
+        let open_circuit = 3.3 + 0.9 * level;
+        let current = power / (open_circuit * CELLS as f32);
+        let cells = (0..CELLS)
+            .map(|cell| open_circuit + 0.004 * cell as f32 + current * RESISTANCE / CELLS as f32)
+            .collect::<Vec<_>>();
+
+        self.state = match (simulation.charging, level) {
+            (true, _) => State::Charging,
+            (false, level) if level >= 0.999 => State::Full,
+            (false, _) => State::Discharging,
+        };
+        self.energy = Energy::new::<watt_hour>(simulation.energy);
+        self.state_of_charge = Ratio::new::<ratio>(level);
+        self.energy_rate = Power::new::<watt>(power.abs());
+        self.voltage = ElectricPotential::new::<volt>(cells.iter().sum());
+        self.temperature = Some(ThermodynamicTemperature::new::<degree_celsius>(simulation.temperature));
+        self.time_to_empty = if power < 0.0 {
+            Some(Time::new::<second>(simulation.energy / -power * 3600.0))
+        } else {
+            None
+        };
+        self.time_to_full = if power > 0.0 {
+            Some(Time::new::<second>(
+                (FULL_CAPACITY - simulation.energy) / power * 3600.0,
+            ))
+        } else {
+            None
+        };
+        self.cell_voltages = cells;
+    }
+}
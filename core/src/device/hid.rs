@@ -0,0 +1,361 @@
+//! Battery levels of the wireless mice and keyboards, read directly over HID.
+//!
+//! Many of them are not surfacing anything via the OS power framework,
+//! but are describing the battery strength (Generic Device Controls page)
+//! or the remaining capacity (Battery System page) in their HID report descriptor.
+
+use std::ffi::CString;
+use std::io;
+use std::time::Duration;
+
+use battery::units::ratio::percent;
+use battery::units::Ratio;
+use hidapi::{HidApi, HidDevice};
+
+use super::{Capabilities, Device, DeviceKind, PowerSource, Source};
+use crate::Result;
+
+const GENERIC_DESKTOP_PAGE: u16 = 0x01;
+const MOUSE_USAGE: u16 = 0x02;
+const KEYBOARD_USAGE: u16 = 0x06;
+/// Usage pages and usages of the battery level, see "HID Usage Tables"
+const BATTERY_USAGES: [(u16, u16); 3] = [
+    (0x06, 0x20), // Generic Device Controls, Battery Strength
+    (0x85, 0x66), // Battery System, Remaining Capacity
+    (0x85, 0x64), // Battery System, Relative State Of Charge
+];
+/// How long to wait for the input report with the battery level, ms
+const READ_TIMEOUT: i32 = 50;
+const MAX_REPORT_SIZE: usize = 4096;
+
+/// HID device path and the location of the battery level in its reports
+#[derive(Debug, Clone)]
+pub struct Handle {
+    path: CString,
+    field: Field,
+}
+
+impl Handle {
+    /// Platform-specific device path
+    pub fn id(&self) -> String {
+        self.path.to_string_lossy().into_owned()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ReportKind {
+    Input,
+    Feature,
+}
+
+/// Report field holding the battery level
+#[derive(Debug, Copy, Clone)]
+struct Field {
+    kind: ReportKind,
+    report_id: u8,
+    /// Offset in bits, report ID byte excluded
+    offset: usize,
+    size: usize,
+    logical_min: i32,
+    logical_max: i32,
+}
+
+#[derive(Debug)]
+pub struct Hid;
+
+impl PowerSource for Hid {
+    fn name(&self) -> &str {
+        "HID"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let api = HidApi::new().map_err(hid_error)?;
+        let mut devices = Vec::new();
+        let mut seen = Vec::new();
+
+        for info in api.device_list() {
+            // The same device is listed once for each of its top-level collections on some platforms
+            if seen.contains(&info.path().to_owned()) {
+                continue;
+            }
+            seen.push(info.path().to_owned());
+
+            let hid = match info.open_device(&api) {
+                Ok(hid) => hid,
+                Err(e) => {
+                    trace!("Unable to open HID device {:?}: {}", info.path(), e);
+                    continue;
+                }
+            };
+            let field = match battery_field(&hid) {
+                Some(field) => field,
+                None => continue,
+            };
+
+            let kind = match (info.usage_page(), info.usage()) {
+                (GENERIC_DESKTOP_PAGE, MOUSE_USAGE) => DeviceKind::Mouse,
+                (GENERIC_DESKTOP_PAGE, KEYBOARD_USAGE) => DeviceKind::Keyboard,
+                _ => DeviceKind::Other,
+            };
+            let handle = Handle {
+                path: info.path().to_owned(),
+                field,
+            };
+            let mut device = Device::empty(Source::Hid(handle), kind);
+            device.vendor = info.manufacturer_string().map(str::to_string);
+            device.model = info.product_string().map(str::to_string);
+            device.serial_number = info
+                .serial_number()
+                .filter(|serial| !serial.is_empty())
+                .map(str::to_string);
+            device.attributes = vec![(
+                "USB ID".to_string(),
+                format!("{:04x}:{:04x}", info.vendor_id(), info.product_id()),
+            )];
+            device.read_hid(&hid, field);
+            devices.push(device);
+        }
+        trace!("Found {} HID devices with a battery", devices.len());
+
+        Ok(devices)
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let (path, field) = match device.source {
+            Source::Hid(ref handle) => (handle.path.clone(), handle.field),
+            _ => return Ok(()),
+        };
+        // Device list is not needed to open the known path
+        let api = HidApi::new_without_enumerate().map_err(hid_error)?;
+        let hid = api.open_path(&path).map_err(hid_error)?;
+        device.read_hid(&hid, field);
+
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::LEVEL_ONLY
+    }
+
+    /// Battery strength is reported in a few coarse levels anyway
+    fn interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(30))
+    }
+}
+
+fn hid_error(e: hidapi::HidError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+impl Device {
+    /// Update the state of charge, last known value is kept if device has not reported anything new
+    fn read_hid(&mut self, hid: &HidDevice, field: Field) {
+        let mut report = [0u8; MAX_REPORT_SIZE];
+        report[0] = field.report_id;
+        let read = match field.kind {
+            ReportKind::Feature => hid.get_feature_report(&mut report),
+            ReportKind::Input => hid.read_timeout(&mut report, READ_TIMEOUT),
+        };
+        let length = match read {
+            Ok(length) if length > 0 => length,
+            Ok(_) => return,
+            Err(e) => {
+                trace!("Unable to read HID report {}: {}", field.report_id, e);
+                return;
+            }
+        };
+
+        // Report ID byte is present only if device is using the report IDs
+        let data = if field.report_id != 0 {
+            if report[0] != field.report_id {
+                return;
+            }
+            &report[1..length]
+        } else {
+            &report[..length]
+        };
+        if let Some(value) = field.extract(data) {
+            self.state_of_charge = Ratio::new::<percent>(value);
+        }
+    }
+}
+
+impl Field {
+    /// Battery level from the report data, %
+    fn extract(&self, data: &[u8]) -> Option<f32> {
+        if self.size == 0 || self.size > 32 || (self.offset + self.size + 7) / 8 > data.len() {
+            return None;
+        }
+
+        let mut raw = 0u64;
+        for bit in 0..self.size {
+            let position = self.offset + bit;
+            if data[position / 8] & (1 << (position % 8)) != 0 {
+                raw |= 1 << bit;
+            }
+        }
+        let range = self.logical_max - self.logical_min;
+        if range <= 0 {
+            return None;
+        }
+        let level = (raw as i64 - i64::from(self.logical_min)) as f32 / range as f32 * 100.0;
+
+        Some(level.max(0.0).min(100.0))
+    }
+}
+
+fn battery_field(hid: &HidDevice) -> Option<Field> {
+    let mut descriptor = [0u8; MAX_REPORT_SIZE];
+    let length = hid.get_report_descriptor(&mut descriptor).ok()?;
+
+    parse_descriptor(&descriptor[..length])
+}
+
+/// State of the report descriptor parser, global items are surviving the main ones
+#[derive(Debug, Default, Clone)]
+struct Globals {
+    usage_page: u16,
+    logical_min: i32,
+    logical_max: i32,
+    report_size: usize,
+    report_count: usize,
+    report_id: u8,
+}
+
+/// Walk the HID report descriptor and find the first field with a battery level usage.
+/// Feature reports are preferred, since they can be requested at any time.
+fn parse_descriptor(descriptor: &[u8]) -> Option<Field> {
+    let mut globals = Globals::default();
+    let mut stack = Vec::new();
+    let mut usages: Vec<(u16, u16)> = Vec::new();
+    let mut usage_range = (None, None);
+    // Offsets of the next field, by the report kind and ID
+    let mut offsets: Vec<(ReportKind, u8, usize)> = Vec::new();
+    let mut found: Option<Field> = None;
+
+    let mut i = 0;
+    while i < descriptor.len() {
+        let prefix = descriptor[i];
+        if prefix == 0xFE {
+            // Long item, size is in the next byte
+            let size = usize::from(*descriptor.get(i + 1)?);
+            i += 3 + size;
+            continue;
+        }
+        let size = match prefix & 0x03 {
+            3 => 4,
+            size => usize::from(size),
+        };
+        let data = descriptor.get(i + 1..i + 1 + size)?;
+        i += 1 + size;
+
+        let unsigned = data
+            .iter()
+            .rev()
+            .fold(0u32, |value, byte| (value << 8) | u32::from(*byte));
+        let signed = match size {
+            1 => i32::from(data[0] as i8),
+            2 => i32::from(unsigned as u16 as i16),
+            _ => unsigned as i32,
+        };
+
+        match (prefix >> 2) & 0x03 {
+            // Main items
+            0 => {
+                let kind = match prefix >> 4 {
+                    0x08 => Some(ReportKind::Input),
+                    0x0B => Some(ReportKind::Feature),
+                    // Output reports are taking no part, collections are not changing the state
+                    _ => None,
+                };
+                if let Some(kind) = kind {
+                    let entry = offsets
+                        .iter()
+                        .position(|(k, id, _)| *k == kind && *id == globals.report_id);
+                    let start = match entry {
+                        Some(index) => offsets[index].2,
+                        None => 0,
+                    };
+                    // Constant fields are a padding
+                    let constant = unsigned & 0x01 != 0;
+
+                    if let (Some(min), Some(max)) = usage_range {
+                        for usage in min..=max {
+                            usages.push((globals.usage_page, usage));
+                        }
+                    }
+                    for index in 0..globals.report_count {
+                        let usage = match usages.get(index).or_else(|| usages.last()) {
+                            Some(usage) => *usage,
+                            None => break,
+                        };
+                        if constant || !BATTERY_USAGES.contains(&usage) {
+                            continue;
+                        }
+                        let field = Field {
+                            kind,
+                            report_id: globals.report_id,
+                            offset: start + index * globals.report_size,
+                            size: globals.report_size,
+                            logical_min: globals.logical_min,
+                            logical_max: globals.logical_max,
+                        };
+                        let better = match found {
+                            None => true,
+                            Some(previous) => previous.kind == ReportKind::Input && kind == ReportKind::Feature,
+                        };
+                        if better {
+                            found = Some(field);
+                        }
+                    }
+
+                    let end = start + globals.report_size * globals.report_count;
+                    match entry {
+                        Some(index) => offsets[index].2 = end,
+                        None => offsets.push((kind, globals.report_id, end)),
+                    }
+                }
+                usages.clear();
+                usage_range = (None, None);
+            }
+            // Global items
+            1 => match prefix >> 4 {
+                0x00 => globals.usage_page = unsigned as u16,
+                0x01 => globals.logical_min = signed,
+                0x02 => {
+                    // Logical maximum is unsigned if minimum is not negative
+                    globals.logical_max = if globals.logical_min >= 0 {
+                        unsigned as i32
+                    } else {
+                        signed
+                    }
+                }
+                0x07 => globals.report_size = unsigned as usize,
+                0x08 => globals.report_id = unsigned as u8,
+                0x09 => globals.report_count = unsigned as usize,
+                0x0A => stack.push(globals.clone()),
+                0x0B => globals = stack.pop()?,
+                _ => {}
+            },
+            // Local items, extended usages are carrying the usage page in the high word
+            2 => {
+                let usage = |value: u32| {
+                    if size == 4 {
+                        ((value >> 16) as u16, value as u16)
+                    } else {
+                        (globals.usage_page, value as u16)
+                    }
+                };
+                match prefix >> 4 {
+                    0x00 => usages.push(usage(unsigned)),
+                    0x01 => usage_range.0 = Some(unsigned as u16),
+                    0x02 => usage_range.1 = Some(unsigned as u16),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    found
+}
@@ -0,0 +1,316 @@
+//! Logitech mice and keyboards, paired to the Unifying or Bolt receivers or connected directly,
+//! read with the HID++ 2.0 protocol.
+//!
+//! Devices are reporting the exact percentage and the charging state this way,
+//! while the generic HID and Bluetooth battery services are limited to a few coarse levels.
+//! Each request goes to the feature index device assigned to the feature, which is asked for first.
+
+use std::ffi::CString;
+use std::io;
+use std::time::{Duration, Instant};
+
+use battery::units::ratio::percent;
+use battery::units::Ratio;
+use battery::State;
+use hidapi::{HidApi, HidDevice};
+
+use super::{Capabilities, Device, DeviceKind, PowerSource, Source};
+use crate::Result;
+
+const LOGITECH: u16 = 0x046d;
+/// Vendor-defined collection of the long HID++ reports
+const HIDPP_USAGE_PAGE: u16 = 0xff00;
+const LONG_USAGE: u16 = 0x0002;
+const LONG_REPORT: u8 = 0x11;
+const LONG_REPORT_SIZE: usize = 20;
+/// Paired devices are at the indices 1 to 6 of the receiver, directly connected ones are answering at 0xff
+const DEVICE_INDICES: [u8; 7] = [1, 2, 3, 4, 5, 6, 0xff];
+/// Arbitrary, but non-zero software ID tells our responses from the device notifications
+const SOFTWARE_ID: u8 = 0x0b;
+/// HID++ 1.0 and 2.0 error responses are using these feature indices
+const ERROR_V1: u8 = 0x8f;
+const ERROR_V2: u8 = 0xff;
+/// Sleeping devices are not answering at all, so do not wait for them too long
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Features, root one is always at index 0
+const ROOT: u16 = 0x0000;
+const DEVICE_INFORMATION: u16 = 0x0003;
+const DEVICE_NAME: u16 = 0x0005;
+const BATTERY_STATUS: u16 = 0x1000;
+const BATTERY_VOLTAGE: u16 = 0x1001;
+const UNIFIED_BATTERY: u16 = 0x1004;
+
+/// Battery voltage, mV, and the matching charge level of the typical Li-ion cell, %
+const DISCHARGE_CURVE: [(u16, f32); 8] = [
+    (4186, 100.0),
+    (4067, 90.0),
+    (3989, 80.0),
+    (3922, 70.0),
+    (3859, 60.0),
+    (3811, 50.0),
+    (3700, 20.0),
+    (3500, 0.0),
+];
+
+/// Receiver or device HID path, device index and the battery feature it supports
+#[derive(Debug, Clone)]
+pub struct Handle {
+    path: CString,
+    index: u8,
+    battery: BatteryFeature,
+}
+
+impl Handle {
+    pub fn id(&self) -> String {
+        format!("{}/{}", self.path.to_string_lossy(), self.index)
+    }
+}
+
+/// Devices are supporting one of these, newest first
+#[derive(Debug, Copy, Clone)]
+enum BatteryFeature {
+    Unified(u8),
+    Status(u8),
+    Voltage(u8),
+}
+
+#[derive(Debug)]
+pub struct Hidpp;
+
+impl PowerSource for Hidpp {
+    fn name(&self) -> &str {
+        "HID++"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let api = HidApi::new().map_err(hid_error)?;
+        let mut devices = Vec::new();
+
+        let receivers = api.device_list().filter(|info| {
+            info.vendor_id() == LOGITECH && info.usage_page() == HIDPP_USAGE_PAGE && info.usage() == LONG_USAGE
+        });
+        for info in receivers {
+            let hid = match info.open_device(&api) {
+                Ok(hid) => hid,
+                Err(e) => {
+                    trace!("Unable to open HID++ device {:?}: {}", info.path(), e);
+                    continue;
+                }
+            };
+            for index in DEVICE_INDICES.iter() {
+                // Not paired or turned off devices are answering with an error
+                if let Some(device) = probe(&hid, info.path().to_owned(), *index) {
+                    devices.push(device);
+                }
+            }
+        }
+        trace!("Found {} HID++ devices", devices.len());
+
+        Ok(devices)
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let (path, index, feature) = match device.source {
+            Source::Hidpp(ref handle) => (handle.path.clone(), handle.index, handle.battery),
+            _ => return Ok(()),
+        };
+        let api = HidApi::new_without_enumerate().map_err(hid_error)?;
+        let hid = api.open_path(&path).map_err(hid_error)?;
+        if !device.read_hidpp(&hid, index, feature) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "device is not responding").into());
+        }
+
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::LEVEL_ONLY
+    }
+
+    /// Each request wakes the device radio up
+    fn interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(30))
+    }
+}
+
+fn hid_error(e: hidapi::HidError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+/// Build the device at `index`, if there is one with a battery
+fn probe(hid: &HidDevice, path: CString, index: u8) -> Option<Device> {
+    let battery = if let Some(feature) = feature_index(hid, index, UNIFIED_BATTERY) {
+        BatteryFeature::Unified(feature)
+    } else if let Some(feature) = feature_index(hid, index, BATTERY_STATUS) {
+        BatteryFeature::Status(feature)
+    } else {
+        BatteryFeature::Voltage(feature_index(hid, index, BATTERY_VOLTAGE)?)
+    };
+
+    let (model, kind) = match feature_index(hid, index, DEVICE_NAME) {
+        Some(feature) => (device_name(hid, index, feature), device_kind(hid, index, feature)),
+        None => (None, DeviceKind::Other),
+    };
+    let handle = Handle {
+        path,
+        index,
+        battery,
+    };
+    let mut device = Device::empty(Source::Hidpp(handle), kind);
+    device.vendor = Some("Logitech".to_string());
+    device.model = model;
+    // Unit ID is unique for each device, so it survives the re-pairing
+    device.serial_number = feature_index(hid, index, DEVICE_INFORMATION)
+        .and_then(|feature| request(hid, index, feature, 0, &[]))
+        .map(|response| {
+            response[1..5]
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect::<String>()
+        });
+    if !device.read_hidpp(hid, index, battery) {
+        return None;
+    }
+
+    Some(device)
+}
+
+impl Device {
+    /// Returns `false` if device has not answered
+    fn read_hidpp(&mut self, hid: &HidDevice, index: u8, feature: BatteryFeature) -> bool {
+        let (level, state) = match feature {
+            // get_status: state of charge, level flags, charging status
+            BatteryFeature::Unified(feature) => match request(hid, index, feature, 1, &[]) {
+                Some(response) => {
+                    let state = match response[2] {
+                        0 => State::Discharging,
+                        1 | 2 => State::Charging,
+                        3 => State::Full,
+                        _ => State::Unknown,
+                    };
+                    (f32::from(response[0]), state)
+                }
+                None => return false,
+            },
+            // getBatteryLevelStatus: level, next level, status
+            BatteryFeature::Status(feature) => match request(hid, index, feature, 0, &[]) {
+                Some(response) => {
+                    let state = match response[2] {
+                        0 => State::Discharging,
+                        1 | 2 | 4 => State::Charging,
+                        3 => State::Full,
+                        _ => State::Unknown,
+                    };
+                    (f32::from(response[0]), state)
+                }
+                None => return false,
+            },
+            // getBatteryInfo: voltage in mV and flags, bit 7 means that external power is connected
+            BatteryFeature::Voltage(feature) => match request(hid, index, feature, 0, &[]) {
+                Some(response) => {
+                    let voltage = u16::from(response[0]) << 8 | u16::from(response[1]);
+                    let state = if response[2] & 0x80 != 0 {
+                        State::Charging
+                    } else {
+                        State::Discharging
+                    };
+                    self.attributes = vec![("Voltage".to_string(), format!("{} mV", voltage))];
+                    (voltage_level(voltage), state)
+                }
+                None => return false,
+            },
+        };
+
+        self.state_of_charge = Ratio::new::<percent>(level.min(100.0));
+        self.state = state;
+        true
+    }
+}
+
+/// Index of the feature in the device feature table, asked from the root feature
+fn feature_index(hid: &HidDevice, index: u8, feature: u16) -> Option<u8> {
+    let response = request(hid, index, ROOT as u8, 0, &[(feature >> 8) as u8, feature as u8])?;
+
+    // Zero means that feature is not supported
+    match response[0] {
+        0 => None,
+        feature_index => Some(feature_index),
+    }
+}
+
+/// Name is read in chunks, as much as fits into the response
+fn device_name(hid: &HidDevice, index: u8, feature: u8) -> Option<String> {
+    let length = usize::from(request(hid, index, feature, 0, &[])?[0]);
+    let mut name = Vec::with_capacity(length);
+    while name.len() < length {
+        let chunk = request(hid, index, feature, 1, &[name.len() as u8])?;
+        let remaining = (length - name.len()).min(chunk.len());
+        name.extend_from_slice(&chunk[..remaining]);
+    }
+
+    Some(String::from_utf8_lossy(&name).trim_matches('\0').to_string()).filter(|name| !name.is_empty())
+}
+
+fn device_kind(hid: &HidDevice, index: u8, feature: u8) -> DeviceKind {
+    match request(hid, index, feature, 2, &[]).map(|response| response[0]) {
+        Some(0) => DeviceKind::Keyboard,
+        // Mouse, trackpad and trackball
+        Some(3) | Some(4) | Some(5) => DeviceKind::Mouse,
+        _ => DeviceKind::Other,
+    }
+}
+
+/// Charge level of the voltage-only devices, interpolated over the typical discharge curve
+fn voltage_level(voltage: u16) -> f32 {
+    for pair in DISCHARGE_CURVE.windows(2) {
+        let ((high_voltage, high_level), (low_voltage, low_level)) = (pair[0], pair[1]);
+        if voltage >= high_voltage {
+            return high_level;
+        }
+        if voltage >= low_voltage {
+            let ratio = f32::from(voltage - low_voltage) / f32::from(high_voltage - low_voltage);
+            return low_level + ratio * (high_level - low_level);
+        }
+    }
+
+    0.0
+}
+
+/// Send the long report with the function call and wait for the response parameters
+fn request(hid: &HidDevice, index: u8, feature: u8, function: u8, params: &[u8]) -> Option<[u8; 16]> {
+    let mut report = [0u8; LONG_REPORT_SIZE];
+    report[0] = LONG_REPORT;
+    report[1] = index;
+    report[2] = feature;
+    report[3] = function << 4 | SOFTWARE_ID;
+    report[4..4 + params.len()].copy_from_slice(params);
+    if let Err(e) = hid.write(&report) {
+        trace!("Unable to send HID++ request: {}", e);
+        return None;
+    }
+
+    let started = Instant::now();
+    while started.elapsed() < RESPONSE_TIMEOUT {
+        let mut response = [0u8; LONG_REPORT_SIZE];
+        let length = match hid.read_timeout(&mut response, RESPONSE_TIMEOUT.subsec_millis() as i32) {
+            Ok(length) => length,
+            Err(_) => return None,
+        };
+        // Notifications and responses to the other requests are skipped
+        if length < 4 || response[1] != index {
+            continue;
+        }
+        match (response[2], response[3]) {
+            (ERROR_V1, sub_id) | (ERROR_V2, sub_id) if sub_id == feature => return None,
+            (response_feature, function_id) if response_feature == feature && function_id == report[3] => {
+                let mut params = [0u8; 16];
+                params.copy_from_slice(&response[4..LONG_REPORT_SIZE]);
+                return Some(params);
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
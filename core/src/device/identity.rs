@@ -0,0 +1,96 @@
+//! Battery pack identity, which is not changing during the pack life: manufacture date,
+//! gauge firmware and the chemistry as the pack itself names it.
+//!
+//! Only a few backends know any of these, see the ones filling the `Device::identity`.
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Calendar date, as the batteries are not reporting anything more precise
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Date {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Date> {
+        if year < 1980 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+
+        Some(Date {
+            year,
+            month,
+            day,
+        })
+    }
+
+    /// Smart Battery Data `ManufactureDate` register format, `(year - 1980) * 512 + month * 32 + day`
+    pub fn from_sbs(value: u16) -> Option<Date> {
+        Date::new(1980 + (value >> 9), ((value >> 5) & 0x0f) as u8, (value & 0x1f) as u8)
+    }
+
+    /// `2019-03-27`
+    #[cfg(target_os = "windows")]
+    pub fn parse(value: &str) -> Option<Date> {
+        let mut parts = value.trim().splitn(3, '-');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next()?.parse().ok()?;
+        // Time might follow the date
+        let day = parts
+            .next()?
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect::<String>()
+            .parse()
+            .ok()?;
+
+        Date::new(year, month, day)
+    }
+
+    /// Midnight UTC of this date
+    pub fn timestamp(&self) -> SystemTime {
+        // Days since the epoch for the proleptic Gregorian calendar, see http://howardhinnant.github.io/date_algorithms.html
+        let (year, month, day) = (i64::from(self.year), i64::from(self.month), i64::from(self.day));
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year / 400;
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+
+        UNIX_EPOCH + Duration::from_secs(days as u64 * DAY)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Identity {
+    pub manufactured: Option<Date>,
+    /// Gauge firmware or the pack revision
+    pub firmware: Option<String>,
+    /// Chemistry name as reported by the pack, ex. `LION`, might be more specific than the `Technology`
+    pub chemistry: Option<String>,
+}
+
+impl Identity {
+    pub fn is_empty(&self) -> bool {
+        self.manufactured.is_none() && self.firmware.is_none() && self.chemistry.is_none()
+    }
+
+    /// Time since the pack was manufactured
+    pub fn age(&self) -> Option<Duration> {
+        let manufactured = self.manufactured?.timestamp();
+
+        SystemTime::now().duration_since(manufactured).ok()
+    }
+}
@@ -0,0 +1,159 @@
+//! Extended macOS battery data from the `AppleSmartBattery` IOKit service, queried with `ioreg`.
+//!
+//! SMC is reporting much more than the `battery` crate exposes:
+//! charge current, voltages of the separate cells, manufacture date and the gauge firmware version.
+//! It also tells when the charging is on hold while the adapter is connected,
+//! usually because of the optimized battery charging parking the level at 80 %;
+//! `pmset` knows when the charging is scheduled to finish.
+
+use std::io;
+
+use super::identity::Date;
+use super::Device;
+use crate::command;
+
+impl Device {
+    /// Add the extended data to the device refreshed by the `battery` crate
+    pub fn augment_ioreg(&mut self) {
+        match ioreg() {
+            Ok(output) => self.parse_ioreg(&output),
+            Err(e) => trace!("Unable to query AppleSmartBattery: {}", e),
+        }
+    }
+
+    /// Update device with the `ioreg -r -n AppleSmartBattery` output.
+    ///
+    /// ```text
+    ///   "CycleCount" = 143
+    ///   "ManufactureDate" = 19317
+    ///   "GasGaugeFirmwareVersion" = 1538
+    ///   "Amperage" = 18446744073709550613
+    ///   "BatteryData" = {"CellVoltage"=(4213,4214,4215),"Voltage"=12642}
+    ///   "ExternalConnected" = Yes
+    ///   "IsCharging" = No
+    ///   "FullyCharged" = No
+    ///   "ChargerData" = {"ChargingCurrent"=0,"NotChargingReason"=4}
+    /// ```
+    fn parse_ioreg(&mut self, output: &str) {
+        let mut attributes = Vec::new();
+
+        let flag = |key: &str| output.contains(&format!("\"{}\" = Yes", key));
+        // Missing reason is not a reason to doubt the flags
+        let not_charging_reason =
+            property(output, "\"NotChargingReason\"=").and_then(|value| value.parse::<u64>().ok());
+        self.charging_held =
+            flag("ExternalConnected") && !flag("IsCharging") && !flag("FullyCharged") && not_charging_reason != Some(0);
+        if self.charging_held {
+            let finish = match pmset() {
+                Ok(output) => clock_time(&output),
+                Err(e) => {
+                    trace!("Unable to query pmset: {}", e);
+                    None
+                }
+            };
+            if let Some(finish) = finish {
+                attributes.push(("Full charge at".to_string(), finish));
+            }
+        }
+
+        // Negative values are printed as the unsigned 64-bit integers, mA
+        if let Some(amperage) = property(output, "\"Amperage\" = ").and_then(|value| value.parse::<u64>().ok()) {
+            attributes.push(("Current".to_string(), format!("{} mA", amperage as i64)));
+        }
+        if self.cycle_count.is_none() {
+            self.cycle_count = property(output, "\"CycleCount\" = ").and_then(|value| value.parse().ok());
+        }
+        // Smart Battery Data format
+        self.identity.manufactured = property(output, "\"ManufactureDate\" = ")
+            .and_then(|value| value.parse().ok())
+            .and_then(Date::from_sbs);
+        self.identity.firmware = property(output, "\"GasGaugeFirmwareVersion\" = ").map(str::to_string);
+
+        // mV
+        self.cell_voltages = match output.find("\"CellVoltage\"=(") {
+            Some(start) => output[start + "\"CellVoltage\"=(".len()..]
+                .split(')')
+                .next()
+                .unwrap_or("")
+                .split(',')
+                .filter_map(|value| value.trim().parse::<f32>().ok())
+                .map(|voltage| voltage / 1000.0)
+                .collect(),
+            None => Vec::new(),
+        };
+        self.attributes = attributes;
+    }
+}
+
+fn ioreg() -> io::Result<String> {
+    let output = command::new("ioreg")
+        .args(&["-r", "-n", "AppleSmartBattery"])
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("ioreg exited with {}", output.status),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn pmset() -> io::Result<String> {
+    let output = command::new("pmset").args(&["-g", "batt"]).output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("pmset exited with {}", output.status),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Time following the `until` or `by` words, ex. `7:15 AM` for `… fully charged by 7:15 AM`
+fn clock_time(output: &str) -> Option<String> {
+    let words = output.split_whitespace().collect::<Vec<_>>();
+    for (index, word) in words.iter().enumerate() {
+        if *word != "until" && *word != "by" {
+            continue;
+        }
+        let time = match words.get(index + 1) {
+            Some(time) => time.trim_end_matches(|c: char| c == ',' || c == '.' || c == ')'),
+            None => continue,
+        };
+        let mut parts = time.splitn(2, ':');
+        let hours = parts.next().unwrap_or("");
+        let minutes = parts.next().unwrap_or("");
+        let is_time = !hours.is_empty()
+            && hours.len() <= 2
+            && minutes.len() == 2
+            && hours.chars().chain(minutes.chars()).all(|c| c.is_ascii_digit());
+        if !is_time {
+            continue;
+        }
+
+        let suffix = words
+            .get(index + 2)
+            .map(|suffix| suffix.trim_end_matches(|c: char| !c.is_ascii_alphabetic()));
+        return match suffix {
+            Some(suffix) if suffix == "AM" || suffix == "PM" => Some(format!("{} {}", time, suffix)),
+            _ => Some(time.to_string()),
+        };
+    }
+
+    None
+}
+
+/// Numeric value following the key, ex. `61` for `"Watts"=61`
+fn property<'a>(output: &'a str, key: &str) -> Option<&'a str> {
+    let start = output.find(key)? + key.len();
+    let value = &output[start..];
+    let end = value.find(|c: char| !c.is_ascii_digit()).unwrap_or_else(|| value.len());
+
+    if end == 0 {
+        None
+    } else {
+        Some(&value[..end])
+    }
+}
@@ -0,0 +1,107 @@
+//! Charge start and stop thresholds, Linux only.
+//!
+//! ThinkPad (`thinkpad_acpi`), ASUS (`asus_wmi`), Framework (`cros_charge-control`)
+//! and a few other drivers are exposing them as the `/sys/class/power_supply/BAT*` attributes,
+//! some of them support the stop threshold only.
+//! Writing requires root or a udev rule granting the write access to these attributes.
+//! macOS does not expose the charge limits to the command line tools.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::Device;
+
+/// Attribute names, as the generic ones and the legacy `thinkpad_acpi` ones
+const START: [&str; 2] = ["charge_control_start_threshold", "charge_start_threshold"];
+const STOP: [&str; 2] = ["charge_control_end_threshold", "charge_stop_threshold"];
+
+/// Charge thresholds of one battery, %
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChargeLimits {
+    /// Power supply directory, ex. `/sys/class/power_supply/BAT0`
+    path: PathBuf,
+    /// Battery starts charging below this level, if platform supports it
+    pub start: Option<u8>,
+    /// Battery stops charging at this level
+    pub stop: u8,
+}
+
+impl ChargeLimits {
+    fn read(path: &Path) -> Option<ChargeLimits> {
+        let stop = read_threshold(path, &STOP)?;
+
+        Some(ChargeLimits {
+            path: path.to_path_buf(),
+            start: read_threshold(path, &START),
+            stop,
+        })
+    }
+
+    /// Write the new thresholds, start one is ignored if platform does not support it.
+    ///
+    /// Kernel refuses the start threshold above the stop one,
+    /// so the order of writes depends on the direction of the change.
+    pub fn apply(&mut self, start: Option<u8>, stop: u8) -> io::Result<()> {
+        let start = match self.start {
+            Some(_) => start,
+            None => None,
+        };
+        let stop_first = match start {
+            Some(start) => start >= self.stop,
+            None => true,
+        };
+
+        if stop_first {
+            self.write(&STOP, stop)?;
+            self.stop = stop;
+        }
+        if let Some(start) = start {
+            self.write(&START, start)?;
+            self.start = Some(start);
+        }
+        if !stop_first {
+            self.write(&STOP, stop)?;
+            self.stop = stop;
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, names: &[&str], value: u8) -> io::Result<()> {
+        let path = names
+            .iter()
+            .map(|name| self.path.join(name))
+            .find(|path| path.exists())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "threshold is not supported"))?;
+
+        fs::write(&path, value.to_string()).map_err(|e| match e.kind() {
+            io::ErrorKind::PermissionDenied => io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("no write access to {}, run battop as root", path.display()),
+            ),
+            _ => e,
+        })
+    }
+}
+
+impl Device {
+    /// Charge thresholds of this battery, if platform allows to control them
+    pub fn charge_limits(&self) -> Option<ChargeLimits> {
+        ChargeLimits::read(&self.power_supply()?)
+    }
+}
+
+fn read_threshold(path: &Path, names: &[&str]) -> Option<u8> {
+    names
+        .iter()
+        .filter_map(|name| read(path, name))
+        .next()
+        .and_then(|value| value.parse().ok())
+}
+
+fn read(path: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(path.join(file))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
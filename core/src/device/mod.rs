@@ -0,0 +1,509 @@
+//! Power sources battop is able to monitor and the backends providing them.
+//!
+//! Each backend converts whatever it reads into the common `Device`,
+//! so the rest of battop does not care where the data came from.
+
+use std::fmt;
+#[cfg(any(
+    feature = "sysfs",
+    all(target_os = "linux", any(feature = "smbus", feature = "vedirect"))
+))]
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::watt_hour;
+use battery::units::power::watt;
+use battery::units::ratio::ratio;
+use battery::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time};
+use battery::{State, Technology};
+
+use crate::system::Readings;
+use crate::{Error, Result};
+
+#[cfg(feature = "adb")]
+mod adb;
+#[cfg(feature = "battery-crate")]
+mod battery_crate;
+#[cfg(feature = "bluez")]
+mod bluez;
+#[cfg(feature = "bsd")]
+mod bsd;
+mod collector;
+mod cros_ec;
+#[cfg(all(unix, feature = "daemon"))]
+mod daemon;
+#[cfg(any(feature = "upower", feature = "bluez"))]
+mod dbus;
+mod demo;
+#[cfg(feature = "hid")]
+mod hid;
+#[cfg(feature = "hid")]
+mod hidpp;
+mod identity;
+#[cfg(all(target_os = "macos", feature = "battery-crate"))]
+mod ioreg;
+mod limits;
+#[cfg(feature = "nut")]
+mod nut;
+mod order;
+mod provider;
+#[cfg(feature = "remote")]
+mod remote;
+mod scripted;
+mod selection;
+#[cfg(all(target_os = "linux", feature = "smbus"))]
+mod smbus;
+mod sysfs;
+#[cfg(feature = "upower")]
+mod upower;
+#[cfg(all(target_os = "linux", feature = "vedirect"))]
+mod vedirect;
+#[cfg(all(target_os = "windows", feature = "battery-crate"))]
+mod wmi;
+
+pub use self::collector::{Collector, Update};
+pub use self::cros_ec::ChargerState;
+#[cfg(all(unix, feature = "daemon"))]
+pub use self::daemon::{default_socket, Daemon, Server};
+pub use self::identity::Identity;
+pub use self::limits::ChargeLimits;
+pub use self::order::Order;
+pub use self::provider::{Capabilities, Options, Plan, PowerSource, Provider};
+pub use self::scripted::{Reading, Scripted};
+pub use self::selection::Selection;
+
+/// Backend used to enumerate and refresh devices
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Backend {
+    /// Cross-platform `battery` crate
+    Battery,
+    /// `/sys/class/power_supply` read directly, Linux only
+    Sysfs,
+    /// UPower daemon over D-Bus, Linux only
+    UPower,
+    /// Network UPS Tools server
+    Nut,
+    /// `acpiconf` or `hw.sensors` sysctl, FreeBSD, DragonFly and OpenBSD only
+    Bsd,
+    /// Simulated battery
+    Demo,
+    /// Devices collected by the `battop daemon`, Unix only
+    Daemon,
+}
+
+impl Backend {
+    pub fn arg_variants() -> [&'static str; 7] {
+        ["battery", "sysfs", "upower", "nut", "bsd", "demo", "daemon"]
+    }
+
+    /// Cargo feature the backend is built with
+    fn feature(self) -> &'static str {
+        match self {
+            Backend::Battery => "battery-crate",
+            Backend::Sysfs => "sysfs",
+            Backend::UPower => "upower",
+            Backend::Nut => "nut",
+            Backend::Bsd => "bsd",
+            Backend::Demo => "demo",
+            Backend::Daemon => "daemon",
+        }
+    }
+
+    /// Whether the backend was built in, the simulated battery always is
+    fn is_enabled(self) -> bool {
+        match self {
+            Backend::Battery => cfg!(feature = "battery-crate"),
+            Backend::Sysfs => cfg!(feature = "sysfs"),
+            Backend::UPower => cfg!(feature = "upower"),
+            Backend::Nut => cfg!(feature = "nut"),
+            Backend::Bsd => cfg!(feature = "bsd"),
+            Backend::Demo => true,
+            Backend::Daemon => cfg!(all(unix, feature = "daemon")),
+        }
+    }
+
+    /// Backends to try in order, starting from this one.
+    ///
+    /// Only the local ones built in are used as a fallback; the daemon has none,
+    /// as polling the hardware next to it is what attaching to it avoids.
+    fn with_fallbacks(self) -> Vec<Backend> {
+        let mut backends = vec![self];
+        if self == Backend::Daemon {
+            return backends;
+        }
+        backends.extend(
+            [Backend::Battery, Backend::Sysfs, Backend::UPower, Backend::Bsd]
+                .iter()
+                .cloned()
+                .filter(|backend| *backend != self && backend.is_enabled()),
+        );
+
+        backends
+    }
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match () {
+            _ if s.eq_ignore_ascii_case("battery") => Ok(Backend::Battery),
+            _ if s.eq_ignore_ascii_case("sysfs") => Ok(Backend::Sysfs),
+            _ if s.eq_ignore_ascii_case("upower") => Ok(Backend::UPower),
+            _ if s.eq_ignore_ascii_case("nut") => Ok(Backend::Nut),
+            _ if s.eq_ignore_ascii_case("bsd") => Ok(Backend::Bsd),
+            _ if s.eq_ignore_ascii_case("demo") => Ok(Backend::Demo),
+            _ if s.eq_ignore_ascii_case("daemon") => Ok(Backend::Daemon),
+            _ => Err(Error::ParseError),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Backend::Battery => f.write_str("battery"),
+            Backend::Sysfs => f.write_str("sysfs"),
+            Backend::UPower => f.write_str("upower"),
+            Backend::Nut => f.write_str("nut"),
+            Backend::Bsd => f.write_str("bsd"),
+            Backend::Demo => f.write_str("demo"),
+            Backend::Daemon => f.write_str("daemon"),
+        }
+    }
+}
+
+/// What kind of thing the device is powering
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum DeviceKind {
+    Battery,
+    Ups,
+    Mouse,
+    Keyboard,
+    Phone,
+    Other,
+}
+
+impl fmt::Display for DeviceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeviceKind::Battery => f.write_str("battery"),
+            DeviceKind::Ups => f.write_str("UPS"),
+            DeviceKind::Mouse => f.write_str("mouse"),
+            DeviceKind::Keyboard => f.write_str("keyboard"),
+            DeviceKind::Phone => f.write_str("phone"),
+            DeviceKind::Other => f.write_str("device"),
+        }
+    }
+}
+
+/// Point of the history stored by the backend itself, ex. UPower
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HistoryPoint {
+    pub timestamp: SystemTime,
+    pub value: f32,
+}
+
+/// Where the device came from and how to refresh it
+#[derive(Debug, Clone)]
+enum Source {
+    /// Vendor, model and serial number of the battery kept by the `battery` crate backend
+    #[cfg(feature = "battery-crate")]
+    Battery(String),
+    /// `/sys/class/power_supply` device directory
+    #[cfg(feature = "sysfs")]
+    Sysfs(PathBuf),
+    /// UPower device object path
+    #[cfg(feature = "upower")]
+    UPower(String),
+    /// UPS name on the NUT server
+    #[cfg(feature = "nut")]
+    Nut(String),
+    /// Bluetooth device address, refreshed independently from the main backend
+    #[cfg(feature = "bluez")]
+    Bluez(String),
+    /// Power supply name on the remote host
+    #[cfg(feature = "remote")]
+    Remote(String),
+    /// `acpiconf` battery unit or OpenBSD sensor device name
+    #[cfg(feature = "bsd")]
+    Bsd(String),
+    /// `i2c-dev` adapter the Smart Battery is attached to
+    #[cfg(all(target_os = "linux", feature = "smbus"))]
+    Smbus(PathBuf),
+    /// Android device serial number, refreshed independently from the main backend
+    #[cfg(feature = "adb")]
+    Adb(String),
+    /// HID device, refreshed independently from the main backend
+    #[cfg(feature = "hid")]
+    Hid(hid::Handle),
+    /// Logitech device, refreshed independently from the main backend
+    #[cfg(feature = "hid")]
+    Hidpp(hidpp::Handle),
+    /// Serial port of the Victron Energy device
+    #[cfg(all(target_os = "linux", feature = "vedirect"))]
+    Vedirect(PathBuf),
+    Demo(demo::Simulation),
+    /// Id of the device given by the daemon
+    #[cfg(all(unix, feature = "daemon"))]
+    Daemon(String),
+    /// Next step of the scripted battery
+    Scripted(usize),
+}
+
+/// Snapshot of the power source state, refreshed by the backend it came from
+#[derive(Debug, Clone)]
+pub struct Device {
+    source: Source,
+    /// Index of the backend in the `Provider` which enumerated this device
+    origin: usize,
+    capabilities: Capabilities,
+    /// Host the device is attached to, if battop is watching more than one
+    host: Option<String>,
+    kind: DeviceKind,
+    vendor: Option<String>,
+    model: Option<String>,
+    serial_number: Option<String>,
+    technology: Technology,
+    state: State,
+    state_of_charge: Ratio,
+    energy: Energy,
+    energy_full: Energy,
+    energy_full_design: Energy,
+    energy_rate: Power,
+    voltage: ElectricPotential,
+    state_of_health: Ratio,
+    temperature: Option<ThermodynamicTemperature>,
+    cycle_count: Option<u32>,
+    time_to_full: Option<Time>,
+    time_to_empty: Option<Time>,
+    rate_history: Vec<HistoryPoint>,
+    cell_voltages: Vec<f32>,
+    capacity_history: Vec<HistoryPoint>,
+    identity: Identity,
+    /// OS is keeping the battery below full on purpose, ex. macOS optimized charging
+    charging_held: bool,
+    /// EC charger state, Chromebooks only
+    charger: Option<ChargerState>,
+    attributes: Vec<(String, String)>,
+    /// Readings taken by the `Collector` along with the refresh
+    system: Option<Readings>,
+}
+
+impl Device {
+    /// Device with nothing known about it yet, backends are filling the values in
+    fn empty(source: Source, kind: DeviceKind) -> Device {
+        Device {
+            source,
+            origin: 0,
+            capabilities: Capabilities::BATTERY,
+            host: None,
+            kind,
+            vendor: None,
+            model: None,
+            serial_number: None,
+            technology: Technology::Unknown,
+            state: State::Unknown,
+            state_of_charge: Ratio::new::<ratio>(0.0),
+            energy: Energy::new::<watt_hour>(0.0),
+            energy_full: Energy::new::<watt_hour>(0.0),
+            energy_full_design: Energy::new::<watt_hour>(0.0),
+            energy_rate: Power::new::<watt>(0.0),
+            voltage: ElectricPotential::new::<volt>(0.0),
+            state_of_health: Ratio::new::<ratio>(0.0),
+            temperature: None,
+            cycle_count: None,
+            time_to_full: None,
+            time_to_empty: None,
+            rate_history: Vec::new(),
+            cell_voltages: Vec::new(),
+            capacity_history: Vec::new(),
+            identity: Identity::default(),
+            charging_held: false,
+            charger: None,
+            attributes: Vec::new(),
+            system: None,
+        }
+    }
+
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    pub fn kind(&self) -> DeviceKind {
+        self.kind
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    pub fn vendor(&self) -> Option<&str> {
+        self.vendor.as_deref()
+    }
+
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+
+    pub fn technology(&self) -> Technology {
+        self.technology
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn state_of_charge(&self) -> Ratio {
+        self.state_of_charge
+    }
+
+    pub fn energy(&self) -> Energy {
+        self.energy
+    }
+
+    pub fn energy_full(&self) -> Energy {
+        self.energy_full
+    }
+
+    pub fn energy_full_design(&self) -> Energy {
+        self.energy_full_design
+    }
+
+    pub fn energy_rate(&self) -> Power {
+        self.energy_rate
+    }
+
+    pub fn voltage(&self) -> ElectricPotential {
+        self.voltage
+    }
+
+    pub fn state_of_health(&self) -> Ratio {
+        self.state_of_health
+    }
+
+    pub fn temperature(&self) -> Option<ThermodynamicTemperature> {
+        self.temperature
+    }
+
+    pub fn cycle_count(&self) -> Option<u32> {
+        self.cycle_count
+    }
+
+    pub fn time_to_full(&self) -> Option<Time> {
+        self.time_to_full
+    }
+
+    pub fn time_to_empty(&self) -> Option<Time> {
+        self.time_to_empty
+    }
+
+    /// Energy rate history stored by the backend before battop was started, W
+    pub fn rate_history(&self) -> &[HistoryPoint] {
+        self.rate_history.as_ref()
+    }
+
+    /// Last full energy history stored by the OS before battop was started, W·h
+    pub fn capacity_history(&self) -> &[HistoryPoint] {
+        self.capacity_history.as_ref()
+    }
+
+    /// Voltages of the separate cells, if backend knows them, V
+    pub fn cell_voltages(&self) -> &[f32] {
+        self.cell_voltages.as_ref()
+    }
+
+    pub fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    /// Adapter is connected, but OS holds the charging, so the level is parked below full
+    pub fn charging_held(&self) -> bool {
+        self.charging_held
+    }
+
+    /// System readings taken along with the latest refresh, `None` if the device was not refreshed by the `Collector`
+    /// or the readings were not requested with `Options::system`
+    pub fn system(&self) -> Option<&Readings> {
+        self.system.as_ref()
+    }
+
+    /// Backend-specific attributes not covered by the getters above, as name and value pairs
+    pub fn attributes(&self) -> &[(String, String)] {
+        self.attributes.as_ref()
+    }
+
+    /// Index of the `Provider` backend this device came from
+    pub fn origin(&self) -> usize {
+        self.origin
+    }
+
+    /// Device read from the `battop daemon`, which is keeping its history
+    pub fn is_attached(&self) -> bool {
+        match self.source {
+            #[cfg(all(unix, feature = "daemon"))]
+            Source::Daemon(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Identifier of the device, stable between the enumerations
+    pub fn id(&self) -> String {
+        let source = match self.source {
+            #[cfg(feature = "battery-crate")]
+            Source::Battery(ref key) => format!("battery:{}", key),
+            #[cfg(feature = "sysfs")]
+            Source::Sysfs(ref path) => format!("sysfs:{}", path.display()),
+            #[cfg(feature = "upower")]
+            Source::UPower(ref path) => format!("upower:{}", path),
+            #[cfg(feature = "nut")]
+            Source::Nut(ref name) => format!("nut:{}", name),
+            #[cfg(feature = "bluez")]
+            Source::Bluez(ref address) => format!("bluez:{}", address),
+            #[cfg(feature = "remote")]
+            Source::Remote(ref name) => format!("remote:{}", name),
+            #[cfg(feature = "bsd")]
+            Source::Bsd(ref unit) => format!("bsd:{}", unit),
+            #[cfg(all(target_os = "linux", feature = "smbus"))]
+            Source::Smbus(ref path) => format!("smbus:{}", path.display()),
+            #[cfg(feature = "adb")]
+            Source::Adb(ref serial) => format!("adb:{}", serial),
+            #[cfg(feature = "hid")]
+            Source::Hid(ref handle) => format!("hid:{}", handle.id()),
+            #[cfg(feature = "hid")]
+            Source::Hidpp(ref handle) => format!("hidpp:{}", handle.id()),
+            #[cfg(all(target_os = "linux", feature = "vedirect"))]
+            Source::Vedirect(ref path) => format!("vedirect:{}", path.display()),
+            Source::Demo(_) => "demo".to_string(),
+            // Daemon id is naming the host already, and is kept as is so the clients are referring the same devices
+            #[cfg(all(unix, feature = "daemon"))]
+            Source::Daemon(ref id) => return id.clone(),
+            Source::Scripted(_) => format!("scripted:{}", self.serial_number().unwrap_or("")),
+        };
+
+        match self.host {
+            Some(ref host) => format!("{}@{}", source, host),
+            None => source,
+        }
+    }
+
+    /// Check if both values are describing the same physical device,
+    /// which might be re-attached under another path in between.
+    pub fn is_same(&self, other: &Device) -> bool {
+        match (self.serial_number(), other.serial_number()) {
+            (Some(serial), Some(other_serial)) => {
+                serial == other_serial && self.model() == other.model() && self.host == other.host
+            }
+            _ => self.id() == other.id(),
+        }
+    }
+}
+
+/// Host name meaning this machine in the list of hosts to watch
+pub const LOCAL_HOST: &str = "local";
@@ -0,0 +1,243 @@
+//! Backend talking to the Network UPS Tools server (`upsd`) over its plain text protocol.
+//!
+//! Every UPS known to the server becomes a separate device; the connection is opened
+//! for each refresh, so restarts of the server are not a problem.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use battery::units::electric_potential::volt;
+use battery::units::power::watt;
+use battery::units::ratio::percent;
+use battery::units::thermodynamic_temperature::degree_celsius;
+use battery::units::time::second;
+use battery::units::{ElectricPotential, Power, Ratio, ThermodynamicTemperature, Time};
+use battery::State;
+
+use super::{Capabilities, Device, DeviceKind, PowerSource, Source};
+use crate::Result;
+
+/// Port `upsd` is listening on, unless configured otherwise
+pub const DEFAULT_PORT: u16 = 3493;
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+pub struct Nut {
+    /// Server address, as a `host` or `host:port`
+    server: String,
+}
+
+impl Nut {
+    pub fn new(server: &str) -> Nut {
+        Nut {
+            server: server.to_string(),
+        }
+    }
+}
+
+impl PowerSource for Nut {
+    fn name(&self) -> &str {
+        "nut"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let mut connection = Connection::open(&self.server)?;
+        let mut devices = Vec::new();
+        for line in connection.list("UPS", "")? {
+            // UPS <name> "<description>"
+            let name = match line.split_whitespace().nth(1) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let vars = connection.vars(&name)?;
+
+            let mut device = Device::empty(Source::Nut(name), DeviceKind::Ups);
+            device.update_nut(&vars);
+            devices.push(device);
+        }
+        connection.close();
+        trace!("NUT server {} reported {} devices", self.server, devices.len());
+
+        Ok(devices)
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let name = match device.source {
+            Source::Nut(ref name) => name.clone(),
+            _ => return Ok(()),
+        };
+        let mut connection = Connection::open(&self.server)?;
+        let vars = connection.vars(&name)?;
+        connection.close();
+        device.update_nut(&vars);
+
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATTERY
+    }
+}
+
+impl Device {
+    fn update_nut(&mut self, vars: &HashMap<String, String>) {
+        let get = |key: &str| vars.get(key).map(String::as_str);
+        let number = |key: &str| get(key).and_then(|value| value.parse::<f32>().ok());
+        let text = |keys: &[&str]| keys.iter().filter_map(|key| get(key)).next().map(str::to_string);
+
+        self.vendor = text(&["device.mfr", "ups.mfr"]);
+        self.model = text(&["device.model", "ups.model"]).or_else(|| match self.source {
+            // Name from the `ups.conf` is better than nothing
+            Source::Nut(ref name) => Some(name.clone()),
+            _ => None,
+        });
+        self.serial_number = text(&["device.serial", "ups.serial"]);
+
+        let charge = number("battery.charge");
+        let status = get("ups.status").unwrap_or("");
+        self.state = parse_status(status, charge.unwrap_or(0.0));
+        self.state_of_charge = Ratio::new::<percent>(charge.unwrap_or(0.0));
+        self.voltage = ElectricPotential::new::<volt>(number("battery.voltage").unwrap_or(0.0));
+        self.temperature = number("battery.temperature")
+            .or_else(|| number("ups.temperature"))
+            .map(ThermodynamicTemperature::new::<degree_celsius>);
+
+        // Output load is what the battery has to supply during the outage
+        let load = number("ups.load");
+        let power = number("ups.realpower").or_else(|| match (number("ups.realpower.nominal"), load) {
+            (Some(nominal), Some(load)) => Some(nominal * load / 100.0),
+            _ => None,
+        });
+        self.energy_rate = Power::new::<watt>(power.unwrap_or(0.0));
+        self.time_to_empty = match number("battery.runtime") {
+            Some(runtime) if runtime > 0.0 => Some(Time::new::<second>(runtime)),
+            _ => None,
+        };
+        self.time_to_full = None;
+
+        let mut attributes = Vec::new();
+        if let Some(load) = load {
+            attributes.push(("Load".to_string(), format!("{:.0} %", load)));
+        }
+        if let Some(voltage) = number("input.voltage") {
+            attributes.push(("Input voltage".to_string(), format!("{:.1} V", voltage)));
+        }
+        if let Some(voltage) = number("output.voltage") {
+            attributes.push(("Output voltage".to_string(), format!("{:.1} V", voltage)));
+        }
+        if !status.is_empty() {
+            attributes.push(("UPS status".to_string(), status.to_string()));
+        }
+        self.attributes = attributes;
+    }
+}
+
+/// Map `ups.status` flags, ex. `OL CHRG` or `OB DISCHRG LB`, into the battery state
+fn parse_status(status: &str, charge: f32) -> State {
+    let flags = status.split_whitespace().collect::<Vec<_>>();
+    match () {
+        _ if flags.contains(&"CHRG") => State::Charging,
+        _ if flags.contains(&"OB") || flags.contains(&"DISCHRG") => State::Discharging,
+        _ if flags.contains(&"OL") && charge >= 100.0 => State::Full,
+        _ => State::Unknown,
+    }
+}
+
+/// Single `upsd` session, see the "Network protocol information" chapter of the NUT developer guide
+struct Connection {
+    reader: BufReader<TcpStream>,
+    stream: TcpStream,
+}
+
+impl Connection {
+    fn open(server: &str) -> io::Result<Connection> {
+        let address = if server.contains(':') {
+            server.to_string()
+        } else {
+            format!("{}:{}", server, DEFAULT_PORT)
+        };
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Unable to resolve {}", server)))?;
+
+        let stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        Ok(Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            stream,
+        })
+    }
+
+    /// Send the `LIST` command and collect the lines between `BEGIN LIST` and `END LIST`
+    fn list(&mut self, what: &str, args: &str) -> io::Result<Vec<String>> {
+        let query = format!("{} {}", what, args);
+        let query = query.trim();
+        writeln!(self.stream, "LIST {}", query)?;
+
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "upsd closed the connection",
+                ));
+            }
+            let line = line.trim_end();
+            if let Some(error) = line.strip_prefix("ERR ") {
+                return Err(io::Error::other(format!("upsd: {}", error)));
+            }
+            if line == format!("END LIST {}", query) {
+                return Ok(lines);
+            }
+            if !line.starts_with("BEGIN LIST") {
+                lines.push(line.to_string());
+            }
+        }
+    }
+
+    /// All the variables of the UPS, ex. `battery.charge` → `100`
+    fn vars(&mut self, ups: &str) -> io::Result<HashMap<String, String>> {
+        let vars = self
+            .list("VAR", ups)?
+            .iter()
+            .filter_map(|line| {
+                // VAR <ups> <name> "<value>"
+                let mut parts = line.splitn(4, ' ');
+                let name = parts.nth(2)?;
+                let value = unquote(parts.next()?);
+
+                Some((name.to_string(), value))
+            })
+            .collect();
+
+        Ok(vars)
+    }
+
+    fn close(mut self) {
+        let _ = writeln!(self.stream, "LOGOUT");
+    }
+}
+
+/// Strip the quotes and backslash escapes from the value
+fn unquote(value: &str) -> String {
+    let mut result = String::new();
+    let mut escaped = false;
+    for c in value.trim().trim_matches('"').chars() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            _ => {
+                result.push(c);
+                escaped = false;
+            }
+        }
+    }
+
+    result
+}
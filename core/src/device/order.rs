@@ -0,0 +1,99 @@
+//! Order the devices are listed in, kept the same between boots by the serial numbers and ids.
+
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use super::Device;
+
+/// Order of the devices, and so of the interface tabs
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Order {
+    /// As the backends have found them
+    #[default]
+    Found,
+    /// By the serial numbers, devices without one go last
+    Serial,
+    /// By the ids, which are the sysfs paths on Linux
+    Path,
+    /// Serial numbers or ids, devices not listed go last in the order they were found
+    List(Vec<String>),
+}
+
+impl Order {
+    pub const VARIANTS: [&'static str; 3] = ["found", "serial", "path"];
+
+    /// Sorting is stable, so the devices compared as equal are kept in the order they were found
+    pub fn compare(&self, a: &Device, b: &Device) -> Ordering {
+        match self {
+            Order::Found => Ordering::Equal,
+            Order::Serial => match (a.serial_number(), b.serial_number()) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => a.id().cmp(&b.id()),
+            },
+            Order::Path => a.id().cmp(&b.id()),
+            Order::List(list) => {
+                let position = |device: &Device| {
+                    let id = device.id();
+                    list.iter()
+                        .position(|name| device.serial_number() == Some(name.as_str()) || *name == id)
+                        .unwrap_or(list.len())
+                };
+                position(a).cmp(&position(b))
+            }
+        }
+    }
+}
+
+impl FromStr for Order {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Order, ()> {
+        match name.to_lowercase().as_str() {
+            "found" => Ok(Order::Found),
+            "serial" => Ok(Order::Serial),
+            "path" => Ok(Order::Path),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Order;
+    use crate::device::{Device, PowerSource, Reading, Scripted};
+
+    fn devices() -> Vec<Device> {
+        ["Spare", "Internal", "Bench"]
+            .iter()
+            .map(|model| {
+                Scripted::new(model, 50.0, vec![Some(Reading::discharging(0.5, 5.0))])
+                    .devices()
+                    .expect("Scripted battery")
+                    .remove(0)
+            })
+            .collect()
+    }
+
+    fn sorted(order: Order) -> Vec<String> {
+        let mut devices = devices();
+        devices.sort_by(|a, b| order.compare(a, b));
+        devices
+            .iter()
+            .filter_map(|device| device.model())
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn orders() {
+        assert_eq!(sorted(Order::Found), vec!["Spare", "Internal", "Bench"]);
+        assert_eq!(sorted(Order::Serial), vec!["Bench", "Internal", "Spare"]);
+        assert_eq!(sorted(Order::Path), vec!["Bench", "Internal", "Spare"]);
+        assert_eq!(
+            sorted(Order::List(vec!["internal".into(), "scripted:bench@scripted".into()])),
+            vec!["Internal", "Bench", "Spare"]
+        );
+    }
+}
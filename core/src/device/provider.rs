@@ -0,0 +1,552 @@
+//! Backends are hidden behind the `PowerSource` trait, so any number of them
+//! can be started at once: the main one, the peripherals and the remote hosts.
+//! Every device remembers which of them it came from and is refreshed by that one only.
+//!
+//! Each backend might be started on a thread of its own out of its `Plan`, see `Collector`.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tracing::{debug_span, trace_span};
+
+#[cfg(feature = "adb")]
+use super::adb::Adb;
+#[cfg(feature = "battery-crate")]
+use super::battery_crate::BatteryCrate;
+#[cfg(feature = "bluez")]
+use super::bluez::Bluez;
+#[cfg(feature = "bsd")]
+use super::bsd::Bsd;
+#[cfg(all(unix, feature = "daemon"))]
+use super::daemon::{default_socket, Daemon};
+use super::demo::Demo;
+#[cfg(feature = "hid")]
+use super::hid::Hid;
+#[cfg(feature = "hid")]
+use super::hidpp::Hidpp;
+#[cfg(feature = "nut")]
+use super::nut::Nut;
+#[cfg(feature = "remote")]
+use super::remote::Remote;
+#[cfg(all(target_os = "linux", feature = "smbus"))]
+use super::smbus::Smbus;
+#[cfg(feature = "sysfs")]
+use super::sysfs::Sysfs;
+#[cfg(feature = "upower")]
+use super::upower::UPower;
+#[cfg(all(target_os = "linux", feature = "vedirect"))]
+use super::vedirect::Vedirect;
+use super::{Backend, Device, Order, Selection, LOCAL_HOST};
+use crate::system;
+use crate::{Error, Result};
+
+/// Backend able to enumerate and refresh the devices
+pub trait PowerSource: fmt::Debug {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    /// Devices available right now
+    fn devices(&mut self) -> Result<Vec<Device>>;
+
+    /// Read the latest state of the device enumerated by this backend,
+    /// error means that device is not available anymore
+    fn refresh(&mut self, device: &mut Device) -> Result<()>;
+
+    fn capabilities(&self) -> Capabilities;
+
+    /// Minimal polling interval, for the backends which are slow or costly to query;
+    /// `None` means devices are refreshed as often as the user asked
+    fn interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Devices are the kernel power supplies, so on Linux the ones connected later are announced
+    /// by the `uevents` and the backend is enumerated again on these only, instead of periodically
+    fn hotplug(&self) -> bool {
+        false
+    }
+}
+
+/// What is worth showing for the devices of the backend, besides the state of charge
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Capabilities {
+    pub voltage: bool,
+    /// System sensors are used if the battery is not reporting its temperature
+    pub temperature: bool,
+}
+
+impl Capabilities {
+    /// Laptop batteries, UPS and phones
+    pub const BATTERY: Capabilities = Capabilities {
+        voltage: true,
+        temperature: true,
+    };
+
+    /// Peripherals are reporting nothing but the charge level
+    pub const LEVEL_ONLY: Capabilities = Capabilities {
+        voltage: false,
+        temperature: false,
+    };
+}
+
+#[derive(Debug)]
+struct Attached {
+    /// Host the backend is reading, if battop is watching more than one
+    host: Option<String>,
+    source: Box<dyn PowerSource>,
+}
+
+/// Which devices to collect and where from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Main backend, the other ones are tried if this one is not available
+    pub backend: Backend,
+    /// Network UPS Tools server used by the `nut` backend, as a `host` or `host:port`
+    pub nut_server: String,
+    /// SSH destinations to collect from instead of this machine, `local` stands for this machine
+    pub remotes: Vec<String>,
+    /// Bluetooth devices reported by BlueZ
+    pub bluetooth: bool,
+    /// Android devices connected over ADB
+    pub adb: bool,
+    /// Wireless mice and keyboards read over HID, requires the `hid` feature
+    pub hid: bool,
+    /// `i2c-dev` SMBus adapter to read the Smart Battery registers from, ex. `/dev/i2c-3`
+    pub smbus: Option<String>,
+    /// Serial ports with the VE.Direct devices, ex. `/dev/ttyUSB0`
+    pub vedirect: Vec<String>,
+    /// Devices to collect out of all the found ones
+    pub selection: Selection,
+    /// Order the found devices are returned in, the `selection` positions are referring it
+    pub order: Order,
+    /// Socket of the `battop daemon` read by the `daemon` backend, the default one if `None`
+    pub socket: Option<PathBuf>,
+    /// System readings the `Collector` takes along with the refreshes, none if `None`
+    pub system: Option<system::Options>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            backend: Backend::Battery,
+            nut_server: "localhost".to_string(),
+            remotes: Vec::new(),
+            bluetooth: false,
+            adb: false,
+            hid: false,
+            smbus: None,
+            vedirect: Vec::new(),
+            selection: Selection::default(),
+            order: Order::default(),
+            socket: None,
+            system: None,
+        }
+    }
+}
+
+/// Backend started along with the devices it had found
+type Started = (Box<dyn PowerSource>, Vec<Device>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Planned {
+    /// Backend requested by user, with the fallbacks
+    Main,
+    Remote(String),
+    Bluez,
+    Adb,
+    Hid,
+    Hidpp,
+    Smbus(String),
+    Vedirect(String),
+}
+
+/// Backend which is not started yet, so it can be sent to the thread it is going to run on,
+/// as the backends do not have to be `Send`
+#[derive(Debug, Clone)]
+pub struct Plan {
+    /// Host the backend is reading, if battop is watching more than one
+    host: Option<String>,
+    planned: Planned,
+    options: Options,
+}
+
+impl Plan {
+    /// Host the backend is reading, if battop is watching more than one
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// Whether battop can't go on without this backend; the remote hosts which are not available are skipped,
+    /// and the peripherals are kept even without devices
+    pub fn is_required(&self) -> bool {
+        self.planned == Planned::Main && self.host.is_none()
+    }
+
+    /// Start the backend and list its devices, `None` if battop was built without it
+    fn start(&self) -> Result<Option<Started>> {
+        let source = match self.planned {
+            Planned::Main => return Provider::start_with_fallbacks(&self.options).map(Some),
+            Planned::Remote(ref host) => return Provider::remote(host).map(Some),
+            Planned::Bluez => Provider::bluez(),
+            Planned::Adb => Provider::adb(),
+            Planned::Hid => Provider::hid(),
+            Planned::Hidpp => Provider::hidpp(),
+            Planned::Smbus(ref path) => Provider::smbus(path),
+            Planned::Vedirect(ref path) => Provider::vedirect(path),
+        };
+
+        Ok(source.map(|mut source| {
+            // Peripherals might be connected later, so backend is kept anyway
+            let found = source.devices().unwrap_or_else(|e| {
+                warn!("Unable to list {} devices: {}", source.name(), e);
+                Vec::new()
+            });
+            (source, found)
+        }))
+    }
+}
+
+/// All the backends started
+#[derive(Debug)]
+pub struct Provider {
+    sources: Vec<Attached>,
+    selection: Selection,
+    /// Index of the first backend, for the providers running a part of them
+    first: usize,
+}
+
+impl Provider {
+    /// Start the backend requested by user (or connect to the remote hosts) along with the peripherals enabled.
+    pub fn new(options: &Options) -> Result<(Provider, Vec<Device>)> {
+        let _span = debug_span!("enumerate").entered();
+        let mut provider = Provider::empty(options.selection.clone(), 0);
+        let mut devices = Vec::new();
+
+        for plan in Provider::plan(options) {
+            match plan.start() {
+                Ok(Some((source, found))) => devices.extend(provider.attach(plan.host.clone(), source, found)),
+                Ok(None) => {}
+                Err(e) => match plan.host {
+                    // Hosts which are not available are skipped
+                    Some(ref host) => warn!("Unable to get devices of the {} host: {}", host, e),
+                    None => return Err(e),
+                },
+            }
+        }
+
+        devices.sort_by(|a, b| options.order.compare(a, b));
+        provider.selection.resolve(&devices);
+        devices.retain(|device| provider.selection.accepts(device));
+
+        Ok((provider, devices))
+    }
+
+    /// Backends to start for the `options`: the requested one or the remote hosts, then the peripherals enabled
+    pub fn plan(options: &Options) -> Vec<Plan> {
+        let plan = |host: Option<&String>, planned| Plan {
+            host: host.cloned(),
+            planned,
+            options: options.clone(),
+        };
+
+        let mut plans = Vec::new();
+        if options.remotes.is_empty() {
+            plans.push(plan(None, Planned::Main));
+        }
+        for host in options.remotes.iter() {
+            if host == LOCAL_HOST {
+                plans.push(plan(Some(host), Planned::Main));
+            } else {
+                plans.push(plan(Some(host), Planned::Remote(host.clone())));
+            }
+        }
+        if options.bluetooth {
+            plans.push(plan(None, Planned::Bluez));
+        }
+        if options.adb {
+            plans.push(plan(None, Planned::Adb));
+        }
+        if options.hid {
+            plans.push(plan(None, Planned::Hid));
+            plans.push(plan(None, Planned::Hidpp));
+        }
+        if let Some(ref path) = options.smbus {
+            plans.push(plan(None, Planned::Smbus(path.clone())));
+        }
+        for path in options.vedirect.iter() {
+            plans.push(plan(None, Planned::Vedirect(path.clone())));
+        }
+
+        plans
+    }
+
+    /// Provider running the one backend of the `plan`, which devices are referring it by `index`
+    pub fn start_planned(index: usize, plan: &Plan) -> Result<(Provider, Vec<Device>)> {
+        let _span = debug_span!("enumerate", host = plan.host().unwrap_or(LOCAL_HOST)).entered();
+        let mut provider = Provider::empty(Selection::default(), index);
+        let devices = match plan.start()? {
+            Some((source, found)) => provider.attach(plan.host.clone(), source, found),
+            None => Vec::new(),
+        };
+
+        Ok((provider, devices))
+    }
+
+    /// Provider with the given backend only, referred by `index`, ex. the `Scripted` one in tests;
+    /// backend is skipped if it is not available
+    pub fn with_source(index: usize, mut source: Box<dyn PowerSource>) -> (Provider, Vec<Device>) {
+        let mut provider = Provider::empty(Selection::default(), index);
+        let devices = match source.devices() {
+            Ok(found) => provider.attach(None, source, found),
+            Err(e) => {
+                warn!("Unable to list {} devices: {}", source.name(), e);
+                Vec::new()
+            }
+        };
+
+        (provider, devices)
+    }
+
+    /// Devices to accept from the rescans, when the selection is resolved out of the devices of all the providers
+    pub fn select(&mut self, selection: Selection) {
+        self.selection = selection;
+    }
+
+    /// Enumerate all devices again, including the ones connected after the start
+    pub fn rescan(&mut self) -> Vec<Device> {
+        let _span = debug_span!("rescan").entered();
+        let mut devices = Vec::new();
+        for (index, attached) in self.sources.iter_mut().enumerate() {
+            let _span = debug_span!("enumerate", source = attached.source.name()).entered();
+            match attached.source.devices() {
+                Ok(found) => devices.extend(adopt(self.first + index, attached, found)),
+                Err(e) => warn!("Unable to list {} devices: {}", attached.source.name(), e),
+            }
+        }
+        devices.retain(|device| self.selection.accepts(device));
+
+        devices
+    }
+
+    /// Index past the last backend started, devices are referring them by their `origin` index
+    pub fn source_count(&self) -> usize {
+        self.first + self.sources.len()
+    }
+
+    /// Minimal polling interval of the backend at `index`, see `PowerSource::interval`
+    pub fn interval(&self, index: usize) -> Option<Duration> {
+        index
+            .checked_sub(self.first)
+            .and_then(|index| self.sources.get(index))
+            .and_then(|attached| attached.source.interval())
+    }
+
+    /// Whether the backend at `index` is enumerated again on the uevents only, see `PowerSource::hotplug`
+    pub fn hotplug(&self, index: usize) -> bool {
+        index
+            .checked_sub(self.first)
+            .and_then(|index| self.sources.get(index))
+            .is_some_and(|attached| attached.source.hotplug())
+    }
+
+    pub fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let attached = match device.origin.checked_sub(self.first) {
+            Some(index) => self.sources.get_mut(index),
+            None => None,
+        };
+        match attached {
+            Some(attached) => {
+                let _span = trace_span!("refresh", device = %device.id()).entered();
+                attached.source.refresh(device)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn empty(selection: Selection, first: usize) -> Provider {
+        Provider {
+            sources: Vec::new(),
+            selection,
+            first,
+        }
+    }
+
+    fn attach(&mut self, host: Option<String>, source: Box<dyn PowerSource>, devices: Vec<Device>) -> Vec<Device> {
+        let index = self.sources.len();
+        self.sources.push(Attached {
+            host,
+            source,
+        });
+
+        adopt(self.first + index, &self.sources[index], devices)
+    }
+
+    #[cfg(feature = "remote")]
+    fn remote(host: &str) -> Result<(Box<dyn PowerSource>, Vec<Device>)> {
+        let mut remote = Remote::new(host);
+        remote
+            .devices()
+            .map(|found| (Box::new(remote) as Box<dyn PowerSource>, found))
+    }
+
+    #[cfg(not(feature = "remote"))]
+    fn remote(_host: &str) -> Result<(Box<dyn PowerSource>, Vec<Device>)> {
+        Err(Error::Disabled("remote".to_string()))
+    }
+
+    #[cfg(feature = "bluez")]
+    fn bluez() -> Option<Box<dyn PowerSource>> {
+        Some(Box::new(Bluez::default()))
+    }
+
+    #[cfg(not(feature = "bluez"))]
+    fn bluez() -> Option<Box<dyn PowerSource>> {
+        warn!("battop was built without the Bluetooth support, rebuild it with the `bluez` feature enabled");
+        None
+    }
+
+    #[cfg(feature = "adb")]
+    fn adb() -> Option<Box<dyn PowerSource>> {
+        Some(Box::new(Adb))
+    }
+
+    #[cfg(not(feature = "adb"))]
+    fn adb() -> Option<Box<dyn PowerSource>> {
+        warn!("battop was built without the Android support, rebuild it with the `adb` feature enabled");
+        None
+    }
+
+    #[cfg(all(target_os = "linux", feature = "smbus"))]
+    fn smbus(path: &str) -> Option<Box<dyn PowerSource>> {
+        Some(Box::new(Smbus::new(path)))
+    }
+
+    #[cfg(all(target_os = "linux", not(feature = "smbus")))]
+    fn smbus(_path: &str) -> Option<Box<dyn PowerSource>> {
+        warn!("battop was built without the SMBus support, rebuild it with the `smbus` feature enabled");
+        None
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn smbus(_path: &str) -> Option<Box<dyn PowerSource>> {
+        warn!("Reading Smart Battery over SMBus is supported on Linux only");
+        None
+    }
+
+    #[cfg(all(target_os = "linux", feature = "vedirect"))]
+    fn vedirect(path: &str) -> Option<Box<dyn PowerSource>> {
+        Some(Box::new(Vedirect::new(path)))
+    }
+
+    #[cfg(all(target_os = "linux", not(feature = "vedirect")))]
+    fn vedirect(_path: &str) -> Option<Box<dyn PowerSource>> {
+        warn!("battop was built without the VE.Direct support, rebuild it with the `vedirect` feature enabled");
+        None
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn vedirect(_path: &str) -> Option<Box<dyn PowerSource>> {
+        warn!("Reading VE.Direct devices is supported on Linux only");
+        None
+    }
+
+    /// Generic HID batteries
+    #[cfg(feature = "hid")]
+    fn hid() -> Option<Box<dyn PowerSource>> {
+        Some(Box::new(Hid))
+    }
+
+    #[cfg(not(feature = "hid"))]
+    fn hid() -> Option<Box<dyn PowerSource>> {
+        warn!("battop was built without the HID support, rebuild it with the `hid` feature enabled");
+        None
+    }
+
+    /// Logitech HID++ devices
+    #[cfg(feature = "hid")]
+    fn hidpp() -> Option<Box<dyn PowerSource>> {
+        Some(Box::new(Hidpp))
+    }
+
+    /// Already warned about by `hid`
+    #[cfg(not(feature = "hid"))]
+    fn hidpp() -> Option<Box<dyn PowerSource>> {
+        None
+    }
+
+    /// Start the backend requested by user, falling back to the other ones
+    /// if requested backend is not available or has no devices.
+    fn start_with_fallbacks(options: &Options) -> Result<(Box<dyn PowerSource>, Vec<Device>)> {
+        let mut started = None;
+        let mut error = None;
+        for backend in options.backend.with_fallbacks() {
+            match Provider::start(backend, options) {
+                Ok((source, devices)) => {
+                    if !devices.is_empty() {
+                        return Ok((source, devices));
+                    }
+                    warn!("No devices found with the {} backend", backend);
+                    if started.is_none() {
+                        started = Some(source);
+                    }
+                }
+                Err(e) => {
+                    warn!("Unable to start the {} backend: {}", backend, e);
+                    if error.is_none() {
+                        error = Some(e);
+                    }
+                }
+            }
+        }
+
+        match (started, error) {
+            (Some(source), _) => Ok((source, Vec::new())),
+            (None, Some(e)) => Err(e),
+            (None, None) => Err(Error::NoBatteries),
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "nut", feature = "daemon")), allow(unused_variables))]
+    fn start(backend: Backend, options: &Options) -> Result<(Box<dyn PowerSource>, Vec<Device>)> {
+        trace!("Starting the {} backend", backend);
+        let mut source: Box<dyn PowerSource> = match backend {
+            #[cfg(feature = "battery-crate")]
+            Backend::Battery => Box::new(BatteryCrate::new()?),
+            #[cfg(feature = "sysfs")]
+            Backend::Sysfs => Box::new(Sysfs),
+            #[cfg(feature = "upower")]
+            Backend::UPower => Box::new(UPower),
+            #[cfg(feature = "nut")]
+            Backend::Nut => Box::new(Nut::new(&options.nut_server)),
+            #[cfg(feature = "bsd")]
+            Backend::Bsd => Box::new(Bsd),
+            Backend::Demo => Box::new(Demo),
+            #[cfg(all(unix, feature = "daemon"))]
+            Backend::Daemon => Box::new(Daemon::connect(&options.socket.clone().unwrap_or_else(default_socket))?),
+            #[allow(unreachable_patterns)]
+            backend => return Err(Error::Disabled(backend.feature().to_string())),
+        };
+        let devices = source.devices()?;
+
+        Ok((source, devices))
+    }
+}
+
+/// Mark devices as the ones provided by the backend at `index`
+fn adopt(index: usize, attached: &Attached, devices: Vec<Device>) -> Vec<Device> {
+    let capabilities = attached.source.capabilities();
+
+    devices
+        .into_iter()
+        .map(|mut device| {
+            device.origin = index;
+            // Scripted devices are telling their host themselves
+            if attached.host.is_some() {
+                device.host = attached.host.clone();
+            }
+            device.capabilities = capabilities;
+            device
+        })
+        .collect()
+}
@@ -0,0 +1,172 @@
+//! Batteries of the remote machine, collected over SSH.
+//!
+//! Small shell script dumps the `/sys/class/power_supply` of the remote Linux host,
+//! so nothing has to be installed there; output is parsed the same way as the local sysfs.
+
+use std::collections::HashMap;
+use std::io;
+use std::time::{Duration, Instant};
+
+use super::sysfs::{parse_uevent, EXTRA_ATTRIBUTES};
+use super::{Capabilities, Device, DeviceKind, PowerSource, Source};
+use crate::command;
+use crate::Result;
+
+/// All devices are refreshed one after another during the same tick,
+/// so one snapshot of the remote host is enough for all of them
+const SNAPSHOT_TTL: Duration = Duration::from_millis(500);
+
+/// Power supply of the remote host: its kind and the `uevent` properties
+type Supply = (DeviceKind, HashMap<String, String>);
+
+#[derive(Debug)]
+pub struct Remote {
+    /// `ssh` destination, ex. `user@host`
+    host: String,
+    snapshot: Option<(Instant, HashMap<String, Supply>)>,
+}
+
+impl Remote {
+    pub fn new(host: &str) -> Remote {
+        Remote {
+            host: host.to_string(),
+            snapshot: None,
+        }
+    }
+
+    fn snapshot(&mut self) -> io::Result<&HashMap<String, Supply>> {
+        let fresh = match self.snapshot {
+            Some((taken, _)) => taken.elapsed() < SNAPSHOT_TTL,
+            None => false,
+        };
+        if !fresh {
+            let output = self.collect()?;
+            self.snapshot = Some((Instant::now(), parse_collected(&output)));
+        }
+
+        match self.snapshot {
+            Some((_, ref supplies)) => Ok(supplies),
+            None => unreachable!(),
+        }
+    }
+
+    fn collect(&self) -> io::Result<String> {
+        let files = EXTRA_ATTRIBUTES
+            .iter()
+            .map(|(file, _, _)| *file)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let script = format!(
+            "cd /sys/class/power_supply || exit 1; \
+             for supply in *; do \
+             echo \"@$supply $(cat $supply/type)\"; cat $supply/uevent; \
+             for file in {}; do [ -r $supply/$file ] && echo \"$file=$(cat $supply/$file)\"; done; \
+             done; true",
+            files
+        );
+
+        let output = command::new("ssh")
+            .args(["-o", "BatchMode=yes", "-o", "ConnectTimeout=5", &self.host, &script])
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "ssh {} exited with {}: {}",
+                self.host,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl PowerSource for Remote {
+    fn name(&self) -> &str {
+        &self.host
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let supplies = self.snapshot()?;
+        let mut names = supplies.keys().collect::<Vec<_>>();
+        names.sort();
+
+        let devices = names
+            .into_iter()
+            .map(|name| {
+                let (kind, ref props) = supplies[name];
+                let mut device = Device::empty(Source::Remote(name.clone()), kind);
+                device.update_sysfs(props);
+                device
+            })
+            .collect::<Vec<_>>();
+        trace!("Remote host {} reported {} devices", self.host, devices.len());
+
+        Ok(devices)
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let name = match device.source {
+            Source::Remote(ref name) => name.clone(),
+            _ => return Ok(()),
+        };
+        let host = self.host.clone();
+        match self.snapshot()?.get(&name) {
+            Some((_, props)) => {
+                device.update_sysfs(props);
+                Ok(())
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Power supply {} disappeared from {}", name, host),
+            )
+            .into()),
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATTERY
+    }
+
+    /// Each refresh is an SSH round trip
+    fn interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(5))
+    }
+}
+
+/// Split the collector output into the power supplies, AC adapters are skipped.
+///
+/// ```text
+/// @BAT0 Battery
+/// POWER_SUPPLY_STATUS=Discharging
+/// charge_control_end_threshold=80
+/// ```
+fn parse_collected(output: &str) -> HashMap<String, Supply> {
+    let mut supplies = HashMap::new();
+    let mut current: Option<(String, Option<DeviceKind>, String)> = None;
+
+    for line in output.lines().chain(Some("@")) {
+        if !line.starts_with('@') {
+            if let Some((_, _, ref mut body)) = current {
+                body.push_str(line);
+                body.push('\n');
+            }
+            continue;
+        }
+
+        if let Some((name, Some(kind), body)) = current.take() {
+            supplies.insert(name, (kind, parse_uevent(&body)));
+        }
+        let mut header = line[1..].split_whitespace();
+        if let Some(name) = header.next() {
+            let kind = match header.next() {
+                Some("Battery") => Some(DeviceKind::Battery),
+                Some("UPS") => Some(DeviceKind::Ups),
+                _ => None,
+            };
+            current = Some((name.to_string(), kind, String::new()));
+        }
+    }
+
+    supplies
+}
@@ -0,0 +1,146 @@
+//! Battery replaying the readings given upfront, for the tests of everything built on top of the backends.
+//!
+//! Each refresh moves to the next step, the last one is repeated once the script is over.
+//! Devices are reported as attached to the `scripted` host, so the frontends are not mixing
+//! the readings of this machine, ex. the CPU power or the adapter, into them.
+//!
+//! ```
+//! use battop_core::device::{PowerSource, Reading, Scripted};
+//!
+//! let mut source = Scripted::new("Test battery", 50.0, vec![Some(Reading::discharging(0.5, 10.0)), None]);
+//! let mut devices = source.devices()?;
+//! assert!(source.refresh(&mut devices[0]).is_err());
+//! # Ok::<(), battop_core::Error>(())
+//! ```
+
+use std::io;
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::watt_hour;
+use battery::units::power::watt;
+use battery::units::ratio::ratio;
+use battery::units::thermodynamic_temperature::degree_celsius;
+use battery::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature};
+use battery::{State, Technology};
+
+use super::{Capabilities, Device, DeviceKind, PowerSource, Source};
+use crate::{Error, Result};
+
+const HOST: &str = "scripted";
+
+/// Battery state at one refresh
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub state: State,
+    /// Ratio, 0–1
+    pub state_of_charge: f32,
+    /// W
+    pub energy_rate: f32,
+    /// V
+    pub voltage: f32,
+    /// °C
+    pub temperature: Option<f32>,
+}
+
+impl Reading {
+    /// Discharging with the `power` (W), voltage is following the state of charge
+    pub fn discharging(state_of_charge: f32, power: f32) -> Reading {
+        Reading {
+            state: State::Discharging,
+            state_of_charge,
+            energy_rate: power,
+            voltage: 10.8 + 1.8 * state_of_charge,
+            temperature: Some(30.0),
+        }
+    }
+
+    /// Charging with the `power` (W)
+    pub fn charging(state_of_charge: f32, power: f32) -> Reading {
+        Reading {
+            state: State::Charging,
+            ..Reading::discharging(state_of_charge, power)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Scripted {
+    model: String,
+    /// W·h
+    full_capacity: f32,
+    /// `None` steps are failing the refresh, as if the device was gone
+    steps: Vec<Option<Reading>>,
+}
+
+impl Scripted {
+    /// Battery going through the `steps`, the first one is read when the device is enumerated
+    pub fn new(model: &str, full_capacity: f32, steps: Vec<Option<Reading>>) -> Scripted {
+        Scripted {
+            model: model.to_string(),
+            full_capacity,
+            steps,
+        }
+    }
+
+    /// Step at `index` applied to the `device`
+    fn apply(&self, index: usize, device: &mut Device) -> Result<()> {
+        let last = self.steps.len().saturating_sub(1);
+        let reading = match self.steps.get(index.min(last)) {
+            Some(Some(reading)) => reading,
+            _ => {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is gone at the step {}", self.model, index),
+                )))
+            }
+        };
+
+        device.state = reading.state;
+        device.state_of_charge = Ratio::new::<ratio>(reading.state_of_charge);
+        device.energy = Energy::new::<watt_hour>(self.full_capacity * reading.state_of_charge);
+        device.energy_rate = Power::new::<watt>(reading.energy_rate);
+        device.voltage = ElectricPotential::new::<volt>(reading.voltage);
+        device.temperature = reading
+            .temperature
+            .map(ThermodynamicTemperature::new::<degree_celsius>);
+
+        Ok(())
+    }
+}
+
+impl PowerSource for Scripted {
+    fn name(&self) -> &str {
+        "scripted"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let mut device = Device::empty(Source::Scripted(0), DeviceKind::Battery);
+        device.host = Some(HOST.to_string());
+        device.vendor = Some("battop".to_string());
+        device.model = Some(self.model.clone());
+        device.serial_number = Some(self.model.to_lowercase().replace(' ', "-"));
+        device.technology = Technology::LithiumIon;
+        device.energy_full = Energy::new::<watt_hour>(self.full_capacity);
+        device.energy_full_design = Energy::new::<watt_hour>(self.full_capacity);
+        device.state_of_health = Ratio::new::<ratio>(1.0);
+        self.apply(0, &mut device)?;
+
+        Ok(vec![device])
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let step = match device.source {
+            Source::Scripted(ref mut step) => {
+                *step += 1;
+                *step
+            }
+            _ => return Ok(()),
+        };
+
+        self.apply(step, device)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATTERY
+    }
+}
@@ -0,0 +1,102 @@
+//! Devices the user asked for, by their ids, serial numbers or positions.
+//!
+//! Positions are written as `#2`, so the serial numbers made of digits are not read as ones.
+
+use super::Device;
+
+/// Devices to collect, all of them by default
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Selection {
+    /// Device ids, serial numbers or the `#` positions in the list of devices found on start,
+    /// as sorted by the `Order`, starting from `#1`; if any are given, the rest of the devices are skipped
+    pub include: Vec<String>,
+    /// Same as `include`, for the devices to skip
+    pub exclude: Vec<String>,
+}
+
+impl Selection {
+    /// Replace the positions with the ids of the devices found on start, already sorted,
+    /// so the devices connected later are not shifting them
+    pub fn resolve(&mut self, devices: &[Device]) {
+        for patterns in [&mut self.include, &mut self.exclude].iter_mut() {
+            for pattern in patterns.iter_mut() {
+                let position = match pattern.strip_prefix('#').and_then(|position| position.parse::<usize>().ok()) {
+                    Some(position) => position,
+                    None => continue,
+                };
+                match position.checked_sub(1).and_then(|index| devices.get(index)) {
+                    Some(device) => *pattern = device.id(),
+                    None => warn!("There is no device #{}, only {} were found", position, devices.len()),
+                }
+            }
+        }
+    }
+
+    pub fn accepts(&self, device: &Device) -> bool {
+        let id = device.id();
+        let matches = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|pattern| *pattern == id || device.serial_number() == Some(pattern.as_str()))
+        };
+
+        (self.include.is_empty() || matches(&self.include)) && !matches(&self.exclude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Selection;
+    use crate::device::{PowerSource, Reading, Scripted};
+
+    fn selection(include: &[&str], exclude: &[&str]) -> Selection {
+        Selection {
+            include: include.iter().map(|pattern| pattern.to_string()).collect(),
+            exclude: exclude.iter().map(|pattern| pattern.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn positions_are_kept_for_the_devices_found_later() {
+        let devices = ["Main", "UPS", "Mouse"]
+            .iter()
+            .flat_map(|model| Scripted::new(model, 50.0, vec![Some(Reading::discharging(0.5, 10.0))]).devices().unwrap())
+            .collect::<Vec<_>>();
+
+        let mut selection = selection(&["#2", "mouse", "#4"], &[]);
+        selection.resolve(&devices);
+        assert_eq!(selection.include, vec!["scripted:ups@scripted", "mouse", "#4"]);
+        let accepted = devices.iter().filter(|device| selection.accepts(device)).count();
+        assert_eq!(accepted, 2);
+
+        // Positions are referring the first enumeration only
+        selection.resolve(&devices[2..]);
+        assert!(selection.accepts(&devices[1]));
+    }
+
+    #[test]
+    fn excluded_devices_are_skipped() {
+        let device = Scripted::new("UPS", 50.0, vec![Some(Reading::discharging(0.5, 10.0))])
+            .devices()
+            .unwrap()
+            .remove(0);
+
+        assert!(Selection::default().accepts(&device));
+        assert!(!selection(&[], &["ups"]).accepts(&device));
+        assert!(!selection(&["scripted:ups@scripted"], &["scripted:ups@scripted"]).accepts(&device));
+        assert!(!selection(&["main"], &[]).accepts(&device));
+    }
+
+    #[test]
+    fn numeric_serial_numbers_are_not_positions() {
+        // Serial number of the scripted battery is its model
+        let devices = Scripted::new("2", 50.0, vec![Some(Reading::discharging(0.5, 10.0))])
+            .devices()
+            .unwrap();
+
+        let mut selection = selection(&["2"], &["#2"]);
+        selection.resolve(&devices);
+        assert_eq!(selection.include, vec!["2"]);
+        assert!(selection.accepts(&devices[0]));
+    }
+}
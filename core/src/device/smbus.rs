@@ -0,0 +1,270 @@
+//! Smart Battery System registers, read directly from the pack over the Linux `i2c-dev` SMBus interface.
+//!
+//! ACPI firmware frequently truncates or rounds what the battery gauge knows,
+//! and is not exposing the cell voltages or the manufacture date at all.
+//! Only the read commands are ever sent, and only when user asks for it explicitly.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::watt_hour;
+use battery::units::power::watt;
+use battery::units::ratio::{percent, ratio};
+use battery::units::thermodynamic_temperature::kelvin;
+use battery::units::time::second;
+use battery::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time};
+use battery::{State, Technology};
+
+use super::identity::Date;
+use super::{Capabilities, Device, DeviceKind, PowerSource, Source};
+use crate::Result;
+
+/// Smart Battery is always at this SMBus address
+const ADDRESS: libc::c_ulong = 0x0b;
+
+// See `linux/i2c-dev.h` and `linux/i2c.h`
+const I2C_SLAVE: libc::c_ulong = 0x0703;
+const I2C_SMBUS: libc::c_ulong = 0x0720;
+const I2C_SMBUS_READ: u8 = 1;
+const I2C_SMBUS_WORD_DATA: u32 = 3;
+const I2C_SMBUS_BLOCK_DATA: u32 = 5;
+const I2C_SMBUS_BLOCK_MAX: usize = 32;
+
+// Smart Battery Data Specification 1.1 registers
+const BATTERY_MODE: u8 = 0x03;
+const TEMPERATURE: u8 = 0x08;
+const VOLTAGE: u8 = 0x09;
+const CURRENT: u8 = 0x0a;
+const RELATIVE_STATE_OF_CHARGE: u8 = 0x0d;
+const REMAINING_CAPACITY: u8 = 0x0f;
+const FULL_CHARGE_CAPACITY: u8 = 0x10;
+const RUN_TIME_TO_EMPTY: u8 = 0x11;
+const AVERAGE_TIME_TO_FULL: u8 = 0x13;
+const BATTERY_STATUS: u8 = 0x16;
+const CYCLE_COUNT: u8 = 0x17;
+const DESIGN_CAPACITY: u8 = 0x18;
+const DESIGN_VOLTAGE: u8 = 0x19;
+const MANUFACTURE_DATE: u8 = 0x1b;
+const SERIAL_NUMBER: u8 = 0x1c;
+const MANUFACTURER_NAME: u8 = 0x20;
+const DEVICE_NAME: u8 = 0x21;
+const DEVICE_CHEMISTRY: u8 = 0x22;
+/// Optional manufacturer-specific registers of the popular gauges, cell 4 down to the cell 1
+const CELL_VOLTAGES: [u8; 4] = [0x3c, 0x3d, 0x3e, 0x3f];
+
+/// Capacities are reported in 10 mW·h units instead of mA·h
+const CAPACITY_MODE: u16 = 1 << 15;
+const FULLY_CHARGED: u16 = 1 << 5;
+/// Time registers are using this value for "not applicable"
+const NOT_APPLICABLE: u16 = 0xffff;
+
+#[derive(Debug)]
+pub struct Smbus {
+    /// `i2c-dev` adapter device, ex. `/dev/i2c-3`
+    path: PathBuf,
+}
+
+impl Smbus {
+    pub fn new(path: &str) -> Smbus {
+        Smbus {
+            path: PathBuf::from(path),
+        }
+    }
+}
+
+impl PowerSource for Smbus {
+    fn name(&self) -> &str {
+        "SMBus"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let bus = Bus::open(&self.path)?;
+        let mut device = Device::empty(Source::Smbus(self.path.clone()), DeviceKind::Battery);
+        device.read_identity(&bus);
+        device.read_smbus(&bus)?;
+
+        Ok(vec![device])
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let bus = Bus::open(&self.path)?;
+        device.read_smbus(&bus)?;
+
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATTERY
+    }
+}
+
+/// Subset of the `i2c_smbus_ioctl_data` struct and `i2c_smbus_data` union
+#[repr(C)]
+struct IoctlData {
+    read_write: u8,
+    command: u8,
+    size: u32,
+    data: *mut [u8; I2C_SMBUS_BLOCK_MAX + 2],
+}
+
+/// SMBus adapter with the Smart Battery selected
+struct Bus {
+    file: File,
+}
+
+impl Bus {
+    fn open(path: &Path) -> io::Result<Bus> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        // Address is passed as the ioctl argument itself
+        if unsafe { libc::ioctl(file.as_raw_fd(), I2C_SLAVE as _, ADDRESS) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Bus {
+            file,
+        })
+    }
+
+    fn read(&self, command: u8, size: u32) -> io::Result<[u8; I2C_SMBUS_BLOCK_MAX + 2]> {
+        let mut data = [0u8; I2C_SMBUS_BLOCK_MAX + 2];
+        let mut args = IoctlData {
+            read_write: I2C_SMBUS_READ,
+            command,
+            size,
+            data: &mut data,
+        };
+        if unsafe { libc::ioctl(self.file.as_raw_fd(), I2C_SMBUS as _, &mut args) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(data)
+    }
+
+    fn word(&self, command: u8) -> io::Result<u16> {
+        let data = self.read(command, I2C_SMBUS_WORD_DATA)?;
+
+        Ok(u16::from(data[0]) | u16::from(data[1]) << 8)
+    }
+
+    /// Block read, first byte is the length
+    fn string(&self, command: u8) -> io::Result<String> {
+        let data = self.read(command, I2C_SMBUS_BLOCK_DATA)?;
+        let length = usize::from(data[0]).min(I2C_SMBUS_BLOCK_MAX);
+        let value = String::from_utf8_lossy(&data[1..=length]);
+
+        Ok(value.trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string())
+    }
+}
+
+impl Device {
+    /// Values which are not changing, so there is no need to read them on each refresh
+    fn read_identity(&mut self, bus: &Bus) {
+        let text = |command| bus.string(command).ok().filter(|value| !value.is_empty());
+        self.vendor = text(MANUFACTURER_NAME);
+        self.model = text(DEVICE_NAME);
+        self.serial_number = bus.word(SERIAL_NUMBER).ok().map(|serial| serial.to_string());
+        let chemistry = text(DEVICE_CHEMISTRY);
+        self.technology = chemistry
+            .as_ref()
+            .map(|chemistry| parse_chemistry(chemistry))
+            .unwrap_or(Technology::Unknown);
+        self.identity.chemistry = chemistry;
+        self.identity.manufactured = bus.word(MANUFACTURE_DATE).ok().and_then(Date::from_sbs);
+    }
+
+    fn read_smbus(&mut self, bus: &Bus) -> io::Result<()> {
+        // Voltage is mandatory, so a failure here means there is no Smart Battery at all
+        let voltage = f32::from(bus.word(VOLTAGE)?) / 1000.0;
+        let current = f32::from(bus.word(CURRENT)? as i16) / 1000.0;
+        let design_voltage = bus
+            .word(DESIGN_VOLTAGE)
+            .map(|value| f32::from(value) / 1000.0)
+            .unwrap_or(voltage);
+        let energy_mode = bus
+            .word(BATTERY_MODE)
+            .map(|mode| mode & CAPACITY_MODE != 0)
+            .unwrap_or(false);
+        let capacity = |command| {
+            bus.word(command).ok().map(|value| {
+                if energy_mode {
+                    f32::from(value) / 100.0
+                } else {
+                    // Charge is converted to energy with the design voltage
+                    f32::from(value) / 1000.0 * design_voltage
+                }
+            })
+        };
+        let energy_now = capacity(REMAINING_CAPACITY).unwrap_or(0.0);
+        let energy_full = capacity(FULL_CHARGE_CAPACITY).unwrap_or(0.0);
+        let energy_full_design = capacity(DESIGN_CAPACITY).unwrap_or(0.0);
+        let minutes = |command| {
+            bus.word(command)
+                .ok()
+                .filter(|value| *value != NOT_APPLICABLE)
+                .map(|value| Time::new::<second>(f32::from(value) * 60.0))
+        };
+        let status = bus.word(BATTERY_STATUS).unwrap_or(0);
+
+        // Current is positive while charging
+        self.state = match current {
+            _ if status & FULLY_CHARGED != 0 => State::Full,
+            current if current > 0.0 => State::Charging,
+            current if current < 0.0 => State::Discharging,
+            _ => State::Unknown,
+        };
+        self.voltage = ElectricPotential::new::<volt>(voltage);
+        self.energy_rate = Power::new::<watt>((current * voltage).abs());
+        self.energy = Energy::new::<watt_hour>(energy_now);
+        self.energy_full = Energy::new::<watt_hour>(energy_full);
+        self.energy_full_design = Energy::new::<watt_hour>(energy_full_design);
+        self.state_of_charge = match bus.word(RELATIVE_STATE_OF_CHARGE) {
+            Ok(level) => Ratio::new::<percent>(f32::from(level)),
+            Err(_) if energy_full > 0.0 => Ratio::new::<ratio>(energy_now / energy_full),
+            Err(_) => Ratio::new::<ratio>(0.0),
+        };
+        self.state_of_health = if energy_full_design > 0.0 {
+            Ratio::new::<ratio>(energy_full / energy_full_design)
+        } else {
+            Ratio::new::<ratio>(0.0)
+        };
+        // 0.1 K units
+        self.temperature = bus
+            .word(TEMPERATURE)
+            .ok()
+            .map(|value| ThermodynamicTemperature::new::<kelvin>(f32::from(value) / 10.0));
+        self.cycle_count = bus.word(CYCLE_COUNT).ok().map(u32::from).filter(|cycles| *cycles > 0);
+        self.time_to_empty = match self.state {
+            State::Discharging => minutes(RUN_TIME_TO_EMPTY),
+            _ => None,
+        };
+        self.time_to_full = match self.state {
+            State::Charging => minutes(AVERAGE_TIME_TO_FULL),
+            _ => None,
+        };
+        // Unpopulated cells are reading as zero
+        self.cell_voltages = CELL_VOLTAGES
+            .iter()
+            .rev()
+            .filter_map(|command| bus.word(*command).ok())
+            .filter(|value| *value > 0)
+            .map(|value| f32::from(value) / 1000.0)
+            .collect();
+
+        Ok(())
+    }
+}
+
+/// `DeviceChemistry` register value, ex. `LION`
+fn parse_chemistry(value: &str) -> Technology {
+    match value.to_ascii_uppercase().as_str() {
+        "LION" | "LI-ION" => Technology::LithiumIon,
+        "LIP" | "LIPO" | "LI-PO" => Technology::LithiumPolymer,
+        "PBAC" => Technology::LeadAcid,
+        "NICD" => Technology::NickelCadmium,
+        "NIMH" => Technology::NickelMetalHydride,
+        _ => Technology::Unknown,
+    }
+}
@@ -0,0 +1,269 @@
+//! Native Linux backend, reading the `/sys/class/power_supply` directly.
+//!
+//! Whole device state is read at once from the `uevent` file, so values are consistent
+//! with each other, and the attributes the `battery` crate drops are exposed as well.
+//!
+//! The `uevent` parsing is shared with the `remote` backend, and the power supply lookup
+//! with the charge limits, so only the backend itself is behind the `sysfs` feature.
+
+use std::collections::HashMap;
+use std::fs;
+#[cfg(feature = "sysfs")]
+use std::io;
+#[cfg(feature = "sysfs")]
+use std::path::Path;
+use std::path::PathBuf;
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::watt_hour;
+use battery::units::power::watt;
+use battery::units::ratio::{percent, ratio};
+use battery::units::thermodynamic_temperature::degree_celsius;
+use battery::units::time::second;
+use battery::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time};
+use battery::{State, Technology};
+
+#[cfg(feature = "sysfs")]
+use super::{Capabilities, PowerSource};
+#[cfg(any(feature = "battery-crate", feature = "sysfs", feature = "upower"))]
+use super::Source;
+use super::{Device, DeviceKind};
+#[cfg(feature = "sysfs")]
+use crate::Result;
+
+const POWER_SUPPLY: &str = "/sys/class/power_supply";
+
+/// Attributes which are not the part of `uevent`, but are worth showing
+pub const EXTRA_ATTRIBUTES: [(&str, &str, &str); 3] = [
+    ("charge_control_start_threshold", "Charge start at", " %"),
+    ("charge_control_end_threshold", "Charge stop at", " %"),
+    ("charge_behaviour", "Charge behaviour", ""),
+];
+
+#[cfg(feature = "sysfs")]
+#[derive(Debug)]
+pub struct Sysfs;
+
+#[cfg(feature = "sysfs")]
+impl PowerSource for Sysfs {
+    fn name(&self) -> &str {
+        "sysfs"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let mut devices = Vec::new();
+        for entry in fs::read_dir(POWER_SUPPLY)?.flatten() {
+            let path = entry.path();
+            let kind = match fs::read_to_string(path.join("type")) {
+                Ok(ref kind) if kind.trim() == "Battery" => DeviceKind::Battery,
+                Ok(ref kind) if kind.trim() == "UPS" => DeviceKind::Ups,
+                _ => continue,
+            };
+
+            let mut device = Device::empty(Source::Sysfs(path.clone()), kind);
+            match device.read_sysfs(&path) {
+                Ok(()) => devices.push(device),
+                Err(e) => warn!("Unable to read power supply {:?}: {}", path, e),
+            }
+        }
+        trace!("Found {} power supplies in {}", devices.len(), POWER_SUPPLY);
+
+        Ok(devices)
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let path = match device.source {
+            Source::Sysfs(ref path) => path.clone(),
+            _ => return Ok(()),
+        };
+        device.read_sysfs(&path)?;
+
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATTERY
+    }
+
+    fn hotplug(&self) -> bool {
+        true
+    }
+}
+
+impl Device {
+    /// `/sys/class/power_supply` directory of this battery, for the local devices only.
+    ///
+    /// Other local backends are not exposing the device path, so battery is matched
+    /// by its serial number, or it is assumed to be the only battery of the system.
+    pub fn power_supply(&self) -> Option<PathBuf> {
+        if self.kind != DeviceKind::Battery || self.host.is_some() {
+            return None;
+        }
+
+        let lookup = match self.source {
+            #[cfg(feature = "sysfs")]
+            Source::Sysfs(ref path) => return Some(path.clone()),
+            #[cfg(feature = "battery-crate")]
+            Source::Battery(_) => true,
+            #[cfg(feature = "upower")]
+            Source::UPower(_) => true,
+            _ => false,
+        };
+        if !lookup {
+            return None;
+        }
+
+        let batteries = fs::read_dir(POWER_SUPPLY)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| match fs::read_to_string(path.join("type")) {
+                Ok(kind) => kind.trim() == "Battery",
+                Err(_) => false,
+            })
+            .collect::<Vec<_>>();
+        let serial = self.serial_number();
+        let found = batteries.iter().find(|path| {
+            serial.is_some()
+                && fs::read_to_string(path.join("serial_number"))
+                    .ok()
+                    .as_ref()
+                    .map(|value| value.trim())
+                    == serial
+        });
+        match found {
+            Some(path) => Some(path.clone()),
+            None if batteries.len() == 1 => batteries.into_iter().next(),
+            None => None,
+        }
+    }
+
+    #[cfg(feature = "sysfs")]
+    fn read_sysfs(&mut self, path: &Path) -> io::Result<()> {
+        let mut props = parse_uevent(&fs::read_to_string(path.join("uevent"))?);
+        for (file, _, _) in EXTRA_ATTRIBUTES.iter() {
+            if let Ok(value) = fs::read_to_string(path.join(file)) {
+                props.insert(file.to_string(), value.trim().to_string());
+            }
+        }
+        self.update_sysfs(&props);
+        self.augment_cros_ec();
+
+        Ok(())
+    }
+
+    /// Update device with the `uevent` properties and the extra attributes, keyed by their file names
+    pub fn update_sysfs(&mut self, props: &HashMap<String, String>) {
+        let get = |key: &str| props.get(key).map(String::as_str);
+        // Values are in µV, µW, µW·h, µA and µA·h
+        let micro = |key: &str| {
+            get(key)
+                .and_then(|value| value.parse::<f32>().ok())
+                .map(|value| value / 1_000_000.0)
+        };
+
+        if get("SCOPE") == Some("Device") {
+            // Peripheral batteries, ex. wireless mice
+            self.kind = DeviceKind::Other;
+        }
+        self.vendor = get("MANUFACTURER").map(str::to_string);
+        self.model = get("MODEL_NAME").map(str::to_string);
+        self.serial_number = get("SERIAL_NUMBER").map(str::to_string);
+        self.technology = get("TECHNOLOGY").map(parse_technology).unwrap_or(Technology::Unknown);
+
+        let voltage = micro("VOLTAGE_NOW").unwrap_or(0.0);
+        // Charge-based devices are converted to energy with the design voltage
+        let nominal = micro("VOLTAGE_MIN_DESIGN").unwrap_or(voltage);
+        let energy = |energy_key: &str, charge_key: &str| {
+            micro(energy_key)
+                .or_else(|| micro(charge_key).map(|charge| charge * nominal))
+                .unwrap_or(0.0)
+        };
+        let energy_now = energy("ENERGY_NOW", "CHARGE_NOW");
+        let energy_full = energy("ENERGY_FULL", "CHARGE_FULL");
+        let energy_full_design = energy("ENERGY_FULL_DESIGN", "CHARGE_FULL_DESIGN");
+        let power = micro("POWER_NOW")
+            .or_else(|| micro("CURRENT_NOW").map(|current| current * voltage))
+            .unwrap_or(0.0)
+            .abs();
+
+        self.state = get("STATUS").map(parse_state).unwrap_or(State::Unknown);
+        self.voltage = ElectricPotential::new::<volt>(voltage);
+        self.energy = Energy::new::<watt_hour>(energy_now);
+        self.energy_full = Energy::new::<watt_hour>(energy_full);
+        self.energy_full_design = Energy::new::<watt_hour>(energy_full_design);
+        self.energy_rate = Power::new::<watt>(power);
+        self.state_of_charge = match get("CAPACITY").and_then(|value| value.parse::<f32>().ok()) {
+            Some(capacity) => Ratio::new::<percent>(capacity),
+            None if energy_full > 0.0 => Ratio::new::<ratio>(energy_now / energy_full),
+            None => Ratio::new::<ratio>(0.0),
+        };
+        self.state_of_health = if energy_full_design > 0.0 {
+            Ratio::new::<ratio>(energy_full / energy_full_design)
+        } else {
+            Ratio::new::<ratio>(0.0)
+        };
+        self.temperature = get("TEMP")
+            .and_then(|value| value.parse::<f32>().ok())
+            .map(|temp| ThermodynamicTemperature::new::<degree_celsius>(temp / 10.0));
+        self.cycle_count = get("CYCLE_COUNT")
+            .and_then(|value| value.parse().ok())
+            .filter(|cycles| *cycles > 0);
+
+        let seconds = |key: &str| {
+            get(key)
+                .and_then(|value| value.parse::<f32>().ok())
+                .filter(|value| *value > 0.0)
+                .map(Time::new::<second>)
+        };
+        self.time_to_full = seconds("TIME_TO_FULL_NOW");
+        self.time_to_empty = seconds("TIME_TO_EMPTY_NOW");
+
+        let mut attributes = Vec::new();
+        if let Some(level) = get("CAPACITY_LEVEL") {
+            attributes.push(("Capacity level".to_string(), level.to_string()));
+        }
+        for (file, name, suffix) in EXTRA_ATTRIBUTES.iter() {
+            if let Some(value) = get(file) {
+                attributes.push((name.to_string(), format!("{}{}", value, suffix)));
+            }
+        }
+        self.attributes = attributes;
+    }
+}
+
+/// Parse the `uevent` file content, ex. `POWER_SUPPLY_ENERGY_NOW=30480000`, into the map without a prefix
+#[cfg(any(feature = "sysfs", feature = "remote"))]
+pub fn parse_uevent(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next()?.trim_start_matches("POWER_SUPPLY_");
+            let value = parts.next()?.trim();
+
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn parse_state(value: &str) -> State {
+    match value {
+        "Charging" => State::Charging,
+        "Discharging" => State::Discharging,
+        "Full" => State::Full,
+        "Empty" => State::Empty,
+        _ => State::Unknown,
+    }
+}
+
+fn parse_technology(value: &str) -> Technology {
+    match value {
+        "Li-ion" => Technology::LithiumIon,
+        "Li-poly" => Technology::LithiumPolymer,
+        "LiFe" => Technology::LithiumIronPhosphate,
+        "NiMH" => Technology::NickelMetalHydride,
+        "NiCd" => Technology::NickelCadmium,
+        _ => Technology::Unknown,
+    }
+}
@@ -0,0 +1,173 @@
+//! Backend reading the `org.freedesktop.UPower.Device` objects of the UPower daemon over the system D-Bus.
+//!
+//! Besides the laptop batteries UPower knows about the UPS and the wireless peripherals,
+//! and it keeps the charge and energy rate history on its own.
+
+use std::collections::HashMap;
+use std::time::{Duration, UNIX_EPOCH};
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::watt_hour;
+use battery::units::power::watt;
+use battery::units::ratio::percent;
+use battery::units::thermodynamic_temperature::degree_celsius;
+use battery::units::time::second;
+use battery::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time};
+use battery::{State, Technology};
+
+use super::dbus::{self, Value};
+use super::{Capabilities, Device, DeviceKind, HistoryPoint, PowerSource, Source};
+use crate::Result;
+
+const SERVICE: &str = "org.freedesktop.UPower";
+const DEVICE: &str = "org.freedesktop.UPower.Device";
+/// Aggregated device UPower builds out of all the batteries
+const DISPLAY_DEVICE: &str = "DisplayDevice";
+/// Energy rate history points requested, as many as `History::open` keeps
+const HISTORY_POINTS: &str = "512";
+
+#[derive(Debug)]
+pub struct UPower;
+
+impl PowerSource for UPower {
+    fn name(&self) -> &str {
+        "upower"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let reply = dbus::call(SERVICE, "/org/freedesktop/UPower", SERVICE, "EnumerateDevices", &[])?;
+        let mut devices = Vec::new();
+        for path in dbus::array(&reply, 1)? {
+            let path = &path[0];
+            if path.ends_with(DISPLAY_DEVICE) {
+                continue;
+            }
+
+            let mut device = Device::empty(Source::UPower(path.to_string()), DeviceKind::Other);
+            // AC adapters are reported as devices too, but there is nothing to monitor
+            if !device.update_upower(&dbus::properties(SERVICE, path, DEVICE)?) {
+                continue;
+            }
+            match rate_history(path) {
+                Ok(history) => device.rate_history = history,
+                Err(e) => debug!("Unable to get the rate history of {}: {}", path, e),
+            }
+            devices.push(device);
+        }
+        trace!("UPower reported {} devices", devices.len());
+
+        Ok(devices)
+    }
+
+    /// Object of the removed device is gone, so the properties request fails
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let path = match device.source {
+            Source::UPower(ref path) => path.clone(),
+            _ => return Ok(()),
+        };
+        device.update_upower(&dbus::properties(SERVICE, &path, DEVICE)?);
+
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATTERY
+    }
+
+    /// UPower re-reads the batteries on its own schedule, while each refresh spawns `busctl`
+    fn interval(&self) -> Option<Duration> {
+        Some(Duration::from_secs(5))
+    }
+}
+
+/// Energy rate history UPower had collected, oldest points first
+fn rate_history(path: &str) -> Result<Vec<HistoryPoint>> {
+    let reply = dbus::call(SERVICE, path, DEVICE, "GetHistory", &["suu", "rate", "0", HISTORY_POINTS])?;
+    let mut history = dbus::array(&reply, 3)?
+        .into_iter()
+        .filter_map(|point| {
+            // Time, value and the state of each point
+            let timestamp = point[0].parse::<u64>().ok()?;
+            let value = point[1].parse::<f32>().ok()?;
+            Some(HistoryPoint {
+                timestamp: UNIX_EPOCH + Duration::from_secs(timestamp),
+                value,
+            })
+        })
+        .collect::<Vec<_>>();
+    history.sort_by_key(|point| point.timestamp);
+
+    Ok(history)
+}
+
+impl Device {
+    /// Update device with the `org.freedesktop.UPower.Device` properties,
+    /// returns `false` for the devices without a battery
+    fn update_upower(&mut self, properties: &HashMap<String, Value>) -> bool {
+        let string = |name: &str| {
+            properties
+                .get(name)
+                .and_then(Value::as_str)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let number = |name: &str| properties.get(name).and_then(Value::as_f64).unwrap_or(0.0);
+        // Zero stands for the unknown time estimation
+        let time = |name: &str| Some(number(name)).filter(|seconds| *seconds > 0.0).map(|seconds| Time::new::<second>(seconds as f32));
+
+        self.kind = match number("Type") as u32 {
+            2 => DeviceKind::Battery,
+            3 => DeviceKind::Ups,
+            5 => DeviceKind::Mouse,
+            6 => DeviceKind::Keyboard,
+            8 => DeviceKind::Phone,
+            // Line power, unknown devices and monitors
+            0 | 1 | 4 => return false,
+            _ => DeviceKind::Other,
+        };
+        self.vendor = string("Vendor");
+        self.model = string("Model");
+        self.serial_number = string("Serial");
+        self.state = parse_state(number("State") as u32);
+        self.technology = parse_technology(number("Technology") as u32);
+        self.state_of_charge = Ratio::new::<percent>(number("Percentage") as f32);
+        self.state_of_health = Ratio::new::<percent>(number("Capacity") as f32);
+        self.energy = Energy::new::<watt_hour>(number("Energy") as f32);
+        self.energy_full = Energy::new::<watt_hour>(number("EnergyFull") as f32);
+        self.energy_full_design = Energy::new::<watt_hour>(number("EnergyFullDesign") as f32);
+        self.energy_rate = Power::new::<watt>(number("EnergyRate") as f32);
+        self.voltage = ElectricPotential::new::<volt>(number("Voltage") as f32);
+        self.temperature = Some(number("Temperature"))
+            .filter(|celsius| *celsius != 0.0)
+            .map(|celsius| ThermodynamicTemperature::new::<degree_celsius>(celsius as f32));
+        // Unknown count is reported as `-1`
+        self.cycle_count = Some(number("ChargeCycles")).filter(|cycles| *cycles > 0.0).map(|cycles| cycles as u32);
+        self.time_to_full = time("TimeToFull");
+        self.time_to_empty = time("TimeToEmpty");
+
+        true
+    }
+}
+
+/// `State` property, see the UPower D-Bus documentation
+fn parse_state(value: u32) -> State {
+    match value {
+        1 | 5 => State::Charging,
+        2 | 6 => State::Discharging,
+        3 => State::Empty,
+        4 => State::Full,
+        _ => State::Unknown,
+    }
+}
+
+fn parse_technology(value: u32) -> Technology {
+    match value {
+        1 => Technology::LithiumIon,
+        2 => Technology::LithiumPolymer,
+        3 => Technology::LithiumIronPhosphate,
+        4 => Technology::LeadAcid,
+        5 => Technology::NickelCadmium,
+        6 => Technology::NickelMetalHydride,
+        _ => Technology::Unknown,
+    }
+}
@@ -0,0 +1,372 @@
+//! Victron Energy solar charge controllers and battery monitors, read from a serial port
+//! with the VE.Direct text protocol, Linux only.
+//!
+//! Devices are sending a block of `\r\nLABEL\tVALUE` fields each second, whether anybody listens or not,
+//! and the block ends with a checksum byte making the sum of all its bytes zero.
+//! Battery monitors split their fields into two alternating blocks, so the latest values are merged.
+//! Port is read by a separate thread, otherwise each refresh would wait for the next block.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::mem;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use battery::units::electric_potential::volt;
+use battery::units::power::watt;
+use battery::units::ratio::percent;
+use battery::units::thermodynamic_temperature::degree_celsius;
+use battery::units::time::minute;
+use battery::units::{ElectricPotential, Power, Ratio, ThermodynamicTemperature, Time};
+use battery::State;
+
+use super::identity::Identity;
+use super::{Capabilities, Device, DeviceKind, PowerSource, Source};
+use crate::Result;
+
+/// Text protocol is always 19200 baud, 8N1
+const BAUD_RATE: libc::speed_t = libc::B19200;
+/// Devices are sending a block each second, battery monitors two of them
+const FIRST_BLOCK_TIMEOUT: Duration = Duration::from_secs(3);
+/// Device is considered gone if nothing valid came for that long
+const STALE_AFTER: Duration = Duration::from_secs(10);
+/// Protection from the garbage on the line, real blocks are about 200 bytes long
+const MAX_BLOCK_SIZE: usize = 1024;
+const CHECKSUM_LABEL: &[u8] = b"Checksum\t";
+
+#[derive(Debug)]
+pub struct Vedirect {
+    /// Serial port, ex. `/dev/ttyUSB0`
+    path: PathBuf,
+    /// Fields shared with the reading thread, if it was started
+    shared: Option<Arc<Mutex<Shared>>>,
+}
+
+impl Vedirect {
+    pub fn new(path: &str) -> Vedirect {
+        Vedirect {
+            path: PathBuf::from(path),
+            shared: None,
+        }
+    }
+
+    /// Start reading the port, unless the thread is already running
+    fn start(&mut self) -> io::Result<Arc<Mutex<Shared>>> {
+        if let Some(ref shared) = self.shared {
+            if shared.lock().expect("VE.Direct reader panicked").error.is_none() {
+                return Ok(shared.clone());
+            }
+        }
+
+        let port = open(&self.path)?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let reader = shared.clone();
+        thread::spawn(move || read_port(port, &reader));
+        self.shared = Some(shared.clone());
+
+        Ok(shared)
+    }
+}
+
+/// Latest values of all the fields seen so far
+#[derive(Debug, Default)]
+struct Shared {
+    fields: HashMap<String, String>,
+    updated: Option<Instant>,
+    /// Set when the port fails, thread exits right after that
+    error: Option<String>,
+}
+
+impl PowerSource for Vedirect {
+    fn name(&self) -> &str {
+        "VE.Direct"
+    }
+
+    fn devices(&mut self) -> Result<Vec<Device>> {
+        let shared = self.start()?;
+
+        // Nothing is known until the device sends its block
+        let started = Instant::now();
+        while started.elapsed() < FIRST_BLOCK_TIMEOUT {
+            {
+                let shared = shared.lock().expect("VE.Direct reader panicked");
+                if let Some(ref error) = shared.error {
+                    return Err(io::Error::other(error.clone()).into());
+                }
+                if shared.fields.contains_key("V") {
+                    let mut device = Device::empty(Source::Vedirect(self.path.clone()), DeviceKind::Battery);
+                    device.update_vedirect(&shared.fields);
+                    return Ok(vec![device]);
+                }
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        trace!("No VE.Direct blocks received from {}", self.path.display());
+
+        Ok(Vec::new())
+    }
+
+    fn refresh(&mut self, device: &mut Device) -> Result<()> {
+        let shared = match self.shared {
+            Some(ref shared) => shared.lock().expect("VE.Direct reader panicked"),
+            None => return Ok(()),
+        };
+        if let Some(ref error) = shared.error {
+            return Err(io::Error::other(error.clone()).into());
+        }
+        match shared.updated {
+            Some(updated) if updated.elapsed() < STALE_AFTER => {}
+            _ => return Err(io::Error::new(io::ErrorKind::TimedOut, "device stopped sending data").into()),
+        }
+        device.update_vedirect(&shared.fields);
+
+        Ok(())
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATTERY
+    }
+}
+
+impl Device {
+    fn update_vedirect(&mut self, fields: &HashMap<String, String>) {
+        let get = |label: &str| fields.get(label).map(String::as_str);
+        let number = |label: &str| get(label).and_then(|value| value.parse::<f32>().ok());
+
+        // Battery monitors are naming themselves, chargers are reporting the product ID only
+        let product = match (get("BMV"), get("PID")) {
+            (Some(name), _) => format!("BMV-{}", name),
+            (None, Some(pid)) if fields.contains_key("VPV") => format!("MPPT {}", pid),
+            (None, Some(pid)) => format!("VE.Direct {}", pid),
+            (None, None) => "VE.Direct".to_string(),
+        };
+        self.vendor = Some("Victron Energy".to_string());
+        self.model = Some(product);
+        self.serial_number = get("SER#").map(str::to_string);
+        self.identity = Identity {
+            // `159` stands for 1.59
+            firmware: get("FW").map(|version| match version.len() {
+                3 => format!("{}.{}", &version[..1], &version[1..]),
+                _ => version.to_string(),
+            }),
+            ..Identity::default()
+        };
+
+        let voltage = number("V").map(|mv| mv / 1000.0).unwrap_or(0.0);
+        // Positive current is charging the battery
+        let current = number("I").map(|ma| ma / 1000.0).unwrap_or(0.0);
+        let charger_state = get("CS").and_then(|state| state.parse::<u16>().ok());
+        self.state = match charger_state {
+            // Float and storage
+            Some(5) | Some(6) => State::Full,
+            _ if current > 0.05 => State::Charging,
+            _ if current < -0.05 => State::Discharging,
+            _ => State::Unknown,
+        };
+        self.voltage = ElectricPotential::new::<volt>(voltage);
+        self.energy_rate = Power::new::<watt>(number("P").unwrap_or(voltage * current).abs());
+        // Only battery monitors are measuring it, in ‰
+        if let Some(soc) = number("SOC") {
+            self.state_of_charge = Ratio::new::<percent>(soc / 10.0);
+        }
+        self.temperature = number("T").map(ThermodynamicTemperature::new::<degree_celsius>);
+        // Time to go is -1 while the battery is not discharging
+        self.time_to_empty = match number("TTG") {
+            Some(minutes) if minutes > 0.0 && self.state == State::Discharging => Some(Time::new::<minute>(minutes)),
+            _ => None,
+        };
+
+        let mut attributes = Vec::new();
+        if let Some(state) = charger_state {
+            attributes.push(("Charger state".to_string(), charger_state_name(state)));
+        }
+        if let Some(voltage) = number("VPV") {
+            attributes.push(("Panel voltage".to_string(), format!("{:.2} V", voltage / 1000.0)));
+        }
+        if let Some(power) = number("PPV") {
+            attributes.push(("Panel power".to_string(), format!("{:.0} W", power)));
+        }
+        if let Some(power) = number("H21") {
+            attributes.push(("Max power today".to_string(), format!("{:.0} W", power)));
+        }
+        // Yields are in 0.01 kW·h
+        if let Some(yield_today) = number("H20") {
+            attributes.push(("Yield today".to_string(), format!("{:.2} kW·h", yield_today / 100.0)));
+        }
+        if let Some(yield_total) = number("H19") {
+            attributes.push(("Yield total".to_string(), format!("{:.2} kW·h", yield_total / 100.0)));
+        }
+        if let Some(consumed) = number("CE") {
+            attributes.push(("Consumed".to_string(), format!("{:.1} A·h", -consumed / 1000.0)));
+        }
+        match (get("LOAD"), number("IL")) {
+            (Some(load), Some(current)) => attributes.push((
+                "Load output".to_string(),
+                format!("{}, {:.1} A", load, current / 1000.0),
+            )),
+            (Some(load), None) => attributes.push(("Load output".to_string(), load.to_string())),
+            _ => {}
+        }
+        if let Some(alarm) = get("Alarm").filter(|alarm| *alarm == "ON") {
+            attributes.push(("Alarm".to_string(), alarm.to_string()));
+        }
+        match get("ERR").and_then(|error| error.parse::<u16>().ok()) {
+            Some(0) | None => {}
+            Some(error) => attributes.push(("Error".to_string(), error_name(error))),
+        }
+        self.attributes = attributes;
+    }
+}
+
+fn charger_state_name(state: u16) -> String {
+    let name = match state {
+        0 => "Off",
+        1 => "Low power",
+        2 => "Fault",
+        3 => "Bulk",
+        4 => "Absorption",
+        5 => "Float",
+        6 => "Storage",
+        7 => "Equalize",
+        9 => "Inverting",
+        11 => "Power supply",
+        245 => "Starting up",
+        246 => "Repeated absorption",
+        247 => "Auto equalize",
+        248 => "BatterySafe",
+        252 => "External control",
+        _ => return format!("Unknown ({})", state),
+    };
+
+    name.to_string()
+}
+
+fn error_name(error: u16) -> String {
+    let name = match error {
+        2 => "Battery voltage too high",
+        17 => "Charger temperature too high",
+        18 => "Charger over current",
+        19 => "Charger current reversed",
+        20 => "Bulk time limit exceeded",
+        21 => "Current sensor issue",
+        26 => "Terminals overheated",
+        33 => "Panel voltage too high",
+        34 => "Panel current too high",
+        38 | 39 => "Input shutdown",
+        67 => "BMS connection lost",
+        _ => return format!("Error {}", error),
+    };
+
+    name.to_string()
+}
+
+/// Open the port in the raw mode, reads are returning nothing after a second of silence
+fn open(path: &Path) -> io::Result<File> {
+    let port = OpenOptions::new().read(true).custom_flags(libc::O_NOCTTY).open(path)?;
+    let fd = port.as_raw_fd();
+
+    let mut termios: libc::termios = unsafe { mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut termios) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    unsafe {
+        libc::cfmakeraw(&mut termios);
+        libc::cfsetispeed(&mut termios, BAUD_RATE);
+        libc::cfsetospeed(&mut termios, BAUD_RATE);
+    }
+    termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+    termios.c_cc[libc::VMIN] = 0;
+    termios.c_cc[libc::VTIME] = 10;
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(port)
+}
+
+/// Reading thread, runs until the port fails or the backend is dropped
+fn read_port(mut port: File, shared: &Arc<Mutex<Shared>>) {
+    let mut parser = Parser::default();
+    let mut buffer = [0u8; 256];
+    // Backend holds the other reference
+    while Arc::strong_count(shared) > 1 {
+        let length = match port.read(&mut buffer) {
+            Ok(length) => length,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                warn!("Unable to read the VE.Direct port: {}", e);
+                shared.lock().expect("VE.Direct reader panicked").error = Some(e.to_string());
+                return;
+            }
+        };
+        for byte in &buffer[..length] {
+            if let Some(fields) = parser.push(*byte) {
+                let mut shared = shared.lock().expect("VE.Direct reader panicked");
+                shared.fields.extend(fields);
+                shared.updated = Some(Instant::now());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Parser {
+    block: Vec<u8>,
+    /// Inside of the `:…\n` HEX protocol frame, which devices are mixing into the text protocol
+    hex_frame: bool,
+}
+
+impl Parser {
+    /// Feed the next byte, returns the fields when a block with the correct checksum ends
+    fn push(&mut self, byte: u8) -> Option<Vec<(String, String)>> {
+        // Checksum byte can be anything, including the `:`
+        if self.block.ends_with(CHECKSUM_LABEL) {
+            self.block.push(byte);
+            let block = mem::take(&mut self.block);
+            return parse_block(&block);
+        }
+        if self.hex_frame {
+            self.hex_frame = byte != b'\n';
+            return None;
+        }
+        if byte == b':' {
+            self.hex_frame = true;
+            return None;
+        }
+
+        if self.block.len() >= MAX_BLOCK_SIZE {
+            self.block.clear();
+        }
+        self.block.push(byte);
+
+        None
+    }
+}
+
+/// Fields of the block, if the checksum matches; the first block read is usually a partial one
+fn parse_block(block: &[u8]) -> Option<Vec<(String, String)>> {
+    let sum = block.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    if sum != 0 {
+        trace!("Skipping the VE.Direct block with invalid checksum");
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&block[..block.len() - 1]);
+    let fields = text
+        .split("\r\n")
+        .filter_map(|line| {
+            let (label, value) = line.split_once('\t')?;
+            match label {
+                "" | "Checksum" => None,
+                _ => Some((label.to_string(), value.trim().to_string())),
+            }
+        })
+        .collect();
+
+    Some(fields)
+}
@@ -0,0 +1,211 @@
+//! Extended Windows battery data from WMI and the `powercfg` battery report.
+//!
+//! Design and full charged capacities are not always reported by the `battery` crate on Windows,
+//! while they are required for the health and degradation tracking. `powercfg` also keeps
+//! the capacity history, which is used to pre-populate the health history of the new battery.
+
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::io;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use battery::units::energy::watt_hour;
+use battery::units::ratio::ratio;
+use battery::units::{Energy, Ratio};
+
+use super::identity::Date;
+use super::{Device, HistoryPoint};
+
+/// Static data is not changing often and PowerShell is slow to start
+const QUERY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+const QUERY: &str = "\
+$static = Get-CimInstance -Namespace root/WMI -ClassName BatteryStaticData;
+$full = Get-CimInstance -Namespace root/WMI -ClassName BatteryFullChargedCapacity;
+$cycles = Get-CimInstance -Namespace root/WMI -ClassName BatteryCycleCount -ErrorAction SilentlyContinue;
+foreach ($s in $static) {
+    $f = $full | Where-Object InstanceName -eq $s.InstanceName;
+    $c = $cycles | Where-Object InstanceName -eq $s.InstanceName;
+    '{0}|{1}|{2}|{3}|{4:yyyy-MM-dd}' -f $s.SerialNumber, $s.DesignedCapacity, $f.FullChargedCapacity, $c.CycleCount, $s.ManufactureDate
+}";
+
+/// Capacities of one battery, W·h
+#[derive(Debug, Clone)]
+struct StaticData {
+    serial_number: String,
+    design: f32,
+    full: f32,
+    cycle_count: Option<u32>,
+    manufactured: Option<Date>,
+}
+
+thread_local! {
+    static CACHE: RefCell<Option<(Instant, Vec<StaticData>)>> = RefCell::new(None);
+}
+
+impl Device {
+    /// Fill in the capacities and cycles count the `battery` crate was not able to get
+    pub fn augment_wmi(&mut self) {
+        let data = match static_data(self.serial_number()) {
+            Some(data) => data,
+            None => return,
+        };
+
+        if self.energy_full_design.get::<watt_hour>() <= 0.0 && data.design > 0.0 {
+            self.energy_full_design = Energy::new::<watt_hour>(data.design);
+        }
+        if self.energy_full.get::<watt_hour>() <= 0.0 && data.full > 0.0 {
+            self.energy_full = Energy::new::<watt_hour>(data.full);
+        }
+        let design = self.energy_full_design.get::<watt_hour>();
+        if design > 0.0 {
+            self.state_of_health = Ratio::new::<ratio>(self.energy_full.get::<watt_hour>() / design);
+        }
+        if self.cycle_count.is_none() {
+            self.cycle_count = data.cycle_count;
+        }
+        self.identity.manufactured = data.manufactured;
+    }
+
+    /// Load the full charge capacity history from the `powercfg /batteryreport`
+    pub fn load_powercfg_history(&mut self) {
+        match powercfg_report() {
+            Ok(report) => self.capacity_history = parse_report(&report),
+            Err(e) => trace!("Unable to generate powercfg battery report: {}", e),
+        }
+    }
+}
+
+/// WMI data of the battery with the same serial number, or of the first one if serial is unknown
+fn static_data(serial_number: Option<&str>) -> Option<StaticData> {
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let outdated = match *cache {
+            Some((queried, _)) => queried.elapsed() >= QUERY_INTERVAL,
+            None => true,
+        };
+        if outdated {
+            match query() {
+                Ok(data) => *cache = Some((Instant::now(), data)),
+                Err(e) => {
+                    trace!("Unable to query WMI battery data: {}", e);
+                    // Do not try again on every refresh
+                    *cache = Some((Instant::now(), Vec::new()));
+                }
+            }
+        }
+
+        let data = match *cache {
+            Some((_, ref data)) => data,
+            None => return None,
+        };
+        let found = match serial_number {
+            Some(serial) => data.iter().find(|item| item.serial_number.trim() == serial.trim()),
+            None => None,
+        };
+
+        found.or_else(|| data.first()).cloned()
+    })
+}
+
+fn query() -> io::Result<Vec<StaticData>> {
+    let output = powershell(QUERY)?;
+    let data = output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.trim().split('|');
+            let serial_number = parts.next()?.to_string();
+            // mW·h
+            let design = parts.next()?.parse::<f32>().ok()? / 1000.0;
+            let full = parts.next()?.parse::<f32>().unwrap_or(0.0) / 1000.0;
+            let cycle_count = parts
+                .next()
+                .and_then(|cycles| cycles.parse().ok())
+                .filter(|cycles| *cycles > 0);
+            // Empty if firmware does not report it
+            let manufactured = parts.next().and_then(Date::parse);
+
+            Some(StaticData {
+                serial_number,
+                design,
+                full,
+                cycle_count,
+                manufactured,
+            })
+        })
+        .collect();
+
+    Ok(data)
+}
+
+fn powershell(script: &str) -> io::Result<String> {
+    let output = Command::new("powershell")
+        .args(&["-NoProfile", "-NonInteractive", "-Command", script])
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("powershell exited with {}", output.status),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn powercfg_report() -> io::Result<String> {
+    let path = env::temp_dir().join("battop-battery-report.xml");
+    let output = Command::new("powercfg")
+        .arg("/batteryreport")
+        .arg("/xml")
+        .arg("/output")
+        .arg(&path)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("powercfg exited with {}", output.status),
+        ));
+    }
+
+    let report = fs::read_to_string(&path)?;
+    let _ = fs::remove_file(&path);
+
+    Ok(report)
+}
+
+/// Full charge capacity points of the report history, W·h.
+///
+/// ```text
+/// <HistoryEntry StartDate="2019-05-01T00:00:00" DesignCapacity="57000" FullChargeCapacity="51200" ... />
+/// ```
+fn parse_report(report: &str) -> Vec<HistoryPoint> {
+    report
+        .split("<HistoryEntry ")
+        .skip(1)
+        .filter_map(|entry| {
+            let entry = &entry[..entry.find('>').unwrap_or_else(|| entry.len())];
+            let timestamp = humantime::parse_rfc3339_weak(attribute(entry, "StartDate")?).ok()?;
+            let full = attribute(entry, "FullChargeCapacity")?.parse::<f32>().ok()?;
+
+            if full > 0.0 {
+                Some(HistoryPoint {
+                    timestamp,
+                    value: full / 1000.0,
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// XML attribute value, ex. `51200` for the `FullChargeCapacity="51200"`
+fn attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    let key = format!(" {}=\"", name);
+    let start = element.find(&key)? + key.len();
+    let end = element[start..].find('"')?;
+
+    Some(&element[start..start + end])
+}
@@ -0,0 +1,51 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::result;
+
+pub type Result<T> = result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// None of the backends found any device
+    NoBatteries,
+    Battery(battery::Error),
+    Io(io::Error),
+    ParseError,
+    /// Backend was left out at the build time, by its cargo feature name
+    Disabled(String),
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Battery(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NoBatteries => f.write_str("Unable to find any batteries installed"),
+            Error::ParseError => f.write_str("Unable to parse value"),
+            Error::Disabled(feature) => write!(f, "battop was built without the `{}` feature", feature),
+            Error::Battery(e) => fmt::Display::fmt(e, f),
+            Error::Io(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl From<battery::Error> for Error {
+    fn from(e: battery::Error) -> Self {
+        Error::Battery(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,95 @@
+//! Measurement units and the values formatting shared by the frontends.
+
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use battery::units::energy::{joule, watt_hour};
+use battery::units::Energy;
+use battery::Technology;
+
+use crate::stats::DAY;
+use crate::Error;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Units {
+    Human,
+    Si,
+}
+
+impl Units {
+    // Why do you even need this, when clap provides `arg_enum!` macro?
+    // I just do not like that results are capitalized.
+    // Who the hell want to write manually arguments like `-u Human`?
+    // `-u human` is much prettier.
+    pub fn arg_variants() -> [&'static str; 2] {
+        ["human", "si"]
+    }
+}
+
+impl FromStr for Units {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match () {
+            _ if s.eq_ignore_ascii_case("human") => Ok(Units::Human),
+            _ if s.eq_ignore_ascii_case("si") => Ok(Units::Si),
+            _ => Err(Error::ParseError),
+        }
+    }
+}
+
+// Units are spelled here instead of taking the `uom` abbreviations, which are changing between its versions
+pub const WATT: &str = "W";
+pub const VOLT: &str = "V";
+pub const PERCENT: &str = "%";
+pub const WATT_HOUR: &str = "W·h";
+pub const KILOWATT_HOUR: &str = "kW·h";
+pub const JOULE: &str = "J";
+pub const DEGREE_CELSIUS: &str = "°C";
+pub const KELVIN: &str = "K";
+
+/// Format energy value (in W·h) with the proper measurement units
+pub fn energy(units: Units, watt_hours: f32) -> String {
+    match units {
+        Units::Human => format!("{:.2} {}", watt_hours, WATT_HOUR),
+        Units::Si => format!("{:.0} {}", Energy::new::<watt_hour>(watt_hours).get::<joule>(), JOULE),
+    }
+}
+
+/// Chemistry abbreviation, ex. `Li-ion`, or `None` if it is unknown
+pub fn technology(technology: Technology) -> Option<&'static str> {
+    match technology {
+        Technology::LithiumIon => Some("Li-ion"),
+        Technology::LithiumPolymer => Some("Li-poly"),
+        Technology::LithiumIronPhosphate => Some("LiFePO4"),
+        Technology::LeadAcid => Some("Lead-acid"),
+        Technology::NickelMetalHydride => Some("NiMH"),
+        Technology::NickelCadmium => Some("NiCd"),
+        Technology::NickelZinc => Some("NiZn"),
+        Technology::RechargeableAlkalineManganese => Some("RAM"),
+        _ => None,
+    }
+}
+
+/// Format timestamp as a calendar date, ex. `2019-06-04`
+pub fn date(time: SystemTime) -> String {
+    let mut formatted = humantime::format_rfc3339_seconds(time).to_string();
+    formatted.truncate(10);
+    formatted
+}
+
+/// Format timestamp as a time of day in UTC, ex. `12:30`
+pub fn time(time: SystemTime) -> String {
+    let formatted = humantime::format_rfc3339_seconds(time).to_string();
+    formatted[11..16].to_string()
+}
+
+/// Battery age, in days for the new ones and in years for the rest
+pub fn age(age: Duration) -> String {
+    let days = age.as_secs() / DAY.as_secs();
+    if days < 365 {
+        format!("{} days", days)
+    } else {
+        format!("{:.1} years", days as f64 / 365.25)
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use tracing::debug_span;
+
+use super::{Session, SessionKind};
+
+/// Accumulated energy throughput of the battery.
+///
+/// Stored as a single line in the `cycles` file:
+///
+/// ```text
+/// 4210.512 4388.020
+/// ```
+///
+/// where columns are total discharged and total charged energy (W·h).
+#[derive(Debug, Default)]
+pub struct CycleCounter {
+    path: Option<PathBuf>,
+    discharged: f64,
+    charged: f64,
+}
+
+impl CycleCounter {
+    pub fn open(path: PathBuf) -> CycleCounter {
+        let mut counter = CycleCounter {
+            path: None,
+            ..CycleCounter::default()
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                let mut parts = content.split_whitespace().map(str::parse::<f64>);
+                match (parts.next(), parts.next()) {
+                    (Some(Ok(discharged)), Some(Ok(charged))) => {
+                        counter.discharged = discharged;
+                        counter.charged = charged;
+                    }
+                    _ => warn!("Malformed cycles counter in {:?}, starting from zero", path),
+                }
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Unable to read cycles counter from {:?}: {}", path, e),
+        }
+
+        counter.path = Some(path);
+        counter
+    }
+
+    pub fn in_memory() -> CycleCounter {
+        CycleCounter::default()
+    }
+
+    /// Total energy discharged from the battery, W·h
+    pub fn discharged(&self) -> f64 {
+        self.discharged
+    }
+
+    pub fn add(&mut self, session: &Session) {
+        match session.kind {
+            SessionKind::Charging => self.charged += f64::from(session.energy),
+            SessionKind::Discharging => self.discharged += f64::from(session.energy),
+        }
+
+        if let Some(path) = &self.path {
+            let _span = debug_span!("persist", path = %path.display()).entered();
+            let content = format!("{:.3} {:.3}\n", self.discharged, self.charged);
+            if let Err(e) = fs::write(path, content) {
+                warn!("Unable to persist cycles counter to {:?}: {}", path, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::time::SystemTime;
+
+    use super::CycleCounter;
+    use crate::history::{Session, SessionKind};
+
+    fn session(kind: SessionKind, energy: f32) -> Session {
+        Session {
+            kind,
+            start: SystemTime::UNIX_EPOCH,
+            end: SystemTime::UNIX_EPOCH,
+            energy,
+            soc_start: 0.0,
+            soc_end: 0.0,
+        }
+    }
+
+    #[test]
+    fn throughput_is_persisted() {
+        let path = env::temp_dir().join(format!("battop-test-{}-cycles", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut counter = CycleCounter::open(path.clone());
+        counter.add(&session(SessionKind::Discharging, 40.5));
+        counter.add(&session(SessionKind::Charging, 45.0));
+        counter.add(&session(SessionKind::Discharging, 10.0));
+        assert_eq!(counter.discharged(), 50.5);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "50.500 45.000\n");
+
+        assert_eq!(CycleCounter::open(path.clone()).discharged(), 50.5);
+
+        // Malformed counter starts over
+        fs::write(&path, "50.5\n").unwrap();
+        assert_eq!(CycleCounter::open(path.clone()).discharged(), 0.0);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use battery::units::energy::watt_hour;
+use tracing::debug_span;
+
+use crate::device::Device;
+
+/// How often the health snapshot is stored if nothing had changed
+const RECORD_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Minimal interval between two snapshots, even if battery capacity had changed
+const MIN_RECORD_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Snapshot of the battery capacity at some point of time.
+///
+/// Stored as a line in the `health` file:
+///
+/// ```text
+/// 2019-06-04T12:00:00Z 50.210 57.020 120
+/// ```
+///
+/// where columns are: timestamp, last full energy (W·h), design energy (W·h)
+/// and cycles count (`-` if unknown).
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthRecord {
+    pub timestamp: SystemTime,
+    pub energy_full: f32,
+    pub energy_full_design: f32,
+    pub cycle_count: Option<u32>,
+}
+
+impl HealthRecord {
+    pub fn from_battery(battery: &Device) -> HealthRecord {
+        HealthRecord {
+            timestamp: SystemTime::now(),
+            energy_full: battery.energy_full().get::<watt_hour>(),
+            energy_full_design: battery.energy_full_design().get::<watt_hour>(),
+            cycle_count: battery.cycle_count(),
+        }
+    }
+
+    /// Ratio of the last full energy to the design energy
+    pub fn health(&self) -> f32 {
+        if self.energy_full_design > 0.0 {
+            self.energy_full / self.energy_full_design
+        } else {
+            0.0
+        }
+    }
+
+    fn parse(line: &str) -> Option<HealthRecord> {
+        let mut parts = line.split_whitespace();
+        let timestamp = humantime::parse_rfc3339(parts.next()?).ok()?;
+        let energy_full = parts.next()?.parse().ok()?;
+        let energy_full_design = parts.next()?.parse().ok()?;
+        let cycle_count = match parts.next()? {
+            "-" => None,
+            value => Some(value.parse().ok()?),
+        };
+
+        Some(HealthRecord {
+            timestamp,
+            energy_full,
+            energy_full_design,
+            cycle_count,
+        })
+    }
+
+    fn format(&self) -> String {
+        let cycles = match self.cycle_count {
+            Some(cycles) => cycles.to_string(),
+            None => "-".to_string(),
+        };
+
+        format!(
+            "{} {:.3} {:.3} {}",
+            humantime::format_rfc3339_seconds(self.timestamp),
+            self.energy_full,
+            self.energy_full_design,
+            cycles
+        )
+    }
+}
+
+/// Chronologically ordered battery health snapshots
+#[derive(Debug)]
+pub struct HealthLog {
+    path: Option<PathBuf>,
+    records: Vec<HealthRecord>,
+}
+
+impl HealthLog {
+    pub fn open(path: PathBuf) -> HealthLog {
+        let records = match fs::read_to_string(&path) {
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+                .filter_map(|line| {
+                    let record = HealthRecord::parse(line);
+                    if record.is_none() {
+                        warn!("Skipping malformed health record in {:?}: {:?}", path, line);
+                    }
+                    record
+                })
+                .collect(),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                warn!("Unable to read health history from {:?}: {}", path, e);
+                Vec::new()
+            }
+        };
+        trace!("Loaded {} health records from {:?}", records.len(), path);
+
+        HealthLog {
+            path: Some(path),
+            records,
+        }
+    }
+
+    pub fn in_memory() -> HealthLog {
+        HealthLog {
+            path: None,
+            records: Vec::new(),
+        }
+    }
+
+    pub fn records(&self) -> &[HealthRecord] {
+        self.records.as_ref()
+    }
+
+    /// The very first record, made when battop saw this battery for the first time
+    pub fn first(&self) -> Option<&HealthRecord> {
+        self.records.first()
+    }
+
+    pub fn observe(&mut self, battery: &Device) {
+        let record = HealthRecord::from_battery(battery);
+        if record.energy_full_design <= 0.0 || record.energy_full <= 0.0 {
+            return;
+        }
+
+        let should_record = match self.records.last() {
+            None => true,
+            Some(last) => {
+                let elapsed = record
+                    .timestamp
+                    .duration_since(last.timestamp)
+                    .unwrap_or_else(|_| Duration::from_secs(0));
+                let changed = (last.energy_full - record.energy_full).abs() >= 0.01;
+
+                elapsed >= RECORD_INTERVAL || (changed && elapsed >= MIN_RECORD_INTERVAL)
+            }
+        };
+
+        if should_record {
+            if let Err(e) = self.append(&record) {
+                warn!("Unable to persist health record: {}", e);
+            }
+            self.records.push(record);
+        }
+    }
+
+    /// Pre-populate the empty log with the capacity history the OS had kept before battop saw the battery
+    pub fn backfill(&mut self, battery: &Device) {
+        let design = battery.energy_full_design().get::<watt_hour>();
+        if !self.records.is_empty() || design <= 0.0 {
+            return;
+        }
+
+        let mut records = battery
+            .capacity_history()
+            .iter()
+            .map(|point| HealthRecord {
+                timestamp: point.timestamp,
+                energy_full: point.value,
+                energy_full_design: design,
+                cycle_count: None,
+            })
+            .collect::<Vec<_>>();
+        records.sort_by_key(|record| record.timestamp);
+        if !records.is_empty() {
+            debug!("Backfilling {} health records from the OS history", records.len());
+        }
+
+        for record in records {
+            if let Err(e) = self.append(&record) {
+                warn!("Unable to persist health record: {}", e);
+            }
+            self.records.push(record);
+        }
+    }
+
+    fn append(&self, record: &HealthRecord) -> io::Result<()> {
+        if let Some(path) = &self.path {
+            let _span = debug_span!("persist", path = %path.display()).entered();
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", record.format())?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+//! Persistent per-battery history.
+//!
+//! Every battery gets its own directory inside the battop data directory
+//! (`$XDG_DATA_HOME/battop` or `~/.local/share/battop` on Linux),
+//! named after the battery vendor, model and serial number.
+//! Files inside are plain text, one record per line, so they can be inspected
+//! and edited by hand if needed.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::device::Device;
+
+mod cycles;
+mod health;
+mod samples;
+mod sessions;
+mod sleeps;
+mod store;
+mod suspend;
+
+pub use self::cycles::CycleCounter;
+pub use self::health::{HealthLog, HealthRecord};
+pub use self::samples::Sample;
+pub use self::sessions::{Session, SessionKind, SessionLog};
+pub use self::sleeps::{Sleep, SleepLog};
+pub use self::store::{Field, Retention, SampleStore};
+use self::suspend::SuspendClock;
+
+/// Gap between two samples longer than that means battop was not running, ex. system was suspended
+const MIN_SLEEP_GAP: Duration = Duration::from_secs(2 * 60);
+/// How many points of the backend energy rate history are kept along with the samples
+const RATE_HISTORY_POINTS: usize = 512;
+
+/// Collected data for the one specific battery.
+///
+/// Health records, finished sessions and sleeps are persisted, while samples are kept in memory only,
+/// in the `SampleStore`.
+#[derive(Debug)]
+pub struct History {
+    health: HealthLog,
+    sessions: SessionLog,
+    cycles: CycleCounter,
+    sleeps: SleepLog,
+    samples: SampleStore,
+    sleep_gap: Duration,
+    clock: SuspendClock,
+}
+
+impl History {
+    /// History of the `battery`, kept in memory only unless `persist` is set.
+    ///
+    /// `delay` is the polling interval, gaps much longer than it are considered as the system sleeps.
+    pub fn open(battery: &Device, persist: bool, delay: Duration, retention: Retention) -> History {
+        let mut history = History::persisted(persist, battery).unwrap_or_else(History::in_memory);
+        history.samples = SampleStore::new(retention);
+        history.health.backfill(battery);
+        let rate_history = battery.rate_history();
+        history
+            .samples
+            .backfill(&rate_history[rate_history.len().saturating_sub(RATE_HISTORY_POINTS)..]);
+        // Polling delay might be long enough by itself
+        history.sleep_gap = MIN_SLEEP_GAP.max(delay * 3);
+
+        history
+    }
+
+    fn persisted(persist: bool, battery: &Device) -> Option<History> {
+        if !persist {
+            trace!("History persistence is disabled by user");
+            return None;
+        }
+        // Same key as the one of the daemon, which would count the cycles and sessions twice
+        if battery.is_attached() {
+            trace!("History of {} is persisted by the daemon", battery.id());
+            return None;
+        }
+
+        let dir = match data_dir() {
+            Some(dir) => dir.join(battery_key(battery)),
+            None => {
+                warn!("Unable to determine data directory, history will not be persisted");
+                return None;
+            }
+        };
+        if let Err(e) = fs::create_dir_all(&dir) {
+            warn!("Unable to create history directory {:?}: {}", dir, e);
+            return None;
+        }
+
+        trace!("Using {:?} as a history directory", dir);
+        let health = HealthLog::open(dir.join("health"));
+        let sessions = SessionLog::open(dir.join("sessions"));
+        let cycles = CycleCounter::open(dir.join("cycles"));
+        let sleeps = SleepLog::open(dir.join("sleeps"));
+
+        Some(History {
+            health,
+            sessions,
+            cycles,
+            sleeps,
+            samples: SampleStore::default(),
+            sleep_gap: MIN_SLEEP_GAP,
+            clock: SuspendClock::default(),
+        })
+    }
+
+    /// History which lives only while battop is running
+    pub fn in_memory() -> History {
+        History {
+            health: HealthLog::in_memory(),
+            sessions: SessionLog::in_memory(),
+            cycles: CycleCounter::in_memory(),
+            sleeps: SleepLog::in_memory(),
+            samples: SampleStore::default(),
+            sleep_gap: MIN_SLEEP_GAP,
+            clock: SuspendClock::default(),
+        }
+    }
+
+    /// Record the battery state if there is something new to remember.
+    ///
+    /// System suspend, or a gap long enough to be one, finishes the session in progress,
+    /// and the sample after it is marked as `resumed`, so the interval is not used for the averages.
+    pub fn observe(&mut self, battery: &Device) {
+        let mut sample = Sample::from_battery(battery);
+        let suspended = self.clock.check(sample.timestamp);
+
+        self.health.observe(battery);
+        if let Some(previous) = self.samples.latest() {
+            let gap = sample.timestamp.duration_since(previous.timestamp).unwrap_or_default();
+            if gap >= self.sleep_gap || suspended.is_some() {
+                sample.resumed = true;
+                // Nothing is known about the energy rate while battop was not running
+                if let Some(session) = self.sessions.finish() {
+                    self.cycles.add(&session);
+                }
+                if let Some(sleep) = Sleep::between(previous, &sample) {
+                    debug!(
+                        "Woke up after {}, state of charge dropped from {:.3} to {:.3}",
+                        humantime::format_duration(sleep.duration()),
+                        sleep.soc_start,
+                        sleep.soc_end
+                    );
+                    self.sleeps.add(sleep);
+                }
+            }
+        }
+        if let Some(session) = self.sessions.observe(&sample) {
+            self.cycles.add(&session);
+        }
+        self.samples.push(sample);
+    }
+
+    pub fn health(&self) -> &HealthLog {
+        &self.health
+    }
+
+    pub fn sessions(&self) -> &SessionLog {
+        &self.sessions
+    }
+
+    pub fn sleeps(&self) -> &SleepLog {
+        &self.sleeps
+    }
+
+    /// Samples of this run, shared by the charts, statistics and alerts
+    pub fn samples(&self) -> &SampleStore {
+        &self.samples
+    }
+
+    /// Add the value of a series collected along with the battery, at the latest sample
+    pub fn record(&mut self, channel: &str, value: f64) {
+        self.samples.record(channel, value);
+    }
+
+    /// Total energy discharged from the battery, including the session in progress, W·h
+    pub fn discharged(&self) -> f64 {
+        match self.sessions.current() {
+            Some(session) if session.kind == SessionKind::Discharging => {
+                self.cycles.discharged() + f64::from(session.energy)
+            }
+            _ => self.cycles.discharged(),
+        }
+    }
+}
+
+impl Drop for History {
+    fn drop(&mut self) {
+        // Session in progress is still worth to be remembered
+        if let Some(session) = self.sessions.finish() {
+            self.cycles.add(&session);
+        }
+    }
+}
+
+/// Read-only history of the battery, persisted by this or any previous battop run
+#[derive(Debug)]
+pub struct Archive {
+    key: String,
+    health: HealthLog,
+    sessions: SessionLog,
+    sleeps: SleepLog,
+}
+
+impl Archive {
+    /// History persisted in the battery `dir`, named after its `key`
+    pub(crate) fn open(key: String, dir: &Path) -> Archive {
+        Archive {
+            key,
+            health: HealthLog::open(dir.join("health")),
+            sessions: SessionLog::open(dir.join("sessions")),
+            sleeps: SleepLog::open(dir.join("sleeps")),
+        }
+    }
+
+    /// Battery identifier, see `battery_key`
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn health(&self) -> &HealthLog {
+        &self.health
+    }
+
+    pub fn sessions(&self) -> &SessionLog {
+        &self.sessions
+    }
+
+    pub fn sleeps(&self) -> &SleepLog {
+        &self.sleeps
+    }
+}
+
+/// Load histories of all batteries battop had ever seen, including the ones not attached now
+pub fn archives() -> Vec<Archive> {
+    let dir = match data_dir() {
+        Some(dir) => dir,
+        None => return Vec::new(),
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            trace!("Unable to read history directory {:?}: {}", dir, e);
+            return Vec::new();
+        }
+    };
+
+    let mut archives = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| Archive::open(entry.file_name().to_string_lossy().into_owned(), &entry.path()))
+        .collect::<Vec<_>>();
+    archives.sort_by(|a, b| a.key.cmp(&b.key));
+
+    archives
+}
+
+/// Platform-specific directory for the battop persistent data
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_DATA_HOME") {
+        if !dir.is_empty() {
+            return Some(PathBuf::from(dir).join("battop"));
+        }
+    }
+
+    if cfg!(target_os = "windows") {
+        return env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("battop"));
+    }
+
+    let home = PathBuf::from(env::var_os("HOME")?);
+    if cfg!(target_os = "macos") {
+        Some(home.join("Library").join("Application Support").join("battop"))
+    } else {
+        Some(home.join(".local").join("share").join("battop"))
+    }
+}
+
+/// Build a file system-safe identifier for the battery.
+///
+/// Serial number is not enough by itself, because some vendors are using
+/// the same placeholder value for every battery they ship.
+pub fn battery_key(battery: &Device) -> String {
+    let parts = [battery.vendor(), battery.model(), battery.serial_number()];
+    let key = parts
+        .iter()
+        .filter_map(|part| *part)
+        .map(|part| {
+            part.trim()
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect::<String>()
+        })
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if key.is_empty() {
+        "unknown".to_string()
+    } else {
+        key
+    }
+}
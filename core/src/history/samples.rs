@@ -0,0 +1,41 @@
+use std::time::SystemTime;
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::watt_hour;
+use battery::units::power::watt;
+use battery::units::ratio::ratio;
+use battery::units::thermodynamic_temperature::kelvin;
+use battery::State;
+
+use crate::device::Device;
+
+/// Battery measurements made at one specific moment.
+///
+/// Values are stored in the base units (W·h, W, V, K).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sample {
+    pub timestamp: SystemTime,
+    pub state: State,
+    pub state_of_charge: f32,
+    pub energy: f32,
+    pub energy_rate: f32,
+    pub voltage: f32,
+    pub temperature: Option<f32>,
+    /// System was suspended since the previous sample, so the interval between them is not a measurement
+    pub resumed: bool,
+}
+
+impl Sample {
+    pub fn from_battery(battery: &Device) -> Sample {
+        Sample {
+            timestamp: SystemTime::now(),
+            state: battery.state(),
+            state_of_charge: battery.state_of_charge().get::<ratio>(),
+            energy: battery.energy().get::<watt_hour>(),
+            energy_rate: battery.energy_rate().get::<watt>(),
+            voltage: battery.voltage().get::<volt>(),
+            temperature: battery.temperature().map(|temp| temp.get::<kelvin>()),
+            resumed: false,
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use battery::State;
+use tracing::debug_span;
+
+use super::Sample;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum SessionKind {
+    Charging,
+    Discharging,
+}
+
+impl SessionKind {
+    fn from_state(state: State) -> Option<SessionKind> {
+        match state {
+            State::Charging => Some(SessionKind::Charging),
+            State::Discharging => Some(SessionKind::Discharging),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SessionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionKind::Charging => f.write_str("charging"),
+            SessionKind::Discharging => f.write_str("discharging"),
+        }
+    }
+}
+
+impl FromStr for SessionKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "charging" => Ok(SessionKind::Charging),
+            "discharging" => Ok(SessionKind::Discharging),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Continuous period of time when battery was charging or discharging.
+///
+/// Finished sessions are stored as a lines in the `sessions` file:
+///
+/// ```text
+/// charging 2019-06-04T12:00:00Z 2019-06-04T13:30:00Z 35.120 0.210 0.950
+/// ```
+///
+/// where columns are: session kind, start and end timestamps,
+/// energy which went in or out of the battery (W·h, integrated from the energy rate)
+/// and state of charge at the start and the end of the session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub kind: SessionKind,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub energy: f32,
+    pub soc_start: f32,
+    pub soc_end: f32,
+}
+
+impl Session {
+    fn begin(kind: SessionKind, sample: &Sample) -> Session {
+        Session {
+            kind,
+            start: sample.timestamp,
+            end: sample.timestamp,
+            energy: 0.0,
+            soc_start: sample.state_of_charge,
+            soc_end: sample.state_of_charge,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.end.duration_since(self.start).unwrap_or_default()
+    }
+
+    /// Average power going in or out of the battery during the session, W
+    pub fn average_power(&self) -> Option<f64> {
+        let hours = self.duration().as_secs() as f64 / 3600.0;
+        if hours > 0.0 {
+            Some(f64::from(self.energy) / hours)
+        } else {
+            None
+        }
+    }
+
+    fn parse(line: &str) -> Option<Session> {
+        let mut parts = line.split_whitespace();
+
+        Some(Session {
+            kind: parts.next()?.parse().ok()?,
+            start: humantime::parse_rfc3339(parts.next()?).ok()?,
+            end: humantime::parse_rfc3339(parts.next()?).ok()?,
+            energy: parts.next()?.parse().ok()?,
+            soc_start: parts.next()?.parse().ok()?,
+            soc_end: parts.next()?.parse().ok()?,
+        })
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{} {} {} {:.3} {:.3} {:.3}",
+            self.kind,
+            humantime::format_rfc3339_seconds(self.start),
+            humantime::format_rfc3339_seconds(self.end),
+            self.energy,
+            self.soc_start,
+            self.soc_end,
+        )
+    }
+}
+
+/// Finished sessions history and the session in progress
+#[derive(Debug)]
+pub struct SessionLog {
+    path: Option<PathBuf>,
+    records: Vec<Session>,
+    current: Option<Session>,
+    previous: Option<Sample>,
+}
+
+impl SessionLog {
+    pub fn open(path: PathBuf) -> SessionLog {
+        let records = match fs::read_to_string(&path) {
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+                .filter_map(|line| {
+                    let record = Session::parse(line);
+                    if record.is_none() {
+                        warn!("Skipping malformed session record in {:?}: {:?}", path, line);
+                    }
+                    record
+                })
+                .collect(),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                warn!("Unable to read sessions history from {:?}: {}", path, e);
+                Vec::new()
+            }
+        };
+        trace!("Loaded {} sessions from {:?}", records.len(), path);
+
+        SessionLog {
+            path: Some(path),
+            records,
+            current: None,
+            previous: None,
+        }
+    }
+
+    pub fn in_memory() -> SessionLog {
+        SessionLog {
+            path: None,
+            records: Vec::new(),
+            current: None,
+            previous: None,
+        }
+    }
+
+    /// Finished sessions
+    pub fn records(&self) -> &[Session] {
+        self.records.as_ref()
+    }
+
+    /// Session in progress, if battery is charging or discharging right now
+    pub fn current(&self) -> Option<&Session> {
+        self.current.as_ref()
+    }
+
+    /// Update session in progress with a new sample.
+    ///
+    /// Returns the session which was just finished, if battery state had changed.
+    pub fn observe(&mut self, sample: &Sample) -> Option<Session> {
+        let kind = SessionKind::from_state(sample.state);
+        let mut finished = None;
+        if self.current.as_ref().map(|session| session.kind) != kind {
+            finished = self.finish();
+            self.current = kind.map(|kind| Session::begin(kind, sample));
+        }
+
+        if let (Some(session), Some(previous)) = (self.current.as_mut(), self.previous.as_ref()) {
+            if let (true, Ok(elapsed)) = (
+                previous.state == sample.state && !sample.resumed,
+                sample.timestamp.duration_since(previous.timestamp),
+            ) {
+                let hours = elapsed.as_secs() as f32 / 3600.0 + elapsed.subsec_millis() as f32 / 3_600_000.0;
+                // Trapezoidal integration of the energy rate
+                session.energy += (previous.energy_rate + sample.energy_rate) / 2.0 * hours;
+            }
+            session.end = sample.timestamp;
+            session.soc_end = sample.state_of_charge;
+        }
+
+        self.previous = Some(*sample);
+
+        finished
+    }
+
+    /// Store the session in progress, if any
+    pub fn finish(&mut self) -> Option<Session> {
+        // Energy rate should not be integrated across the finished session boundary
+        self.previous = None;
+        let session = self.current.take()?;
+        if session.end == session.start {
+            return None;
+        }
+        if let Err(e) = self.append(&session) {
+            warn!("Unable to persist session: {}", e);
+        }
+        self.records.push(session.clone());
+
+        Some(session)
+    }
+
+    fn append(&self, session: &Session) -> io::Result<()> {
+        if let Some(path) = &self.path {
+            let _span = debug_span!("persist", path = %path.display()).entered();
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", session.format())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use battery::State;
+
+    use super::{Session, SessionKind, SessionLog};
+    use crate::history::Sample;
+
+    fn sample(minutes: u64, state: State, energy_rate: f32) -> Sample {
+        Sample {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(minutes * 60),
+            state,
+            state_of_charge: 0.5 + minutes as f32 / 1000.0,
+            energy: 25.0,
+            energy_rate,
+            voltage: 12.0,
+            temperature: None,
+            resumed: false,
+        }
+    }
+
+    #[test]
+    fn energy_is_integrated_per_session() {
+        let mut log = SessionLog::in_memory();
+        assert!(log.observe(&sample(0, State::Charging, 10.0)).is_none());
+        assert!(log.observe(&sample(30, State::Charging, 30.0)).is_none());
+        assert_eq!(log.current().map(|session| session.energy), Some(10.0));
+
+        let finished = log.observe(&sample(60, State::Discharging, 5.0)).unwrap();
+        assert_eq!(finished.kind, SessionKind::Charging);
+        assert_eq!(finished.energy, 10.0);
+        assert_eq!(finished.duration(), Duration::from_secs(30 * 60));
+        assert_eq!(finished.average_power(), Some(20.0));
+        assert_eq!((finished.soc_start, finished.soc_end), (0.5, 0.53));
+        assert_eq!(log.records(), &[finished]);
+
+        // Interval spanning a suspend is not counted
+        log.observe(&Sample {
+            resumed: true,
+            ..sample(120, State::Discharging, 5.0)
+        });
+        log.observe(&sample(180, State::Discharging, 5.0));
+        assert_eq!(log.current().map(|session| session.energy), Some(5.0));
+    }
+
+    #[test]
+    fn single_sample_sessions_are_dropped() {
+        let mut log = SessionLog::in_memory();
+        log.observe(&sample(0, State::Charging, 10.0));
+        assert!(log.observe(&sample(1, State::Full, 0.0)).is_none());
+        assert!(log.finish().is_none());
+        assert!(log.records().is_empty());
+    }
+
+    #[test]
+    fn records_are_written_and_parsed() {
+        let line = "charging 2019-06-04T12:00:00Z 2019-06-04T13:30:00Z 35.120 0.210 0.950";
+        let session = Session::parse(line).unwrap();
+
+        assert_eq!(session.kind, SessionKind::Charging);
+        assert_eq!(session.duration(), Duration::from_secs(90 * 60));
+        assert_eq!(session.format(), line);
+        assert!(Session::parse("charging 2019-06-04T12:00:00Z").is_none());
+        assert!(Session::parse("idle 2019-06-04T12:00:00Z 2019-06-04T13:30:00Z 1 0 1").is_none());
+    }
+}
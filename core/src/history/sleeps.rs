@@ -0,0 +1,176 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use battery::State;
+use tracing::debug_span;
+
+use super::Sample;
+
+/// Period when battop was not running because system was suspended or battop process was stopped.
+///
+/// Stored as a lines in the `sleeps` file:
+///
+/// ```text
+/// 2019-06-04T23:10:00Z 2019-06-05T07:40:00Z 0.810 0.760
+/// ```
+///
+/// where columns are start and end timestamps and state of charge before and after the sleep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sleep {
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub soc_start: f32,
+    pub soc_end: f32,
+}
+
+impl Sleep {
+    /// Sleep between two samples, separated by a gap.
+    ///
+    /// Sleeps with the charger attached are ignored, since they are not telling anything about the drain.
+    pub fn between(before: &Sample, after: &Sample) -> Option<Sleep> {
+        if before.state == State::Charging || after.state == State::Charging {
+            return None;
+        }
+        if after.state_of_charge > before.state_of_charge {
+            return None;
+        }
+
+        Some(Sleep {
+            start: before.timestamp,
+            end: after.timestamp,
+            soc_start: before.state_of_charge,
+            soc_end: after.state_of_charge,
+        })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.end.duration_since(self.start).unwrap_or_default()
+    }
+
+    fn parse(line: &str) -> Option<Sleep> {
+        let mut parts = line.split_whitespace();
+
+        Some(Sleep {
+            start: humantime::parse_rfc3339(parts.next()?).ok()?,
+            end: humantime::parse_rfc3339(parts.next()?).ok()?,
+            soc_start: parts.next()?.parse().ok()?,
+            soc_end: parts.next()?.parse().ok()?,
+        })
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{} {} {:.3} {:.3}",
+            humantime::format_rfc3339_seconds(self.start),
+            humantime::format_rfc3339_seconds(self.end),
+            self.soc_start,
+            self.soc_end,
+        )
+    }
+}
+
+/// Chronologically ordered sleeps history
+#[derive(Debug)]
+pub struct SleepLog {
+    path: Option<PathBuf>,
+    records: Vec<Sleep>,
+}
+
+impl SleepLog {
+    pub fn open(path: PathBuf) -> SleepLog {
+        let records = match fs::read_to_string(&path) {
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+                .filter_map(|line| {
+                    let record = Sleep::parse(line);
+                    if record.is_none() {
+                        warn!("Skipping malformed sleep record in {:?}: {:?}", path, line);
+                    }
+                    record
+                })
+                .collect(),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                warn!("Unable to read sleeps history from {:?}: {}", path, e);
+                Vec::new()
+            }
+        };
+        trace!("Loaded {} sleeps from {:?}", records.len(), path);
+
+        SleepLog {
+            path: Some(path),
+            records,
+        }
+    }
+
+    pub fn in_memory() -> SleepLog {
+        SleepLog {
+            path: None,
+            records: Vec::new(),
+        }
+    }
+
+    pub fn records(&self) -> &[Sleep] {
+        self.records.as_ref()
+    }
+
+    pub fn add(&mut self, sleep: Sleep) {
+        if let Err(e) = self.append(&sleep) {
+            warn!("Unable to persist sleep: {}", e);
+        }
+        self.records.push(sleep);
+    }
+
+    fn append(&self, sleep: &Sleep) -> io::Result<()> {
+        if let Some(path) = &self.path {
+            let _span = debug_span!("persist", path = %path.display()).entered();
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", sleep.format())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use battery::State;
+
+    use super::Sleep;
+    use crate::history::Sample;
+
+    fn sample(hours: u64, state: State, state_of_charge: f32) -> Sample {
+        Sample {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(hours * 3600),
+            state,
+            state_of_charge,
+            energy: 50.0 * state_of_charge,
+            energy_rate: 0.0,
+            voltage: 12.0,
+            temperature: None,
+            resumed: false,
+        }
+    }
+
+    #[test]
+    fn sleeps_on_battery_are_recorded() {
+        let sleep = Sleep::between(
+            &sample(0, State::Discharging, 0.9),
+            &sample(8, State::Discharging, 0.82),
+        )
+        .unwrap();
+        assert_eq!(sleep.duration(), Duration::from_secs(8 * 3600));
+        assert_eq!(sleep.format(), "1970-01-01T00:00:00Z 1970-01-01T08:00:00Z 0.900 0.820");
+        assert_eq!(Sleep::parse(&sleep.format()), Some(sleep));
+
+        // Charger was attached before or after the suspend
+        assert!(Sleep::between(&sample(0, State::Charging, 0.5), &sample(8, State::Full, 1.0)).is_none());
+        assert!(Sleep::between(&sample(0, State::Full, 1.0), &sample(8, State::Charging, 0.9)).is_none());
+        assert!(Sleep::between(&sample(0, State::Unknown, 0.5), &sample(8, State::Unknown, 0.6)).is_none());
+    }
+}
@@ -0,0 +1,421 @@
+//! Per-battery time series: the samples themselves and the channels recorded along with them,
+//! ex. the CPU package power or the cell voltages.
+//!
+//! Charts, statistics and alerts are all reading the same store, nothing keeps its own copy.
+//! Each sample gets the next sequence number, and the channel values are stamped with the number
+//! of the sample they were recorded at, so all the series are aligned.
+//!
+//! Memory is bounded by the `Retention`: once there are more samples than allowed, the older half
+//! of them is merged pairwise, so the recent samples keep their resolution while the oldest ones
+//! are getting coarser with each merge. Merged sample keeps the sequence number of the newer one.
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+use super::Sample;
+use crate::device::HistoryPoint;
+
+/// How many samples are kept in memory, per battery
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Retention {
+    /// Samples older than that are dropped
+    pub duration: Duration,
+    /// Samples above that are merged, see the module documentation
+    pub samples: usize,
+}
+
+impl Retention {
+    /// Merging is not worth it below that
+    pub const MIN_SAMPLES: usize = 16;
+}
+
+impl Default for Retention {
+    /// A day of the samples made each second
+    fn default() -> Retention {
+        Retention {
+            duration: Duration::from_secs(24 * 60 * 60),
+            samples: 24 * 60 * 60,
+        }
+    }
+}
+
+/// Series which can be read from the store
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    Voltage,
+    Energy,
+    /// Including the history reported by the backend, see `SampleStore::backfill`
+    EnergyRate,
+    /// Ratio, 0–1
+    StateOfCharge,
+    /// Values recorded with `SampleStore::record`
+    Channel(String),
+}
+
+#[derive(Debug)]
+struct Channel {
+    name: String,
+    values: VecDeque<(u64, f64)>,
+}
+
+#[derive(Debug)]
+struct Stored {
+    sequence: u64,
+    /// Amount of the samples merged into this one
+    weight: u32,
+    sample: Sample,
+}
+
+/// In-memory chronologically ordered samples of the current battop run and the channels recorded with them
+#[derive(Debug, Default)]
+pub struct SampleStore {
+    samples: VecDeque<Stored>,
+    /// Sequence number of the latest sample
+    sequence: u64,
+    channels: Vec<Channel>,
+    /// Energy rate history reported by the backend itself, numbered before the first sample
+    rate_history: VecDeque<(u64, f64)>,
+    retention: Retention,
+}
+
+impl SampleStore {
+    pub fn new(retention: Retention) -> SampleStore {
+        SampleStore {
+            retention,
+            ..SampleStore::default()
+        }
+    }
+
+    /// Prepend the energy rate history kept by the backend, ex. UPower, so charts do not start from scratch.
+    ///
+    /// Should be called before the first sample is pushed.
+    pub fn backfill(&mut self, history: &[HistoryPoint]) {
+        for point in history {
+            self.sequence += 1;
+            self.rate_history.push_back((self.sequence, f64::from(point.value)));
+        }
+    }
+
+    pub fn push(&mut self, sample: Sample) {
+        let mut expired = false;
+        while let Some(oldest) = self.samples.front() {
+            match sample.timestamp.duration_since(oldest.sample.timestamp) {
+                Ok(age) if age > self.retention.duration => {
+                    let _ = self.samples.pop_front();
+                    expired = true;
+                }
+                _ => break,
+            }
+        }
+        self.sequence += 1;
+        self.samples.push_back(Stored {
+            sequence: self.sequence,
+            weight: 1,
+            sample,
+        });
+        if self.samples.len() > self.retention.samples.max(Retention::MIN_SAMPLES) {
+            self.downsample();
+        }
+        if !expired {
+            return;
+        }
+
+        // Channels are not outliving the samples they were recorded with
+        let first = self.first_sequence();
+        let expire = |values: &mut VecDeque<(u64, f64)>| {
+            while values.front().is_some_and(|(sequence, _)| *sequence < first) {
+                let _ = values.pop_front();
+            }
+        };
+        expire(&mut self.rate_history);
+        for channel in self.channels.iter_mut() {
+            expire(&mut channel.values);
+        }
+    }
+
+    /// Merge the older half of the samples pairwise, along with the channel values recorded with them
+    fn downsample(&mut self) {
+        let count = self.samples.len() / 4 * 2;
+        let weights = self
+            .samples
+            .iter()
+            .take(count)
+            .map(|stored| (stored.sequence, stored.weight))
+            .collect::<Vec<_>>();
+        let mut samples = VecDeque::with_capacity(self.samples.len() - count / 2);
+        let mut older = self.samples.drain(..count);
+        while let (Some(older), Some(newer)) = (older.next(), older.next()) {
+            samples.push_back(merge(&older, &newer));
+        }
+        drop(older);
+        let merged = samples.iter().map(|stored| stored.sequence).collect::<Vec<_>>();
+        samples.extend(self.samples.drain(..));
+        self.samples = samples;
+
+        for channel in self.channels.iter_mut() {
+            regroup(&mut channel.values, &weights, &merged);
+        }
+    }
+
+    /// Sequence number of the oldest sample kept
+    fn first_sequence(&self) -> u64 {
+        self.samples
+            .front()
+            .map_or(self.sequence + 1, |stored| stored.sequence)
+    }
+
+    /// Add the channel value at the latest sample
+    pub fn record(&mut self, name: &str, value: f64) {
+        let index = match self.channels.iter().position(|channel| channel.name == name) {
+            Some(index) => index,
+            None => {
+                self.channels.push(Channel {
+                    name: name.to_string(),
+                    values: VecDeque::new(),
+                });
+                self.channels.len() - 1
+            }
+        };
+
+        self.channels[index].values.push_back((self.sequence, value));
+    }
+
+    /// Sequence number of the latest sample
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.back().map(|stored| &stored.sample)
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Sample> {
+        self.samples.iter().map(|stored| &stored.sample)
+    }
+
+    /// Samples made between `start` and `end` moments, inclusive
+    pub fn range(&self, start: SystemTime, end: SystemTime) -> impl Iterator<Item = &Sample> {
+        self.iter()
+            .filter(move |sample| sample.timestamp >= start && sample.timestamp <= end)
+    }
+
+    /// Samples made during the last `window` period of time
+    pub fn window(&self, window: Duration) -> impl Iterator<Item = &Sample> {
+        let since = self
+            .latest()
+            .and_then(|latest| latest.timestamp.checked_sub(window))
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        self.iter().filter(move |sample| sample.timestamp >= since)
+    }
+
+    /// Values of the `field` recorded at the last `count` samples, with their sequence numbers, oldest first
+    pub fn values(&self, field: &Field, count: usize) -> Vec<(u64, f64)> {
+        let since = self.sequence.saturating_sub(count as u64);
+        match field {
+            Field::Channel(name) => match self.channels.iter().find(|channel| channel.name == *name) {
+                Some(channel) => latest(channel.values.iter().cloned(), since),
+                None => Vec::new(),
+            },
+            _ => {
+                let samples = self.samples.iter().map(|stored| {
+                    let value = match field {
+                        Field::Voltage => stored.sample.voltage,
+                        Field::Energy => stored.sample.energy,
+                        Field::EnergyRate => stored.sample.energy_rate,
+                        _ => stored.sample.state_of_charge,
+                    };
+                    (stored.sequence, f64::from(value))
+                });
+                let mut values = latest(samples, since);
+                if *field == Field::EnergyRate {
+                    let mut history = latest(self.rate_history.iter().cloned(), since);
+                    history.extend(values);
+                    values = history;
+                }
+                values
+            }
+        }
+    }
+
+    /// The most recent value of the `field`, even if it was not recorded at the latest sample
+    pub fn latest_value(&self, field: &Field) -> Option<f64> {
+        match field {
+            Field::Channel(name) => self
+                .channels
+                .iter()
+                .find(|channel| channel.name == *name)
+                .and_then(|channel| channel.values.back())
+                .map(|(_, value)| *value),
+            _ => self.values(field, 1).pop().map(|(_, value)| value),
+        }
+    }
+}
+
+/// Samples averaged by their weights, the state and the timestamp are the ones of the `newer`
+fn merge(older: &Stored, newer: &Stored) -> Stored {
+    let weight = older.weight + newer.weight;
+    let average =
+        |a: f32, b: f32| (a * older.weight as f32 + b * newer.weight as f32) / weight as f32;
+    let temperature = match (older.sample.temperature, newer.sample.temperature) {
+        (Some(a), Some(b)) => Some(average(a, b)),
+        (a, b) => b.or(a),
+    };
+
+    Stored {
+        sequence: newer.sequence,
+        weight,
+        sample: Sample {
+            state_of_charge: average(older.sample.state_of_charge, newer.sample.state_of_charge),
+            energy: average(older.sample.energy, newer.sample.energy),
+            energy_rate: average(older.sample.energy_rate, newer.sample.energy_rate),
+            voltage: average(older.sample.voltage, newer.sample.voltage),
+            temperature,
+            resumed: older.sample.resumed || newer.sample.resumed,
+            ..newer.sample
+        },
+    }
+}
+
+/// Average the channel `values` recorded up to the last of the `merged` samples,
+/// each one into the first merged sample not older than it.
+///
+/// Values are weighted as the samples they were recorded at, given by their sequence numbers in `weights`.
+fn regroup(values: &mut VecDeque<(u64, f64)>, weights: &[(u64, u32)], merged: &[u64]) {
+    let last = match merged.last() {
+        Some(last) => *last,
+        None => return,
+    };
+
+    // Sequence number of the merged sample, weighted sum of the values and the sum of their weights
+    let mut groups: Vec<(u64, f64, u32)> = Vec::new();
+    let mut targets = merged.iter().peekable();
+    while values.front().is_some_and(|(sequence, _)| *sequence <= last) {
+        let (sequence, value) = values.pop_front().expect("Value was just checked");
+        while targets.peek().is_some_and(|target| **target < sequence) {
+            let _ = targets.next();
+        }
+        let target = **targets.peek().expect("Value is not newer than the last merged sample");
+        let weight = weights
+            .binary_search_by_key(&sequence, |(recorded, _)| *recorded)
+            .map_or(1, |index| weights[index].1);
+        match groups.last_mut() {
+            Some(group) if group.0 == target => {
+                group.1 += value * f64::from(weight);
+                group.2 += weight;
+            }
+            _ => groups.push((target, value * f64::from(weight), weight)),
+        }
+    }
+
+    for (sequence, sum, count) in groups.into_iter().rev() {
+        values.push_front((sequence, sum / f64::from(count)));
+    }
+}
+
+/// Values numbered after `since`, walking from the newest ones so the older are not visited
+fn latest<I>(values: I, since: u64) -> Vec<(u64, f64)>
+where
+    I: DoubleEndedIterator<Item = (u64, f64)>,
+{
+    let mut latest = values
+        .rev()
+        .take_while(|(sequence, _)| *sequence > since)
+        .collect::<Vec<_>>();
+    latest.reverse();
+
+    latest
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use battery::State;
+
+    use super::{Field, Retention, SampleStore};
+    use crate::history::Sample;
+
+    fn sample(timestamp: SystemTime, voltage: f32) -> Sample {
+        Sample {
+            timestamp,
+            state: State::Discharging,
+            state_of_charge: 0.5,
+            energy: 25.0,
+            energy_rate: 10.0,
+            voltage,
+            temperature: None,
+            resumed: false,
+        }
+    }
+
+    #[test]
+    fn channels_are_aligned_with_the_samples() {
+        let mut store = SampleStore::default();
+        let start = SystemTime::UNIX_EPOCH;
+        for i in 0..4 {
+            store.push(sample(start + Duration::from_secs(i), 12.0 + i as f32));
+            // Channel is missing at the second sample
+            if i != 1 {
+                store.record("power.cpu", i as f64);
+            }
+        }
+
+        assert_eq!(store.sequence(), 4);
+        assert_eq!(store.values(&Field::Voltage, 2), vec![(3, 14.0), (4, 15.0)]);
+        assert_eq!(
+            store.values(&Field::Channel("power.cpu".to_string()), 3),
+            vec![(3, 2.0), (4, 3.0)]
+        );
+        assert_eq!(store.values(&Field::Channel("power.gpu".to_string()), 3), vec![]);
+    }
+
+    #[test]
+    fn latest_value_outlives_the_latest_sample() {
+        let mut store = SampleStore::default();
+        assert_eq!(store.latest_value(&Field::Voltage), None);
+
+        store.push(sample(SystemTime::UNIX_EPOCH, 12.0));
+        store.record("temperature", 30.0);
+        store.push(sample(SystemTime::UNIX_EPOCH + Duration::from_secs(1), 11.5));
+
+        assert_eq!(store.latest_value(&Field::Voltage), Some(11.5));
+        assert_eq!(store.latest_value(&Field::Channel("temperature".to_string())), Some(30.0));
+    }
+
+    #[test]
+    fn expired_samples_take_their_channels_along() {
+        let mut store = SampleStore::default();
+        store.push(sample(SystemTime::UNIX_EPOCH, 12.0));
+        store.record("power.cpu", 1.0);
+        let retention = Retention::default().duration;
+        store.push(sample(SystemTime::UNIX_EPOCH + retention + Duration::from_secs(1), 11.0));
+
+        assert_eq!(store.iter().count(), 1);
+        assert_eq!(store.values(&Field::Voltage, 10), vec![(2, 11.0)]);
+        assert_eq!(store.latest_value(&Field::Channel("power.cpu".to_string())), None);
+    }
+
+    #[test]
+    fn older_samples_are_merged_over_the_limit() {
+        let mut store = SampleStore::new(Retention {
+            samples: Retention::MIN_SAMPLES,
+            ..Retention::default()
+        });
+        for i in 0..100 {
+            store.push(sample(SystemTime::UNIX_EPOCH + Duration::from_secs(i), i as f32));
+            store.record("power.cpu", i as f64);
+        }
+
+        assert!(store.iter().count() <= Retention::MIN_SAMPLES);
+        assert_eq!(store.latest_value(&Field::Voltage), Some(99.0));
+        // Merged samples are covering all the samples before them, so the average is kept
+        let voltages = store.values(&Field::Voltage, 100);
+        let first = voltages[0];
+        assert_eq!(first.1, (first.0 - 1) as f64 / 2.0);
+        assert_eq!(store.values(&Field::Channel("power.cpu".to_string()), 100)[0], first);
+        // Recent samples keep their resolution
+        let recent = voltages[voltages.len() - 4..].iter().map(|(_, voltage)| *voltage).collect::<Vec<_>>();
+        assert_eq!(recent, vec![96.0, 97.0, 98.0, 99.0]);
+    }
+}
@@ -0,0 +1,59 @@
+//! Suspends are told apart from the slow polls by comparing two clocks: the wall-clock time keeps
+//! going while the system is suspended, while the monotonic one, `Instant`, does not on Linux and macOS.
+//!
+//! Platforms where the monotonic clock counts the suspended time as well are left to the wall-clock
+//! gap check of the `History`.
+
+use std::time::{Duration, Instant, SystemTime};
+
+/// Clocks divergence below that is the clock adjustments or the scheduling noise
+const MIN_SUSPEND: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+pub struct SuspendClock {
+    /// Both clocks at the previous check
+    previous: Option<(Instant, SystemTime)>,
+}
+
+impl SuspendClock {
+    /// Time the system was suspended for since the previous check, if it was
+    pub fn check(&mut self, now: SystemTime) -> Option<Duration> {
+        self.check_at(Instant::now(), now)
+    }
+
+    fn check_at(&mut self, instant: Instant, now: SystemTime) -> Option<Duration> {
+        let (instant_before, before) = self.previous.replace((instant, now))?;
+        let wall = now.duration_since(before).ok()?;
+        let monotonic = instant.duration_since(instant_before);
+
+        match wall.checked_sub(monotonic) {
+            Some(suspended) if suspended >= MIN_SUSPEND => Some(suspended),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant, SystemTime};
+
+    use super::SuspendClock;
+
+    #[test]
+    fn suspend_is_the_clocks_divergence() {
+        let mut clock = SuspendClock::default();
+        let (instant, now) = (Instant::now(), SystemTime::now());
+        let minute = Duration::from_secs(60);
+
+        assert_eq!(clock.check_at(instant, now), None);
+        // Slow poll, both clocks went the same way
+        assert_eq!(clock.check_at(instant + minute, now + minute), None);
+        // Wall clock went an hour further than the monotonic one
+        assert_eq!(
+            clock.check_at(instant + minute * 2, now + minute * 62),
+            Some(minute * 60)
+        );
+        // Clock set back by the user
+        assert_eq!(clock.check_at(instant + minute * 3, now), None);
+    }
+}
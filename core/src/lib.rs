@@ -0,0 +1,65 @@
+//! Battery data collection and analysis behind the [battop](https://github.com/svartalf/rust-battop) viewer,
+//! usable on its own by the status bars, daemons and other tools.
+//!
+//! * [`device`] enumerates and refreshes the batteries, UPSes and peripherals with the backends
+//!   implementing the [`PowerSource`](device::PowerSource) trait; [`Provider`](device::Provider)
+//!   starts the ones selected with the [`Options`](device::Options), and
+//!   [`Collector`](device::Collector) runs them on a separate thread. On Unix the devices collected
+//!   by one process can be shared over a socket with the [`Server`](device::Server) and read by the others
+//!   with the [`Daemon`](device::Daemon) backend. With the `prometheus` feature, `prometheus`
+//!   serves them to the Prometheus scrapes.
+//! * [`history`] keeps the samples, charge sessions and health records of a battery,
+//!   optionally persisted in the battop data directory.
+//! * [`stats`] turns the history into the statistics: power percentiles, degradation trend,
+//!   runtime forecasts, charging cost and others.
+//! * [`format`] has the measurement units and the formatting shared by the frontends.
+//! * [`script`] evaluates the user expressions over the samples, ex. the derived series.
+//!
+//! The rest are the system readings collected along with the batteries:
+//! CPU package power ([`rapl`]), CPU load ([`cpu`]), temperature sensors ([`sensors`]),
+//! display brightness ([`brightness`]), the power adapter ([`adapter`])
+//! and the per-process power attribution ([`processes`]).
+//! External programs are started with [`command`], so they could be interrupted like battop itself.
+//! [`system`] takes these readings on the collection threads, along with the device refreshes.
+//! [`uevents`] and [`signals`] are turning the power supply notifications and the process signals
+//! into the callbacks on Linux and Unix respectively.
+//!
+//! Enumeration, refreshes and history writes are wrapped in the [`tracing`](https://docs.rs/tracing) spans,
+//! timed by the subscriber the frontend installs, if any.
+//!
+//! ```no_run
+//! use battop_core::device::{Options, Provider};
+//!
+//! let (mut provider, mut devices) = Provider::new(&Options::default())?;
+//! for device in devices.iter_mut() {
+//!     provider.refresh(device)?;
+//!     println!("{}: {:?}", device.id(), device.state_of_charge());
+//! }
+//! # Ok::<(), battop_core::Error>(())
+//! ```
+
+#[macro_use]
+extern crate log;
+
+pub mod adapter;
+pub mod brightness;
+pub mod command;
+pub mod cpu;
+pub mod device;
+mod errors;
+pub mod format;
+pub mod history;
+pub mod processes;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+pub mod rapl;
+pub mod script;
+pub mod sensors;
+#[cfg(unix)]
+pub mod signals;
+pub mod stats;
+pub mod system;
+#[cfg(target_os = "linux")]
+pub mod uevents;
+
+pub use self::errors::{Error, Result};
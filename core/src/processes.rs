@@ -0,0 +1,206 @@
+//! Per-process power attribution, Linux only.
+//!
+//! CPU time consumed by each process between two updates is taken from `/proc`
+//! and the package energy is taken from the RAPL counters (`/sys/class/powercap`),
+//! so each process gets the share of the package power proportional to its share of the CPU time.
+//! RAPL counters are not always readable by the unprivileged users,
+//! in that case battery discharge power is shared between processes instead.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::rapl::{self, Counter};
+
+const PROC: &str = "/proc";
+
+/// Process share of the CPU time consumed between two updates
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessUsage {
+    pub pid: u32,
+    pub name: String,
+    /// Share of the busy CPU time, `0.0..=1.0`
+    pub share: f64,
+}
+
+/// Processes sorted by their share of the busy CPU time, biggest first
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribution {
+    /// CPU package power measured with RAPL, W
+    pub package_power: Option<f64>,
+    pub processes: Vec<ProcessUsage>,
+}
+
+#[derive(Debug)]
+struct Counters {
+    timestamp: Instant,
+    /// CPU time spent by all CPUs not being idle, in clock ticks
+    busy: u64,
+    /// Process name and CPU time spent by process, in clock ticks
+    processes: HashMap<u32, (String, u64)>,
+    /// Package energy counters, µJ
+    energy: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct ProcessMonitor {
+    packages: Vec<Counter>,
+    previous: Option<Counters>,
+}
+
+impl ProcessMonitor {
+    pub fn new() -> ProcessMonitor {
+        let packages = rapl::packages();
+        if packages.is_empty() {
+            debug!("RAPL package energy counters are not available, using battery power instead");
+        }
+
+        ProcessMonitor {
+            packages,
+            previous: None,
+        }
+    }
+
+    /// Read the counters and attribute the CPU time consumed since the previous update.
+    ///
+    /// Returns `None` on the first update, since there is nothing to compare with yet.
+    pub fn update(&mut self) -> io::Result<Option<Attribution>> {
+        let current = Counters {
+            timestamp: Instant::now(),
+            busy: busy_ticks()?,
+            processes: process_ticks()?,
+            energy: self.energy(),
+        };
+
+        let attribution = match self.previous {
+            Some(ref previous) => Some(attribute(previous, &current, &self.packages)),
+            None => None,
+        };
+        self.previous = Some(current);
+
+        Ok(attribution)
+    }
+
+    fn energy(&self) -> Option<u64> {
+        if self.packages.is_empty() {
+            return None;
+        }
+
+        let mut total = 0;
+        for package in self.packages.iter() {
+            total += package.read()?;
+        }
+
+        Some(total)
+    }
+}
+
+impl Default for ProcessMonitor {
+    fn default() -> ProcessMonitor {
+        ProcessMonitor::new()
+    }
+}
+
+fn attribute(previous: &Counters, current: &Counters, packages: &[Counter]) -> Attribution {
+    let busy = current.busy.saturating_sub(previous.busy);
+    let mut processes = current
+        .processes
+        .iter()
+        .filter_map(|(pid, (name, ticks))| {
+            // Process might be a new one with the same pid, its counters are starting over
+            let before = match previous.processes.get(pid) {
+                Some((previous_name, before)) if previous_name == name && before <= ticks => *before,
+                _ => 0,
+            };
+            let delta = ticks - before;
+            if delta == 0 || busy == 0 {
+                return None;
+            }
+
+            Some(ProcessUsage {
+                pid: *pid,
+                name: name.clone(),
+                share: (delta as f64 / busy as f64).min(1.0),
+            })
+        })
+        .collect::<Vec<_>>();
+    processes.sort_by(|a, b| b.share.partial_cmp(&a.share).expect("shares are finite"));
+
+    let package_power = match (previous.energy, current.energy) {
+        (Some(before), Some(after)) => {
+            let elapsed = current.timestamp.duration_since(previous.timestamp);
+            let seconds = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_millis()) / 1000.0;
+            // Sum of the wrap-around ranges is good enough for the multiple packages
+            let max = packages.iter().map(Counter::max).sum::<u64>();
+            let consumed = rapl::consumed(before, after, max);
+            if seconds > 0.0 {
+                Some(consumed as f64 / 1_000_000.0 / seconds)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+
+    Attribution {
+        package_power,
+        processes,
+    }
+}
+
+/// Non-idle time of all CPUs from the `/proc/stat`
+fn busy_ticks() -> io::Result<u64> {
+    let stat = fs::read_to_string(PathBuf::from(PROC).join("stat"))?;
+    let line = stat
+        .lines()
+        .find(|line| line.starts_with("cpu "))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing cpu line in /proc/stat"))?;
+
+    let values = line
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|value| value.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+    // user nice system idle iowait irq softirq steal ...
+    let total = values.iter().take(8).sum::<u64>();
+    let idle = values.iter().skip(3).take(2).sum::<u64>();
+
+    Ok(total.saturating_sub(idle))
+}
+
+/// Name and CPU time (user and system) of each process
+fn process_ticks() -> io::Result<HashMap<u32, (String, u64)>> {
+    let mut processes = HashMap::new();
+    for entry in fs::read_dir(PROC)?.flatten() {
+        let pid = match entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) {
+            Some(pid) => pid,
+            None => continue,
+        };
+        // Process might exit while we are iterating, it is not an error
+        let stat = match fs::read_to_string(entry.path().join("stat")) {
+            Ok(stat) => stat,
+            Err(_) => continue,
+        };
+        if let Some(process) = parse_stat(&stat) {
+            let _ = processes.insert(pid, process);
+        }
+    }
+
+    Ok(processes)
+}
+
+/// Parse `/proc/[pid]/stat`, where process name is wrapped into parentheses and might contain spaces
+fn parse_stat(stat: &str) -> Option<(String, u64)> {
+    let name_start = stat.find('(')?;
+    let name_end = stat.rfind(')')?;
+    let name = stat.get(name_start + 1..name_end)?.to_string();
+
+    // Fields after the name are starting from the `state`, `utime` and `stime` are 14th and 15th fields
+    let mut fields = stat.get(name_end + 1..)?.split_whitespace().skip(11);
+    let utime = fields.next()?.parse::<u64>().ok()?;
+    let stime = fields.next()?.parse::<u64>().ok()?;
+
+    Some((name, utime + stime))
+}
@@ -0,0 +1,242 @@
+//! Devices exported in the Prometheus text format over HTTP, served by the `battop daemon`.
+//!
+//! Metrics are rendered once per `publish` and the scrapes are answered with the last rendering,
+//! so a scrape never waits for the devices. Every metric is labeled with the device id,
+//! its kind and model:
+//!
+//! ```text
+//! # HELP battop_state_of_charge State of charge, from 0 to 1
+//! # TYPE battop_state_of_charge gauge
+//! battop_state_of_charge{device="sysfs:/sys/class/power_supply/BAT0",kind="battery",model="5B10W13930"} 0.81
+//! ```
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::watt_hour;
+use battery::units::power::watt;
+use battery::units::ratio::ratio;
+use battery::units::thermodynamic_temperature::degree_celsius;
+use battery::units::time::second;
+use battery::State;
+use tracing::debug_span;
+
+use crate::device::Device;
+use crate::Result;
+
+/// Path the metrics are served on, the rest are not found
+const PATH: &str = "/metrics";
+/// Scraper which is not sending its request in time is dropped
+const TIMEOUT: Duration = Duration::from_secs(5);
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Listening side of the exporter, answering with the devices `publish`ed last
+#[derive(Debug)]
+pub struct Exporter {
+    address: String,
+    rendered: Arc<Mutex<String>>,
+}
+
+impl Exporter {
+    /// Listen on the `address`, ex. `127.0.0.1:9851`
+    pub fn bind(address: &str) -> Result<Exporter> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| io::Error::new(e.kind(), format!("unable to listen on {}: {}", address, e)))?;
+        let address = listener.local_addr()?.to_string();
+        let rendered = Arc::new(Mutex::new(String::new()));
+        let shared = rendered.clone();
+        thread::Builder::new().name("prometheus".to_string()).spawn(move || {
+            // Scrapes are rare and answered from memory, so they are served one by one
+            for stream in listener.incoming() {
+                let result = stream.and_then(|stream| serve(stream, &shared));
+                if let Err(e) = result {
+                    debug!("Prometheus scrape dropped: {}", e);
+                }
+            }
+        })?;
+        info!("Prometheus metrics are served on http://{}{}", address, PATH);
+
+        Ok(Exporter {
+            address,
+            rendered,
+        })
+    }
+
+    /// Address the exporter listens on, with the port chosen if the `0` one was requested
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Devices the scrapes are getting from now on
+    pub fn publish<'a, I>(&self, devices: I)
+    where
+        I: IntoIterator<Item = &'a Device>,
+    {
+        let _span = debug_span!("export").entered();
+        let rendered = render(devices);
+        *self.rendered.lock().expect("Prometheus exporter had panicked") = rendered;
+    }
+}
+
+/// Answer one scrape and close the connection
+fn serve(stream: TcpStream, rendered: &Mutex<String>) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut lines = BufReader::new(stream).lines();
+    let request = lines.next().unwrap_or_else(|| Ok(String::new()))?;
+    // Headers are not needed, but the scraper expects them to be read
+    for line in lines {
+        if line?.is_empty() {
+            break;
+        }
+    }
+    trace!("Prometheus scrape: {}", request);
+
+    let mut parts = request.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) if path == PATH || path.starts_with("/metrics?") => {
+            ("200 OK", rendered.lock().expect("Prometheus exporter had panicked").clone())
+        }
+        (Some("GET"), Some(_)) => ("404 Not Found", format!("Metrics are served on {}\n", PATH)),
+        _ => ("405 Method Not Allowed", String::new()),
+    };
+    write!(
+        writer,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        CONTENT_TYPE,
+        body.len(),
+        body
+    )?;
+    writer.flush()
+}
+
+/// Metrics of the `devices` in the text exposition format
+pub fn render<'a, I>(devices: I) -> String
+where
+    I: IntoIterator<Item = &'a Device>,
+{
+    let devices = devices.into_iter().collect::<Vec<_>>();
+    let mut output = String::new();
+    let mut metric = |name: &str, help: &str, value: &dyn Fn(&Device) -> Option<f32>| {
+        let samples = devices
+            .iter()
+            .filter_map(|device| value(device).map(|value| (labels(device), value)))
+            .collect::<Vec<_>>();
+        if samples.is_empty() {
+            return;
+        }
+        output.push_str(&format!("# HELP battop_{} {}\n# TYPE battop_{} gauge\n", name, help, name));
+        for (labels, value) in samples {
+            output.push_str(&format!("battop_{}{{{}}} {}\n", name, labels, value));
+        }
+    };
+
+    metric("state_of_charge", "State of charge, from 0 to 1", &|device| {
+        Some(device.state_of_charge().get::<ratio>())
+    });
+    metric("energy_wh", "Energy stored, in W·h", &|device| {
+        Some(device.energy().get::<watt_hour>())
+    });
+    metric("energy_full_wh", "Energy stored when fully charged, in W·h", &|device| {
+        Some(device.energy_full().get::<watt_hour>())
+    });
+    metric("energy_full_design_wh", "Designed full energy, in W·h", &|device| {
+        Some(device.energy_full_design().get::<watt_hour>())
+    });
+    metric("energy_rate_watts", "Power drawn or charged with, in W", &|device| {
+        Some(device.energy_rate().get::<watt>())
+    });
+    metric("voltage_volts", "Voltage, in V", &|device| {
+        Some(device.voltage().get::<volt>())
+    });
+    metric("state_of_health", "Full energy relative to the designed one, from 0 to 1", &|device| {
+        Some(device.state_of_health().get::<ratio>())
+    });
+    metric("temperature_celsius", "Temperature, in °C", &|device| {
+        device.temperature().map(|temp| temp.get::<degree_celsius>())
+    });
+    metric("cycle_count", "Charge cycles", &|device| device.cycle_count().map(|count| count as f32));
+    metric("time_to_full_seconds", "Time until fully charged, in seconds", &|device| {
+        device.time_to_full().map(|time| time.get::<second>())
+    });
+    metric("time_to_empty_seconds", "Time until empty, in seconds", &|device| {
+        device.time_to_empty().map(|time| time.get::<second>())
+    });
+    metric("charging", "1 if the device is charging, 0 otherwise", &|device| {
+        Some(if device.state() == State::Charging { 1.0 } else { 0.0 })
+    });
+
+    output
+}
+
+fn labels(device: &Device) -> String {
+    let mut labels = vec![
+        ("device", device.id()),
+        ("kind", device.kind().to_string().to_lowercase()),
+    ];
+    if let Some(model) = device.model() {
+        labels.push(("model", model.to_string()));
+    }
+
+    labels
+        .into_iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape(&value)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Label values are quoted, with the backslashes, quotes and line breaks escaped
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use super::{escape, render, Exporter};
+    use crate::device::{PowerSource, Reading, Scripted};
+
+    #[test]
+    fn devices_are_rendered() {
+        let mut source = Scripted::new("Test \"battery\"", 50.0, vec![Some(Reading::discharging(0.5, 10.0))]);
+        let device = source.devices().expect("Scripted battery").remove(0);
+        let rendered = render(vec![&device]);
+
+        let labels = format!("device=\"{}\",kind=\"battery\",model=\"Test \\\"battery\\\"\"", escape(&device.id()));
+        assert!(rendered.contains("# TYPE battop_state_of_charge gauge\n"));
+        assert!(rendered.contains(&format!("battop_state_of_charge{{{}}} 0.5\n", labels)));
+        assert!(rendered.contains(&format!("battop_energy_rate_watts{{{}}} 10\n", labels)));
+        assert!(rendered.contains(&format!("battop_charging{{{}}} 0\n", labels)));
+        assert_eq!(render(Vec::new()), "");
+    }
+
+    #[test]
+    fn metrics_are_served() {
+        let mut source = Scripted::new("Test battery", 50.0, vec![Some(Reading::discharging(0.5, 10.0))]);
+        let device = source.devices().expect("Scripted battery").remove(0);
+        let exporter = Exporter::bind("127.0.0.1:0").expect("Exporter");
+        exporter.publish(vec![&device]);
+
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(exporter.address()).expect("Scrape");
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).expect("Request");
+            let mut response = String::new();
+            stream.read_to_string(&mut response).expect("Response");
+            response
+        };
+        let response = get("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(&render(vec![&device])));
+        assert!(get("/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
@@ -0,0 +1,171 @@
+//! RAPL (Running Average Power Limit) energy counters, Linux only.
+//!
+//! Intel and AMD CPUs are counting the energy consumed by the package and some of its parts,
+//! kernel exposes these counters in the `/sys/class/powercap`.
+//! Counters are readable by root only on the recent kernels.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+const POWERCAP: &str = "/sys/class/powercap";
+
+/// One RAPL domain energy counter, ex. `/sys/class/powercap/intel-rapl:0`
+#[derive(Debug)]
+pub struct Counter {
+    path: PathBuf,
+    /// Counter wraps around after reaching this value, µJ
+    max: u64,
+}
+
+impl Counter {
+    /// Current counter value, µJ
+    pub fn read(&self) -> Option<u64> {
+        read_u64(&self.path.join("energy_uj")).ok()
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+}
+
+/// Readable package domains
+pub fn packages() -> Vec<Counter> {
+    domains(POWERCAP, |name| name.starts_with("package"))
+}
+
+/// Readable integrated GPU domains, which are the `uncore` subdomains of the packages
+pub fn gpus() -> Vec<Counter> {
+    let mut gpus = Vec::new();
+    for package in packages() {
+        gpus.extend(domains(&package.path, |name| name == "uncore"));
+    }
+
+    gpus
+}
+
+fn domains<P: AsRef<Path>, F: Fn(&str) -> bool>(dir: P, filter: F) -> Vec<Counter> {
+    let entries = match fs::read_dir(dir.as_ref()) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut domains = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| match fs::read_to_string(path.join("name")) {
+            Ok(name) => filter(name.trim()),
+            Err(_) => false,
+        })
+        .filter_map(|path| {
+            read_u64(&path.join("energy_uj")).ok()?;
+            let max = read_u64(&path.join("max_energy_range_uj")).unwrap_or(u64::MAX);
+            trace!("Found RAPL counter at {:?}", path);
+
+            Some(Counter {
+                path,
+                max,
+            })
+        })
+        .collect::<Vec<_>>();
+    domains.sort_by(|a, b| a.path.cmp(&b.path));
+
+    domains
+}
+
+/// Energy consumed between two readings of the counters with the `max` wrap-around range, µJ
+pub fn consumed(before: u64, after: u64, max: u64) -> u64 {
+    if after >= before {
+        after - before
+    } else {
+        max.saturating_sub(before) + after
+    }
+}
+
+/// Power measured by the RAPL counters since the previous update, W
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Power {
+    pub package: f64,
+    /// Integrated GPU, if its domain is available
+    pub gpu: Option<f64>,
+}
+
+/// Turns the energy counters into the power readings
+#[derive(Debug)]
+pub struct PowerMeter {
+    packages: Vec<Counter>,
+    gpus: Vec<Counter>,
+    previous: Option<(Instant, u64, Option<u64>)>,
+}
+
+impl PowerMeter {
+    /// Meter for the readable counters, `None` if there are none
+    pub fn new() -> Option<PowerMeter> {
+        let packages = packages();
+        if packages.is_empty() {
+            return None;
+        }
+
+        Some(PowerMeter {
+            packages,
+            gpus: gpus(),
+            previous: None,
+        })
+    }
+
+    /// Read the counters, returns `None` on the first update, since there is nothing to compare with yet
+    pub fn update(&mut self) -> Option<Power> {
+        let now = Instant::now();
+        let package = total(&self.packages)?;
+        let gpu = total(&self.gpus);
+
+        let power = match self.previous {
+            Some((timestamp, package_before, gpu_before)) => {
+                let elapsed = now.duration_since(timestamp);
+                let seconds = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_millis()) / 1000.0;
+                if seconds > 0.0 {
+                    let power = |before: u64, after: u64, counters: &[Counter]| {
+                        // Sum of the wrap-around ranges is good enough for the multiple packages
+                        let max = counters.iter().map(Counter::max).sum::<u64>();
+                        consumed(before, after, max) as f64 / 1_000_000.0 / seconds
+                    };
+                    Some(Power {
+                        package: power(package_before, package, &self.packages),
+                        gpu: match (gpu_before, gpu) {
+                            (Some(before), Some(after)) => Some(power(before, after, &self.gpus)),
+                            _ => None,
+                        },
+                    })
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+        self.previous = Some((now, package, gpu));
+
+        power
+    }
+}
+
+/// Sum of the counters values, `None` if there are no counters or some of them are not readable
+fn total(counters: &[Counter]) -> Option<u64> {
+    if counters.is_empty() {
+        return None;
+    }
+
+    let mut total = 0;
+    for counter in counters {
+        total += counter.read()?;
+    }
+
+    Some(total)
+}
+
+fn read_u64(path: &Path) -> io::Result<u64> {
+    fs::read_to_string(path)?
+        .trim()
+        .parse()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("Malformed counter in {:?}", path)))
+}
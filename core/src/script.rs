@@ -0,0 +1,252 @@
+//! User scripts: derived series and custom text panels computed from the `SampleStore`,
+//! written in [Rhai](https://rhai.rs).
+//!
+//! Script is evaluated at the latest sample and its value is the one of the last expression:
+//!
+//! * `voltage` (V), `power` (energy rate, W), `energy` (W·h) and `soc` (state of charge, 0–1)
+//!   are the values of the latest sample;
+//! * `value(series)` is the latest value of the series referred by name, either one of the above
+//!   or the recorded channel, ex. `value("power.cpu")`, `value("cell.0")` or `value("temperature")`;
+//! * `mean(series, n)` and `delta(series, n)` are the mean value and the change over the last `n` samples.
+//!
+//! Script has no value if any series it refers has none yet, on the division by zero or if it fails.
+//! Scripts are evaluated for every sample and on each redraw of the scripts page, so each evaluation
+//! is limited to `MAX_OPERATIONS`: a runaway loop fails the script instead of hanging the interface.
+//! Scripts can't load the modules, and `print` and `debug` are going to the log.
+//!
+//! ```
+//! use battop_core::history::SampleStore;
+//! use battop_core::script::Expr;
+//!
+//! let rest: Expr = r#"power - value("power.cpu")"#.parse()?;
+//! assert_eq!(rest.eval(&SampleStore::default()), None);
+//! # Ok::<(), battop_core::script::ParseError>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST, FLOAT, INT};
+use scoped_tls::scoped_thread_local;
+
+use crate::history::{Field, SampleStore};
+
+/// Operations allowed for one evaluation, see `Engine::set_max_operations`
+pub const MAX_OPERATIONS: u64 = 100_000;
+/// Longest string, array or map a script may build
+const MAX_SIZE: usize = 10_000;
+/// Latest sample values available to the scripts as the variables
+const SAMPLE_VALUES: [&str; 4] = ["voltage", "power", "energy", "soc"];
+
+// Store the script is evaluated over, available to the functions registered in the engine
+scoped_thread_local!(static STORE: SampleStore);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError(rhai::ParseError);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// Compiled script, see the module documentation for what it can refer
+#[derive(Debug, Clone)]
+pub struct Expr {
+    ast: AST,
+}
+
+impl Expr {
+    /// Value at the latest sample of the `store`
+    pub fn eval(&self, store: &SampleStore) -> Option<f64> {
+        // Missing ones are not found by the script, which fails it
+        let mut scope = Scope::new();
+        for name in SAMPLE_VALUES.iter() {
+            if let Some(value) = store.latest_value(&field(name)) {
+                scope.push_constant(*name, value);
+            }
+        }
+        let result = STORE.set(store, || engine().eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast));
+        let value = match result {
+            Ok(value) => value,
+            Err(e) => {
+                trace!("Script has no value: {}", e);
+                return None;
+            }
+        };
+
+        value
+            .as_float()
+            .ok()
+            .or_else(|| value.as_int().ok().map(|value| value as FLOAT))
+            .filter(|value| value.is_finite())
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Expr, ParseError> {
+        engine()
+            .compile(s)
+            .map(|ast| Expr {
+                ast,
+            })
+            .map_err(ParseError)
+    }
+}
+
+/// Engine shared by all the scripts, with the battop functions and the limits set
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_string_size(MAX_SIZE)
+            .set_max_array_size(MAX_SIZE)
+            .set_max_map_size(MAX_SIZE)
+            .on_print(|text| debug!("Script printed: {}", text))
+            .on_debug(|text, _, position| debug!("Script debug at {}: {}", position, text));
+
+        engine.register_fn("value", |name: &str| -> Result<FLOAT, Box<EvalAltResult>> {
+            STORE
+                .with(|store| store.latest_value(&field(name)))
+                .ok_or_else(|| format!("`{}` has no value yet", name).into())
+        });
+        engine.register_fn("mean", |name: &str, count: INT| {
+            window(name, count, |values| {
+                values.iter().map(|(_, value)| value).sum::<f64>() / values.len() as f64
+            })
+        });
+        engine.register_fn("delta", |name: &str, count: INT| {
+            window(name, count, |values| values[values.len() - 1].1 - values[0].1)
+        });
+
+        engine
+    })
+}
+
+/// `calculate` over the last `count` values of the series, which are never empty
+fn window<F>(name: &str, count: INT, calculate: F) -> Result<FLOAT, Box<EvalAltResult>>
+where
+    F: FnOnce(&[(u64, f64)]) -> f64,
+{
+    if count < 1 {
+        return Err("amount of samples should be a positive integer".into());
+    }
+    let values = STORE.with(|store| store.values(&field(name), count as usize));
+    if values.is_empty() {
+        return Err(format!("`{}` has no value yet", name).into());
+    }
+
+    Ok(calculate(&values))
+}
+
+/// Sample value or the channel referred by name
+fn field(name: &str) -> Field {
+    match name {
+        "voltage" => Field::Voltage,
+        "power" => Field::EnergyRate,
+        "energy" => Field::Energy,
+        "soc" => Field::StateOfCharge,
+        _ => Field::Channel(name.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use battery::State;
+
+    use super::{Expr, ParseError};
+    use crate::history::{Sample, SampleStore};
+
+    fn parse(source: &str) -> Result<Expr, ParseError> {
+        source.parse()
+    }
+
+    fn eval(source: &str, store: &SampleStore) -> Option<f64> {
+        parse(source).unwrap().eval(store)
+    }
+
+    fn store(powers: &[f32]) -> SampleStore {
+        let mut store = SampleStore::default();
+        for (i, power) in powers.iter().enumerate() {
+            store.push(Sample {
+                timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(i as u64),
+                state: State::Discharging,
+                state_of_charge: 0.5,
+                energy: 25.0,
+                energy_rate: *power,
+                voltage: 12.0,
+                temperature: None,
+                resumed: false,
+            });
+            store.record("power.cpu", 2.0);
+        }
+
+        store
+    }
+
+    #[test]
+    fn multiplication_takes_precedence() {
+        let store = store(&[10.0]);
+        assert_eq!(eval("1 + 2 * soc", &store), Some(2.0));
+        assert_eq!(eval("(1 + 2) * 3", &store), Some(9.0));
+        assert_eq!(eval("8 - 2 - 1", &store), Some(5.0));
+    }
+
+    #[test]
+    fn window_functions() {
+        let store = store(&[4.0, 8.0, 10.0, 12.0]);
+        assert_eq!(eval(r#"mean("power", 3)"#, &store), Some(10.0));
+        assert_eq!(eval(r#"delta("power", 10)"#, &store), Some(8.0));
+        assert_eq!(eval(r#"max(power - value("power.cpu"), 11.0)"#, &store), Some(11.0));
+        assert_eq!(eval(r#"mean("power", 0)"#, &store), None);
+
+        // Compiled once, evaluated over the different stores
+        let mean = parse(r#"mean("power", 2)"#).unwrap();
+        assert_eq!(mean.eval(&store), Some(11.0));
+        assert_eq!(mean.eval(&self::store(&[1.0, 3.0])), Some(2.0));
+    }
+
+    #[test]
+    fn missing_values() {
+        let store = store(&[10.0]);
+        assert_eq!(eval(r#"value("power.gpu") + 1"#, &store), None);
+        assert_eq!(eval("power / (soc - 0.5)", &store), None);
+        assert_eq!(eval(r#"-abs(value("power.cpu"))"#, &store), Some(-2.0));
+        assert_eq!(eval("power", &SampleStore::default()), None);
+    }
+
+    #[test]
+    fn control_flow() {
+        let store = store(&[4.0, 8.0, 10.0, 12.0]);
+        let source = r#"
+            let peak = 0.0;
+            for n in 1..=4 {
+                peak = max(peak, mean("power", n));
+            }
+            if peak > 11.0 { peak } else { 0 }
+        "#;
+        assert_eq!(eval(source, &store), Some(12.0));
+    }
+
+    #[test]
+    fn runaway_scripts_are_stopped() {
+        assert_eq!(eval("loop {}", &store(&[10.0])), None);
+        assert_eq!(eval(r#"let s = "x"; loop { s += s; }"#, &store(&[10.0])), None);
+    }
+
+    #[test]
+    fn errors_point_at_the_position() {
+        let error = |source: &str| parse(source).unwrap_err().to_string();
+
+        assert!(error("power +").contains("line 1, position 8"), "{}", error("power +"));
+        assert!(error("power )").contains("line 1, position 7"), "{}", error("power )"));
+        assert!(error("power\n+ )").contains("line 2"), "{}", error("power\n+ )"));
+    }
+}
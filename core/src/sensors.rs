@@ -0,0 +1,141 @@
+//! System temperature sensors, Linux only.
+//!
+//! Plenty of batteries are not reporting their temperature, in that case
+//! ACPI thermal zones (`/sys/class/thermal`) and hwmon sensors (`/sys/class/hwmon`)
+//! are the next best thing to look at.
+//! Some packs on the other hand have several sensors of their own, exposed as hwmon chips too.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const THERMAL: &str = "/sys/class/thermal";
+const HWMON: &str = "/sys/class/hwmon";
+/// Sensors closest to the battery or at least describing the whole system, in the order of preference
+const PREFERRED: [&str; 4] = ["acpitz", "x86_pkg_temp", "k10temp", "coretemp"];
+
+/// Temperature reported by one sensor
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    /// Thermal zone type or hwmon chip name, ex. `acpitz`
+    pub name: String,
+    /// °C
+    pub celsius: f32,
+}
+
+/// All the readable system temperature sensors
+pub fn readings() -> Vec<Reading> {
+    let mut readings = Vec::new();
+
+    // thermal_zone0/type and thermal_zone0/temp
+    for zone in entries(THERMAL, "thermal_zone") {
+        if let (Some(name), Some(celsius)) = (read_trimmed(&zone.join("type")), millidegrees(&zone.join("temp"))) {
+            readings.push(Reading {
+                name,
+                celsius,
+            });
+        }
+    }
+
+    // hwmon0/name and hwmon0/temp1_input, only the first input of each chip
+    for chip in entries(HWMON, "hwmon") {
+        if let (Some(name), Some(celsius)) = (
+            read_trimmed(&chip.join("name")),
+            millidegrees(&chip.join("temp1_input")),
+        ) {
+            if !readings.iter().any(|reading| reading.name == name) {
+                readings.push(Reading {
+                    name,
+                    celsius,
+                });
+            }
+        }
+    }
+
+    readings
+}
+
+/// Temperature sensors of the battery itself, ex. the separate cell and PCB sensors.
+///
+/// These are the hwmon chips of the power supply device and the sensors labelled as the battery ones
+/// by the other chips (ex. embedded controller), labelled with the sensor label or the chip name and input number.
+pub fn battery_readings(supply: &Path) -> Vec<Reading> {
+    let supply = match fs::canonicalize(supply) {
+        Ok(supply) => supply,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut readings = Vec::new();
+    for chip in entries(HWMON, "hwmon") {
+        let own = fs::canonicalize(chip.join("device"))
+            .map(|device| device == supply)
+            .unwrap_or(false);
+        let chip_name = read_trimmed(&chip.join("name")).unwrap_or_default();
+
+        for input in entries(&chip, "temp") {
+            let file = input.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            if !file.ends_with("_input") {
+                continue;
+            }
+            let sensor = file.trim_end_matches("_input");
+            let label = read_trimmed(&chip.join(format!("{}_label", sensor)));
+            let is_battery = match label {
+                Some(ref label) => label.to_ascii_lowercase().contains("bat"),
+                None => false,
+            };
+            if !own && !is_battery {
+                continue;
+            }
+
+            if let Some(celsius) = millidegrees(&input) {
+                readings.push(Reading {
+                    name: label.unwrap_or_else(|| format!("{} {}", chip_name, sensor)),
+                    celsius,
+                });
+            }
+        }
+    }
+
+    readings
+}
+
+/// Temperature of the most representative sensor, or the hottest one if none of them is known
+pub fn system_temperature() -> Option<Reading> {
+    let readings = readings();
+    let preferred = PREFERRED
+        .iter()
+        .filter_map(|name| readings.iter().find(|reading| reading.name == *name))
+        .next()
+        .cloned();
+
+    preferred.or_else(|| {
+        readings
+            .into_iter()
+            .filter(|reading| reading.celsius > 0.0)
+            .fold(None, |hottest: Option<Reading>, reading| match hottest {
+                Some(ref hottest) if hottest.celsius >= reading.celsius => Some(hottest.clone()),
+                _ => Some(reading),
+            })
+    })
+}
+
+fn entries<P: AsRef<Path>>(dir: P, prefix: &str) -> Vec<PathBuf> {
+    let mut entries = match fs::read_dir(dir.as_ref()) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(prefix))
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>(),
+        Err(_) => Vec::new(),
+    };
+    entries.sort();
+
+    entries
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|value| value.trim().to_string())
+}
+
+fn millidegrees(path: &Path) -> Option<f32> {
+    read_trimmed(path)?.parse::<f32>().ok().map(|value| value / 1000.0)
+}
@@ -0,0 +1,74 @@
+//! Process signals, Unix only.
+//!
+//! Signals are blocked for the whole process with `block` and received synchronously by `watch`
+//! on a thread of its own, so nothing has to be done in the signal handler context.
+//! External programs should be started with `command::new`, which unblocks them again in the child.
+
+use std::io;
+use std::mem;
+use std::ptr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Signal {
+    /// `SIGTERM` or `SIGINT`
+    Terminate,
+    /// `SIGHUP`
+    Reload,
+    /// `SIGUSR1`
+    Dump,
+}
+
+const HANDLED: [libc::c_int; 4] = [libc::SIGTERM, libc::SIGINT, libc::SIGHUP, libc::SIGUSR1];
+
+pub(crate) fn handled() -> libc::sigset_t {
+    unsafe {
+        let mut set: libc::sigset_t = mem::zeroed();
+        libc::sigemptyset(&mut set);
+        for signal in HANDLED.iter() {
+            libc::sigaddset(&mut set, *signal);
+        }
+
+        set
+    }
+}
+
+/// Block the handled signals for the calling thread and the threads it will start later.
+///
+/// Should be called before any other thread is started, default actions are applied in the ones started earlier.
+pub fn block() -> io::Result<()> {
+    let set = handled();
+    match unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut()) } {
+        0 => Ok(()),
+        code => Err(io::Error::from_raw_os_error(code)),
+    }
+}
+
+/// Unblock the handled signals for the calling thread, ex. in the child before it executes another program
+pub(crate) fn unblock(set: &libc::sigset_t) -> io::Result<()> {
+    match unsafe { libc::pthread_sigmask(libc::SIG_UNBLOCK, set, ptr::null_mut()) } {
+        0 => Ok(()),
+        code => Err(io::Error::from_raw_os_error(code)),
+    }
+}
+
+/// Call `notify` on each handled signal received, until it returns `false`; signals should be `block`ed first
+pub fn watch<F: FnMut(Signal) -> bool>(mut notify: F) -> io::Result<()> {
+    let set = handled();
+    loop {
+        let mut received: libc::c_int = 0;
+        match unsafe { libc::sigwait(&set, &mut received) } {
+            0 => {}
+            code => return Err(io::Error::from_raw_os_error(code)),
+        }
+
+        let signal = match received {
+            libc::SIGHUP => Signal::Reload,
+            libc::SIGUSR1 => Signal::Dump,
+            _ => Signal::Terminate,
+        };
+        trace!("Received signal {} ({:?})", received, signal);
+        if !notify(signal) {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::history::Sample;
+use crate::Error;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Band {
+    Idle,
+    Light,
+    Heavy,
+}
+
+impl Band {
+    pub fn all() -> [Band; 3] {
+        [Band::Idle, Band::Light, Band::Heavy]
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Band::Idle => 0,
+            Band::Light => 1,
+            Band::Heavy => 2,
+        }
+    }
+}
+
+impl fmt::Display for Band {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Band::Idle => f.write_str("Idle"),
+            Band::Light => f.write_str("Light"),
+            Band::Heavy => f.write_str("Heavy"),
+        }
+    }
+}
+
+/// Power draw cut-offs (in watts) between the idle, light and heavy load bands.
+///
+/// Parsed from the `idle,heavy` string, ex. `5,15` means that everything below 5 W
+/// is an idle, everything starting from 15 W is a heavy load and the rest is a light one.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct PowerBands {
+    idle: f32,
+    heavy: f32,
+}
+
+impl PowerBands {
+    pub fn classify(&self, watts: f32) -> Band {
+        match () {
+            _ if watts < self.idle => Band::Idle,
+            _ if watts < self.heavy => Band::Light,
+            _ => Band::Heavy,
+        }
+    }
+
+    /// Human-readable power range of the band
+    pub fn range(&self, band: Band) -> String {
+        match band {
+            Band::Idle => format!("< {} W", self.idle),
+            Band::Light => format!("{}-{} W", self.idle, self.heavy),
+            Band::Heavy => format!(">= {} W", self.heavy),
+        }
+    }
+
+    /// Time and energy spent in each band, in the `Band::all()` order.
+    ///
+    /// Each interval between two consecutive samples is attributed to the band
+    /// of its average power draw, the ones the system was suspended during are skipped.
+    pub fn usage<'s, I>(&self, samples: I) -> [BandUsage; 3]
+    where
+        I: IntoIterator<Item = &'s Sample>,
+    {
+        let mut usage = [BandUsage::default(); 3];
+        let mut previous: Option<&Sample> = None;
+
+        for sample in samples {
+            if let Some(prev) = previous.filter(|_| !sample.resumed) {
+                if let Ok(elapsed) = sample.timestamp.duration_since(prev.timestamp) {
+                    let watts = (prev.energy_rate + sample.energy_rate) / 2.0;
+                    let band = &mut usage[self.classify(watts).index()];
+                    band.time += elapsed;
+                    let hours = elapsed.as_secs() as f64 / 3600.0 + f64::from(elapsed.subsec_millis()) / 3_600_000.0;
+                    band.energy += f64::from(watts) * hours;
+                }
+            }
+            previous = Some(sample);
+        }
+
+        usage
+    }
+}
+
+impl FromStr for PowerBands {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',').map(|part| part.trim().parse::<f32>());
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(idle)), Some(Ok(heavy)), None) if 0.0 <= idle && idle < heavy => Ok(PowerBands {
+                idle,
+                heavy,
+            }),
+            _ => Err(Error::ParseError),
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct BandUsage {
+    pub time: Duration,
+    /// Energy spent, W·h
+    pub energy: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use battery::State;
+
+    use super::{Band, BandUsage, PowerBands};
+    use crate::history::Sample;
+
+    fn sample(minutes: u64, energy_rate: f32) -> Sample {
+        Sample {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(minutes * 60),
+            state: State::Discharging,
+            state_of_charge: 0.5,
+            energy: 25.0,
+            energy_rate,
+            voltage: 12.0,
+            temperature: None,
+            resumed: false,
+        }
+    }
+
+    #[test]
+    fn bands_are_parsed() {
+        let bands: PowerBands = " 5, 15 ".parse().unwrap();
+        assert_eq!(bands.classify(4.9), Band::Idle);
+        assert_eq!(bands.classify(5.0), Band::Light);
+        assert_eq!(bands.classify(15.0), Band::Heavy);
+        assert_eq!(bands.range(Band::Light), "5-15 W");
+        assert_eq!(bands.range(Band::Heavy), ">= 15 W");
+
+        for invalid in ["15,5", "5,5", "-1,5", "5", "5,15,25", "five,15"].iter() {
+            assert!(invalid.parse::<PowerBands>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn intervals_are_attributed_to_the_bands() {
+        let bands: PowerBands = "5,15".parse().unwrap();
+        let samples = [
+            sample(0, 2.0),
+            sample(60, 4.0),
+            // Average of 10 W
+            sample(90, 16.0),
+            sample(120, 20.0),
+            // Suspended in between, the next interval is not counted
+            Sample {
+                resumed: true,
+                ..sample(600, 20.0)
+            },
+        ];
+
+        let hour = Duration::from_secs(3600);
+        assert_eq!(
+            bands.usage(samples.iter()),
+            [
+                BandUsage {
+                    time: hour,
+                    energy: 3.0,
+                },
+                BandUsage {
+                    time: hour / 2,
+                    energy: 5.0,
+                },
+                BandUsage {
+                    time: hour / 2,
+                    energy: 9.0,
+                },
+            ]
+        );
+        assert_eq!(bands.usage(&[]), [BandUsage::default(); 3]);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::history::Session;
+
+/// Sessions which moved the state of charge less than that are too short
+/// to compare the integrated energy with the firmware-reported one
+const MIN_SOC_DELTA: f32 = 0.2;
+/// How many latest sessions are considered
+const MAX_SESSIONS: usize = 10;
+/// Drift is not trusted until there are at least that many sessions
+const MIN_SESSIONS: usize = 3;
+/// Relative drift above which the fuel gauge calibration is recommended
+const DRIFT_THRESHOLD: f64 = 0.1;
+
+/// Discrepancy between the energy counted by battop (integrated from the energy rate)
+/// and the energy implied by the state of charge change, reported by the battery firmware.
+///
+/// Positive drift means that more energy went in or out of the battery than firmware
+/// accounts for, negative means that the state of charge changes faster than it should.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Calibration {
+    /// Median relative drift across the recent sessions
+    pub drift: f64,
+    /// Amount of sessions the drift was calculated from
+    pub sessions: usize,
+}
+
+impl Calibration {
+    /// Calculate the drift from the finished `sessions` for a battery with `capacity` W·h.
+    pub fn new(sessions: &[Session], capacity: f64) -> Option<Calibration> {
+        if capacity <= 0.0 {
+            return None;
+        }
+
+        let mut drifts = sessions
+            .iter()
+            .rev()
+            .filter_map(|session| {
+                let soc_delta = (session.soc_end - session.soc_start).abs();
+                if soc_delta < MIN_SOC_DELTA {
+                    return None;
+                }
+
+                let reported = f64::from(soc_delta) * capacity;
+                Some(f64::from(session.energy) / reported - 1.0)
+            })
+            .filter(|drift| drift.is_finite())
+            .take(MAX_SESSIONS)
+            .collect::<Vec<_>>();
+        if drifts.is_empty() {
+            return None;
+        }
+
+        drifts.sort_by(|a, b| a.partial_cmp(b).expect("NaN values were filtered out"));
+        let middle = drifts.len() / 2;
+        let drift = if drifts.len() % 2 == 0 {
+            (drifts[middle - 1] + drifts[middle]) / 2.0
+        } else {
+            drifts[middle]
+        };
+
+        Some(Calibration {
+            drift,
+            sessions: drifts.len(),
+        })
+    }
+
+    /// Drift is consistently big enough to run the full charge-discharge cycle
+    pub fn recommended(&self) -> bool {
+        self.sessions >= MIN_SESSIONS && self.drift.abs() > DRIFT_THRESHOLD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::Calibration;
+    use crate::history::{Session, SessionKind};
+
+    /// Session which took the state of charge from 25% to 75%, 25 W·h of the 50 W·h battery
+    fn session(energy: f32) -> Session {
+        Session {
+            kind: SessionKind::Charging,
+            start: SystemTime::UNIX_EPOCH,
+            end: SystemTime::UNIX_EPOCH,
+            energy,
+            soc_start: 0.25,
+            soc_end: 0.75,
+        }
+    }
+
+    #[test]
+    fn drift_is_the_median_one() {
+        let sessions = [session(30.0), session(20.0), session(35.0)];
+        let calibration = Calibration::new(&sessions, 50.0).unwrap();
+        assert!((calibration.drift - 0.2).abs() < 1e-6, "{:?}", calibration);
+        assert_eq!(calibration.sessions, 3);
+        assert!(calibration.recommended());
+
+        // Even amount of sessions, mean of the middle ones
+        let calibration = Calibration::new(&sessions[..2], 50.0).unwrap();
+        assert!(calibration.drift.abs() < 1e-6, "{:?}", calibration);
+    }
+
+    #[test]
+    fn drift_needs_enough_sessions() {
+        // Only the latest ten sessions are counted
+        let mut sessions = vec![session(25.0); 12];
+        sessions.push(Session {
+            soc_end: 0.3,
+            ..session(50.0)
+        });
+        let calibration = Calibration::new(&sessions, 50.0).unwrap();
+        assert_eq!(calibration.sessions, 10);
+        assert!(!calibration.recommended());
+
+        let few = Calibration::new(&[session(40.0), session(40.0)], 50.0).unwrap();
+        assert!(few.drift > 0.5);
+        assert!(!few.recommended());
+
+        assert_eq!(Calibration::new(&sessions, 0.0), None);
+        assert_eq!(Calibration::new(&sessions[12..], 50.0), None);
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt;
+
+use super::MIN_SESSION_DURATION;
+use crate::history::{Session, SessionKind};
+
+/// Charging with a C-rate below this one is a trickle charge
+const TRICKLE_C_RATE: f64 = 0.1;
+/// Charging with a C-rate starting from this one is a fast charge
+const FAST_C_RATE: f64 = 0.5;
+/// Batteries are accepting the maximum power until this state of charge,
+/// after that charging controllers are starting to taper the current down
+const TAPER_STATE_OF_CHARGE: f32 = 0.8;
+/// Charger is considered weak if it supplies less than this share of the power
+/// battery had accepted before, or if it is a trickle charge before the tapering
+const WEAK_CHARGER_RATIO: f64 = 0.5;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ChargingSpeed {
+    Trickle,
+    Standard,
+    Fast,
+}
+
+impl ChargingSpeed {
+    pub fn from_c_rate(c_rate: f64) -> ChargingSpeed {
+        match () {
+            _ if c_rate < TRICKLE_C_RATE => ChargingSpeed::Trickle,
+            _ if c_rate < FAST_C_RATE => ChargingSpeed::Standard,
+            _ => ChargingSpeed::Fast,
+        }
+    }
+}
+
+impl fmt::Display for ChargingSpeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChargingSpeed::Trickle => f.write_str("Trickle"),
+            ChargingSpeed::Standard => f.write_str("Standard"),
+            ChargingSpeed::Fast => f.write_str("Fast"),
+        }
+    }
+}
+
+/// Charging power relative to the battery capacity
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Charging {
+    /// Observed charging power, W
+    pub power: f64,
+    /// Charging power divided by the battery capacity, 1/h
+    pub c_rate: f64,
+    pub speed: ChargingSpeed,
+    /// Highest average power battery had accepted during the previous charging sessions, W
+    pub accepted: Option<f64>,
+    /// Charger supplies less than the battery is able to accept
+    pub weak_charger: bool,
+}
+
+impl Charging {
+    /// Assess the charging with `power` watts for a battery with `capacity` W·h
+    /// and current `state_of_charge` (`0.0..=1.0`), based on the `previous` sessions.
+    pub fn new(power: f64, capacity: f64, state_of_charge: f32, previous: &[Session]) -> Option<Charging> {
+        if capacity <= 0.0 {
+            return None;
+        }
+
+        let c_rate = power / capacity;
+        let speed = ChargingSpeed::from_c_rate(c_rate);
+        let accepted = previous
+            .iter()
+            .filter(|session| session.kind == SessionKind::Charging && session.soc_start < TAPER_STATE_OF_CHARGE)
+            .filter(|session| session.duration() >= MIN_SESSION_DURATION)
+            .filter_map(Session::average_power)
+            .fold(None, |max: Option<f64>, power| match max {
+                Some(max) if max >= power => Some(max),
+                _ => Some(power),
+            });
+
+        let underpowered = match accepted {
+            Some(accepted) => power < accepted * WEAK_CHARGER_RATIO,
+            None => false,
+        };
+        let weak_charger = state_of_charge < TAPER_STATE_OF_CHARGE && (speed == ChargingSpeed::Trickle || underpowered);
+
+        Some(Charging {
+            power,
+            c_rate,
+            speed,
+            accepted,
+            weak_charger,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{Charging, ChargingSpeed};
+    use crate::history::{Session, SessionKind};
+
+    fn session(kind: SessionKind, minutes: u64, energy: f32, soc_start: f32) -> Session {
+        Session {
+            kind,
+            start: SystemTime::UNIX_EPOCH,
+            end: SystemTime::UNIX_EPOCH + Duration::from_secs(minutes * 60),
+            energy,
+            soc_start,
+            soc_end: 1.0,
+        }
+    }
+
+    #[test]
+    fn speed_is_the_c_rate() {
+        assert_eq!(ChargingSpeed::from_c_rate(0.05), ChargingSpeed::Trickle);
+        assert_eq!(ChargingSpeed::from_c_rate(0.1), ChargingSpeed::Standard);
+        assert_eq!(ChargingSpeed::from_c_rate(0.5), ChargingSpeed::Fast);
+
+        let charging = Charging::new(10.0, 50.0, 0.5, &[]).unwrap();
+        assert_eq!((charging.c_rate, charging.speed), (0.2, ChargingSpeed::Standard));
+        assert_eq!(charging.accepted, None);
+        assert!(!charging.weak_charger);
+        assert_eq!(Charging::new(10.0, 0.0, 0.5, &[]), None);
+    }
+
+    #[test]
+    fn weak_chargers_are_compared_with_the_previous_sessions() {
+        let previous = [
+            session(SessionKind::Charging, 60, 30.0, 0.2),
+            // Too short, tapering and discharging ones are not telling what the battery accepts
+            session(SessionKind::Charging, 5, 10.0, 0.2),
+            session(SessionKind::Charging, 60, 60.0, 0.85),
+            session(SessionKind::Discharging, 60, 60.0, 0.2),
+        ];
+
+        let weak = Charging::new(10.0, 50.0, 0.5, &previous).unwrap();
+        assert_eq!(weak.accepted, Some(30.0));
+        assert!(weak.weak_charger);
+        assert!(!Charging::new(20.0, 50.0, 0.5, &previous).unwrap().weak_charger);
+        // Tapering is expected to slow the charge down
+        assert!(!Charging::new(10.0, 50.0, 0.85, &previous).unwrap().weak_charger);
+        // Trickle charge is weak even without the previous sessions
+        assert!(Charging::new(3.0, 50.0, 0.5, &[]).unwrap().weak_charger);
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::SystemTime;
+
+use super::{Degradation, Efficiency, Rolling, SleepDrain, Summary, DAY, MIN_SESSION_DURATION, WEEK};
+use crate::history::{archives, Archive, SessionKind};
+
+/// Long-term summary of one battery, used to compare it with the other ones
+#[derive(Debug)]
+pub struct Comparison {
+    pub key: String,
+    pub degradation: Option<Degradation>,
+    /// Average power of the discharging sessions, W
+    pub discharge: Option<Summary>,
+    pub efficiency: Option<Efficiency>,
+    pub sleep: Option<SleepDrain>,
+    /// Totals over the last 24 hours
+    pub day: Rolling,
+    /// Totals over the last 7 days
+    pub week: Rolling,
+    /// Amount of the finished sessions, both charging and discharging
+    pub sessions: usize,
+}
+
+impl Comparison {
+    pub fn new(archive: &Archive) -> Comparison {
+        let sessions = archive.sessions().records();
+        let discharge = Summary::new(
+            sessions
+                .iter()
+                .filter(|session| session.kind == SessionKind::Discharging)
+                .filter(|session| session.duration() >= MIN_SESSION_DURATION)
+                .filter_map(|session| session.average_power()),
+        );
+
+        let capacity = archive
+            .health()
+            .records()
+            .last()
+            .map(|record| f64::from(record.energy_full))
+            .unwrap_or(0.0);
+        let now = SystemTime::now();
+
+        Comparison {
+            key: archive.key().to_string(),
+            degradation: Degradation::new(archive.health().records()),
+            discharge,
+            efficiency: Efficiency::new(sessions),
+            sleep: SleepDrain::new(archive.sleeps().records()),
+            day: Rolling::new(sessions, now, DAY, capacity),
+            week: Rolling::new(sessions, now, WEEK, capacity),
+            sessions: sessions.len(),
+        }
+    }
+
+    /// Compare all the batteries battop has history for
+    pub fn all() -> Vec<Comparison> {
+        archives().iter().map(Comparison::new).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::time::Duration;
+
+    use super::Comparison;
+    use crate::history::Archive;
+
+    #[test]
+    fn archive_is_summarized() {
+        let dir = env::temp_dir().join(format!("battop-test-{}-comparison", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("health"),
+            "2019-06-01T12:00:00Z 50.000 57.000 100\n2019-06-04T12:00:00Z 49.500 57.000 101\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("sessions"),
+            "charging 2019-06-04T12:00:00Z 2019-06-04T13:00:00Z 30.000 0.250 0.750\n\
+             discharging 2019-06-04T13:00:00Z 2019-06-04T15:00:00Z 24.000 0.750 0.250\n\
+             discharging 2019-06-04T15:00:00Z 2019-06-04T15:05:00Z 1.000 0.250 0.240\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("sleeps"),
+            "2019-06-04T22:00:00Z 2019-06-05T06:00:00Z 0.900 0.820\n",
+        )
+        .unwrap();
+
+        let comparison = Comparison::new(&Archive::open("Vendor-Model-1234".to_string(), &dir));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(comparison.key, "Vendor-Model-1234");
+        assert_eq!(comparison.sessions, 3);
+        // Five minutes long discharge is too short to be counted
+        let discharge = comparison.discharge.unwrap();
+        assert_eq!((discharge.count, discharge.mean), (1, 12.0));
+        assert!((comparison.efficiency.unwrap().recent - 0.8).abs() < 1e-6);
+        assert_eq!(comparison.sleep.unwrap().duration, Duration::from_secs(8 * 3600));
+        // History is too short for the trend
+        assert!(comparison.degradation.unwrap().trend().is_none());
+        // Sessions are long gone from the rolling windows
+        assert_eq!(comparison.week.discharged, 0.0);
+        assert_eq!(comparison.day.cycles, Some(0.0));
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use crate::history::{Session, SessionKind};
+
+/// Energy which went into the battery during the charging sessions, grouped by calendar month.
+///
+/// Returns `(month, W·h)` pairs, ordered from the oldest month, where month is formatted as `2019-06`.
+/// Sessions are attributed to the month they had ended in.
+pub fn monthly_charging<'s, I>(sessions: I) -> Vec<(String, f64)>
+where
+    I: IntoIterator<Item = &'s Session>,
+{
+    let mut months = BTreeMap::new();
+    for session in sessions {
+        if session.kind != SessionKind::Charging {
+            continue;
+        }
+
+        let mut month = humantime::format_rfc3339_seconds(session.end).to_string();
+        month.truncate(7);
+        *months.entry(month).or_insert(0.0) += f64::from(session.energy);
+    }
+
+    months.into_iter().collect()
+}
+
+/// Cost of the `watt_hours` energy with the `price` per kW·h
+pub fn cost(watt_hours: f64, price: f64) -> f64 {
+    watt_hours / 1000.0 * price
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{cost, monthly_charging};
+    use crate::history::{Session, SessionKind};
+
+    fn session(kind: SessionKind, end: &str, energy: f32) -> Session {
+        let end = humantime::parse_rfc3339(end).unwrap();
+        Session {
+            kind,
+            start: end - Duration::from_secs(3600),
+            end,
+            energy,
+            soc_start: 0.2,
+            soc_end: 0.8,
+        }
+    }
+
+    #[test]
+    fn charging_is_grouped_by_the_end_month() {
+        let sessions = [
+            session(SessionKind::Charging, "2019-07-01T00:30:00Z", 20.0),
+            session(SessionKind::Charging, "2019-06-30T23:00:00Z", 30.0),
+            session(SessionKind::Discharging, "2019-06-15T12:00:00Z", 40.0),
+            session(SessionKind::Charging, "2019-06-02T08:00:00Z", 15.0),
+        ];
+
+        assert_eq!(
+            monthly_charging(sessions.iter()),
+            vec![("2019-06".to_string(), 45.0), ("2019-07".to_string(), 20.0)]
+        );
+        assert!(monthly_charging(&[]).is_empty());
+    }
+
+    #[test]
+    fn cost_is_per_kilowatt_hour() {
+        assert_eq!(cost(2500.0, 0.3), 0.75);
+        assert_eq!(cost(0.0, 0.3), 0.0);
+    }
+}
@@ -0,0 +1,24 @@
+/// Equivalent full cycles: how many times the full capacity of the battery
+/// had been discharged, in total.
+///
+/// Both `discharged` energy and battery `capacity` are in W·h.
+pub fn equivalent_cycles(discharged: f64, capacity: f64) -> Option<f64> {
+    if capacity > 0.0 {
+        Some(discharged / capacity)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::equivalent_cycles;
+
+    #[test]
+    fn cycles_are_the_full_capacities_discharged() {
+        assert_eq!(equivalent_cycles(150.0, 50.0), Some(3.0));
+        assert_eq!(equivalent_cycles(25.0, 50.0), Some(0.5));
+        // Capacity of the devices which are not reporting it
+        assert_eq!(equivalent_cycles(150.0, 0.0), None);
+    }
+}
@@ -0,0 +1,146 @@
+use std::time::{Duration, SystemTime};
+
+use super::Trend;
+use crate::history::HealthRecord;
+
+const SECONDS_IN_DAY: f64 = 24.0 * 60.0 * 60.0;
+/// Trend over the shorter period is mostly a noise
+pub const MIN_SPAN_DAYS: f64 = 7.0;
+
+/// Battery capacity degradation trend, built from the persisted health records.
+///
+/// Trend `x` axis is in days since the first record and `y` is in health percents.
+#[derive(Debug)]
+pub struct Degradation {
+    since: SystemTime,
+    span_days: f64,
+    points: Vec<(f64, f64)>,
+    trend: Option<Trend>,
+}
+
+impl Degradation {
+    pub fn new(records: &[HealthRecord]) -> Option<Degradation> {
+        let since = records.first()?.timestamp;
+        let points = records
+            .iter()
+            .map(|record| {
+                let days = record
+                    .timestamp
+                    .duration_since(since)
+                    .map(|d| d.as_secs() as f64 / SECONDS_IN_DAY)
+                    .unwrap_or(0.0);
+                (days, f64::from(record.health()) * 100.0)
+            })
+            .collect::<Vec<_>>();
+        let span_days = points.last().map(|(x, _)| *x).unwrap_or(0.0);
+        let trend = if span_days >= MIN_SPAN_DAYS {
+            Trend::fit(&points)
+        } else {
+            None
+        };
+
+        Some(Degradation {
+            since,
+            span_days,
+            points,
+            trend,
+        })
+    }
+
+    pub fn since(&self) -> SystemTime {
+        self.since
+    }
+
+    /// How many days of history are collected
+    pub fn span_days(&self) -> f64 {
+        self.span_days
+    }
+
+    /// Recorded health values, in the `(days, percents)` form
+    pub fn points(&self) -> &[(f64, f64)] {
+        self.points.as_ref()
+    }
+
+    /// Latest recorded health, in percents
+    pub fn current(&self) -> Option<f64> {
+        self.points.last().map(|(_, y)| *y)
+    }
+
+    pub fn trend(&self) -> Option<&Trend> {
+        self.trend.as_ref()
+    }
+
+    /// Health loss per year, in percents
+    pub fn yearly_loss(&self) -> Option<f64> {
+        self.trend.map(|trend| -trend.slope * 365.0)
+    }
+
+    /// Extrapolated date when the battery health will drop to `percent`.
+    ///
+    /// Returns `None` if there is not enough data or health is not declining.
+    pub fn projected(&self, percent: f64) -> Option<SystemTime> {
+        let trend = self.trend?;
+        if trend.slope >= 0.0 {
+            return None;
+        }
+        let days = trend.solve(percent)?.max(self.span_days);
+
+        Some(self.since + Duration::from_secs((days * SECONDS_IN_DAY) as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{Degradation, SECONDS_IN_DAY};
+    use crate::history::HealthRecord;
+
+    /// One record per day, health is going from 90% down by `daily_loss` percents
+    fn records(days: u64, daily_loss: f32) -> Vec<HealthRecord> {
+        (0..=days)
+            .map(|day| HealthRecord {
+                timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(day * SECONDS_IN_DAY as u64),
+                energy_full: 90.0 - daily_loss * day as f32,
+                energy_full_design: 100.0,
+                cycle_count: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn declining_health_is_projected() {
+        let degradation = Degradation::new(&records(10, 0.1)).unwrap();
+
+        assert_eq!(degradation.since(), SystemTime::UNIX_EPOCH);
+        assert_eq!(degradation.span_days(), 10.0);
+        assert_eq!(degradation.points().len(), 11);
+        assert!((degradation.current().unwrap() - 89.0).abs() < 1e-3);
+        assert!((degradation.yearly_loss().unwrap() - 36.5).abs() < 1e-3);
+
+        // 80% is 100 days away from the first record
+        let projected = degradation.projected(80.0).unwrap();
+        let days = projected.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as f64 / SECONDS_IN_DAY;
+        assert!((days - 100.0).abs() < 0.01, "{}", days);
+        // Already crossed levels are not in the past
+        assert_eq!(
+            degradation.projected(95.0),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(10 * 86400))
+        );
+    }
+
+    #[test]
+    fn short_or_growing_history_has_no_projection() {
+        assert!(Degradation::new(&[]).is_none());
+
+        let week = Degradation::new(&records(6, 0.1)).unwrap();
+        assert!(week.trend().is_none());
+        assert_eq!(week.yearly_loss(), None);
+        assert_eq!(week.projected(80.0), None);
+
+        // Recalibrated gauge reporting more capacity over time
+        let growing = Degradation::new(&records(10, -0.1)).unwrap();
+        assert!(growing.yearly_loss().unwrap() < 0.0);
+        assert_eq!(growing.projected(80.0), None);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::history::{Session, SessionKind};
+
+/// Sessions which discharged less than that share of the capacity are too short to compare
+const MIN_DISCHARGED: f64 = 0.02;
+
+/// Difference between the state of charge reported by the battery firmware
+/// and the one counted from the energy discharged during the session.
+///
+/// Fuel gauges are usually the least accurate near the bottom of the range,
+/// where a big positive divergence means that battery will run out earlier than it shows.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Divergence {
+    /// State of charge reported by the firmware, `0.0..=1.0`
+    pub reported: f64,
+    /// State of charge counted from the session start, `0.0..=1.0`
+    pub counted: f64,
+}
+
+impl Divergence {
+    /// Compare the discharging `session` in progress for a battery with `capacity` W·h
+    pub fn new(session: &Session, capacity: f64) -> Option<Divergence> {
+        if session.kind != SessionKind::Discharging || capacity <= 0.0 {
+            return None;
+        }
+        let discharged = f64::from(session.energy) / capacity;
+        if discharged < MIN_DISCHARGED {
+            return None;
+        }
+
+        Some(Divergence {
+            reported: f64::from(session.soc_end),
+            counted: (f64::from(session.soc_start) - discharged).max(0.0),
+        })
+    }
+
+    /// Reported minus counted state of charge, percentage points
+    pub fn difference(&self) -> f64 {
+        (self.reported - self.counted) * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::Divergence;
+    use crate::history::{Session, SessionKind};
+
+    fn session(kind: SessionKind, energy: f32, soc_start: f32, soc_end: f32) -> Session {
+        Session {
+            kind,
+            start: SystemTime::UNIX_EPOCH,
+            end: SystemTime::UNIX_EPOCH,
+            energy,
+            soc_start,
+            soc_end,
+        }
+    }
+
+    #[test]
+    fn reported_charge_is_compared_with_the_counted_one() {
+        // 25 W·h out of 50 W·h, but firmware had lost only 40%
+        let divergence = Divergence::new(&session(SessionKind::Discharging, 25.0, 0.9, 0.5), 50.0).unwrap();
+        assert!((divergence.counted - 0.4).abs() < 1e-6);
+        assert!((divergence.difference() - 10.0).abs() < 1e-4);
+
+        // Counted charge does not go below zero
+        let drained = Divergence::new(&session(SessionKind::Discharging, 40.0, 0.5, 0.1), 50.0).unwrap();
+        assert_eq!(drained.counted, 0.0);
+
+        assert_eq!(
+            Divergence::new(&session(SessionKind::Discharging, 0.5, 0.9, 0.89), 50.0),
+            None
+        );
+        assert_eq!(
+            Divergence::new(&session(SessionKind::Charging, 25.0, 0.4, 0.9), 50.0),
+            None
+        );
+        assert_eq!(
+            Divergence::new(&session(SessionKind::Discharging, 25.0, 0.9, 0.5), 0.0),
+            None
+        );
+    }
+}
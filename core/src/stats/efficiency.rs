@@ -0,0 +1,138 @@
+use super::Summary;
+use crate::history::{Session, SessionKind};
+
+/// Sessions which moved the state of charge less than that are too short to compare
+const MIN_SOC_DELTA: f32 = 0.2;
+/// Amount of the latest session pairs compared against the earlier ones
+const RECENT_PAIRS: usize = 5;
+/// Pack efficiency is dropping if recent pairs are worse than earlier ones by that much
+const DROP_THRESHOLD: f64 = 0.05;
+
+/// Round-trip charge efficiency: energy recovered during the discharge
+/// relative to the energy delivered during the preceding charge.
+///
+/// Sessions are rarely covering the same state of charge range, so both energies
+/// are normalized by the state of charge change before comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Efficiency {
+    /// Efficiency of each charge-discharge pair, in chronological order, `0.0..=1.0`
+    pub pairs: Vec<f64>,
+    /// Median efficiency of the recent pairs
+    pub recent: f64,
+    /// Median efficiency of the pairs preceding the recent ones
+    pub earlier: Option<f64>,
+}
+
+impl Efficiency {
+    pub fn new(sessions: &[Session]) -> Option<Efficiency> {
+        let pairs = sessions
+            .windows(2)
+            .filter_map(|pair| match (&pair[0], &pair[1]) {
+                (charge, discharge)
+                    if charge.kind == SessionKind::Charging && discharge.kind == SessionKind::Discharging =>
+                {
+                    let delivered = energy_per_soc(charge)?;
+                    let recovered = energy_per_soc(discharge)?;
+                    Some(recovered / delivered)
+                }
+                _ => None,
+            })
+            .filter(|efficiency| efficiency.is_finite())
+            .collect::<Vec<_>>();
+
+        let split = pairs.len().saturating_sub(RECENT_PAIRS);
+        let recent = Summary::new(pairs[split..].iter().cloned())?.p50;
+        let earlier = Summary::new(pairs[..split].iter().cloned()).map(|summary| summary.p50);
+
+        Some(Efficiency {
+            pairs,
+            recent,
+            earlier,
+        })
+    }
+
+    /// Recent charge-discharge pairs are noticeably less efficient than the earlier ones
+    pub fn dropping(&self) -> bool {
+        match self.earlier {
+            Some(earlier) => earlier - self.recent > DROP_THRESHOLD,
+            None => false,
+        }
+    }
+}
+
+/// Energy which moved the state of charge by 100 %, W·h
+fn energy_per_soc(session: &Session) -> Option<f64> {
+    let delta = (session.soc_end - session.soc_start).abs();
+    if delta < MIN_SOC_DELTA || session.energy <= 0.0 {
+        return None;
+    }
+
+    Some(f64::from(session.energy) / f64::from(delta))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::Efficiency;
+    use crate::history::{Session, SessionKind};
+
+    fn session(kind: SessionKind, energy: f32, soc_start: f32, soc_end: f32) -> Session {
+        Session {
+            kind,
+            start: SystemTime::UNIX_EPOCH,
+            end: SystemTime::UNIX_EPOCH,
+            energy,
+            soc_start,
+            soc_end,
+        }
+    }
+
+    /// Charge of 40 W·h from 25% to 75% followed by the discharge of `recovered` W·h back to 25%
+    fn pair(recovered: f32) -> Vec<Session> {
+        vec![
+            session(SessionKind::Charging, 40.0, 0.25, 0.75),
+            session(SessionKind::Discharging, recovered, 0.75, 0.25),
+        ]
+    }
+
+    #[test]
+    fn pairs_are_normalized_by_the_state_of_charge() {
+        let mut sessions = pair(36.0);
+        // Discharge over the half of the charged range
+        sessions.push(session(SessionKind::Charging, 40.0, 0.25, 0.75));
+        sessions.push(session(SessionKind::Discharging, 16.0, 0.75, 0.5));
+
+        let efficiency = Efficiency::new(&sessions).unwrap();
+        assert_eq!(efficiency.pairs.len(), 2);
+        assert!((efficiency.pairs[0] - 0.9).abs() < 1e-6);
+        assert!((efficiency.pairs[1] - 0.8).abs() < 1e-6);
+        assert_eq!(efficiency.earlier, None);
+        assert!(!efficiency.dropping());
+    }
+
+    #[test]
+    fn recent_pairs_are_compared_with_the_earlier_ones() {
+        let mut sessions = Vec::new();
+        for recovered in [36.0, 36.0, 32.0, 32.0, 32.0, 32.0, 32.0].iter() {
+            sessions.extend(pair(*recovered));
+        }
+
+        let efficiency = Efficiency::new(&sessions).unwrap();
+        assert!((efficiency.recent - 0.8).abs() < 1e-6);
+        assert!((efficiency.earlier.unwrap() - 0.9).abs() < 1e-6);
+        assert!(efficiency.dropping());
+    }
+
+    #[test]
+    fn unpaired_sessions_are_skipped() {
+        let sessions = [
+            session(SessionKind::Discharging, 30.0, 0.75, 0.25),
+            session(SessionKind::Charging, 40.0, 0.25, 0.75),
+            // Too small to compare
+            session(SessionKind::Discharging, 5.0, 0.75, 0.7),
+        ];
+        assert_eq!(Efficiency::new(&sessions), None);
+        assert_eq!(Efficiency::new(&[]), None);
+    }
+}
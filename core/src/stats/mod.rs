@@ -0,0 +1,47 @@
+//! Analysis of the collected battery data.
+
+mod bands;
+mod calibration;
+mod charging;
+mod comparison;
+mod cost;
+mod cycles;
+mod degradation;
+mod divergence;
+mod efficiency;
+mod percentiles;
+mod rolling;
+mod sag;
+mod sleep;
+mod thermal;
+mod trend;
+
+pub use self::bands::{Band, PowerBands};
+pub use self::calibration::Calibration;
+pub use self::charging::Charging;
+pub use self::comparison::Comparison;
+pub use self::cost::{cost, monthly_charging};
+pub use self::cycles::equivalent_cycles;
+pub use self::degradation::{Degradation, MIN_SPAN_DAYS};
+pub use self::divergence::Divergence;
+pub use self::efficiency::Efficiency;
+pub use self::percentiles::{histogram, Summary};
+pub use self::rolling::{Rolling, DAY, WEEK};
+pub use self::sag::VoltageModel;
+pub use self::sleep::SleepDrain;
+pub use self::thermal::{Thermal, STRONG_CORRELATION};
+pub use self::trend::Trend;
+
+use std::time::Duration;
+
+/// Sessions shorter than that are too noisy to learn the average power from
+pub const MIN_SESSION_DURATION: Duration = Duration::from_secs(10 * 60);
+
+/// Time windows available for the statistics page
+pub const WINDOWS: [Duration; 5] = [
+    Duration::from_secs(5 * 60),
+    Duration::from_secs(15 * 60),
+    Duration::from_secs(60 * 60),
+    Duration::from_secs(6 * 60 * 60),
+    Duration::from_secs(24 * 60 * 60),
+];
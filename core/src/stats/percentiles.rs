@@ -0,0 +1,93 @@
+/// Descriptive statistics for a set of values
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+impl Summary {
+    pub fn new<I>(values: I) -> Option<Summary>
+    where
+        I: IntoIterator<Item = f64>,
+    {
+        let mut values = values.into_iter().filter(|value| value.is_finite()).collect::<Vec<_>>();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(|a, b| a.partial_cmp(b).expect("NaN values were filtered out"));
+
+        Some(Summary {
+            count: values.len(),
+            min: values[0],
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            max: values[values.len() - 1],
+            p50: percentile(&values, 50.0),
+            p90: percentile(&values, 90.0),
+            p99: percentile(&values, 99.0),
+        })
+    }
+}
+
+/// Nearest-rank percentile for the sorted non-empty values slice
+fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    debug_assert!(!sorted.is_empty());
+
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.max(1).min(sorted.len()) - 1]
+}
+
+/// Split values into `buckets` equal-width ranges from zero to the maximum value
+/// and count values falling into each one.
+pub fn histogram(values: &[f64], buckets: usize, max: f64) -> Vec<u64> {
+    let mut counts = vec![0; buckets];
+    if buckets == 0 || max <= 0.0 {
+        return counts;
+    }
+
+    for value in values {
+        let idx = ((value / max) * buckets as f64) as usize;
+        counts[idx.min(buckets - 1)] += 1;
+    }
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{histogram, Summary};
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let summary = Summary::new((1..=100).rev().map(f64::from)).unwrap();
+        assert_eq!(
+            summary,
+            Summary {
+                count: 100,
+                min: 1.0,
+                mean: 50.5,
+                max: 100.0,
+                p50: 50.0,
+                p90: 90.0,
+                p99: 99.0,
+            }
+        );
+
+        // Single value is every percentile, invalid values are skipped
+        let single = Summary::new(vec![f64::NAN, 7.5, f64::INFINITY]).unwrap();
+        assert_eq!((single.count, single.p50, single.p99), (1, 7.5, 7.5));
+        assert_eq!(Summary::new(vec![f64::NAN]), None);
+        assert_eq!(Summary::new(Vec::new()), None);
+    }
+
+    #[test]
+    fn histogram_buckets() {
+        assert_eq!(histogram(&[0.5, 1.5, 1.9, 2.5, 4.0, 10.0], 4, 4.0), vec![1, 2, 1, 2]);
+        assert_eq!(histogram(&[1.0, 2.0], 3, 0.0), vec![0, 0, 0]);
+        assert!(histogram(&[1.0], 0, 4.0).is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::{Duration, SystemTime};
+
+use super::equivalent_cycles;
+use crate::history::{Session, SessionKind};
+
+pub const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+pub const WEEK: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Energy and time totals over the period ending now.
+///
+/// Sessions crossing the period start are accounted proportionally to their overlap with the period.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rolling {
+    /// Energy consumed from the battery, W·h
+    pub discharged: f64,
+    /// Energy put into the battery, W·h
+    pub charged: f64,
+    /// Time spent discharging
+    pub on_battery: Duration,
+    /// Equivalent full cycles
+    pub cycles: Option<f64>,
+}
+
+impl Rolling {
+    /// Sum up the `sessions` within the `period` before `now` for a battery with `capacity` W·h
+    pub fn new<'s, I>(sessions: I, now: SystemTime, period: Duration, capacity: f64) -> Rolling
+    where
+        I: IntoIterator<Item = &'s Session>,
+    {
+        let since = now.checked_sub(period).unwrap_or(now);
+        let (mut discharged, mut charged, mut on_battery) = (0.0, 0.0, 0);
+
+        for session in sessions {
+            let start = session.start.max(since);
+            let end = session.end.min(now);
+            let overlap = match end.duration_since(start) {
+                Ok(overlap) if overlap.as_secs() > 0 => overlap.as_secs(),
+                _ => continue,
+            };
+            let share = match session.duration().as_secs() {
+                0 => continue,
+                duration => overlap as f64 / duration as f64,
+            };
+
+            let energy = f64::from(session.energy) * share;
+            match session.kind {
+                SessionKind::Discharging => {
+                    discharged += energy;
+                    on_battery += overlap;
+                }
+                SessionKind::Charging => charged += energy,
+            }
+        }
+
+        Rolling {
+            discharged,
+            charged,
+            on_battery: Duration::from_secs(on_battery),
+            cycles: equivalent_cycles(discharged, capacity),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{Rolling, DAY, WEEK};
+    use crate::history::{Session, SessionKind};
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn session(kind: SessionKind, start: SystemTime, hours: u32, energy: f32) -> Session {
+        Session {
+            kind,
+            start,
+            end: start + HOUR * hours,
+            energy,
+            soc_start: 0.0,
+            soc_end: 0.0,
+        }
+    }
+
+    #[test]
+    fn sessions_are_counted_by_their_overlap() {
+        let now = SystemTime::UNIX_EPOCH + WEEK * 2;
+        let sessions = [
+            // Half of it is within the last day
+            session(SessionKind::Discharging, now - HOUR * 25, 2, 10.0),
+            session(SessionKind::Charging, now - HOUR * 5, 1, 30.0),
+            session(SessionKind::Discharging, now - HOUR * 3, 2, 20.0),
+            session(SessionKind::Discharging, now - WEEK - HOUR, 1, 50.0),
+        ];
+
+        assert_eq!(
+            Rolling::new(sessions.iter(), now, DAY, 50.0),
+            Rolling {
+                discharged: 25.0,
+                charged: 30.0,
+                on_battery: HOUR * 3,
+                cycles: Some(0.5),
+            }
+        );
+        let week = Rolling::new(sessions.iter(), now, WEEK, 0.0);
+        assert_eq!((week.discharged, week.on_battery), (30.0, HOUR * 4));
+        assert_eq!(week.cycles, None);
+    }
+}
@@ -0,0 +1,198 @@
+use battery::State;
+
+use crate::history::Sample;
+
+/// Model is not trusted until it learned from that many discharging samples
+const MIN_SAMPLES: usize = 60;
+/// Model is fitted with that many latest discharging samples at most, 15 minutes at the default delay,
+/// so the fit costs the same on every update whatever the retention is, and it follows the aging battery
+const WINDOW: usize = 900;
+/// Voltage lower than expected by that many standard deviations is an anomaly
+const SIGMA: f64 = 3.0;
+/// Voltage measurement noise, sags smaller than that are ignored, V
+const MIN_SAG: f64 = 0.1;
+
+/// Learned relation between the battery voltage, power draw and state of charge:
+/// `voltage = intercept + power_slope * power + soc_slope * state_of_charge`.
+///
+/// Voltage dropping under load noticeably more than expected is a sign
+/// of the increased internal resistance.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VoltageModel {
+    pub intercept: f64,
+    /// Voltage change per watt of the power draw, V/W
+    pub power_slope: f64,
+    /// Voltage change per state of charge, from empty to full, V
+    pub soc_slope: f64,
+    /// Standard deviation of the voltage from the expected one, V
+    pub deviation: f64,
+}
+
+impl VoltageModel {
+    /// Fit the model with the ordinary least squares from the discharging samples,
+    /// only the first `WINDOW` of them are used, so the samples are expected from the latest one back
+    pub fn fit<'s, I>(samples: I) -> Option<VoltageModel>
+    where
+        I: Iterator<Item = &'s Sample>,
+    {
+        let points = samples
+            .filter(|sample| sample.state == State::Discharging)
+            .take(WINDOW)
+            .map(|sample| {
+                (
+                    f64::from(sample.energy_rate.abs()),
+                    f64::from(sample.state_of_charge),
+                    f64::from(sample.voltage),
+                )
+            })
+            .collect::<Vec<_>>();
+        if points.len() < MIN_SAMPLES {
+            return None;
+        }
+
+        let n = points.len() as f64;
+        let mean_p = points.iter().map(|(p, _, _)| p).sum::<f64>() / n;
+        let mean_s = points.iter().map(|(_, s, _)| s).sum::<f64>() / n;
+        let mean_v = points.iter().map(|(_, _, v)| v).sum::<f64>() / n;
+
+        let (mut pp, mut ss, mut ps, mut pv, mut sv) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for (p, s, v) in points.iter() {
+            let (p, s, v) = (p - mean_p, s - mean_s, v - mean_v);
+            pp += p * p;
+            ss += s * s;
+            ps += p * s;
+            pv += p * v;
+            sv += s * v;
+        }
+        if pp == 0.0 {
+            return None;
+        }
+
+        // State of charge barely moves over the short runs,
+        // in that case voltage is explained by the power draw alone
+        let det = pp * ss - ps * ps;
+        let (power_slope, soc_slope) = if det.abs() > f64::EPSILON * pp * ss {
+            ((ss * pv - ps * sv) / det, (pp * sv - ps * pv) / det)
+        } else {
+            (pv / pp, 0.0)
+        };
+        let intercept = mean_v - power_slope * mean_p - soc_slope * mean_s;
+
+        let squares = points
+            .iter()
+            .map(|(p, s, v)| (v - (intercept + power_slope * p + soc_slope * s)).powi(2))
+            .sum::<f64>();
+        let deviation = (squares / (n - 3.0)).sqrt();
+
+        Some(VoltageModel {
+            intercept,
+            power_slope,
+            soc_slope,
+            deviation,
+        })
+    }
+
+    /// Expected voltage for the sample conditions, V
+    pub fn expected(&self, sample: &Sample) -> f64 {
+        self.intercept
+            + self.power_slope * f64::from(sample.energy_rate.abs())
+            + self.soc_slope * f64::from(sample.state_of_charge)
+    }
+
+    /// Sample voltage is abnormally low for its power draw and state of charge
+    pub fn is_sag(&self, sample: &Sample) -> bool {
+        if sample.state != State::Discharging {
+            return false;
+        }
+
+        let drop = self.expected(sample) - f64::from(sample.voltage);
+        drop > MIN_SAG && drop > self.deviation * SIGMA
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use battery::State;
+
+    use super::VoltageModel;
+    use crate::history::Sample;
+
+    fn sample(energy_rate: f32, state_of_charge: f32, voltage: f32) -> Sample {
+        Sample {
+            timestamp: SystemTime::UNIX_EPOCH,
+            state: State::Discharging,
+            state_of_charge,
+            energy: 50.0 * state_of_charge,
+            energy_rate,
+            voltage,
+            temperature: None,
+            resumed: false,
+        }
+    }
+
+    /// Battery with `11 V + 2 V * soc - 0.02 V/W * power` voltage
+    fn samples(count: usize) -> Vec<Sample> {
+        (0..count)
+            .map(|i| {
+                let power = 5.0 + (i % 10) as f32 * 2.0;
+                let soc = 1.0 - i as f32 / 200.0;
+                sample(power, soc, 11.0 + 2.0 * soc - 0.02 * power)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn model_is_learned() {
+        let model = VoltageModel::fit(samples(100).iter()).unwrap();
+        assert!((model.intercept - 11.0).abs() < 1e-3, "{:?}", model);
+        assert!((model.power_slope + 0.02).abs() < 1e-4, "{:?}", model);
+        assert!((model.soc_slope - 2.0).abs() < 1e-3, "{:?}", model);
+        assert!(model.deviation < 1e-3, "{:?}", model);
+
+        // Same state of charge all along, voltage is explained by the power draw
+        let short = samples(100)
+            .into_iter()
+            .map(|sample| Sample {
+                state_of_charge: 0.5,
+                voltage: 12.0 - 0.02 * sample.energy_rate,
+                ..sample
+            })
+            .collect::<Vec<_>>();
+        let model = VoltageModel::fit(short.iter()).unwrap();
+        assert_eq!(model.soc_slope, 0.0);
+        assert!((model.power_slope + 0.02).abs() < 1e-4, "{:?}", model);
+    }
+
+    #[test]
+    fn sags_are_detected() {
+        let model = VoltageModel::fit(samples(100).iter()).unwrap();
+        assert!((model.expected(&sample(10.0, 0.5, 0.0)) - 11.8).abs() < 1e-3);
+
+        assert!(model.is_sag(&sample(10.0, 0.5, 11.3)));
+        // Measurement noise
+        assert!(!model.is_sag(&sample(10.0, 0.5, 11.75)));
+        assert!(!model.is_sag(&Sample {
+            state: State::Charging,
+            ..sample(10.0, 0.5, 11.3)
+        }));
+    }
+
+    #[test]
+    fn model_needs_enough_varied_samples() {
+        assert_eq!(VoltageModel::fit(samples(59).iter()), None);
+
+        let constant = (0..100).map(|_| sample(10.0, 0.5, 11.8)).collect::<Vec<_>>();
+        assert_eq!(VoltageModel::fit(constant.iter()), None);
+
+        let charging = samples(100)
+            .into_iter()
+            .map(|sample| Sample {
+                state: State::Charging,
+                ..sample
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(VoltageModel::fit(charging.iter()), None);
+    }
+}
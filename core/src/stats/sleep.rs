@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use crate::history::Sleep;
+
+/// Sleeps shorter than that are not moving the state of charge enough to be measured
+const MIN_SLEEP_DURATION: Duration = Duration::from_secs(30 * 60);
+
+/// Average state of charge lost while system was suspended
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SleepDrain {
+    /// Percents of the state of charge lost per hour
+    pub rate: f64,
+    /// Amount of sleeps the rate was calculated from
+    pub count: usize,
+    /// Total time spent asleep
+    pub duration: Duration,
+}
+
+impl SleepDrain {
+    pub fn new(sleeps: &[Sleep]) -> Option<SleepDrain> {
+        let (lost, seconds, count) = sleeps
+            .iter()
+            .filter(|sleep| sleep.duration() >= MIN_SLEEP_DURATION)
+            .fold((0.0, 0, 0), |(lost, seconds, count), sleep| {
+                (
+                    lost + f64::from(sleep.soc_start - sleep.soc_end),
+                    seconds + sleep.duration().as_secs(),
+                    count + 1,
+                )
+            });
+        if count == 0 {
+            return None;
+        }
+
+        Some(SleepDrain {
+            rate: lost * 100.0 / (seconds as f64 / 3600.0),
+            count,
+            duration: Duration::from_secs(seconds),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::SleepDrain;
+    use crate::history::Sleep;
+
+    fn sleep(hours: u64, soc_start: f32, soc_end: f32) -> Sleep {
+        Sleep {
+            start: SystemTime::UNIX_EPOCH,
+            end: SystemTime::UNIX_EPOCH + Duration::from_secs(hours * 3600),
+            soc_start,
+            soc_end,
+        }
+    }
+
+    #[test]
+    fn drain_is_averaged_over_the_time_asleep() {
+        let sleeps = [
+            sleep(8, 0.9, 0.82),
+            sleep(2, 0.5, 0.48),
+            // Too short to move the state of charge
+            Sleep {
+                end: SystemTime::UNIX_EPOCH + Duration::from_secs(600),
+                ..sleep(0, 0.5, 0.4)
+            },
+        ];
+
+        let drain = SleepDrain::new(&sleeps).unwrap();
+        assert!((drain.rate - 1.0).abs() < 1e-4, "{:?}", drain);
+        assert_eq!(drain.count, 2);
+        assert_eq!(drain.duration, Duration::from_secs(10 * 3600));
+        assert_eq!(SleepDrain::new(&sleeps[2..]), None);
+    }
+}
@@ -0,0 +1,128 @@
+use super::Trend;
+use crate::history::Sample;
+
+/// Correlation coefficient starting from which battery heating is explained by the load
+pub const STRONG_CORRELATION: f64 = 0.5;
+
+/// Relation between the battery temperature and the power going in or out of it.
+///
+/// Points are `(power, temperature)` pairs, where power is in watts (absolute value,
+/// both charging and discharging are heating the battery) and temperature is in kelvins.
+#[derive(Debug)]
+pub struct Thermal {
+    points: Vec<(f64, f64)>,
+    coefficient: Option<f64>,
+    trend: Option<Trend>,
+}
+
+impl Thermal {
+    pub fn new<'s, T>(samples: T) -> Thermal
+    where
+        T: Iterator<Item = &'s Sample>,
+    {
+        let points = samples
+            .filter_map(|sample| {
+                let temperature = sample.temperature?;
+                Some((f64::from(sample.energy_rate.abs()), f64::from(temperature)))
+            })
+            .collect::<Vec<_>>();
+
+        Thermal {
+            coefficient: pearson(&points),
+            trend: Trend::fit(&points),
+            points,
+        }
+    }
+
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// Pearson correlation coefficient, `-1.0..=1.0`
+    pub fn coefficient(&self) -> Option<f64> {
+        self.coefficient
+    }
+
+    /// Temperature change per watt of the power draw, K/W
+    pub fn trend(&self) -> Option<Trend> {
+        self.trend
+    }
+}
+
+fn pearson(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let (covariance, variance_x, variance_y) = points.iter().fold((0.0, 0.0, 0.0), |(cov, var_x, var_y), (x, y)| {
+        (
+            cov + (x - mean_x) * (y - mean_y),
+            var_x + (x - mean_x).powi(2),
+            var_y + (y - mean_y).powi(2),
+        )
+    });
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use battery::State;
+
+    use super::{Thermal, STRONG_CORRELATION};
+    use crate::history::Sample;
+
+    fn sample(energy_rate: f32, temperature: Option<f32>) -> Sample {
+        Sample {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(60),
+            state: State::Discharging,
+            state_of_charge: 0.5,
+            energy: 25.0,
+            energy_rate,
+            voltage: 12.0,
+            temperature,
+            resumed: false,
+        }
+    }
+
+    #[test]
+    fn heating_follows_the_load() {
+        let samples = [
+            sample(5.0, Some(300.0)),
+            // Charging is heating the battery the same way
+            sample(-10.0, Some(301.0)),
+            sample(20.0, Some(303.0)),
+            sample(30.0, None),
+        ];
+        let thermal = Thermal::new(samples.iter());
+
+        assert_eq!(thermal.points(), &[(5.0, 300.0), (10.0, 301.0), (20.0, 303.0)]);
+        let coefficient = thermal.coefficient().unwrap();
+        assert!(
+            coefficient > STRONG_CORRELATION && coefficient <= 1.0,
+            "{}",
+            coefficient
+        );
+        assert!((thermal.trend().unwrap().slope - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn constant_values_are_not_correlated() {
+        let steady = [sample(10.0, Some(300.0)), sample(20.0, Some(300.0))];
+        assert_eq!(Thermal::new(steady.iter()).coefficient(), None);
+
+        let single = [sample(10.0, Some(300.0))];
+        let thermal = Thermal::new(single.iter());
+        assert_eq!(thermal.coefficient(), None);
+        assert_eq!(thermal.trend(), None);
+    }
+}
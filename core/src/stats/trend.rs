@@ -0,0 +1,73 @@
+/// Linear trend `y = slope * x + intercept`, fitted with the ordinary least squares method
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Trend {
+    pub slope: f64,
+    pub intercept: f64,
+}
+
+impl Trend {
+    /// Fit the trend line for the given points.
+    ///
+    /// At least two points with a different `x` values are required.
+    pub fn fit(points: &[(f64, f64)]) -> Option<Trend> {
+        if points.len() < 2 {
+            return None;
+        }
+
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+        let (covariance, variance) = points.iter().fold((0.0, 0.0), |(cov, var), (x, y)| {
+            (cov + (x - mean_x) * (y - mean_y), var + (x - mean_x).powi(2))
+        });
+        if variance == 0.0 {
+            return None;
+        }
+
+        let slope = covariance / variance;
+        Some(Trend {
+            slope,
+            intercept: mean_y - slope * mean_x,
+        })
+    }
+
+    pub fn value_at(&self, x: f64) -> f64 {
+        self.slope * x + self.intercept
+    }
+
+    /// Find the `x` where trend reaches `y` value, if it is ever going to
+    pub fn solve(&self, y: f64) -> Option<f64> {
+        if self.slope == 0.0 {
+            None
+        } else {
+            Some((y - self.intercept) / self.slope)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Trend;
+
+    #[test]
+    fn line_is_fitted() {
+        let trend = Trend::fit(&[(0.0, 1.0), (1.0, 3.1), (2.0, 4.9), (3.0, 7.0)]).unwrap();
+        assert!((trend.slope - 1.98).abs() < 1e-9, "{:?}", trend);
+        assert!((trend.intercept - 1.03).abs() < 1e-9, "{:?}", trend);
+        assert!((trend.value_at(10.0) - 20.83).abs() < 1e-9);
+        assert!((trend.solve(20.83).unwrap() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn degenerate_points() {
+        assert_eq!(Trend::fit(&[]), None);
+        assert_eq!(Trend::fit(&[(1.0, 2.0)]), None);
+        // Same `x` for all the points, the line would be vertical
+        assert_eq!(Trend::fit(&[(1.0, 2.0), (1.0, 3.0)]), None);
+
+        let flat = Trend::fit(&[(0.0, 5.0), (1.0, 5.0)]).unwrap();
+        assert_eq!(flat.slope, 0.0);
+        assert_eq!(flat.solve(4.0), None);
+    }
+}
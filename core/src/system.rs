@@ -0,0 +1,103 @@
+//! System readings taken along with the device refreshes, ex. the CPU power or the adapter in use.
+//!
+//! Some of them are as slow as the backends are, ex. `ioreg` on macOS or the hwmon sensors
+//! waking the embedded controller up, so the `Collector` takes them on the collection thread
+//! right after the device refresh and delivers them with it, see `Device::system`.
+//! CPU, display and adapter readings are describing this machine, so only its own batteries get them.
+
+use std::path::PathBuf;
+
+use crate::adapter::{self, Adapter};
+use crate::brightness;
+use crate::cpu::{CpuMeter, CpuMetric, CpuReading};
+use crate::device::{Device, DeviceKind};
+use crate::rapl::{self, PowerMeter};
+use crate::sensors::{self, Reading};
+
+/// Optional readings to take, the RAPL power and the adapter are always read
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    /// CPU metric plotted along with the power draw
+    pub cpu: Option<CpuMetric>,
+    /// Display backlight level
+    pub brightness: bool,
+    /// System temperature sensor for the batteries which are not reporting their own temperature
+    pub temperature: bool,
+}
+
+/// Readings taken right after the device refresh
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Readings {
+    /// CPU package and integrated GPU power
+    pub power: Option<rapl::Power>,
+    pub cpu: Option<CpuReading>,
+    /// Display backlight level, %
+    pub brightness: Option<f32>,
+    pub adapter: Option<Adapter>,
+    /// System temperature sensor, if the device does not report its own temperature
+    pub temperature: Option<Reading>,
+    /// Device own temperature sensors, read only if there are several of them
+    pub sensors: Vec<Reading>,
+}
+
+/// Meters of one device, the counters are compared with the ones of its previous refresh
+#[derive(Debug)]
+pub struct Meters {
+    options: Options,
+    /// This machine own battery
+    local: bool,
+    /// Power supply directory, where the device own temperature sensors are looked for
+    power_supply: Option<PathBuf>,
+    rapl: Option<PowerMeter>,
+    cpu: Option<CpuMeter>,
+}
+
+impl Meters {
+    pub fn new(options: &Options, device: &Device) -> Meters {
+        let local = device.kind() == DeviceKind::Battery && device.host().is_none();
+        let (rapl, cpu) = if local {
+            (PowerMeter::new(), options.cpu.and_then(CpuMeter::new))
+        } else {
+            (None, None)
+        };
+
+        Meters {
+            options: options.clone(),
+            local,
+            power_supply: device.power_supply(),
+            rapl,
+            cpu,
+        }
+    }
+
+    /// Readings for the just refreshed `device`
+    pub fn read(&mut self, device: &Device) -> Readings {
+        let mut readings = Readings {
+            power: self.rapl.as_mut().and_then(PowerMeter::update),
+            cpu: self.cpu.as_mut().and_then(CpuMeter::update),
+            ..Readings::default()
+        };
+        if self.local {
+            if self.options.brightness {
+                readings.brightness = brightness::level();
+            }
+            readings.adapter = adapter::active();
+        }
+
+        if device.temperature().is_none() && self.options.temperature && device.capabilities().temperature {
+            readings.temperature = sensors::system_temperature();
+        }
+        if device.temperature().is_some() || readings.temperature.is_some() {
+            // Single sensor is reporting the same value the backend does
+            let sensors = match self.power_supply {
+                Some(ref supply) => sensors::battery_readings(supply),
+                None => Vec::new(),
+            };
+            if sensors.len() > 1 {
+                readings.sensors = sensors;
+            }
+        }
+
+        readings
+    }
+}
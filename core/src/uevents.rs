@@ -0,0 +1,154 @@
+//! Power supply change notifications, Linux only.
+//!
+//! Kernel broadcasts the `power_supply` uevents over the netlink socket whenever
+//! adapter is plugged or unplugged, battery changes its state or firmware reports a capacity step;
+//! ACPI battery and AC adapter notifications are turned into these uevents too.
+//! acpid, if running, is forwarding the rest of the ACPI events over its socket.
+//! No privileges are required to listen to any of them.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::mem;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::net::UnixStream;
+use std::time::{Duration, Instant};
+
+const ACPID_SOCKET: &str = "/var/run/acpid.socket";
+/// Kernel uevents multicast group, udev ones are re-broadcasted to the group 2
+const KERNEL_GROUP: u32 = 1;
+/// Events are coming in bursts, ex. for the adapter and every battery at once
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Power supply uevent worth reacting to
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Uevent {
+    /// Power supply was added or removed, ex. the battery was inserted or the wireless mouse had connected
+    Hotplug,
+    /// Existing power supply changed its state
+    Change,
+}
+
+/// Netlink socket subscribed to the kernel uevents
+#[derive(Debug)]
+pub struct Uevents {
+    socket: File,
+}
+
+impl Uevents {
+    /// Subscribe to the uevents, fails if netlink is not available, ex. in some containers
+    pub fn open() -> io::Result<Uevents> {
+        Ok(Uevents {
+            socket: open()?,
+        })
+    }
+
+    /// Call `notify` on each power supply change, until it returns `false` or socket fails;
+    /// bursts of the state changes are reported once, while the hotplugs are reported each
+    pub fn watch<F: FnMut(Uevent) -> bool>(mut self, mut notify: F) -> io::Result<()> {
+        let mut buffer = [0u8; 8192];
+        let mut notified: Option<Instant> = None;
+        loop {
+            let length = self.socket.read(&mut buffer)?;
+            let uevent = match parse(&buffer[..length]) {
+                Some(Uevent::Change) => match notified {
+                    Some(notified) if notified.elapsed() < DEBOUNCE => continue,
+                    _ => {
+                        notified = Some(Instant::now());
+                        Uevent::Change
+                    }
+                },
+                Some(uevent) => uevent,
+                None => continue,
+            };
+
+            trace!(
+                "Power supply uevent: {}",
+                String::from_utf8_lossy(&buffer[..length]).replace('\0', " ")
+            );
+            if !notify(uevent) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Power supply uevent of the `message`, ex. `change@/devices/…\0ACTION=change\0SUBSYSTEM=power_supply\0…`,
+/// `None` for the other subsystems
+fn parse(message: &[u8]) -> Option<Uevent> {
+    let fields = message.split(|byte| *byte == 0).collect::<Vec<_>>();
+    if !fields.contains(&&b"SUBSYSTEM=power_supply"[..]) {
+        return None;
+    }
+
+    if fields.contains(&&b"ACTION=add"[..]) || fields.contains(&&b"ACTION=remove"[..]) {
+        Some(Uevent::Hotplug)
+    } else {
+        Some(Uevent::Change)
+    }
+}
+
+/// Call `notify` on each battery or AC adapter ACPI event forwarded by acpid,
+/// ex. `ac_adapter ACPI0003:00 00000080 00000001`
+pub fn watch_acpid<F: FnMut() -> bool>(mut notify: F) -> io::Result<()> {
+    let socket = UnixStream::connect(ACPID_SOCKET)?;
+    for line in BufReader::new(socket).lines() {
+        let line = line?;
+        if line.starts_with("ac_adapter") || line.starts_with("battery") {
+            trace!("ACPI event: {}", line);
+            if !notify() {
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn open() -> io::Result<File> {
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            libc::NETLINK_KOBJECT_UEVENT,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // File closes the socket when dropped, reads are the `recv` calls
+    let socket = unsafe { File::from_raw_fd(fd) };
+
+    let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    address.nl_groups = KERNEL_GROUP;
+    let result = unsafe {
+        libc::bind(
+            fd,
+            &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Uevent};
+
+    #[test]
+    fn hotplugs_are_told_from_changes() {
+        let added = b"add@/devices/virtual/misc/uhid/0005:046D:B023.0001/power_supply/hidpp_battery_0\0\
+                      ACTION=add\0SUBSYSTEM=power_supply\0POWER_SUPPLY_NAME=hidpp_battery_0\0";
+        let changed = b"change@/devices/LNXSYSTM:00/PNP0C0A:00/power_supply/BAT0\0\
+                        ACTION=change\0SUBSYSTEM=power_supply\0POWER_SUPPLY_NAME=BAT0\0";
+        let other = b"add@/devices/pci0000:00/usb1/1-1\0ACTION=add\0SUBSYSTEM=usb\0";
+
+        assert_eq!(parse(added), Some(Uevent::Hotplug));
+        assert_eq!(parse(changed), Some(Uevent::Change));
+        assert_eq!(parse(other), None);
+    }
+}
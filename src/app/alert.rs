@@ -0,0 +1,47 @@
+//! User-provided command, executed when battop notices something worth attention.
+//!
+//! Command is executed with the system shell and receives the event details
+//! in the `BATTOP_*` environment variables, ex. `BATTOP_EVENT=voltage-sag`.
+
+use std::process::Stdio;
+use std::thread;
+
+use battop_core::command;
+
+use crate::app::Config;
+
+pub fn notify(config: &Config, event: &str, battery: &str, details: &[(&str, String)]) {
+    let command = match config.alert_command() {
+        Some(command) => command,
+        None => return,
+    };
+
+    let mut shell = if cfg!(target_os = "windows") {
+        let mut shell = command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .env("BATTOP_EVENT", event)
+        .env("BATTOP_BATTERY", battery)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    for (name, value) in details {
+        shell.env(format!("BATTOP_{}", name), value);
+    }
+
+    match shell.spawn() {
+        Ok(mut child) => {
+            trace!("Alert command was executed for the {} event", event);
+            // Reaping the command in background, so it will not block the interface
+            let _ = thread::spawn(move || child.wait());
+        }
+        Err(e) => warn!("Unable to execute alert command {:?}: {}", command, e),
+    }
+}
@@ -1,73 +1,452 @@
 use std::fmt;
 use std::sync::Arc;
+use std::time::Duration;
 
 use tui::backend::Backend;
 
-use super::config::Config;
-use super::events::{Event, EventHandler};
+use battop_core::device::{Collector, Device, Update};
+use battop_core::processes::ProcessMonitor;
+#[cfg(unix)]
+use battop_core::signals::Signal;
+
+use super::cadence::Cadence;
+use super::config::{Config, Watcher};
+use super::dump;
+use super::events::{Event, EventHandler, Timer};
+use super::profiles::PowerProfiles;
+use super::terminal::{Native, Screen};
 use super::ui;
-use crate::{Error, Result};
+use crate::{Error, Recovery, Result};
+
+/// How often devices are enumerated again to find the ones connected after the start,
+/// or less often if the backend asks for it
+const RESCAN_INTERVAL: Duration = Duration::from_secs(10);
+/// Device refresh taking longer than that is considered to be hung
+const REFRESH_TIMEOUT: Duration = Duration::from_secs(10);
 
+/// Start with the default terminal library, see `terminal::Native`
 pub fn init(config: Arc<Config>) -> Result<Application<impl Backend>> {
-    let manager = battery::Manager::new()?;
+    init_with(config, Native::default())
+}
+
+pub fn init_with<S: Screen>(config: Arc<Config>, screen: S) -> Result<Application<S::Backend>> {
+    let mut events = EventHandler::from_config(&config, screen.clone());
+    let collected_tx = events.sender();
+    let mut sources = config.sources();
+    sources.system = Some(config.system());
+    // Devices are already in the `order` set
+    let (collector, devices) = Collector::spawn(sources, move || collected_tx.send(Event::Collected).is_ok())?;
 
     // This vec will be used for UI data pre-population before the first tick
-    let batteries = manager
-        .batteries()?
-        .flatten()
-        .map(|battery| ui::View::new(config.clone(), battery))
+    let batteries = devices
+        .into_iter()
+        .map(|device| ui::View::new(config.clone(), device))
         .collect::<Vec<_>>();
 
     // Probing if any batteries are installed at all
     if batteries.is_empty() {
+        if config.sources().selection != Default::default() {
+            return Err(Error::Config(
+                "none of the devices found are selected by the `--battery` and `--exclude` options".to_string(),
+            ));
+        }
         error!("Unable to find any batteries in system, exiting");
         return Err(Error::NoBatteries);
     } else {
         trace!("Found {} batteries during initialization", batteries.len());
     }
 
-    let events = EventHandler::from_config(&config);
-    let interface = ui::init(config.clone(), batteries)?;
+    events.schedule(Timer::Housekeeping, *config.delay());
+    let cadence = Cadence::from_config(&config);
+    let mut interface = ui::init(config.clone(), screen.open()?, batteries)?;
+    let processes = if config.processes() {
+        Some(ProcessMonitor::new())
+    } else {
+        None
+    };
+    let watcher = config.path().map(Watcher::new);
+    let profiles = PowerProfiles::new();
+    if let Some(ref profiles) = profiles {
+        interface.set_profile(profiles.active());
+    }
 
-    Ok(Application {
-        manager,
+    let mut app = Application {
+        collector,
         config,
         events,
+        cadence,
         interface,
-    })
+        processes,
+        profiles,
+        watcher,
+    };
+    app.schedule_polls();
+    app.schedule_rescans();
+
+    Ok(app)
 }
 
 pub struct Application<B: Backend> {
-    manager: battery::Manager,
+    collector: Collector,
     config: Arc<Config>,
     events: EventHandler,
+    cadence: Cadence,
     interface: ui::Interface<B>,
+    processes: Option<ProcessMonitor>,
+    profiles: Option<PowerProfiles>,
+    watcher: Option<Watcher>,
 }
 
 impl<B: Backend> Application<B> {
+    /// Every source of changes is an event, interface is re-drawn only if some of them changed anything
     pub fn run(&mut self) -> Result<()> {
+        self.interface.draw()?;
         loop {
-            self.interface.draw()?;
-            self.handle_event()?;
+            let redraw = match self.handle_event() {
+                Ok(redraw) => redraw,
+                Err(e) => self.recover(e)?,
+            };
+            if redraw {
+                self.interface.draw()?;
+            }
         }
     }
 
-    fn handle_event(&mut self) -> Result<()> {
-        match self.events.next()? {
-            Event::Exit => Err(Error::UserExit),
-            Event::PreviousTab => {
-                self.interface.tabs_mut().previous();
-                Ok(())
+    /// Apply the recovery policy of the error, the ones which can't be recovered from are returned back
+    fn recover(&mut self, e: Error) -> Result<bool> {
+        match e.recovery() {
+            Recovery::Exit => return Err(e),
+            Recovery::Degrade => {
+                debug!("{}", e);
+                if let Error::Backend(ref id, _) = e {
+                    if let Some(view) = self.view_mut(id) {
+                        view.degrade();
+                    }
+                }
+            }
+            Recovery::Report => {
+                warn!("{}", e);
+                self.interface.log(e.to_string());
             }
-            Event::NextTab => {
-                self.interface.tabs_mut().next();
-                Ok(())
+        }
+
+        Ok(true)
+    }
+
+    fn view_mut(&mut self, id: &str) -> Option<&mut ui::View> {
+        self.interface
+            .views_mut()
+            .iter_mut()
+            .find(|view| view.battery().id() == id)
+    }
+
+    /// Poll each backend with the current cadence, or slower if the backend asks for it
+    fn schedule_polls(&mut self) {
+        let interval = self.cadence.interval(self.interface.views());
+        for index in 0..self.collector.source_count() {
+            let source_interval = self.collector.interval(index).map_or(interval, |min| min.max(interval));
+            self.events.schedule(Timer::Poll(index), source_interval);
+        }
+    }
+
+    /// Rescan each backend no more often than it is polled; the ones reading the kernel power supplies
+    /// are rescanned on the hotplug uevents only, if these are received
+    fn schedule_rescans(&mut self) {
+        for index in 0..self.collector.source_count() {
+            if self.collector.hotplug(index) && self.events.follows_hotplug() {
+                continue;
             }
-            Event::Tick => {
-                for view in self.interface.views_mut() {
-                    view.update(&mut self.manager)?;
+            let interval = self
+                .collector
+                .interval(index)
+                .map_or(RESCAN_INTERVAL, |min| min.max(RESCAN_INTERVAL));
+            self.events.schedule(Timer::Rescan(index), interval);
+        }
+    }
+
+    /// Apply the devices refreshed by the collection threads so far
+    ///
+    /// Failures are limited to the device they happened with, so the rest of updates are applied anyway.
+    fn collect(&mut self) -> Result<()> {
+        let mut refreshed = false;
+        let updates = self.collector.updates().collect::<Vec<_>>();
+        for update in updates {
+            match update {
+                Update::Refreshed(device) => {
+                    refreshed = true;
+                    let id = device.id();
+                    let result = match self.view_mut(&id) {
+                        Some(view) => view.update(device),
+                        None => Ok(()),
+                    };
+                    if let Err(e) = result {
+                        let _ = self.recover(Error::Backend(id, Box::new(e)))?;
+                    }
+                }
+                Update::Failed(id, e) => {
+                    let _ = self.recover(Error::Backend(id, Box::new(e.into())))?;
+                }
+                Update::Disconnected(id) => {
+                    if let Some(view) = self.view_mut(&id) {
+                        view.disconnect();
+                    }
                 }
-                Ok(())
+                Update::Connected(device) => self.connect(device),
+            }
+        }
+        // Cadence depends on the latest values
+        if refreshed {
+            self.schedule_polls();
+        }
+
+        Ok(())
+    }
+
+    /// Add tab for the device connected after the start, or resume the disconnected one if it is back
+    fn connect(&mut self, device: Device) {
+        if let Some(view) = self
+            .interface
+            .views_mut()
+            .iter_mut()
+            .find(|view| !view.is_connected() && view.battery().is_same(&device))
+        {
+            info!("Device reconnected: {}", device.id());
+            view.reconnect(device);
+            return;
+        }
+
+        info!("New device connected: {}", device.id());
+        let view = ui::View::new(self.config.clone(), device);
+        self.interface.add_view(view);
+    }
+
+    /// Apply the configuration file changes, if there are any
+    fn reload_config(&mut self) -> bool {
+        let changed = match self.watcher {
+            Some(ref mut watcher) => watcher.changed(),
+            None => false,
+        };
+        if !changed {
+            return false;
+        }
+
+        self.apply_config()
+    }
+
+    /// Read the configuration again, collected chart history is kept
+    fn apply_config(&mut self) -> bool {
+        let config = match self.config.reload() {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Unable to reload the configuration: {}", e);
+                // Status bar has room for the first problem only, the log has all of them
+                let error = e.to_string();
+                let first = error.lines().next().unwrap_or_default();
+                let message = self
+                    .config
+                    .locale()
+                    .message("status.config_failed", &[("error", &first)]);
+                self.interface.log(message);
+                return true;
+            }
+        };
+        let restart_required = self.config.restart_required(&config);
+
+        let config = Arc::new(config);
+        self.cadence.reconfigure(&config);
+        self.events.schedule(Timer::Housekeeping, *config.delay());
+        self.interface.set_config(config.clone());
+        self.config = config;
+        self.schedule_polls();
+        info!("Configuration reloaded");
+        let locale = self.config.locale();
+        let message = if restart_required.is_empty() {
+            locale.text("status.config_reloaded").to_string()
+        } else {
+            locale.message("status.config_restart", &[("options", &restart_required.join(", "))])
+        };
+        self.interface.log(message);
+
+        true
+    }
+
+    /// Hung refresh keeps the last values of the device, marked as stale until it returns
+    fn watchdog(&mut self) -> bool {
+        let id = match self.collector.hung(REFRESH_TIMEOUT) {
+            Some(id) => id,
+            None => return false,
+        };
+        warn!("Refresh of {} takes longer than {:?}", id, REFRESH_TIMEOUT);
+        let title = match self.view_mut(&id) {
+            Some(view) => {
+                view.degrade();
+                view.title()
+            }
+            None => id,
+        };
+        let message = self.config.locale().message(
+            "status.hung",
+            &[("title", &title), ("seconds", &REFRESH_TIMEOUT.as_secs())],
+        );
+        self.interface.log(message);
+
+        true
+    }
+
+    fn update_processes(&mut self) -> bool {
+        let result = match self.processes {
+            Some(ref mut monitor) => monitor.update(),
+            None => return false,
+        };
+
+        match result {
+            Ok(Some(attribution)) => {
+                self.interface.set_attribution(attribution);
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                warn!("Unable to collect processes CPU time, disabling attribution: {}", e);
+                self.processes = None;
+                true
+            }
+        }
+    }
+
+    /// Power profile might be changed by some other tool too
+    fn update_profile(&mut self) -> bool {
+        let result = match self.profiles {
+            Some(ref mut profiles) => profiles.update(),
+            None => return false,
+        };
+
+        match result {
+            Ok(Some(profile)) => {
+                info!("Power profile changed to {}", profile);
+                self.interface.set_profile(profile);
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                warn!("Unable to read the power profile, hiding it: {}", e);
+                self.profiles = None;
+                true
+            }
+        }
+    }
+
+    fn switch_profile(&mut self) {
+        let result = match self.profiles {
+            Some(ref mut profiles) => profiles.switch(),
+            None => return,
+        };
+
+        match result {
+            Ok(profile) => {
+                info!("Switched power profile to {}", profile);
+                self.interface.set_profile(profile);
+            }
+            Err(e) => warn!("Unable to switch the power profile: {}", e),
+        }
+    }
+
+    /// Returns `true` if the interface should be re-drawn
+    fn handle_event(&mut self) -> Result<bool> {
+        let event = self.events.next()?;
+        let input = match event {
+            Event::Timer(_) | Event::PowerChanged | Event::Hotplug | Event::Collected => false,
+            #[cfg(unix)]
+            Event::Signal(_) => false,
+            _ => true,
+        };
+        if input {
+            self.cadence.interacted();
+            self.schedule_polls();
+        }
+
+        // Navigation, forecast and the dialog keys
+        if self.interface.handle(&event) {
+            return Ok(true);
+        }
+
+        match event {
+            // Cancel closes the dialog if there is one, see `Interface::handle`
+            Event::Exit | Event::Cancel => Err(Error::UserExit),
+            Event::NextProfile => {
+                self.switch_profile();
+                Ok(true)
+            }
+            // Collection thread will send `Event::Collected` when it is done
+            Event::Timer(Timer::Poll(index)) => {
+                self.collector.refresh(Some(index));
+                Ok(false)
+            }
+            Event::PowerChanged => {
+                self.collector.refresh(None);
+                Ok(false)
+            }
+            Event::Hotplug => {
+                for index in 0..self.collector.source_count() {
+                    if self.collector.hotplug(index) {
+                        self.collector.rescan(Some(index));
+                    }
+                }
+                Ok(false)
+            }
+            Event::Timer(Timer::Rescan(index)) => {
+                self.collector.rescan(Some(index));
+                Ok(false)
+            }
+            Event::Collected => {
+                self.collect()?;
+                Ok(true)
+            }
+            Event::Timer(Timer::Housekeeping) => {
+                let hung = self.watchdog();
+                let processes = self.update_processes();
+                let profile = self.update_profile();
+                let config = self.reload_config();
+                Ok(hung || processes || profile || config)
+            }
+            // Terminal is restored and the sessions in progress are persisted as the application is dropped
+            #[cfg(unix)]
+            Event::Signal(Signal::Terminate) => {
+                info!("Terminated by signal");
+                Err(Error::UserExit)
+            }
+            #[cfg(unix)]
+            Event::Signal(Signal::Reload) => {
+                // File change is applied now, not by the next housekeeping
+                if let Some(ref mut watcher) = self.watcher {
+                    let _ = watcher.changed();
+                }
+                Ok(self.apply_config())
+            }
+            #[cfg(unix)]
+            Event::Signal(Signal::Dump) => {
+                self.dump_state();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn dump_state(&mut self) {
+        let path = dump::default_path();
+        let interval = self.cadence.interval(self.interface.views());
+        match dump::write(&path, &self.config, self.interface.views(), interval) {
+            Ok(()) => {
+                info!("State dumped to {}", path.display());
+                let message = self
+                    .config
+                    .locale()
+                    .message("status.dumped", &[("path", &path.display())]);
+                self.interface.log(message);
+            }
+            Err(e) => {
+                warn!("Unable to dump the state to {}: {}", path.display(), e);
+                let message = self.config.locale().message("status.dump_failed", &[("error", &e)]);
+                self.interface.log(message);
             }
         }
     }
@@ -77,7 +456,7 @@ impl<B: Backend> fmt::Debug for Application<B> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Application")
             .field("config", &self.config)
-            .field("manager", &self.manager)
+            .field("collector", &self.collector)
             .finish()
     }
 }
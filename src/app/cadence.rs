@@ -0,0 +1,95 @@
+//! Adaptive polling interval.
+//!
+//! battop itself should not be the reason of the battery drain,
+//! so when nothing interesting happens, batteries are polled less often.
+
+use std::time::{Duration, Instant};
+
+use battery::State;
+
+use crate::app::ui::View;
+use crate::app::Config;
+
+/// Interval used when values are changing rapidly or user interacts with battop
+const FAST_INTERVAL: Duration = Duration::from_secs(1);
+/// Interval used when battery is full or idle and nothing changes
+const SLOW_INTERVAL: Duration = Duration::from_secs(30);
+/// For how long polling stays fast after the user input
+const INTERACTION_BOOST: Duration = Duration::from_secs(10);
+/// Energy rate change between two consecutive samples considered as a rapid one
+const VOLATILE_CHANGE: f32 = 0.2;
+/// Battery with the smaller energy rate is considered idle, W
+const IDLE_POWER: f32 = 0.5;
+
+#[derive(Debug)]
+pub struct Cadence {
+    enabled: bool,
+    base: Duration,
+    last_interaction: Option<Instant>,
+}
+
+impl Cadence {
+    pub fn from_config(config: &Config) -> Cadence {
+        Cadence {
+            enabled: config.adaptive(),
+            base: *config.delay(),
+            last_interaction: None,
+        }
+    }
+
+    /// Configuration reloaded at runtime, recent interaction is still taken into account
+    pub fn reconfigure(&mut self, config: &Config) {
+        self.enabled = config.adaptive();
+        self.base = *config.delay();
+    }
+
+    /// User pressed some key
+    pub fn interacted(&mut self) {
+        self.last_interaction = Some(Instant::now());
+    }
+
+    /// Choose the polling interval depending on what is happening with batteries
+    pub fn interval(&self, views: &[View]) -> Duration {
+        if !self.enabled {
+            return self.base;
+        }
+
+        let fast = self.base.min(FAST_INTERVAL);
+        let slow = self.base.max(SLOW_INTERVAL);
+
+        let interacting = match self.last_interaction {
+            Some(at) => at.elapsed() < INTERACTION_BOOST,
+            None => false,
+        };
+        if interacting || views.iter().any(is_volatile) {
+            fast
+        } else if views.iter().all(is_resting) {
+            slow
+        } else {
+            self.base
+        }
+    }
+}
+
+/// Energy rate changed significantly since the previous sample, not counting the wake up
+fn is_volatile(view: &View) -> bool {
+    let mut samples = view.history().samples().iter().rev();
+    match (samples.next(), samples.next()) {
+        (Some(latest), Some(previous)) if !latest.resumed => {
+            let change = (latest.energy_rate - previous.energy_rate).abs();
+            change / previous.energy_rate.abs().max(1.0) > VOLATILE_CHANGE
+        }
+        _ => false,
+    }
+}
+
+/// Battery is full or is not charging or discharging at all
+fn is_resting(view: &View) -> bool {
+    match view.history().samples().latest() {
+        Some(sample) => match sample.state {
+            State::Full => true,
+            _ => sample.energy_rate.abs() < IDLE_POWER,
+        },
+        None => false,
+    }
+}
@@ -0,0 +1,160 @@
+//! Per-device sections of the configuration file.
+//!
+//! ```toml
+//! [device.ups]
+//! match = "Back-UPS*"
+//! title = "UPS"
+//! bands = "50,150"
+//!
+//! [device.ups.charts]
+//! temperature = false
+//! ```
+//!
+//! Pattern is matched against the device serial number, model and id, `*` and `?` wildcards
+//! are supported and the case is ignored. First matching section wins.
+//!
+//! Tabs order and the nicknames are kept by the serial numbers, so they are not depending
+//! on the order the system happened to enumerate the devices in:
+//!
+//! ```toml
+//! order = ["4H5G2X1", "sysfs:/sys/class/power_supply/BAT1"]
+//!
+//! [nicknames]
+//! 4H5G2X1 = "Internal"
+//! ```
+
+use battop_core::device::{Device, Order};
+use battop_core::stats::PowerBands;
+
+use super::file::ConfigFile;
+#[cfg(feature = "ui")]
+use super::theme;
+use super::{charts, Charts, BANDS};
+#[cfg(feature = "ui")]
+use crate::app::ui::Theme;
+use crate::Result;
+
+/// Settings which might be overridden for the specific devices
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceSettings {
+    /// Tab title instead of the device model
+    pub title: Option<String>,
+    #[cfg(feature = "ui")]
+    pub theme: Theme,
+    pub charts: Charts,
+    pub bands: PowerBands,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    pattern: String,
+    settings: DeviceSettings,
+}
+
+impl Override {
+    pub fn matches(&self, device: &Device) -> bool {
+        device
+            .serial_number()
+            .into_iter()
+            .chain(device.model())
+            .any(|value| matches(&self.pattern, value))
+            || matches(&self.pattern, &device.id())
+    }
+
+    pub fn settings(&self) -> &DeviceSettings {
+        &self.settings
+    }
+}
+
+/// `order` setting, either one of the `Order::VARIANTS` or an explicit list
+pub fn order(file: &ConfigFile) -> Result<Order> {
+    if file.string("order").is_ok() {
+        return Ok(file.choice("order", &Order::VARIANTS)?.unwrap_or_default());
+    }
+
+    match file.strings("order") {
+        Ok(list) => Ok(list.map(Order::List).unwrap_or_default()),
+        Err(_) => Err(file.invalid_key(
+            "order",
+            "one of `found`, `serial`, `path` or an array of the serial numbers and ids",
+        )),
+    }
+}
+
+/// `[nicknames]` section, names given to the devices by their serial numbers
+pub fn nicknames(file: &ConfigFile) -> Result<Vec<(String, String)>> {
+    let mut nicknames = Vec::new();
+    for serial in file.table("nicknames") {
+        let key = format!("nicknames.{}", serial);
+        if let Some(nickname) = file.string(&key)? {
+            nicknames.push((serial.to_string(), nickname));
+        }
+    }
+
+    Ok(nicknames)
+}
+
+/// `[device.*]` sections, layered over the global settings
+pub fn overrides(file: &ConfigFile, base: &DeviceSettings) -> Result<Vec<Override>> {
+    let mut names: Vec<&str> = Vec::new();
+    for name in file.table("device").into_iter().filter_map(|key| key.split('.').next()) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    let mut overrides = Vec::with_capacity(names.len());
+    for name in names {
+        let prefix = format!("device.{}.", name);
+        let key = |key: &str| format!("{}{}", prefix, key);
+        let pattern = file
+            .string(&key("match"))?
+            .ok_or_else(|| file.invalid_key(&key("match"), "a serial number or model pattern, ex. \"Back-UPS*\""))?;
+        let settings = DeviceSettings {
+            title: file.string(&key("title"))?.or_else(|| base.title.clone()),
+            #[cfg(feature = "ui")]
+            theme: theme(file, &prefix, &base.theme)?,
+            charts: charts(file, &prefix, &base.charts)?,
+            bands: file.parse(&key("bands"), BANDS)?.unwrap_or(base.bands),
+        };
+
+        overrides.push(Override {
+            pattern,
+            settings,
+        });
+    }
+
+    Ok(overrides)
+}
+
+/// Case-insensitive glob match, `*` stands for any sequence and `?` for any single character
+fn matches(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let value = value.to_lowercase().chars().collect::<Vec<_>>();
+
+    // Position after the last `*` in the pattern and the value position it was tried at
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, v));
+            }
+            Some(&c) if c == '?' || c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match backtrack {
+                Some((star, tried)) => {
+                    p = star;
+                    v = tried + 1;
+                    backtrack = Some((star, v));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
@@ -0,0 +1,322 @@
+//! Configuration file, `$XDG_CONFIG_HOME/battop/config.toml` or `~/.config/battop/config.toml` on Linux,
+//! `~/Library/Application Support/battop/config.toml` on macOS and `%APPDATA%\battop\config.toml` on Windows.
+//!
+//! File is optional; values set there are used unless the same option is given on the command line.
+//! Named `[profile.<name>]` sections, selected with the `--profile` option or the top-level `profile` key,
+//! are overriding the rest of the file.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::schema;
+use super::toml::{self, Entry, Value};
+use crate::{Error, Result};
+
+/// Platform-specific path of the configuration file
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        if !dir.is_empty() {
+            return Some(PathBuf::from(dir).join("battop").join("config.toml"));
+        }
+    }
+
+    if cfg!(target_os = "windows") {
+        return env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("battop").join("config.toml"));
+    }
+
+    let home = PathBuf::from(env::var_os("HOME")?);
+    if cfg!(target_os = "macos") {
+        Some(
+            home.join("Library")
+                .join("Application Support")
+                .join("battop")
+                .join("config.toml"),
+        )
+    } else {
+        Some(home.join(".config").join("battop").join("config.toml"))
+    }
+}
+
+/// Parsed configuration file with the typed access to its values.
+///
+/// Getters return `None` for the missing keys and an error for the values of the wrong type.
+#[derive(Debug)]
+pub struct ConfigFile {
+    path: PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl ConfigFile {
+    pub fn open(path: &Path) -> Result<ConfigFile> {
+        let text =
+            fs::read_to_string(path).map_err(|e| Error::Config(format!("unable to read {}: {}", path.display(), e)))?;
+        let entries = toml::parse(&text).map_err(|e| Error::Config(format!("{}, {}", path.display(), e)))?;
+        trace!("Loaded {} settings from {}", entries.len(), path.display());
+
+        Ok(ConfigFile {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Apply the `[profile.<name>]` section over the rest of the file.
+    ///
+    /// Profile keys are the same as the top-level ones, ex. `profile.bench.interval`
+    /// replaces the `interval` and `profile.bench.charts.voltage` replaces the `charts.voltage`.
+    pub fn select_profile(&mut self, name: &str) -> Result<()> {
+        let prefix = format!("profile.{}.", name);
+        if !self.entries.iter().any(|entry| entry.key.starts_with(&prefix)) {
+            return Err(Error::Config(format!(
+                "{}: profile `{}` is not defined",
+                self.path.display(),
+                name
+            )));
+        }
+
+        let (profile, mut entries): (Vec<Entry>, Vec<Entry>) = self
+            .entries
+            .drain(..)
+            .filter(|entry| entry.key != "profile")
+            .partition(|entry| entry.key.starts_with("profile."));
+        for mut entry in profile.into_iter().filter(|entry| entry.key.starts_with(&prefix)) {
+            entry.key = entry.key[prefix.len()..].to_string();
+            entries.retain(|existing| existing.key != entry.key);
+            entries.push(entry);
+        }
+        self.entries = entries;
+
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Report all the keys which are not `known`, see `schema` for the patterns
+    pub fn check_keys(&self, known: &[String]) -> Result<()> {
+        let unknown = self
+            .entries
+            .iter()
+            .filter(|entry| !known.iter().any(|known| schema::matches(known, &entry.key)))
+            .map(|entry| {
+                let mut message = format!(
+                    "{}, line {}: unknown key `{}`",
+                    self.path.display(),
+                    entry.line,
+                    entry.key
+                );
+                if let Some(suggestion) = schema::suggest(&entry.key, known) {
+                    message.push_str(&format!(", did you mean `{}`?", suggestion));
+                }
+                message
+            })
+            .collect::<Vec<_>>();
+
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Config(unknown.join("\n")))
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.key == key)
+    }
+
+    /// Keys of the `[name]` table, without the table prefix
+    pub fn table(&self, name: &str) -> Vec<&str> {
+        let prefix = format!("{}.", name);
+        self.entries
+            .iter()
+            .filter(|entry| entry.key.starts_with(&prefix))
+            .map(|entry| &entry.key[prefix.len()..])
+            .collect()
+    }
+
+    pub fn string(&self, key: &str) -> Result<Option<String>> {
+        match self.get(key) {
+            Some(Entry {
+                value: Value::String(value),
+                ..
+            }) => Ok(Some(value.clone())),
+            Some(entry) => Err(self.invalid(entry, "a string")),
+            None => Ok(None),
+        }
+    }
+
+    pub fn boolean(&self, key: &str) -> Result<Option<bool>> {
+        match self.get(key) {
+            Some(Entry {
+                value: Value::Boolean(value),
+                ..
+            }) => Ok(Some(*value)),
+            Some(entry) => Err(self.invalid(entry, "`true` or `false`")),
+            None => Ok(None),
+        }
+    }
+
+    pub fn integer(&self, key: &str) -> Result<Option<i64>> {
+        match self.get(key) {
+            Some(Entry {
+                value: Value::Integer(value),
+                ..
+            }) => Ok(Some(*value)),
+            Some(entry) => Err(self.invalid(entry, "an integer")),
+            None => Ok(None),
+        }
+    }
+
+    /// Integers are accepted as well
+    pub fn float(&self, key: &str) -> Result<Option<f64>> {
+        match self.get(key) {
+            Some(Entry {
+                value: Value::Float(value),
+                ..
+            }) => Ok(Some(*value)),
+            Some(Entry {
+                value: Value::Integer(value),
+                ..
+            }) => Ok(Some(*value as f64)),
+            Some(entry) => Err(self.invalid(entry, "a number")),
+            None => Ok(None),
+        }
+    }
+
+    pub fn strings(&self, key: &str) -> Result<Option<Vec<String>>> {
+        let entry = match self.get(key) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let values = match entry.value {
+            Value::Array(ref values) => values,
+            _ => return Err(self.invalid(entry, "an array of strings")),
+        };
+
+        values
+            .iter()
+            .map(|value| match value {
+                Value::String(value) => Ok(value.clone()),
+                _ => Err(self.invalid(entry, "an array of strings")),
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Some)
+    }
+
+    /// String value parsed the same way as the command line argument, `expected` describes the valid ones
+    pub fn parse<T: FromStr>(&self, key: &str, expected: &str) -> Result<Option<T>> {
+        match self.string(key)? {
+            Some(value) => match value.parse() {
+                Ok(value) => Ok(Some(value)),
+                Err(_) => Err(self.invalid(self.get(key).expect("Key was just read"), expected)),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// One of the `variants` names, the case is ignored
+    pub fn choice<T: FromStr>(&self, key: &str, variants: &[&str]) -> Result<Option<T>> {
+        let value = match self.string(key)? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        if let Ok(value) = value.parse() {
+            return Ok(Some(value));
+        }
+
+        let names = variants.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>();
+        let mut error = self.invalid(
+            self.get(key).expect("Key was just read"),
+            &format!("one of {}", names.join(", ")),
+        );
+        if let (Error::Config(ref mut message), Some(similar)) =
+            (&mut error, schema::similar(&value, variants.iter().cloned()))
+        {
+            message.push_str(&format!(", did you mean `{}`?", similar));
+        }
+
+        Err(error)
+    }
+
+    /// Error for the value which has the right type, but is out of range
+    pub fn invalid_key(&self, key: &str, expected: &str) -> Error {
+        match self.get(key) {
+            Some(entry) => self.invalid(entry, expected),
+            None => Error::Config(format!("{}: `{}` should be {}", self.path.display(), key, expected)),
+        }
+    }
+
+    fn invalid(&self, entry: &Entry, expected: &str) -> Error {
+        Error::Config(format!(
+            "{}, line {}: `{}` should be {}, found {}",
+            self.path.display(),
+            entry.line,
+            entry.key,
+            expected,
+            describe(&entry.value)
+        ))
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::String(value) => format!("\"{}\"", value),
+        Value::Integer(value) => value.to_string(),
+        Value::Float(value) => value.to_string(),
+        Value::Boolean(value) => value.to_string(),
+        Value::Datetime(value) => value.clone(),
+        Value::Array(_) => "an array".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use battop_core::format::Units;
+
+    use super::ConfigFile;
+    use crate::app::config::schema;
+
+    fn file(name: &str, text: &str) -> ConfigFile {
+        let path = env::temp_dir().join(format!("battop-{}-{}.toml", process::id(), name));
+        fs::write(&path, text).unwrap();
+        let file = ConfigFile::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        file
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_together() {
+        let file = file("unknown", "intervall = 5\nunits = \"si\"\n\n[charts]\nvoltag = false\n");
+        let message = file.check_keys(&schema::config()).unwrap_err().to_string();
+        let path = file.path().display();
+
+        assert_eq!(
+            message,
+            format!(
+                "Invalid configuration: {0}, line 1: unknown key `intervall`, did you mean `interval`?\n\
+                 {0}, line 5: unknown key `charts.voltag`, did you mean `charts.voltage`?",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn choices_are_listed() {
+        let file = file("choice", "units = \"SI\"\ncpu = \"laod\"\n");
+        let units = file.choice::<Units>("units", &Units::arg_variants()).unwrap();
+        assert_eq!(units, Some(Units::Si));
+
+        let message = file
+            .choice::<Units>("cpu", &["load", "frequency"])
+            .unwrap_err()
+            .to_string();
+        assert!(message
+            .ends_with("line 2: `cpu` should be one of `load`, `frequency`, found \"laod\", did you mean `load`?"));
+    }
+}
@@ -0,0 +1,895 @@
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use structopt::clap::ArgMatches;
+use structopt::StructOpt;
+
+use battop_core::cpu::CpuMetric;
+use battop_core::device::{self, Backend, Device, Order};
+use battop_core::format::Units;
+use battop_core::history::Retention;
+use battop_core::stats::PowerBands;
+use battop_core::system;
+
+#[cfg(feature = "ui")]
+use crate::app::events::{parse_key, Event, KeyBindings};
+use crate::app::i18n::{self, Locale};
+#[cfg(feature = "ui")]
+use crate::app::terminal::Key;
+#[cfg(feature = "ui")]
+use crate::app::ui::{parse_color, Theme};
+use crate::{Error, Result};
+
+mod devices;
+mod file;
+mod schema;
+#[cfg(feature = "ui")]
+mod scripts;
+pub mod toml;
+#[cfg(feature = "ui")]
+mod watch;
+
+pub use self::devices::DeviceSettings;
+use self::devices::Override;
+use self::file::ConfigFile;
+#[cfg(feature = "ui")]
+pub use self::scripts::{Part, Scripts};
+#[cfg(feature = "ui")]
+pub use self::watch::Watcher;
+
+/// Power bands as they are written, for the configuration errors
+const BANDS: &str = "two increasing power cut-offs in watts, ex. \"5,15\"";
+
+fn parse_duration(raw: &str) -> ::std::result::Result<Duration, String> {
+    match u64::from_str(raw) {
+        Ok(seconds) if seconds > 0 => Ok(Duration::from_secs(seconds)),
+        _ => Err(format!("{} isn't a positive number", raw)),
+    }
+}
+
+fn parse_retention(raw: &str) -> ::std::result::Result<Duration, String> {
+    match humantime::parse_duration(raw) {
+        Ok(duration) if duration > Duration::from_secs(0) => Ok(duration),
+        _ => Err(format!("{} isn't a positive duration, ex. 12h or 2days", raw)),
+    }
+}
+
+fn parse_samples(raw: &str) -> ::std::result::Result<usize, String> {
+    match usize::from_str(raw) {
+        Ok(count) if count >= Retention::MIN_SAMPLES => Ok(count),
+        _ => Err(format!(
+            "{} isn't a number of samples, at least {}",
+            raw,
+            Retention::MIN_SAMPLES
+        )),
+    }
+}
+
+fn parse_price(raw: &str) -> ::std::result::Result<f64, String> {
+    match f64::from_str(raw) {
+        Ok(price) if price >= 0.0 && price.is_finite() => Ok(price),
+        _ => Err(format!("{} isn't a valid price", raw)),
+    }
+}
+
+/// Interactive batteries viewer.
+///
+/// The following commands are supported while in battop:
+///
+/// * Right: move to next tab
+///
+/// * Left: move to previous tab
+///
+/// * 1: show battery overview
+///
+/// * 2: show battery health history
+///
+/// * 3: show statistics
+///
+/// * 4: compare all batteries battop has history for
+///
+/// * 5: show processes responsible for the power draw (requires `--processes` flag)
+///
+/// * 6: show the panels defined by the scripts in the `battop/scripts` directory
+///
+/// * W: switch statistics time window
+///
+/// * +, -: adjust power draw for the runtime forecast
+///
+/// * p, P: use median (p50) or p90 power draw of the discharging session for the runtime forecast
+///
+/// * Q, Ctrl+C, Esc: close viewer
+///
+/// Settings are also read from the `battop/config.toml` in the user configuration directory,
+/// command line arguments take precedence over it.
+#[derive(StructOpt, Debug)]
+struct Arguments {
+    #[structopt(long = "config", parse(from_os_str))]
+    /// Configuration file to use instead of the default `battop/config.toml`
+    /// in the user configuration directory
+    config: Option<PathBuf>,
+
+    #[structopt(long = "profile")]
+    /// Named `[profile.<name>]` section of the configuration file applied over the rest of it
+    profile: Option<String>,
+
+    #[structopt(short = "v", long = "verbose", max_values = 5, parse(from_occurrences))]
+    /// Verbosity level, might be repeated up to 5 times (-vvvvv).
+    /// Log is accessible from the stderr.
+    verbose: usize,
+
+    #[structopt(long = "trace-file", parse(from_os_str))]
+    /// Append the full log to this file, including the timings of the device refreshes,
+    /// interface drawing and history writes, regardless of the verbosity level
+    trace_file: Option<PathBuf>,
+
+    #[structopt(
+        short = "d",
+        long = "delay",
+        default_value = "1",
+        parse(try_from_str = "parse_duration")
+    )]
+    /// Delay between updates, in seconds
+    delay: Duration,
+
+    #[structopt(
+        short = "b",
+        long = "backend",
+        default_value = "battery",
+        raw(possible_values = "&Backend::arg_variants()", case_insensitive = "true")
+    )]
+    /// Backend used to access the devices, the other ones are used if this one is not available
+    backend: Backend,
+
+    #[structopt(long = "demo")]
+    /// Show the simulated battery instead of the real ones, same as `--backend demo`.
+    /// History is not persisted in this mode
+    demo: bool,
+
+    #[structopt(long = "nut-server", default_value = "localhost")]
+    /// Network UPS Tools server used by the `nut` backend, as a `host` or `host:port`
+    nut_server: String,
+
+    #[structopt(long = "socket", parse(from_os_str))]
+    /// Unix socket the `battop daemon` listens on and the `daemon` backend attaches to,
+    /// `battop.sock` in the `$XDG_RUNTIME_DIR` by default
+    socket: Option<PathBuf>,
+
+    #[structopt(long = "prometheus")]
+    /// Serve the devices to Prometheus from the `battop daemon` on this address, ex. `127.0.0.1:9851`,
+    /// requires battop to be built with the `prometheus` feature
+    prometheus: Option<String>,
+
+    #[structopt(long = "remote", number_of_values = 1)]
+    /// Show the batteries of the remote Linux host instead, collected over SSH, ex. `user@host`.
+    /// Can be repeated to watch several hosts at once, `local` stands for this machine
+    remotes: Vec<String>,
+
+    #[structopt(long = "bluetooth")]
+    /// Also show the battery levels of Bluetooth devices, as reported by BlueZ (Linux only)
+    bluetooth: bool,
+
+    #[structopt(long = "adb")]
+    /// Also show the batteries of Android devices connected over ADB
+    adb: bool,
+
+    #[structopt(long = "hid")]
+    /// Also show the battery levels of wireless mice and keyboards read directly over HID,
+    /// requires battop to be built with the `hid` feature
+    hid: bool,
+
+    #[structopt(long = "smbus")]
+    /// Also read the Smart Battery registers directly over the given `i2c-dev` SMBus adapter,
+    /// ex. `/dev/i2c-3` (Linux only, usually requires root)
+    smbus: Option<String>,
+
+    #[structopt(long = "vedirect", number_of_values = 1)]
+    /// Also show the Victron Energy solar charger or battery monitor connected to the given serial port
+    /// with the VE.Direct cable, ex. `/dev/ttyUSB0` (Linux only). Can be repeated for several devices
+    vedirect: Vec<String>,
+
+    #[structopt(long = "battery", number_of_values = 1)]
+    /// Show only the given device: its id, serial number or position among the tabs on start,
+    /// starting from #1. Can be repeated for several devices
+    batteries: Vec<String>,
+
+    #[structopt(long = "exclude", number_of_values = 1)]
+    /// Do not show the given device, referred the same way as with `--battery`. Can be repeated
+    exclude: Vec<String>,
+
+    #[structopt(short = "a", long = "adaptive")]
+    /// Poll batteries less often (up to 30 seconds) when nothing changes
+    /// and more often (each second) when power draw changes rapidly or on user input
+    adaptive: bool,
+
+    #[structopt(
+        short = "u",
+        long = "units",
+        default_value = "human",
+        raw(possible_values = "&Units::arg_variants()", case_insensitive = "true")
+    )]
+    /// Measurement units displayed
+    units: Units,
+
+    #[structopt(long = "locale")]
+    /// Language of the interface, ex. `de`; taken from the `LC_ALL`, `LC_MESSAGES` or `LANG`
+    /// environment variables by default
+    locale: Option<String>,
+
+    #[structopt(long = "no-history")]
+    /// Do not persist battery health history between runs
+    no_history: bool,
+
+    #[structopt(long = "retention", default_value = "24h", parse(try_from_str = "parse_retention"))]
+    /// For how long the samples are kept in memory, ex. `12h` or `2days`
+    retention: Duration,
+
+    #[structopt(long = "max-samples", default_value = "86400", parse(try_from_str = "parse_samples"))]
+    /// Most samples kept in memory per device; older half of them is merged into the coarser ones
+    /// once there are more
+    max_samples: usize,
+
+    #[structopt(long = "price", parse(try_from_str = "parse_price"))]
+    /// Electricity price per kW·h, used to estimate the charging cost
+    price: Option<f64>,
+
+    #[structopt(long = "currency", default_value = "")]
+    /// Currency symbol displayed next to the charging cost
+    currency: String,
+
+    #[structopt(long = "bands", default_value = "5,15")]
+    /// Power draw cut-offs in watts between the idle, light and heavy load bands
+    bands: PowerBands,
+
+    #[structopt(long = "processes")]
+    /// Attribute power draw to the processes based on their CPU time (Linux only).
+    /// RAPL package energy is used when readable, battery discharge power otherwise
+    processes: bool,
+
+    #[structopt(long = "system-temperature")]
+    /// Show the system temperature from ACPI thermal zones and hwmon sensors
+    /// for batteries which are not reporting their own (Linux only)
+    system_temperature: bool,
+
+    #[structopt(long = "brightness")]
+    /// Overlay the display backlight level on the energy rate chart, % of the chart height
+    brightness: bool,
+
+    #[structopt(
+        long = "cpu",
+        raw(possible_values = "&CpuMetric::arg_variants()", case_insensitive = "true")
+    )]
+    /// Overlay the CPU load or the average CPU frequency on the energy rate chart,
+    /// % of the chart height (Linux only)
+    cpu: Option<CpuMetric>,
+
+    #[structopt(long = "alert-command")]
+    /// Shell command executed on the battery anomalies, like an abnormal voltage sag.
+    /// Event details are passed in the `BATTOP_*` environment variables
+    alert_command: Option<String>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(StructOpt, Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Command {
+    #[structopt(name = "report")]
+    /// Print health trajectories and typical discharge rates of all batteries battop has history for
+    Report,
+    #[structopt(name = "daemon")]
+    /// Poll the devices and keep their history for any number of battop instances
+    /// attached with `--backend daemon` (Unix only)
+    Daemon,
+}
+
+/// Command line arguments layered over the configuration file
+#[derive(Debug)]
+pub struct Config {
+    /// Raw command line, kept to merge it again with the reloaded file
+    argv: Vec<OsString>,
+    args: Arguments,
+    /// Configuration file actually loaded, if any
+    path: Option<PathBuf>,
+    /// Global settings and the ones for the specific devices
+    settings: DeviceSettings,
+    devices: Vec<Override>,
+    order: Order,
+    /// Serial numbers and the names given to them
+    nicknames: Vec<(String, String)>,
+    #[cfg(feature = "ui")]
+    keys: KeyBindings,
+    locale: Locale,
+    #[cfg(feature = "ui")]
+    scripts: Scripts,
+}
+
+/// Charts shown on the overview page besides the energy rate one
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Charts {
+    pub voltage: bool,
+    pub energy_rate: bool,
+    pub temperature: bool,
+}
+
+impl Default for Charts {
+    fn default() -> Charts {
+        Charts {
+            voltage: true,
+            energy_rate: true,
+            temperature: true,
+        }
+    }
+}
+
+impl Config {
+    /// Parse the command line arguments and merge them with the configuration file.
+    ///
+    /// Exits the process on the invalid arguments or on `--help`, same as the `structopt` does.
+    pub fn load() -> Result<Config> {
+        Config::load_from(env::args_os())
+    }
+
+    pub fn load_from<I, T>(args: I) -> Result<Config>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let argv = args.into_iter().map(Into::into).collect::<Vec<OsString>>();
+        let matches = Arguments::clap().get_matches_from(argv.clone());
+        let mut args = Arguments::from_clap(&matches);
+
+        // Explicitly requested file must exist, the default one is optional
+        let path = match args.config {
+            Some(ref path) => Some(path.clone()),
+            None => file::default_path().filter(|path| path.is_file()),
+        };
+        let mut file = match path {
+            Some(path) => {
+                let file = ConfigFile::open(&path)?;
+                file.check_keys(&schema::config())?;
+                file
+            }
+            None if args.profile.is_some() => {
+                return Err(Error::Config("profiles require the configuration file".to_string()));
+            }
+            None => {
+                return Ok(Config {
+                    locale: locale(args.locale.as_deref())?,
+                    #[cfg(feature = "ui")]
+                    scripts: scripts(file::default_path().as_deref())?,
+                    argv,
+                    path: None,
+                    settings: DeviceSettings {
+                        title: None,
+                        #[cfg(feature = "ui")]
+                        theme: Theme::default(),
+                        charts: Charts::default(),
+                        bands: args.bands,
+                    },
+                    args,
+                    devices: Vec::new(),
+                    order: Order::default(),
+                    nicknames: Vec::new(),
+                    #[cfg(feature = "ui")]
+                    keys: KeyBindings::new(),
+                })
+            }
+        };
+        // Profile given on the command line takes precedence over the default one from the file
+        let profile = match args.profile {
+            Some(ref profile) => Some(profile.clone()),
+            None => file.string("profile")?,
+        };
+        if let Some(profile) = profile {
+            debug!("Using configuration profile `{}`", profile);
+            file.select_profile(&profile)?;
+        }
+        merge(&mut args, &file, &matches)?;
+        let settings = DeviceSettings {
+            title: None,
+            #[cfg(feature = "ui")]
+            theme: theme(&file, "", &Theme::default())?,
+            charts: charts(&file, "", &Charts::default())?,
+            bands: args.bands,
+        };
+
+        Ok(Config {
+            locale: locale(args.locale.as_deref())?,
+            #[cfg(feature = "ui")]
+            scripts: scripts(Some(file.path()))?,
+            argv,
+            args,
+            devices: devices::overrides(&file, &settings)?,
+            order: devices::order(&file)?,
+            nicknames: devices::nicknames(&file)?,
+            settings,
+            #[cfg(feature = "ui")]
+            keys: key_bindings(&file)?,
+            path: Some(file.path().to_path_buf()),
+        })
+    }
+
+    /// Read the configuration file again, command line arguments still take precedence over it
+    pub fn reload(&self) -> Result<Config> {
+        Config::load_from(self.argv.clone())
+    }
+
+    /// Options changed in the `other` configuration which are applied only on the start
+    pub fn restart_required(&self, other: &Config) -> Vec<&'static str> {
+        let mut options = Vec::new();
+        if self.backend() != other.backend() {
+            options.push("backend");
+        }
+        if self.nut_server() != other.nut_server() {
+            options.push("nut_server");
+        }
+        if self.socket() != other.socket() {
+            options.push("socket");
+        }
+        if self.remotes() != other.remotes() {
+            options.push("remotes");
+        }
+        if self.bluetooth() != other.bluetooth() {
+            options.push("bluetooth");
+        }
+        if self.adb() != other.adb() {
+            options.push("adb");
+        }
+        if self.hid() != other.hid() {
+            options.push("hid");
+        }
+        if self.smbus() != other.smbus() {
+            options.push("smbus");
+        }
+        if self.vedirect() != other.vedirect() {
+            options.push("vedirect");
+        }
+        if self.args.batteries != other.args.batteries {
+            options.push("batteries");
+        }
+        if self.args.exclude != other.args.exclude {
+            options.push("exclude");
+        }
+        if self.order != other.order {
+            options.push("order");
+        }
+        if self.history_enabled() != other.history_enabled() {
+            options.push("history");
+        }
+        if self.retention() != other.retention() {
+            options.push("retention");
+        }
+        if self.processes() != other.processes() {
+            options.push("processes");
+        }
+        if self.cpu() != other.cpu() {
+            options.push("cpu");
+        }
+        #[cfg(feature = "ui")]
+        {
+            if self.keys != other.keys {
+                options.push("keys");
+            }
+        }
+
+        options
+    }
+
+    /// Configuration file in use
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Settings for the device, with its `[device.*]` section applied if there is a matching one
+    pub fn settings(&self, device: &Device) -> &DeviceSettings {
+        self.devices
+            .iter()
+            .find(|section| section.matches(device))
+            .map(Override::settings)
+            .unwrap_or(&self.settings)
+    }
+
+    /// Order of the device tabs
+    pub fn order(&self) -> &Order {
+        &self.order
+    }
+
+    /// Name given to the device in the `[nicknames]` section
+    pub fn nickname(&self, device: &Device) -> Option<&str> {
+        let serial = device.serial_number()?;
+        self.nicknames
+            .iter()
+            .find(|(known, _)| known == serial)
+            .map(|(_, nickname)| nickname.as_str())
+    }
+
+    #[cfg(feature = "ui")]
+    pub fn key_bindings(&self) -> &[(Key, Event)] {
+        &self.keys
+    }
+
+    pub fn verbosity(&self) -> usize {
+        self.args.verbose
+    }
+
+    pub fn trace_file(&self) -> Option<&Path> {
+        self.args.trace_file.as_deref()
+    }
+
+    pub fn delay(&self) -> &Duration {
+        &self.args.delay
+    }
+
+    pub fn backend(&self) -> Backend {
+        if self.args.demo {
+            Backend::Demo
+        } else {
+            self.args.backend
+        }
+    }
+
+    /// Devices to collect, for the `Provider`
+    pub fn sources(&self) -> device::Options {
+        device::Options {
+            backend: self.backend(),
+            nut_server: self.args.nut_server.clone(),
+            remotes: self.args.remotes.clone(),
+            bluetooth: self.args.bluetooth,
+            adb: self.args.adb,
+            hid: self.args.hid,
+            smbus: self.args.smbus.clone(),
+            vedirect: self.args.vedirect.clone(),
+            selection: device::Selection {
+                include: self.args.batteries.clone(),
+                exclude: self.args.exclude.clone(),
+            },
+            order: self.order.clone(),
+            socket: self.args.socket.clone(),
+            system: None,
+        }
+    }
+
+    pub fn nut_server(&self) -> &str {
+        &self.args.nut_server
+    }
+
+    /// Socket of the `battop daemon`, if it is not the default one
+    pub fn socket(&self) -> Option<&Path> {
+        self.args.socket.as_deref()
+    }
+
+    /// Address the `battop daemon` serves the Prometheus metrics on
+    #[cfg(all(unix, feature = "daemon"))]
+    pub fn prometheus(&self) -> Option<&str> {
+        self.args.prometheus.as_deref()
+    }
+
+    pub fn remotes(&self) -> &[String] {
+        &self.args.remotes
+    }
+
+    pub fn bluetooth(&self) -> bool {
+        self.args.bluetooth
+    }
+
+    pub fn adb(&self) -> bool {
+        self.args.adb
+    }
+
+    pub fn hid(&self) -> bool {
+        self.args.hid
+    }
+
+    pub fn smbus(&self) -> Option<&str> {
+        self.args.smbus.as_deref()
+    }
+
+    pub fn vedirect(&self) -> &[String] {
+        &self.args.vedirect
+    }
+
+    pub fn adaptive(&self) -> bool {
+        self.args.adaptive
+    }
+
+    pub fn units(&self) -> Units {
+        self.args.units
+    }
+
+    /// Translated user-visible strings
+    pub fn locale(&self) -> &Locale {
+        &self.locale
+    }
+
+    /// Derived series and custom panels
+    #[cfg(feature = "ui")]
+    pub fn scripts(&self) -> &Scripts {
+        &self.scripts
+    }
+
+    pub fn history_enabled(&self) -> bool {
+        // Simulated battery should not pollute the real history
+        !self.args.no_history && self.backend() != Backend::Demo
+    }
+
+    /// Samples kept in memory per device
+    pub fn retention(&self) -> Retention {
+        Retention {
+            duration: self.args.retention,
+            samples: self.args.max_samples,
+        }
+    }
+
+    pub fn price(&self) -> Option<f64> {
+        self.args.price
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.args.currency
+    }
+
+    pub fn processes(&self) -> bool {
+        self.args.processes
+    }
+
+    /// System readings collected along with the devices shown
+    pub fn system(&self) -> system::Options {
+        system::Options {
+            cpu: self.cpu(),
+            brightness: self.args.brightness,
+            temperature: self.args.system_temperature,
+        }
+    }
+
+    pub fn cpu(&self) -> Option<CpuMetric> {
+        self.args.cpu
+    }
+
+    pub fn alert_command(&self) -> Option<&str> {
+        self.args.alert_command.as_deref()
+    }
+
+    pub fn command(&self) -> Option<Command> {
+        self.args.command
+    }
+}
+
+/// Fill the arguments not given on the command line from the configuration file
+fn merge(args: &mut Arguments, file: &ConfigFile, matches: &ArgMatches) -> Result<()> {
+    let missing = |name: &str| matches.occurrences_of(name) == 0;
+
+    if missing("delay") {
+        if let Some(interval) = file.integer("interval")? {
+            if interval <= 0 {
+                return Err(file.invalid_key("interval", "a positive number of seconds"));
+            }
+            args.delay = Duration::from_secs(interval as u64);
+        }
+    }
+    if missing("backend") {
+        if let Some(backend) = file.choice("backend", &Backend::arg_variants())? {
+            args.backend = backend;
+        }
+    }
+    if missing("demo") {
+        args.demo = file.boolean("demo")?.unwrap_or(args.demo);
+    }
+    if missing("nut_server") {
+        args.nut_server = file.string("nut_server")?.unwrap_or_else(|| args.nut_server.clone());
+    }
+    if missing("socket") {
+        args.socket = file
+            .string("socket")?
+            .map(PathBuf::from)
+            .or_else(|| args.socket.clone());
+    }
+    if missing("prometheus") {
+        args.prometheus = file
+            .string("exporters.prometheus.listen")?
+            .or_else(|| args.prometheus.clone());
+    }
+    if missing("remotes") {
+        args.remotes = file.strings("remotes")?.unwrap_or_else(|| args.remotes.clone());
+    }
+    if missing("bluetooth") {
+        args.bluetooth = file.boolean("bluetooth")?.unwrap_or(args.bluetooth);
+    }
+    if missing("adb") {
+        args.adb = file.boolean("adb")?.unwrap_or(args.adb);
+    }
+    if missing("hid") {
+        args.hid = file.boolean("hid")?.unwrap_or(args.hid);
+    }
+    if missing("smbus") {
+        args.smbus = file.string("smbus")?.or_else(|| args.smbus.clone());
+    }
+    if missing("vedirect") {
+        args.vedirect = file.strings("vedirect")?.unwrap_or_else(|| args.vedirect.clone());
+    }
+    if missing("batteries") {
+        args.batteries = file.strings("batteries")?.unwrap_or_else(|| args.batteries.clone());
+    }
+    if missing("exclude") {
+        args.exclude = file.strings("exclude")?.unwrap_or_else(|| args.exclude.clone());
+    }
+    if missing("adaptive") {
+        args.adaptive = file.boolean("adaptive")?.unwrap_or(args.adaptive);
+    }
+    if missing("units") {
+        if let Some(units) = file.choice("units", &Units::arg_variants())? {
+            args.units = units;
+        }
+    }
+    if missing("locale") {
+        if let Some(locale) = file.string("locale")? {
+            if Locale::new(&locale).is_none() {
+                return Err(file.invalid_key("locale", &format!("one of {}", i18n::languages().join(", "))));
+            }
+            args.locale = Some(locale);
+        }
+    }
+    if missing("no_history") {
+        if let Some(history) = file.boolean("history")? {
+            args.no_history = !history;
+        }
+    }
+    if missing("retention") {
+        if let Some(retention) = file.string("retention")? {
+            args.retention = parse_retention(&retention)
+                .map_err(|_| file.invalid_key("retention", "a positive duration, ex. \"12h\" or \"2days\""))?;
+        }
+    }
+    if missing("max_samples") {
+        if let Some(count) = file.integer("max_samples")? {
+            if count < Retention::MIN_SAMPLES as i64 {
+                return Err(file.invalid_key(
+                    "max_samples",
+                    &format!("a number of samples, at least {}", Retention::MIN_SAMPLES),
+                ));
+            }
+            args.max_samples = count as usize;
+        }
+    }
+    if missing("price") {
+        if let Some(price) = file.float("price")? {
+            if price < 0.0 {
+                return Err(file.invalid_key("price", "a non-negative number"));
+            }
+            args.price = Some(price);
+        }
+    }
+    if missing("currency") {
+        args.currency = file.string("currency")?.unwrap_or_else(|| args.currency.clone());
+    }
+    if missing("bands") {
+        if let Some(bands) = file.parse("bands", BANDS)? {
+            args.bands = bands;
+        }
+    }
+    if missing("processes") {
+        args.processes = file.boolean("processes")?.unwrap_or(args.processes);
+    }
+    if missing("system_temperature") {
+        args.system_temperature = file.boolean("system_temperature")?.unwrap_or(args.system_temperature);
+    }
+    if missing("brightness") {
+        args.brightness = file.boolean("brightness")?.unwrap_or(args.brightness);
+    }
+    if missing("cpu") {
+        if let Some(cpu) = file.choice("cpu", &CpuMetric::arg_variants())? {
+            args.cpu = Some(cpu);
+        }
+    }
+    if missing("alert_command") {
+        args.alert_command = file.string("alerts.command")?.or_else(|| args.alert_command.clone());
+    }
+
+    Ok(())
+}
+
+/// Locale given explicitly must have the catalog, the one of the environment falls back to English
+fn locale(name: Option<&str>) -> Result<Locale> {
+    match name {
+        Some(name) => Locale::new(name).ok_or_else(|| {
+            Error::Config(format!(
+                "no translations for the `{}` locale, available are {}",
+                name,
+                i18n::languages().join(", ")
+            ))
+        }),
+        None => Ok(Locale::from_env()),
+    }
+}
+
+/// Scripts from the `scripts` directory next to the configuration file
+#[cfg(feature = "ui")]
+fn scripts(config: Option<&Path>) -> Result<Scripts> {
+    match config.and_then(Path::parent) {
+        Some(dir) => Scripts::load(&dir.join("scripts")),
+        None => Ok(Scripts::default()),
+    }
+}
+
+/// `[theme]` section, colors are given by their names or as `#rrggbb`.
+///
+/// `prefix` is the enclosing section, if any, ex. `device.ups.`
+#[cfg(feature = "ui")]
+fn theme(file: &ConfigFile, prefix: &str, base: &Theme) -> Result<Theme> {
+    let color = |key: &str| -> Result<Option<tui::style::Color>> {
+        let key = format!("{}{}", prefix, key);
+        let key = key.as_str();
+        match file.string(key)? {
+            Some(name) => parse_color(&name)
+                .map(Some)
+                .ok_or_else(|| file.invalid_key(key, "a color name or `#rrggbb`")),
+            None => Ok(None),
+        }
+    };
+    let colors = |key: &str| -> Result<Option<Vec<tui::style::Color>>> {
+        let key = format!("{}{}", prefix, key);
+        let key = key.as_str();
+        match file.strings(key)? {
+            Some(ref names) if names.is_empty() => Err(file.invalid_key(key, "a non-empty array of colors")),
+            Some(names) => names
+                .iter()
+                .map(|name| {
+                    parse_color(name).ok_or_else(|| file.invalid_key(key, "an array of color names or `#rrggbb`"))
+                })
+                .collect::<Result<Vec<_>>>()
+                .map(Some),
+            None => Ok(None),
+        }
+    };
+
+    let mut theme = base.clone();
+    if let Some(main) = color("theme.main")? {
+        theme.main = main;
+    }
+    if let Some(overlays) = colors("theme.overlays")? {
+        theme.overlays = overlays;
+    }
+    if let Some(secondary) = colors("theme.secondary")? {
+        theme.secondary = secondary;
+    }
+    if let Some(annotations) = color("theme.annotations")? {
+        theme.annotations = annotations;
+    }
+    if let Some(markers) = color("theme.markers")? {
+        theme.markers = markers;
+    }
+
+    Ok(theme)
+}
+
+/// `[charts]` section, overview charts might be hidden to give more room to the rest
+fn charts(file: &ConfigFile, prefix: &str, base: &Charts) -> Result<Charts> {
+    let chart = |key: &str, default: bool| -> Result<bool> {
+        Ok(file.boolean(&format!("{}charts.{}", prefix, key))?.unwrap_or(default))
+    };
+
+    Ok(Charts {
+        voltage: chart("voltage", base.voltage)?,
+        energy_rate: chart("energy_rate", base.energy_rate)?,
+        temperature: chart("temperature", base.temperature)?,
+    })
+}
+
+/// `[keys]` section, `action = "key"` pairs
+#[cfg(feature = "ui")]
+fn key_bindings(file: &ConfigFile) -> Result<KeyBindings> {
+    let mut bindings = KeyBindings::new();
+    for action in file.table("keys") {
+        let setting = format!("keys.{}", action);
+        let event = Event::from_action(action).ok_or_else(|| file.invalid_key(&setting, "bound to a known action"))?;
+        if let Some(name) = file.string(&setting)? {
+            let key = parse_key(&name).ok_or_else(|| file.invalid_key(&setting, "a key name, ex. `q` or `Ctrl+n`"))?;
+            bindings.push((key, event));
+        }
+    }
+
+    Ok(bindings)
+}
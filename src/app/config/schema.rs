@@ -0,0 +1,254 @@
+//! Keys the configuration file and the scripts may have.
+//!
+//! Keys are written with the dots between the parts, as they are prefixed with the table names,
+//! `*` part stands for any name, ex. `device.*.title`. Keys matching none of them are reported
+//! along with the closest known one, so the typos are not silently ignored.
+
+/// Keys of the `[theme]` section
+const THEME: [&str; 5] = ["main", "overlays", "secondary", "annotations", "markers"];
+/// Keys of the `[charts]` section
+const CHARTS: [&str; 3] = ["voltage", "energy_rate", "temperature"];
+
+/// Settings of the top level and of the `[profile.<name>]` sections
+fn settings() -> Vec<String> {
+    let mut keys = [
+        "interval",
+        "backend",
+        "demo",
+        "nut_server",
+        "socket",
+        "exporters.prometheus.listen",
+        "remotes",
+        "bluetooth",
+        "adb",
+        "hid",
+        "smbus",
+        "vedirect",
+        "batteries",
+        "exclude",
+        "order",
+        "nicknames.*",
+        "adaptive",
+        "units",
+        "locale",
+        "history",
+        "retention",
+        "max_samples",
+        "price",
+        "currency",
+        "bands",
+        "processes",
+        "system_temperature",
+        "brightness",
+        "cpu",
+        "alerts.command",
+        "device.*.match",
+        "device.*.title",
+        "device.*.bands",
+    ]
+    .iter()
+    .map(|key| key.to_string())
+    .collect::<Vec<_>>();
+    for prefix in ["", "device.*."].iter() {
+        keys.extend(CHARTS.iter().map(|key| format!("{}charts.{}", prefix, key)));
+        keys.extend(THEME.iter().map(|key| format!("{}theme.{}", prefix, key)));
+    }
+    keys.extend(actions().map(|action| format!("keys.{}", action)));
+
+    keys
+}
+
+/// Names of the `[keys]` section actions
+#[cfg(feature = "ui")]
+fn actions() -> impl Iterator<Item = &'static str> {
+    crate::app::events::actions()
+}
+
+/// Key bindings are not used without the interface, but the file is shared with the full builds
+#[cfg(not(feature = "ui"))]
+fn actions() -> impl Iterator<Item = &'static str> {
+    Some("*").into_iter()
+}
+
+/// Keys of the configuration file
+pub fn config() -> Vec<String> {
+    let settings = settings();
+    let mut keys = vec!["profile".to_string()];
+    keys.extend(settings.iter().map(|key| format!("profile.*.{}", key)));
+    keys.extend(settings);
+
+    keys
+}
+
+/// Keys of the script files
+#[cfg(feature = "ui")]
+pub fn scripts() -> Vec<String> {
+    [
+        "series.*.expr",
+        "series.*.label",
+        "series.*.chart",
+        "panel.*.text",
+        "panel.*.title",
+    ]
+    .iter()
+    .map(|key| key.to_string())
+    .collect()
+}
+
+/// `key` of the file is the `known` one
+pub fn matches(known: &str, key: &str) -> bool {
+    let (mut known, mut key) = (known.split('.'), key.split('.'));
+    loop {
+        match (known.next(), key.next()) {
+            (Some("*"), Some(_)) => continue,
+            (Some(expected), Some(part)) if expected == part => continue,
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Known key the `key` was most likely meant to be, with the `*` parts filled from the `key` itself
+pub fn suggest(key: &str, known: &[String]) -> Option<String> {
+    let parts = key.split('.').collect::<Vec<_>>();
+    let candidates = known
+        .iter()
+        .filter_map(|known| {
+            if !known.contains('*') {
+                return Some(known.clone());
+            }
+            let pattern = known.split('.').collect::<Vec<_>>();
+            if pattern.len() != parts.len() {
+                return None;
+            }
+            let filled = pattern
+                .iter()
+                .zip(parts.iter())
+                .map(|(expected, part)| if *expected == "*" { *part } else { *expected })
+                .collect::<Vec<_>>();
+            Some(filled.join("."))
+        })
+        .collect::<Vec<_>>();
+
+    // Each part is compared on its own, so the long table names are not hiding the typos in the short keys
+    let closest = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let known = candidate.split('.').collect::<Vec<_>>();
+            if known.len() != parts.len() {
+                return None;
+            }
+            let mut total = 0;
+            for (part, known) in parts.iter().zip(known) {
+                let distance = distance(&part.to_lowercase(), &known.to_lowercase());
+                if distance > limit(part) {
+                    return None;
+                }
+                total += distance;
+            }
+            Some((total, candidate))
+        })
+        .min_by_key(|(total, _)| *total);
+    if let Some((_, closest)) = closest {
+        return Some(closest.clone());
+    }
+
+    // Right key in the wrong table, ex. `interval` written after the `[charts]` header
+    let last = parts[parts.len() - 1];
+    candidates
+        .into_iter()
+        .find(|candidate| candidate.rsplit('.').next() == Some(last))
+}
+
+/// Most edits a typo of the `word` might have
+fn limit(word: &str) -> usize {
+    (word.chars().count() / 3).max(1)
+}
+
+/// The closest of the `candidates` to the `word`, if it is close enough to be a typo of it
+pub fn similar<'a, I>(word: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    candidates
+        .into_iter()
+        .map(|candidate| (distance(&word.to_lowercase(), &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= limit(word))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Amount of the inserted, removed, replaced and swapped neighbour characters to turn `a` into `b`
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+    // Rows of the distances between the prefixes of `a` and all the prefixes of `b`
+    let mut before = Vec::new();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for i in 0..a.len() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for j in 0..b.len() {
+            let replaced = previous[j] + if a[i] == b[j] { 0 } else { 1 };
+            current[j + 1] = replaced.min(previous[j + 1] + 1).min(current[j] + 1);
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                current[j + 1] = current[j + 1].min(before[j - 1] + 1);
+            }
+        }
+        before = previous;
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{config, distance, matches, similar, suggest};
+
+    #[test]
+    fn known_keys() {
+        let known = config();
+        for key in [
+            "interval",
+            "profile.bench.charts.voltage",
+            "device.ups.theme.main",
+            "keys.exit",
+        ]
+        .iter()
+        {
+            assert!(known.iter().any(|known| matches(known, key)), "{} is unknown", key);
+        }
+        for key in ["intervall", "device.ups", "profile.bench.profile", "keys.exit.now"].iter() {
+            assert!(!known.iter().any(|known| matches(known, key)), "{} is known", key);
+        }
+    }
+
+    #[test]
+    fn suggestions() {
+        let known = config();
+        assert_eq!(suggest("intervall", &known), Some("interval".to_string()));
+        assert_eq!(
+            suggest("device.ups.titel", &known),
+            Some("device.ups.title".to_string())
+        );
+        assert_eq!(
+            suggest("profile.bench.unit", &known),
+            Some("profile.bench.units".to_string())
+        );
+        assert_eq!(suggest("charts.interval", &known), Some("interval".to_string()));
+        assert_eq!(
+            suggest("device.ups.charts.profile", &known),
+            Some("profile".to_string())
+        );
+        assert_eq!(suggest("chart.voltage", &known), Some("charts.voltage".to_string()));
+        assert_eq!(suggest("colors", &known), None);
+    }
+
+    #[test]
+    fn similar_words() {
+        assert_eq!(distance("upowr", "upower"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("laod", "load"), 1);
+        assert_eq!(similar("UPowr", vec!["sysfs", "upower"]), Some("upower"));
+        assert_eq!(similar("metric", vec!["human", "si"]), None);
+    }
+}
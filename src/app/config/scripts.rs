@@ -0,0 +1,175 @@
+//! User scripts, `*.toml` files in the `scripts` directory next to the configuration file,
+//! read in the file name order.
+//!
+//! `[series.<name>]` sections define the derived series: the `expr` expression is evaluated
+//! at each sample, recorded as the `script.<name>` channel and drawn over the `chart` one of
+//! the overview charts. `[panel.<name>]` sections define the text panels of the scripts page,
+//! where each `{expr}` placeholder of the `text` is replaced with the value of the Rhai script in it.
+//! See `battop_core::script` for what the scripts can refer.
+
+use std::fs;
+use std::path::Path;
+
+use battop_core::script::Expr;
+
+use super::file::ConfigFile;
+use super::schema;
+use crate::app::ui::ChartType;
+use crate::Result;
+
+/// Derived series drawn over one of the overview charts
+#[derive(Debug)]
+pub struct Series {
+    /// Channel the values are recorded to, `script.<name>`
+    pub channel: String,
+    pub label: String,
+    pub chart: ChartType,
+    pub expr: Expr,
+}
+
+#[derive(Debug)]
+pub enum Part {
+    Text(String),
+    Expr(Expr),
+}
+
+/// Text panel with the expression values substituted
+#[derive(Debug)]
+pub struct Panel {
+    pub title: String,
+    pub parts: Vec<Part>,
+}
+
+#[derive(Debug, Default)]
+pub struct Scripts {
+    pub series: Vec<Series>,
+    pub panels: Vec<Panel>,
+}
+
+impl Scripts {
+    /// Scripts from the `dir`, none if it does not exist
+    pub fn load(dir: &Path) -> Result<Scripts> {
+        let mut scripts = Scripts::default();
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(scripts),
+        };
+        let mut paths = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "toml"))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        for path in paths {
+            let file = ConfigFile::open(&path)?;
+            file.check_keys(&schema::scripts())?;
+            for name in names(&file, "series") {
+                scripts.series.push(series(&file, name)?);
+            }
+            for name in names(&file, "panel") {
+                scripts.panels.push(panel(&file, name)?);
+            }
+            debug!("Loaded scripts from {}", path.display());
+        }
+
+        Ok(scripts)
+    }
+}
+
+/// Section names of the `[<table>.<name>]` sections, in the order they are written
+fn names<'a>(file: &'a ConfigFile, table: &str) -> Vec<&'a str> {
+    let mut names = Vec::new();
+    for key in file.table(table) {
+        let name = key.split('.').next().unwrap_or(key);
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+fn expression(file: &ConfigFile, key: &str, source: &str) -> Result<Expr> {
+    source
+        .parse()
+        .map_err(|e| file.invalid_key(key, &format!("a valid expression ({})", e)))
+}
+
+fn series(file: &ConfigFile, name: &str) -> Result<Series> {
+    let prefix = format!("series.{}.", name);
+    let key = format!("{}expr", prefix);
+    let expr = match file.string(&key)? {
+        Some(source) => expression(file, &key, &source)?,
+        None => return Err(file.invalid_key(&key, "set")),
+    };
+    let chart_key = format!("{}chart", prefix);
+    let chart = match file.string(&chart_key)?.as_deref() {
+        None | Some("energy_rate") => ChartType::EnergyRate,
+        Some("voltage") => ChartType::Voltage,
+        Some("temperature") => ChartType::Temperature,
+        Some("state_of_charge") => ChartType::StateOfCharge,
+        Some(_) => {
+            return Err(file.invalid_key(
+                &chart_key,
+                "one of `energy_rate`, `voltage`, `temperature` or `state_of_charge`",
+            ))
+        }
+    };
+
+    Ok(Series {
+        channel: format!("script.{}", name),
+        label: file
+            .string(&format!("{}label", prefix))?
+            .unwrap_or_else(|| name.to_string()),
+        chart,
+        expr,
+    })
+}
+
+/// Position of the brace closing the one `text` starts with, the blocks of the script are skipped
+fn closing(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (position, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 1 => return Some(position),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn panel(file: &ConfigFile, name: &str) -> Result<Panel> {
+    let prefix = format!("panel.{}.", name);
+    let key = format!("{}text", prefix);
+    let text = match file.string(&key)? {
+        Some(text) => text,
+        None => return Err(file.invalid_key(&key, "set")),
+    };
+
+    let mut parts = Vec::new();
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find('{') {
+        let end = match closing(&rest[start..]) {
+            Some(end) => start + end,
+            None => return Err(file.invalid_key(&key, "a text with the closed `{expr}` placeholders")),
+        };
+        if start > 0 {
+            parts.push(Part::Text(rest[..start].to_string()));
+        }
+        parts.push(Part::Expr(expression(file, &key, &rest[start + 1..end])?));
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest.to_string()));
+    }
+
+    Ok(Panel {
+        title: file
+            .string(&format!("{}title", prefix))?
+            .unwrap_or_else(|| name.to_string()),
+        parts,
+    })
+}
@@ -0,0 +1,276 @@
+//! TOML documents, the battop configuration file, the scripts and the message catalogs,
+//! parsed with the `toml` crate.
+//!
+//! Documents are flattened into the `key = value` entries, keys prefixed with the names
+//! of the tables they are in, so `[theme]` followed by `main = "green"` and `theme = { main = "green" }`
+//! are both the `theme.main` entry. Elements of the arrays of tables, `[[name]]` and `name = [{ … }]`,
+//! are the tables numbered from zero, ex. `name.0.key`. Entries are in the order they are written,
+//! each with the line of its key, so the unknown keys and invalid values can be pointed at.
+
+use std::fmt;
+
+use toml::de::{DeTable, DeValue};
+use toml::Spanned;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    /// Offset or local date-time, date or time, in RFC 3339 form
+    Datetime(String),
+    Array(Vec<Value>),
+}
+
+/// One `key = value` pair, key is prefixed with the table name, ex. `theme.main`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: String,
+    pub value: Value,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Parse the whole document into the entries, in the order they are written
+pub fn parse(text: &str) -> Result<Vec<Entry>, ParseError> {
+    let document = DeTable::parse(text).map_err(|e| ParseError {
+        line: line(text, e.span().map_or(0, |span| span.start)),
+        message: e.message().trim_end().to_string(),
+    })?;
+
+    // Tables are keyed by name, so the entries are put back into the document order
+    let mut entries = Vec::new();
+    flatten(text, "", document.into_inner(), &mut entries)?;
+    entries.sort_by_key(|(offset, _)| *offset);
+
+    Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+}
+
+/// Entries of the `table`, prefixed with its name, along with the offsets of their keys
+fn flatten(text: &str, prefix: &str, table: DeTable, entries: &mut Vec<(usize, Entry)>) -> Result<(), ParseError> {
+    for (key, value) in table {
+        let offset = key.span().start;
+        let key = format!("{}{}", prefix, key.get_ref());
+        match value.into_inner() {
+            DeValue::Table(table) => flatten(text, &format!("{}.", key), table, entries)?,
+            DeValue::Array(items) if items.iter().any(|item| item.get_ref().is_table()) => {
+                for (i, item) in items.into_iter().enumerate() {
+                    match item.into_inner() {
+                        DeValue::Table(table) => flatten(text, &format!("{}.{}.", key, i), table, entries)?,
+                        _ => {
+                            return Err(ParseError {
+                                line: line(text, offset),
+                                message: format!("`{}` should have either the tables or the values", key),
+                            })
+                        }
+                    }
+                }
+            }
+            value => {
+                let value = convert(text, &key, offset, value)?;
+                entries.push((
+                    offset,
+                    Entry {
+                        key,
+                        value,
+                        line: line(text, offset),
+                    },
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Value which is not a table, `key` and its `offset` are for the errors
+fn convert(text: &str, key: &str, offset: usize, value: DeValue) -> Result<Value, ParseError> {
+    let error = |message: &str| ParseError {
+        line: line(text, offset),
+        message: format!("`{}` {}", key, message),
+    };
+
+    Ok(match value {
+        DeValue::String(value) => Value::String(value.into_owned()),
+        DeValue::Integer(value) => Value::Integer(
+            i64::from_str_radix(value.as_str(), value.radix()).map_err(|_| error("is out of the integer range"))?,
+        ),
+        DeValue::Float(value) => Value::Float(value.as_str().parse().map_err(|_| error("is not a valid float"))?),
+        DeValue::Boolean(value) => Value::Boolean(value),
+        DeValue::Datetime(value) => Value::Datetime(value.to_string()),
+        DeValue::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item: Spanned<DeValue>| convert(text, key, offset, item.into_inner()))
+                .collect::<Result<_, _>>()?,
+        ),
+        DeValue::Table(_) => return Err(error("should have either the tables or the values")),
+    })
+}
+
+/// Line number of the `offset` in the `text`, from one
+fn line(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Entry, Value};
+
+    fn values(text: &str) -> Vec<(String, Value)> {
+        parse(text)
+            .expect("Valid document")
+            .into_iter()
+            .map(
+                |Entry {
+                     key,
+                     value,
+                     ..
+                 }| (key, value),
+            )
+            .collect()
+    }
+
+    fn string(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+
+    #[test]
+    fn inline_tables_are_flattened() {
+        let text = "theme = { main = \"green\", overlays = [\"cyan\"] }\n\
+                    [device.ups]\n\
+                    charts = { voltage = false, energy_rate.visible = true }\n";
+
+        assert_eq!(
+            values(text),
+            vec![
+                ("theme.main".to_string(), string("green")),
+                ("theme.overlays".to_string(), Value::Array(vec![string("cyan")])),
+                ("device.ups.charts.voltage".to_string(), Value::Boolean(false)),
+                (
+                    "device.ups.charts.energy_rate.visible".to_string(),
+                    Value::Boolean(true)
+                ),
+            ]
+        );
+        // TOML 1.1 inline tables may span several lines
+        assert_eq!(
+            values("theme = { main = \"green\",\n overlays = [], }"),
+            vec![
+                ("theme.main".to_string(), string("green")),
+                ("theme.overlays".to_string(), Value::Array(Vec::new())),
+            ]
+        );
+        assert!(parse("theme = { main = 1, main = 2 }").is_err());
+    }
+
+    #[test]
+    fn arrays_of_tables_are_numbered() {
+        let text = "[[exporters.prometheus]]\nlisten = \"127.0.0.1:9851\"\n\
+                    [exporters.prometheus.labels]\nhost = \"nas\"\n\
+                    [[exporters.prometheus]]\nlisten = \"[::1]:9851\"\n\
+                    [alerts]\nrules = [{ level = 10 }, { level = 5, command = \"poweroff\" }]\n";
+
+        assert_eq!(
+            values(text),
+            vec![
+                ("exporters.prometheus.0.listen".to_string(), string("127.0.0.1:9851")),
+                ("exporters.prometheus.0.labels.host".to_string(), string("nas")),
+                ("exporters.prometheus.1.listen".to_string(), string("[::1]:9851")),
+                ("alerts.rules.0.level".to_string(), Value::Integer(10)),
+                ("alerts.rules.1.level".to_string(), Value::Integer(5)),
+                ("alerts.rules.1.command".to_string(), string("poweroff")),
+            ]
+        );
+        assert!(parse("[[alerts]]\n[alerts]\n").is_err());
+        assert!(parse("rules = [1, { level = 5 }]").is_err());
+    }
+
+    #[test]
+    fn multiline_strings_are_parsed() {
+        let text = "basic = \"\"\"\nOne\\tline\nand \"two\"\"\"\"\n\
+                    trimmed = \"\"\"\\\n    joined \\\n    words\"\"\"\n\
+                    literal = '''C:\\battop\n'''\n\
+                    after = 1\n";
+
+        assert_eq!(
+            values(text),
+            vec![
+                ("basic".to_string(), string("One\tline\nand \"two\"")),
+                ("trimmed".to_string(), string("joined words")),
+                ("literal".to_string(), string("C:\\battop\n")),
+                ("after".to_string(), Value::Integer(1)),
+            ]
+        );
+        let entries = parse(text).expect("Valid document");
+        assert_eq!(entries[3].line, 9);
+        assert!(parse("basic = \"\"\"never closed\n").is_err());
+    }
+
+    #[test]
+    fn dates_are_parsed() {
+        let text = "a = 1979-05-27T07:32:00Z\nb = 1979-05-27 00:32:00.999-07:00\nc = 1979-05-27\nd = 07:32:00\n";
+
+        assert_eq!(
+            values(text),
+            vec![
+                ("a".to_string(), Value::Datetime("1979-05-27T07:32:00Z".to_string())),
+                (
+                    "b".to_string(),
+                    Value::Datetime("1979-05-27T00:32:00.999-07:00".to_string())
+                ),
+                ("c".to_string(), Value::Datetime("1979-05-27".to_string())),
+                ("d".to_string(), Value::Datetime("07:32:00".to_string())),
+            ]
+        );
+        assert!(parse("a = 1979-05-27T25:32:00").is_err());
+        assert!(parse("a = 07:32:00Z").is_err());
+        assert_eq!(values("a = -5"), vec![("a".to_string(), Value::Integer(-5))]);
+    }
+
+    #[test]
+    fn numbers_follow_the_specification() {
+        assert_eq!(
+            values("a = 0xff\nb = 0o17\nc = 0b101\nd = 1_000"),
+            vec![
+                ("a".to_string(), Value::Integer(255)),
+                ("b".to_string(), Value::Integer(15)),
+                ("c".to_string(), Value::Integer(5)),
+                ("d".to_string(), Value::Integer(1000)),
+            ]
+        );
+        assert_eq!(values("a = inf"), vec![("a".to_string(), Value::Float(f64::INFINITY))]);
+        assert_eq!(
+            values("a = -inf"),
+            vec![("a".to_string(), Value::Float(f64::NEG_INFINITY))]
+        );
+        match &values("a = nan")[0].1 {
+            Value::Float(value) => assert!(value.is_nan()),
+            value => panic!("{:?} is not a float", value),
+        }
+        assert!(parse("a = 012").is_err());
+        assert!(parse("a = 9223372036854775808").is_err());
+    }
+
+    #[test]
+    fn invalid_documents_are_rejected() {
+        assert!(parse("a = \"\\u+123\"").is_err());
+        assert!(parse("[a]\nb = 1\n[a]\nc = 2\n").is_err());
+        assert!(parse("a.b = 1\n[a]\nb = 2\n").is_err());
+
+        let error = parse("a = 1\n\nb = \"never closed\n").unwrap_err();
+        assert_eq!(error.line, 3);
+    }
+}
@@ -0,0 +1,260 @@
+//! `battop daemon` command: the only battop instance polling the devices and keeping their history,
+//! shared over a Unix socket with the instances attached with `--backend daemon`.
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use battop_core::device::{default_socket, Backend, Collector, Device, Server, Update};
+use battop_core::history::History;
+#[cfg(feature = "prometheus")]
+use battop_core::prometheus::Exporter;
+use battop_core::signals::{self, Signal};
+#[cfg(target_os = "linux")]
+use battop_core::uevents::{Uevent, Uevents};
+
+use crate::app::Config;
+use crate::{Error, Result};
+
+/// How often devices are enumerated again to find the ones connected after the start,
+/// or less often if the backend asks for it
+const RESCAN_INTERVAL: Duration = Duration::from_secs(10);
+/// Device refresh taking longer than that is considered to be hung
+const REFRESH_TIMEOUT: Duration = Duration::from_secs(10);
+
+enum Event {
+    Collected,
+    /// Power supply was added or removed
+    Hotplug,
+    Signal(Signal),
+}
+
+/// Device collected by the daemon along with its history
+struct Tracked {
+    device: Device,
+    history: History,
+}
+
+/// Socket of the daemon and the exporters, all getting the same devices
+struct Outputs {
+    server: Server,
+    #[cfg(feature = "prometheus")]
+    prometheus: Option<Exporter>,
+}
+
+impl Outputs {
+    fn bind(config: &Config) -> Result<Outputs> {
+        #[cfg(not(feature = "prometheus"))]
+        {
+            if config.prometheus().is_some() {
+                return Err(Error::Disabled("prometheus".to_string()));
+            }
+        }
+        let path = config
+            .socket()
+            .map(|path| path.to_path_buf())
+            .unwrap_or_else(default_socket);
+
+        Ok(Outputs {
+            server: Server::bind(&path)?,
+            #[cfg(feature = "prometheus")]
+            prometheus: match config.prometheus() {
+                Some(address) => Some(Exporter::bind(address)?),
+                None => None,
+            },
+        })
+    }
+
+    fn publish(&self, tracked: &[Tracked]) {
+        self.server
+            .publish(tracked.iter().map(|entry| (&entry.device, &entry.history)));
+        #[cfg(feature = "prometheus")]
+        {
+            if let Some(ref exporter) = self.prometheus {
+                exporter.publish(tracked.iter().map(|entry| &entry.device));
+            }
+        }
+    }
+}
+
+impl Tracked {
+    fn new(config: &Config, device: Device) -> Tracked {
+        let mut history = History::open(&device, config.history_enabled(), *config.delay(), config.retention());
+        history.observe(&device);
+
+        Tracked {
+            device,
+            history,
+        }
+    }
+}
+
+pub fn run(config: &Config) -> Result<()> {
+    if config.backend() == Backend::Daemon {
+        return Err(Error::Config(
+            "the daemon can't attach to another one, choose the backend reading the devices".to_string(),
+        ));
+    }
+
+    // Signals are received by the thread of their own, which has to be started before any other one
+    signals::block()?;
+    let (events_tx, events) = mpsc::channel();
+    let signals_tx = events_tx.clone();
+    thread::Builder::new().name("signals".to_string()).spawn(move || {
+        if let Err(e) = signals::watch(|signal| signals_tx.send(Event::Signal(signal)).is_ok()) {
+            warn!("Unable to receive the signals: {}", e);
+        }
+    })?;
+    // Power supplies connected later are announced by the kernel, so their backends are not rescanned on a timer
+    #[cfg(target_os = "linux")]
+    let hotplug = watch_hotplug(&events_tx);
+    #[cfg(not(target_os = "linux"))]
+    let hotplug = false;
+
+    let outputs = Outputs::bind(config)?;
+    let (collector, devices) = Collector::spawn(config.sources(), move || events_tx.send(Event::Collected).is_ok())?;
+    if devices.is_empty() {
+        warn!("No devices found yet, waiting for them to be connected");
+    }
+    let mut tracked = devices
+        .into_iter()
+        .map(|device| Tracked::new(config, device))
+        .collect::<Vec<_>>();
+    outputs.publish(&tracked);
+
+    let now = Instant::now();
+    let mut polls = (0..collector.source_count())
+        .map(|index| now + interval(config, &collector, index))
+        .collect::<Vec<_>>();
+    let mut rescans = (0..collector.source_count())
+        .map(|index| {
+            if hotplug && collector.hotplug(index) {
+                None
+            } else {
+                Some(now + rescan_interval(&collector, index))
+            }
+        })
+        .collect::<Vec<_>>();
+    loop {
+        let now = Instant::now();
+        for (index, poll) in polls.iter_mut().enumerate() {
+            if *poll <= now {
+                collector.refresh(Some(index));
+                *poll = now + interval(config, &collector, index);
+            }
+        }
+        for (index, rescan) in rescans.iter_mut().enumerate() {
+            if let Some(ref mut due) = *rescan {
+                if *due <= now {
+                    collector.rescan(Some(index));
+                    *due = now + rescan_interval(&collector, index);
+                }
+            }
+        }
+        if let Some(id) = collector.hung(REFRESH_TIMEOUT) {
+            warn!(
+                "Refresh of {} takes longer than {:?}, clients are getting its last values",
+                id, REFRESH_TIMEOUT
+            );
+        }
+
+        let next = polls
+            .iter()
+            .chain(rescans.iter().flatten())
+            .cloned()
+            .min()
+            .unwrap_or(now + RESCAN_INTERVAL);
+        let timeout = if next > now { next - now } else { Duration::from_secs(0) };
+        match events.recv_timeout(timeout) {
+            Ok(Event::Collected) => {
+                collect(config, &collector, &mut tracked);
+                outputs.publish(&tracked);
+            }
+            Ok(Event::Hotplug) => {
+                for index in 0..collector.source_count() {
+                    if collector.hotplug(index) {
+                        collector.rescan(Some(index));
+                    }
+                }
+            }
+            // Sessions in progress are persisted and the socket is removed as they are dropped
+            Ok(Event::Signal(Signal::Terminate)) => {
+                info!("Terminated by signal");
+                return Ok(());
+            }
+            Ok(Event::Signal(signal)) => debug!("Ignoring {:?}, daemon configuration is read on start only", signal),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Err(Error::Channel(mpsc::RecvError)),
+        }
+    }
+}
+
+/// Poll each backend with the configured delay, or slower if the backend asks for it
+fn interval(config: &Config, collector: &Collector, index: usize) -> Duration {
+    let delay = *config.delay();
+    collector.interval(index).map_or(delay, |min| min.max(delay))
+}
+
+/// Rescan each backend no more often than it is polled
+fn rescan_interval(collector: &Collector, index: usize) -> Duration {
+    collector
+        .interval(index)
+        .map_or(RESCAN_INTERVAL, |min| min.max(RESCAN_INTERVAL))
+}
+
+/// Send the power supply hotplug uevents as the `Event::Hotplug`, `false` if they are not available
+#[cfg(target_os = "linux")]
+fn watch_hotplug(tx: &mpsc::Sender<Event>) -> bool {
+    let uevents = match Uevents::open() {
+        Ok(uevents) => uevents,
+        Err(e) => {
+            warn!(
+                "Unable to listen for the power supply uevents, rescanning periodically: {}",
+                e
+            );
+            return false;
+        }
+    };
+    let hotplug_tx = tx.clone();
+    let spawned = thread::Builder::new().name("uevents".to_string()).spawn(move || {
+        let result = uevents.watch(|uevent| uevent != Uevent::Hotplug || hotplug_tx.send(Event::Hotplug).is_ok());
+        if let Err(e) = result {
+            warn!("Power supply uevents are not received anymore: {}", e);
+        }
+    });
+    if let Err(e) = spawned {
+        warn!(
+            "Unable to listen for the power supply uevents, rescanning periodically: {}",
+            e
+        );
+        return false;
+    }
+
+    true
+}
+
+/// Apply the devices refreshed by the collection threads so far
+fn collect(config: &Config, collector: &Collector, tracked: &mut Vec<Tracked>) {
+    for update in collector.updates() {
+        match update {
+            Update::Refreshed(device) => {
+                let id = device.id();
+                if let Some(entry) = tracked.iter_mut().find(|entry| entry.device.id() == id) {
+                    entry.history.observe(&device);
+                    entry.device = device;
+                }
+            }
+            Update::Failed(id, e) => warn!("Unable to refresh {}: {}", id, e),
+            // History of the device is closed, clients are dropping it the same way
+            Update::Disconnected(id) => {
+                info!("Device disconnected: {}", id);
+                tracked.retain(|entry| entry.device.id() != id);
+            }
+            Update::Connected(device) => {
+                info!("Device connected: {}", device.id());
+                tracked.retain(|entry| !entry.device.is_same(&device));
+                tracked.push(Tracked::new(config, device));
+            }
+        }
+    }
+}
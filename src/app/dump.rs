@@ -0,0 +1,97 @@
+//! Plain text snapshot of the running battop, written on `SIGUSR1`.
+//!
+//! Meant for the bug reports about the stalled refreshes or the odd values:
+//! what every device had reported last and what is kept in memory for it.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use std::{env, process};
+
+use battery::units::electric_potential::volt;
+use battery::units::power::watt;
+use battery::units::ratio::percent;
+use battery::units::thermodynamic_temperature::degree_celsius;
+
+use battop_core::format;
+
+use super::config::Config;
+use super::ui::View;
+use crate::Result;
+
+/// File in the temporary directory named after the process, overwritten by each dump
+pub fn default_path() -> PathBuf {
+    env::temp_dir().join(format!("battop-{}.txt", process::id()))
+}
+
+pub fn write(path: &Path, config: &Config, views: &[View], interval: Duration) -> Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(
+        out,
+        "battop {} state at {}",
+        env!("CARGO_PKG_VERSION"),
+        humantime::format_rfc3339_seconds(SystemTime::now())
+    )?;
+    match config.path() {
+        Some(path) => writeln!(out, "Configuration: {}", path.display())?,
+        None => writeln!(out, "Configuration: command line only")?,
+    }
+    writeln!(out, "Polling interval: {}", humantime::format_duration(interval))?;
+    writeln!(out, "Locale: {}", config.locale().language())?;
+
+    for view in views {
+        let battery = view.battery();
+        let status = if !view.is_connected() {
+            "disconnected"
+        } else if view.is_stale() {
+            "not responding"
+        } else {
+            "connected"
+        };
+        writeln!(out)?;
+        writeln!(out, "{} ({}), {}", view.title(), battery.id(), status)?;
+        writeln!(
+            out,
+            "  {:?}, {:.1} %, {:.2} W, {:.2} V",
+            battery.state(),
+            battery.state_of_charge().get::<percent>(),
+            battery.energy_rate().get::<watt>(),
+            battery.voltage().get::<volt>()
+        )?;
+        if let Some(temperature) = battery.temperature() {
+            writeln!(out, "  Temperature: {:.1} °C", temperature.get::<degree_celsius>())?;
+        }
+
+        let samples = view.history().samples();
+        match samples.iter().next() {
+            Some(oldest) => writeln!(
+                out,
+                "  Samples: {} in memory since {}, sequence {}",
+                samples.iter().count(),
+                format::time(oldest.timestamp),
+                samples.sequence()
+            )?,
+            None => writeln!(out, "  Samples: none")?,
+        }
+        if let Some(session) = view.history().sessions().current() {
+            writeln!(
+                out,
+                "  Session: {:?} since {}, {}",
+                session.kind,
+                format::time(session.start),
+                format::energy(config.units(), session.energy)
+            )?;
+        }
+        if let Some(power) = view.session_power() {
+            writeln!(
+                out,
+                "  Discharging power: p50 {:.2} W, p90 {:.2} W, p99 {:.2} W over {} samples",
+                power.p50, power.p90, power.p99, power.count
+            )?;
+        }
+    }
+    out.flush()?;
+
+    Ok(())
+}
@@ -1,84 +1,346 @@
-use std::io;
 use std::sync::mpsc;
 use std::thread;
+use std::time::{Duration, Instant};
 
-use termion::event::Key;
-use termion::input::TermRead;
+#[cfg(unix)]
+use battop_core::signals;
+#[cfg(target_os = "linux")]
+use battop_core::uevents::{self, Uevent, Uevents};
 
+use crate::app::terminal::{Key, Screen};
+use crate::app::ui::Page;
 use crate::app::Config;
 use crate::Result;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub enum Event {
     Exit,
     NextTab,
     PreviousTab,
-    Tick,
+    Page(Page),
+    NextWindow,
+    IncreaseForecast,
+    DecreaseForecast,
+    ForecastMedian,
+    ForecastP90,
+    NextProfile,
+    ChargeLimits,
+    Up,
+    Down,
+    NextField,
+    Confirm,
+    /// Closes the dialog, if any is open, exits otherwise
+    Cancel,
+    /// Periodic job is due
+    Timer(Timer),
+    /// Power supply state changed, refresh is needed right away
+    PowerChanged,
+    /// Power supply was added or removed, the backends reading them have to enumerate the devices again
+    Hotplug,
+    /// Collection thread has refreshed some devices
+    Collected,
+    /// Terminate, reload the configuration or dump the state, depending on the process signal received
+    #[cfg(unix)]
+    Signal(signals::Signal),
+}
+
+/// Periodic jobs, each one is running with its own interval
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Timer {
+    /// Refresh the devices of the backend with the given index
+    Poll(usize),
+    /// Look for the devices of the backend with the given index connected after the start
+    Rescan(usize),
+    /// Process attribution, power profile and configuration file changes
+    Housekeeping,
+}
+
+/// User-defined keys, taking precedence over the default ones
+pub type KeyBindings = Vec<(Key, Event)>;
+
+/// Action names used in the `[keys]` section of the configuration file
+static ACTIONS: [(&str, Event); 16] = [
+    ("exit", Event::Exit),
+    ("next_tab", Event::NextTab),
+    ("previous_tab", Event::PreviousTab),
+    ("overview", Event::Page(Page::Overview)),
+    ("health", Event::Page(Page::Health)),
+    ("statistics", Event::Page(Page::Statistics)),
+    ("comparison", Event::Page(Page::Comparison)),
+    ("processes", Event::Page(Page::Processes)),
+    ("scripts", Event::Page(Page::Scripts)),
+    ("next_window", Event::NextWindow),
+    ("increase_forecast", Event::IncreaseForecast),
+    ("decrease_forecast", Event::DecreaseForecast),
+    ("forecast_median", Event::ForecastMedian),
+    ("forecast_p90", Event::ForecastP90),
+    ("next_profile", Event::NextProfile),
+    ("charge_limits", Event::ChargeLimits),
+];
+
+impl Event {
+    /// Event for the action name used in the `[keys]` section of the configuration file
+    pub fn from_action(action: &str) -> Option<Event> {
+        ACTIONS
+            .iter()
+            .find(|(name, _)| *name == action)
+            .map(|(_, event)| event.clone())
+    }
+}
+
+/// Names of the actions which might be bound to the keys
+pub fn actions() -> impl Iterator<Item = &'static str> {
+    ACTIONS.iter().map(|(name, _)| *name)
+}
+
+/// Key name as written in the configuration file, ex. `q`, `Left`, `Ctrl+n` or `F5`
+pub fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Key::Char(c));
+    }
+
+    let lower = name.to_ascii_lowercase();
+    let key = match lower.as_str() {
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "backspace" => Key::Backspace,
+        "delete" => Key::Delete,
+        "tab" => Key::Char('\t'),
+        "enter" => Key::Char('\n'),
+        "space" => Key::Char(' '),
+        "esc" => Key::Esc,
+        _ if lower.starts_with("ctrl+") || lower.starts_with("alt+") => {
+            let (modifier, rest) = lower.split_at(lower.find('+')? + 1);
+            let mut chars = rest.chars();
+            let c = match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return None,
+            };
+            if modifier == "ctrl+" {
+                Key::Ctrl(c)
+            } else {
+                Key::Alt(c)
+            }
+        }
+        _ if lower.starts_with('f') => Key::F(lower[1..].parse().ok().filter(|n| *n >= 1 && *n <= 12)?),
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+/// Event for the pressed key, user-defined `bindings` take precedence over the default keys
+pub fn key_event(bindings: &[(Key, Event)], key: Key) -> Option<Event> {
+    if let Some((_, event)) = bindings.iter().find(|(bound, _)| *bound == key) {
+        return Some(event.clone());
+    }
+
+    let event = match key {
+        Key::Left => Event::PreviousTab,
+        Key::Right => Event::NextTab,
+        Key::Char('1') => Event::Page(Page::Overview),
+        Key::Char('2') => Event::Page(Page::Health),
+        Key::Char('3') => Event::Page(Page::Statistics),
+        Key::Char('4') => Event::Page(Page::Comparison),
+        Key::Char('5') => Event::Page(Page::Processes),
+        Key::Char('6') => Event::Page(Page::Scripts),
+        Key::Char('w') => Event::NextWindow,
+        Key::Char('+') | Key::Char('=') => Event::IncreaseForecast,
+        Key::Char('-') => Event::DecreaseForecast,
+        Key::Char('p') => Event::ForecastMedian,
+        Key::Char('P') => Event::ForecastP90,
+        Key::Char('m') => Event::NextProfile,
+        Key::Char('l') => Event::ChargeLimits,
+        Key::Up => Event::Up,
+        Key::Down => Event::Down,
+        Key::Char('\t') => Event::NextField,
+        Key::Char('\n') => Event::Confirm,
+        Key::Char('q') => Event::Exit,
+        Key::Ctrl('c') => Event::Exit,
+        Key::Esc => Event::Cancel,
+        _ => return None,
+    };
+
+    Some(event)
+}
+
+/// Send the power supply uevents as the `Event::PowerChanged` and `Event::Hotplug` ones, `false` if they are not
+/// available
+#[cfg(target_os = "linux")]
+fn watch_uevents(tx: &mpsc::Sender<Event>) -> bool {
+    let uevents = match Uevents::open() {
+        Ok(uevents) => uevents,
+        Err(e) => {
+            warn!("Unable to listen for the power supply uevents, polling only: {}", e);
+            return false;
+        }
+    };
+    let uevents_tx = tx.clone();
+    thread::spawn(move || {
+        let result = uevents.watch(|uevent| {
+            let event = match uevent {
+                Uevent::Hotplug => Event::Hotplug,
+                Uevent::Change => Event::PowerChanged,
+            };
+            uevents_tx.send(event).is_ok()
+        });
+        if let Err(e) = result {
+            warn!("Power supply uevents are not received anymore: {}", e);
+        }
+    });
+
+    true
 }
 
 #[derive(Debug)]
 pub struct EventHandler {
+    tx: mpsc::Sender<Event>,
     rx: mpsc::Receiver<Event>,
-    input_handle: thread::JoinHandle<()>,
-    tick_handle: thread::JoinHandle<()>,
+    schedule_tx: mpsc::Sender<(Timer, Duration)>,
+    /// Intervals requested so far, so the unchanged ones are not sent again
+    intervals: Vec<(Timer, Duration)>,
+    /// Power supply hotplugs are reported by the `Event::Hotplug`
+    hotplug: bool,
 }
 
 impl EventHandler {
-    pub fn from_config(config: &Config) -> EventHandler {
+    /// Keys are read from the `screen` on a separate thread
+    pub fn from_config<S: Screen>(config: &Config, screen: S) -> EventHandler {
         let (tx, rx) = mpsc::channel();
 
+        // Has to be done before any thread is started, so the signals are delivered to the watcher only
+        #[cfg(unix)]
+        {
+            match signals::block() {
+                Ok(()) => {
+                    let signals_tx = tx.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = signals::watch(|signal| signals_tx.send(Event::Signal(signal)).is_ok()) {
+                            warn!("Unable to receive the process signals: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Unable to block the process signals, default actions apply: {}", e),
+            }
+        }
+
         // Thread than will handle user input and send events to receiver
-        let input_handle = {
+        {
             let tx = tx.clone();
+            let bindings = config.key_bindings().to_vec();
             thread::spawn(move || {
-                let stdin = io::stdin();
                 trace!("Input thread spawned");
-                for possible_key in stdin.keys() {
-                    if let Ok(key) = possible_key {
-                        let event = match key {
-                            Key::Left => Event::PreviousTab,
-                            Key::Right => Event::NextTab,
-                            Key::Char('q') => Event::Exit,
-                            Key::Ctrl('c') => Event::Exit,
-                            Key::Esc => Event::Exit,
-                            _ => continue,
-                        };
-                        let is_exit = event == Event::Exit;
-
-                        if let Err(e) = tx.send(event) {
-                            // Now that's just terrible thing to do with poor thread :(
-                            warn!("Input thread failed to send event and will be terminated: {:?}", e);
-                            return;
-                        }
+                for key in screen.keys() {
+                    let event = match key_event(&bindings, key) {
+                        Some(event) => event,
+                        None => continue,
+                    };
+                    let is_exit = event == Event::Exit;
+
+                    if let Err(e) = tx.send(event) {
+                        // Now that's just terrible thing to do with poor thread :(
+                        warn!("Input thread failed to send event and will be terminated: {:?}", e);
+                        return;
+                    }
+
+                    // User had requested an exit, closing this thread too
+                    if is_exit {
+                        trace!("Input thread just sent the Exit event and going to terminate now");
+                        return;
+                    }
+                }
+            });
+        }
+
+        // Linux reports the power supply changes, so these are not waiting for the next tick
+        #[cfg(target_os = "linux")]
+        let hotplug = watch_uevents(&tx);
+        #[cfg(not(target_os = "linux"))]
+        let hotplug = false;
+        #[cfg(target_os = "linux")]
+        {
+            let acpid_tx = tx.clone();
+            thread::spawn(move || {
+                if let Err(e) = uevents::watch_acpid(|| acpid_tx.send(Event::PowerChanged).is_ok()) {
+                    debug!("acpid events are not available: {}", e);
+                }
+            });
+        }
 
-                        // User had requested an exit, closing this thread too
-                        if is_exit {
-                            trace!("Input thread just sent the Exit event and going to terminate now");
+        // Thread sending the `Event::Timer` events, timers are added or changed later with `schedule`.
+        // Changed timer fires right away, so the new interval is applied immediately.
+        let (schedule_tx, schedule_rx) = mpsc::channel();
+        let timers_tx = tx.clone();
+        thread::spawn(move || {
+            let mut timers: Vec<(Timer, Duration, Instant)> = Vec::new();
+            trace!("Timers thread is spawned");
+            loop {
+                let now = Instant::now();
+                for (timer, interval, due) in timers.iter_mut() {
+                    if *due <= now {
+                        if timers_tx.send(Event::Timer(*timer)).is_err() {
                             return;
                         }
+                        *due = now + *interval;
                     }
                 }
-            })
-        };
 
-        // Thread that will "tick" with some user-defined interval.
-        // Application might update state and re-draw UI on that event
-        let interval = *config.delay();
-        let tick_handle = {
-            thread::spawn(move || {
-                let tx = tx.clone();
-                trace!("Tick thread is spawned with {:?} interval", interval);
-                loop {
-                    tx.send(Event::Tick).expect("Tick receiver is dead");
-                    thread::sleep(interval);
+                let next = timers.iter().map(|(_, _, due)| *due).min();
+                let received = match next {
+                    Some(due) => {
+                        let now = Instant::now();
+                        schedule_rx.recv_timeout(if due > now { due - now } else { Duration::from_secs(0) })
+                    }
+                    None => schedule_rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+                };
+                match received {
+                    Ok((timer, interval)) => {
+                        trace!("{:?} timer interval changed to {:?}", timer, interval);
+                        timers.retain(|(existing, _, _)| *existing != timer);
+                        timers.push((timer, interval, Instant::now()));
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
                 }
-            })
-        };
+            }
+        });
 
         EventHandler {
+            tx,
             rx,
-            input_handle,
-            tick_handle,
+            schedule_tx,
+            intervals: Vec::new(),
+            hotplug,
+        }
+    }
+
+    /// Sender for the events produced outside of the handler, ex. `Event::Collected`
+    pub fn sender(&self) -> mpsc::Sender<Event> {
+        self.tx.clone()
+    }
+
+    /// Whether the power supply hotplugs are reported, so the backends reading them are not rescanned on a timer
+    pub fn follows_hotplug(&self) -> bool {
+        self.hotplug
+    }
+
+    /// Start the periodic `Event::Timer` events or change their interval
+    pub fn schedule(&mut self, timer: Timer, interval: Duration) {
+        if self.intervals.contains(&(timer, interval)) {
+            return;
+        }
+
+        self.intervals.retain(|(existing, _)| *existing != timer);
+        self.intervals.push((timer, interval));
+        if let Err(e) = self.schedule_tx.send((timer, interval)) {
+            warn!("Unable to schedule the {:?} timer: {:?}", timer, e);
         }
     }
 
@@ -92,3 +354,36 @@ impl EventHandler {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{key_event, parse_key, Event};
+    use crate::app::terminal::Key;
+    use crate::app::ui::Page;
+
+    #[test]
+    fn key_names() {
+        assert_eq!(parse_key("q"), Some(Key::Char('q')));
+        assert_eq!(parse_key("PageDown"), Some(Key::PageDown));
+        assert_eq!(parse_key("space"), Some(Key::Char(' ')));
+        assert_eq!(parse_key("Ctrl+n"), Some(Key::Ctrl('n')));
+        assert_eq!(parse_key("alt+x"), Some(Key::Alt('x')));
+        assert_eq!(parse_key("F12"), Some(Key::F(12)));
+        assert_eq!(parse_key("F13"), None);
+        assert_eq!(parse_key("Ctrl+nn"), None);
+        assert_eq!(parse_key("Hyper"), None);
+    }
+
+    #[test]
+    fn bindings_take_precedence() {
+        let bindings = vec![(Key::Char('q'), Event::Page(Page::Health)), (Key::F(1), Event::Exit)];
+
+        assert_eq!(key_event(&bindings, Key::Char('q')), Some(Event::Page(Page::Health)));
+        assert_eq!(key_event(&bindings, Key::F(1)), Some(Event::Exit));
+        assert_eq!(
+            key_event(&bindings, Key::Char('3')),
+            Some(Event::Page(Page::Statistics))
+        );
+        assert_eq!(key_event(&bindings, Key::Char('z')), None);
+    }
+}
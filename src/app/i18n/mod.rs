@@ -0,0 +1,155 @@
+//! User-visible strings of the interface and of the `battop report` output.
+//!
+//! Messages are kept in the per-language catalogs embedded into the binary, in the same TOML subset
+//! as the configuration file: tables are grouping the messages by page, `{name}` placeholders
+//! are replaced with the arguments. Messages missing from the selected catalog are taken from the English one.
+//!
+//! Language is selected with the `--locale` option or the `locale` configuration key,
+//! otherwise with the `LC_ALL`, `LC_MESSAGES` or `LANG` environment variables.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+
+use super::config::toml::{self, Entry, Value};
+
+const DEFAULT: &str = "en";
+
+/// Shipped catalogs, language code and the catalog source
+const CATALOGS: [(&str, &str); 2] = [("en", include_str!("en.toml")), ("de", include_str!("de.toml"))];
+
+pub struct Locale {
+    language: &'static str,
+    /// Messages of the catalog by their keys, along with the English ones missing from it
+    messages: HashMap<String, String>,
+}
+
+impl Locale {
+    /// Catalog for the language code, ex. `de`, or the POSIX locale name, ex. `de_DE.UTF-8`
+    pub fn new(name: &str) -> Option<Locale> {
+        let language = language(name);
+        let (language, source) = CATALOGS.iter().find(|(code, _)| *code == language)?;
+
+        let mut messages = HashMap::new();
+        if *language != DEFAULT {
+            messages.extend(strings(parse(CATALOGS[0].1)));
+        }
+        messages.extend(strings(parse(source)));
+
+        Some(Locale {
+            language,
+            messages,
+        })
+    }
+
+    /// Language of the environment, English if there is no catalog for it
+    pub fn from_env() -> Locale {
+        let name = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|variable| env::var(variable).ok())
+            .find(|value| !value.is_empty());
+
+        match name.as_ref().and_then(|name| Locale::new(name)) {
+            Some(locale) => locale,
+            None => {
+                debug!("No translations for the {:?} locale, using English", name);
+                Locale::new(DEFAULT).expect("English catalog is always shipped")
+            }
+        }
+    }
+
+    pub fn language(&self) -> &str {
+        self.language
+    }
+
+    /// Message without the arguments, key itself is returned if it is missing from the catalogs
+    pub fn text<'a>(&'a self, key: &'a str) -> &'a str {
+        self.messages.get(key).map_or(key, String::as_str)
+    }
+
+    /// Message with the `{name}` placeholders replaced by the arguments
+    pub fn message(&self, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+        let mut message = self.text(key).to_string();
+        for (name, value) in args {
+            message = message.replace(&format!("{{{}}}", name), &value.to_string());
+        }
+
+        message
+    }
+
+    /// Name of the enum variant, looked up by its lowercase `Display` form in the `group` table,
+    /// ex. `state.charging`; the `Display` form itself is used if there is no such message
+    pub fn name<T: fmt::Display>(&self, group: &str, value: T) -> String {
+        let display = value.to_string();
+        let key = format!("{}.{}", group, display.to_lowercase().replace(' ', "_"));
+        match self.text(&key) {
+            text if text == key => display,
+            text => text.to_string(),
+        }
+    }
+}
+
+impl fmt::Debug for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Locale").field("language", &self.language).finish()
+    }
+}
+
+/// Languages with the shipped catalogs
+pub fn languages() -> Vec<&'static str> {
+    CATALOGS.iter().map(|(code, _)| *code).collect()
+}
+
+/// `de_DE.UTF-8` and `de-DE` are both `de`
+fn language(name: &str) -> String {
+    name.split(['_', '-', '.', '@'])
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+fn parse(source: &str) -> Vec<Entry> {
+    // Catalogs are embedded into the binary, so the invalid one is a bug
+    toml::parse(source).unwrap_or_else(|e| panic!("Invalid message catalog, {}", e))
+}
+
+/// Message texts by their keys, catalogs have nothing but the strings
+fn strings(entries: Vec<Entry>) -> impl Iterator<Item = (String, String)> {
+    entries.into_iter().filter_map(|entry| match entry.value {
+        Value::String(text) => Some((entry.key, text)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{parse, CATALOGS};
+
+    #[test]
+    fn catalogs_have_the_same_keys() {
+        let keys = |source| {
+            parse(source)
+                .into_iter()
+                .map(|entry| entry.key)
+                .collect::<BTreeSet<_>>()
+        };
+        let english = keys(CATALOGS[0].1);
+        for (language, source) in CATALOGS.iter().skip(1) {
+            let translated = keys(source);
+            assert_eq!(
+                english.difference(&translated).collect::<Vec<_>>(),
+                Vec::<&String>::new(),
+                "Messages missing from the `{}` catalog",
+                language
+            );
+            assert_eq!(
+                translated.difference(&english).collect::<Vec<_>>(),
+                Vec::<&String>::new(),
+                "Messages of the `{}` catalog unknown to the English one",
+                language
+            );
+        }
+    }
+}
@@ -1,7 +1,37 @@
+#[cfg(feature = "ui")]
+mod alert;
+#[cfg(feature = "ui")]
 mod application;
+#[cfg(feature = "ui")]
+mod cadence;
 pub mod config;
+#[cfg(all(unix, feature = "daemon"))]
+mod daemon;
+#[cfg(feature = "ui")]
+mod dump;
+#[cfg(feature = "ui")]
 mod events;
+mod i18n;
+#[cfg(feature = "ui")]
+mod profiles;
+mod report;
+#[cfg(feature = "ui")]
+mod terminal;
+#[cfg(feature = "ui")]
 mod ui;
 
-pub use self::application::{init, Application};
-pub use self::config::Config;
+#[cfg(feature = "ui")]
+pub use self::application::init;
+pub use self::config::{Command, Config};
+pub use self::report::print as print_report;
+
+/// Serve the devices to the attached clients until terminated, see the `daemon` module
+#[cfg(all(unix, feature = "daemon"))]
+pub fn run_daemon(config: &Config) -> crate::Result<()> {
+    daemon::run(config)
+}
+
+#[cfg(not(all(unix, feature = "daemon")))]
+pub fn run_daemon(_config: &Config) -> crate::Result<()> {
+    Err(crate::Error::Disabled("daemon".to_string()))
+}
@@ -0,0 +1,118 @@
+//! Platform power profile managed by the power-profiles-daemon, Linux only.
+//!
+//! Daemon is queried and controlled over the system D-Bus with the `busctl` command line tool.
+//! Recent daemon versions are registered as `org.freedesktop.UPower.PowerProfiles`,
+//! older ones as `net.hadess.PowerProfiles` only.
+
+use std::io;
+
+use battop_core::command;
+
+/// Known D-Bus names of the daemon, as the service, object path and interface
+const SERVICES: [(&str, &str, &str); 2] = [
+    (
+        "org.freedesktop.UPower.PowerProfiles",
+        "/org/freedesktop/UPower/PowerProfiles",
+        "org.freedesktop.UPower.PowerProfiles",
+    ),
+    (
+        "net.hadess.PowerProfiles",
+        "/net/hadess/PowerProfiles",
+        "net.hadess.PowerProfiles",
+    ),
+];
+
+/// Profiles in the switching order, `performance` is not available on all platforms
+const PROFILES: [&str; 3] = ["power-saver", "balanced", "performance"];
+
+#[derive(Debug)]
+pub struct PowerProfiles {
+    service: (&'static str, &'static str, &'static str),
+    active: String,
+}
+
+impl PowerProfiles {
+    /// Connect to the daemon, if it is running
+    pub fn new() -> Option<PowerProfiles> {
+        for service in SERVICES.iter() {
+            if let Ok(active) = get_active(*service) {
+                debug!("Power profile is {}, managed by {}", active, service.0);
+                return Some(PowerProfiles {
+                    service: *service,
+                    active,
+                });
+            }
+        }
+
+        debug!("power-profiles-daemon is not available");
+        None
+    }
+
+    pub fn active(&self) -> &str {
+        &self.active
+    }
+
+    /// Read the active profile, returns it if it was changed since the previous update
+    pub fn update(&mut self) -> io::Result<Option<&str>> {
+        let active = get_active(self.service)?;
+        if active == self.active {
+            return Ok(None);
+        }
+
+        self.active = active;
+        Ok(Some(&self.active))
+    }
+
+    /// Switch to the next available profile, returns the new one.
+    ///
+    /// Daemon refuses the profiles platform does not support, so these are skipped.
+    pub fn switch(&mut self) -> io::Result<&str> {
+        let current = PROFILES.iter().position(|profile| *profile == self.active).unwrap_or(0);
+        let mut error = None;
+        for offset in 1..PROFILES.len() {
+            let profile = PROFILES[(current + offset) % PROFILES.len()];
+            match set_active(self.service, profile) {
+                Ok(()) => {
+                    self.active = profile.to_string();
+                    return Ok(&self.active);
+                }
+                Err(e) => {
+                    debug!("Unable to switch to the {} power profile: {}", profile, e);
+                    error = Some(e);
+                }
+            }
+        }
+
+        Err(error.unwrap_or_else(|| io::Error::other("no other power profiles")))
+    }
+}
+
+/// `busctl` prints the string property as `s "balanced"`
+fn get_active(service: (&str, &str, &str)) -> io::Result<String> {
+    let (name, path, interface) = service;
+    let output = busctl(&["get-property", name, path, interface, "ActiveProfile"])?;
+
+    match output.trim().split_once(' ') {
+        Some((_, value)) => Ok(value.trim_matches('"').to_string()),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected busctl output: {}", output.trim()),
+        )),
+    }
+}
+
+fn set_active(service: (&str, &str, &str), profile: &str) -> io::Result<()> {
+    let (name, path, interface) = service;
+    busctl(&["set-property", name, path, interface, "ActiveProfile", "s", profile]).map(|_| ())
+}
+
+fn busctl(args: &[&str]) -> io::Result<String> {
+    let output = command::new("busctl").arg("--system").args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,174 @@
+//! Plain text report for the `battop report` command.
+//!
+//! Compares all batteries battop has history for: their health trajectories
+//! and typical discharge rates, so the swappable packs can be told apart.
+
+use std::io::{self, Write};
+
+use battop_core::device::{Device, Provider};
+use battop_core::history::battery_key;
+use battop_core::stats::Comparison;
+
+use crate::app::Config;
+use crate::Result;
+
+pub fn print(config: &Config) -> Result<()> {
+    let devices = attached_devices(config);
+    let attached = devices.iter().map(battery_key).collect::<Vec<_>>();
+    let comparisons = Comparison::all();
+
+    let locale = config.locale();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if comparisons.is_empty() {
+        writeln!(out, "{}", locale.text("report.no_history"))?;
+        return Ok(());
+    }
+
+    writeln!(
+        out,
+        "{:<40} {:<11} {:>6} {:>9} {:>10} {:>9} {:>9} {:>10} {:>11} {:>9}",
+        locale.text("report.battery"),
+        locale.text("report.since"),
+        locale.text("report.days"),
+        locale.text("report.health"),
+        locale.text("report.loss"),
+        locale.text("report.p50"),
+        locale.text("report.p90"),
+        locale.text("report.round_trip"),
+        locale.text("report.sleep_drain"),
+        locale.text("report.sessions")
+    )?;
+    for comparison in comparisons.iter() {
+        let marker = if attached.contains(&comparison.key) { "*" } else { "" };
+        let name = format!("{}{}", comparison.key, marker);
+
+        let (since, days, health, loss) = match comparison.degradation {
+            Some(ref degradation) => {
+                let mut since = humantime::format_rfc3339_seconds(degradation.since()).to_string();
+                since.truncate(10);
+                (
+                    since,
+                    format!("{:.0}", degradation.span_days().floor()),
+                    optional(degradation.current(), "%"),
+                    optional(degradation.yearly_loss(), "%"),
+                )
+            }
+            None => ("-".to_string(), "-".to_string(), "-".to_string(), "-".to_string()),
+        };
+        let (p50, p90) = match comparison.discharge {
+            Some(summary) => (optional(Some(summary.p50), "W"), optional(Some(summary.p90), "W")),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        let efficiency = match comparison.efficiency {
+            Some(ref efficiency) if efficiency.dropping() => format!("{:.1} %!", efficiency.recent * 100.0),
+            Some(ref efficiency) => format!("{:.1} %", efficiency.recent * 100.0),
+            None => "-".to_string(),
+        };
+        let sleep = optional(comparison.sleep.map(|drain| drain.rate), "%/h");
+
+        writeln!(
+            out,
+            "{:<40} {:<11} {:>6} {:>9} {:>10} {:>9} {:>9} {:>10} {:>11} {:>9}",
+            name, since, days, health, loss, p50, p90, efficiency, sleep, comparison.sessions
+        )?;
+    }
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{:<40} {:>14} {:>10} {:>10} {:>14} {:>10} {:>10}",
+        locale.text("report.battery"),
+        locale.text("report.day_discharged"),
+        locale.text("report.day_cycles"),
+        locale.text("report.day_hours"),
+        locale.text("report.week_discharged"),
+        locale.text("report.week_cycles"),
+        locale.text("report.week_hours")
+    )?;
+    for comparison in comparisons.iter() {
+        let (day, week) = (&comparison.day, &comparison.week);
+        writeln!(
+            out,
+            "{:<40} {:>14} {:>10} {:>10} {:>14} {:>10} {:>10}",
+            comparison.key,
+            optional(Some(day.discharged), "W·h"),
+            optional(day.cycles, ""),
+            format!("{:.1}", day.on_battery.as_secs() as f64 / 3600.0),
+            optional(Some(week.discharged), "W·h"),
+            optional(week.cycles, ""),
+            format!("{:.1}", week.on_battery.as_secs() as f64 / 3600.0),
+        )?;
+    }
+
+    let identified = devices
+        .iter()
+        .filter(|device| !device.identity().is_empty())
+        .collect::<Vec<_>>();
+    if !identified.is_empty() {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{:<40} {:<12} {:>10} {:<12} {}",
+            locale.text("report.battery"),
+            locale.text("report.manufactured"),
+            locale.text("report.age"),
+            locale.text("report.firmware"),
+            locale.text("report.chemistry")
+        )?;
+    }
+    for device in identified {
+        let identity = device.identity();
+        let manufactured = match identity.manufactured {
+            Some(date) => date.to_string(),
+            None => "-".to_string(),
+        };
+        let age = identity
+            .age()
+            .map(|age| age.as_secs() as f64 / (365.25 * 24.0 * 60.0 * 60.0));
+        writeln!(
+            out,
+            "{:<40} {:<12} {:>10} {:<12} {}",
+            battery_key(device),
+            manufactured,
+            optional(age, ""),
+            identity.firmware.as_deref().unwrap_or("-"),
+            identity.chemistry.as_deref().unwrap_or("-"),
+        )?;
+    }
+
+    let dropping = comparisons.iter().any(|comparison| match comparison.efficiency {
+        Some(ref efficiency) => efficiency.dropping(),
+        None => false,
+    });
+    if !attached.is_empty() || dropping {
+        writeln!(out)?;
+    }
+    if !attached.is_empty() {
+        writeln!(out, "{}", locale.text("report.attached"))?;
+    }
+    if dropping {
+        writeln!(out, "{}", locale.text("report.dropping"))?;
+    }
+
+    Ok(())
+}
+
+/// Batteries attached to the system right now
+fn attached_devices(config: &Config) -> Vec<Device> {
+    match Provider::new(&config.sources()) {
+        Ok((_, devices)) => devices,
+        Err(e) => {
+            warn!("Unable to fetch attached batteries: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn optional(value: Option<f64>, unit: &str) -> String {
+    match value {
+        Some(value) if unit.is_empty() => format!("{:.2}", value),
+        Some(value) => format!("{:.2} {}", value, unit),
+        None => "-".to_string(),
+    }
+}
@@ -0,0 +1,54 @@
+//! Works on the Windows console as well, where termion is not available.
+
+use std::io;
+
+use crossterm::{AlternateScreen, InputEvent, KeyEvent};
+use tui::backend::CrosstermBackend;
+
+use super::{Key, Screen};
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Crossterm;
+
+impl Screen for Crossterm {
+    type Backend = CrosstermBackend;
+
+    fn open(&self) -> io::Result<Self::Backend> {
+        // Raw mode is left along with the alternate screen, once the backend is dropped
+        let screen = AlternateScreen::to_alternate(true)?;
+
+        CrosstermBackend::with_alternate_screen(screen)
+    }
+
+    fn keys(&self) -> Box<dyn Iterator<Item = Key>> {
+        Box::new(crossterm::input().read_sync().filter_map(|event| match event {
+            InputEvent::Keyboard(key) => convert(key),
+            _ => None,
+        }))
+    }
+}
+
+fn convert(key: KeyEvent) -> Option<Key> {
+    let key = match key {
+        // Windows console reports Enter as the carriage return
+        KeyEvent::Char('\r') => Key::Char('\n'),
+        KeyEvent::Char(c) => Key::Char(c),
+        KeyEvent::Ctrl(c) => Key::Ctrl(c),
+        KeyEvent::Alt(c) => Key::Alt(c),
+        KeyEvent::Left => Key::Left,
+        KeyEvent::Right => Key::Right,
+        KeyEvent::Up => Key::Up,
+        KeyEvent::Down => Key::Down,
+        KeyEvent::Home => Key::Home,
+        KeyEvent::End => Key::End,
+        KeyEvent::PageUp => Key::PageUp,
+        KeyEvent::PageDown => Key::PageDown,
+        KeyEvent::Backspace => Key::Backspace,
+        KeyEvent::Delete => Key::Delete,
+        KeyEvent::Esc => Key::Esc,
+        KeyEvent::F(n) => Key::F(n),
+        _ => return None,
+    };
+
+    Some(key)
+}
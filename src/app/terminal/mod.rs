@@ -0,0 +1,65 @@
+//! Terminal libraries the interface is drawn with and the keys are read from.
+//!
+//! Each one is a `Screen`: it sets the terminal up for the `tui` backend and translates
+//! the pressed keys into the `Key`, so the rest of the interface does not depend on any of them.
+
+use std::fmt;
+use std::io;
+
+#[cfg(all(unix, feature = "ui-termion"))]
+mod termion;
+
+// Off Unix crossterm is always built in, as termion is not available there
+#[cfg(any(feature = "ui-crossterm", not(unix)))]
+mod crossterm;
+
+#[cfg(all(unix, feature = "ui-termion"))]
+pub use self::termion::Termion;
+
+#[cfg(any(feature = "ui-crossterm", not(unix)))]
+pub use self::crossterm::Crossterm;
+
+/// Terminal library used by default: termion on Unix, if it was built in, crossterm otherwise
+#[cfg(all(unix, feature = "ui-termion"))]
+pub type Native = Termion;
+
+#[cfg(all(any(feature = "ui-crossterm", not(unix)), not(all(unix, feature = "ui-termion"))))]
+pub type Native = Crossterm;
+
+#[cfg(all(unix, not(any(feature = "ui-termion", feature = "ui-crossterm"))))]
+compile_error!("The interface requires a terminal library, enable the `ui-termion` or the `ui-crossterm` feature");
+
+/// Pressed key, as far as battop is concerned
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Alt(char),
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Backspace,
+    Delete,
+    Esc,
+    /// Function keys, F1–F12
+    F(u8),
+}
+
+/// Terminal library able to draw the interface and to read the keys
+pub trait Screen: fmt::Debug + Clone + Send + 'static {
+    type Backend: tui::backend::Backend;
+
+    /// Switch the terminal to the raw mode and the alternate screen,
+    /// it is restored once the backend is dropped
+    fn open(&self) -> io::Result<Self::Backend>;
+
+    /// Blocking iterator over the pressed keys, called from the input thread.
+    ///
+    /// Keys battop has no use for are skipped.
+    fn keys(&self) -> Box<dyn Iterator<Item = Key>>;
+}
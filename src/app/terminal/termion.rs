@@ -0,0 +1,51 @@
+use std::io::{self, Stdout};
+
+use termion::event;
+use termion::input::{MouseTerminal, TermRead};
+use termion::raw::{IntoRawMode, RawTerminal};
+use termion::screen::AlternateScreen;
+use tui::backend::TermionBackend;
+
+use super::{Key, Screen};
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Termion;
+
+impl Screen for Termion {
+    type Backend = TermionBackend<AlternateScreen<MouseTerminal<RawTerminal<Stdout>>>>;
+
+    fn open(&self) -> io::Result<Self::Backend> {
+        let stdout = io::stdout().into_raw_mode()?;
+        let stdout = MouseTerminal::from(stdout);
+        let stdout = AlternateScreen::from(stdout);
+
+        Ok(TermionBackend::new(stdout))
+    }
+
+    fn keys(&self) -> Box<dyn Iterator<Item = Key>> {
+        Box::new(io::stdin().keys().filter_map(|key| key.ok()).filter_map(convert))
+    }
+}
+
+fn convert(key: event::Key) -> Option<Key> {
+    let key = match key {
+        event::Key::Char(c) => Key::Char(c),
+        event::Key::Ctrl(c) => Key::Ctrl(c),
+        event::Key::Alt(c) => Key::Alt(c),
+        event::Key::Left => Key::Left,
+        event::Key::Right => Key::Right,
+        event::Key::Up => Key::Up,
+        event::Key::Down => Key::Down,
+        event::Key::Home => Key::Home,
+        event::Key::End => Key::End,
+        event::Key::PageUp => Key::PageUp,
+        event::Key::PageDown => Key::PageDown,
+        event::Key::Backspace => Key::Backspace,
+        event::Key::Delete => Key::Delete,
+        event::Key::Esc => Key::Esc,
+        event::Key::F(n) => Key::F(n),
+        _ => return None,
+    };
+
+    Some(key)
+}
@@ -1,24 +1,48 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
 use std::sync::Arc;
 
-use battery::units::electric_potential::volt;
-use battery::units::power::watt;
-use battery::units::thermodynamic_temperature::{degree_celsius, kelvin};
-use battery::units::Unit;
 use battery::State;
 use itertools::{Itertools, MinMaxResult};
 
-use super::Units;
+use battop_core::format::{self, Units};
+use battop_core::history::{Field, SampleStore};
+
 use crate::app::Config;
 
+/// Amount of the latest samples drawn
 const RESOLUTION: usize = 512;
+/// Amount of points used to draw the annotation line across the chart
+const ANNOTATION_RESOLUTION: usize = 32;
+/// Difference between the kelvin and the degree Celsius scales, temperatures are stored in °C
+const KELVIN_OFFSET: f64 = 273.15;
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum ChartType {
     Voltage,
     EnergyRate,
     Temperature,
+    StateOfCharge,
+    CellVoltage,
+}
+
+/// Additional series drawn over the main one, in the same units
+#[derive(Debug)]
+struct Overlay {
+    label: String,
+    field: Field,
+}
+
+/// Additional series in different units, ex. display brightness over the power draw
+#[derive(Debug)]
+struct Secondary {
+    label: String,
+    /// Share of the full scale, 0–1
+    field: Field,
+    text_latest: String,
 }
 
+/// How to draw one of the `SampleStore` series; values themselves are read from the store when drawn
 #[derive(Debug)]
 pub struct ChartData {
     config: Arc<Config>,
@@ -26,29 +50,42 @@ pub struct ChartData {
     enabled: bool,
 
     battery_state: State,
+    /// Where the values are coming from, if not from the battery itself
+    source: Option<String>,
 
-    points: Vec<(f64, f64)>,
-    value_latest: f64,
-    value_min: f64,
-    value_max: f64,
+    field: Field,
+    /// Sequence numbers of the samples highlighted as anomalies
+    markers: VecDeque<u64>,
+    /// Events worth noting, drawn as the vertical lines
+    annotations: VecDeque<(u64, String)>,
+    overlays: Vec<Overlay>,
+    /// Series in their own units, scaled to the main series range when drawn
+    secondaries: Vec<Secondary>,
 }
 
 impl ChartData {
-    pub fn new(config: Arc<Config>, chart_type: ChartType) -> Self {
+    pub fn new(config: Arc<Config>, chart_type: ChartType, field: Field) -> Self {
         ChartData {
             config,
             chart_type,
             enabled: true,
 
             battery_state: State::Unknown,
+            source: None,
 
-            points: Vec::with_capacity(256),
-            value_latest: 0.0,
-            value_min: 100.0,
-            value_max: 0.0,
+            field,
+            markers: VecDeque::new(),
+            annotations: VecDeque::new(),
+            overlays: Vec::new(),
+            secondaries: Vec::new(),
         }
     }
 
+    /// Configuration reloaded at runtime
+    pub fn set_config(&mut self, config: Arc<Config>) {
+        self.config = config;
+    }
+
     pub fn enabled(&mut self, value: bool) {
         self.enabled = value;
     }
@@ -57,70 +94,199 @@ impl ChartData {
         &mut self.battery_state
     }
 
-    #[allow(clippy::cast_lossless)]
-    pub fn push<T>(&mut self, value: T)
-    where
-        T: Into<f64>,
-    {
-        let value = value.into();
+    pub fn set_source(&mut self, source: Option<String>) {
+        self.source = source;
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
 
-        if self.points.len() == RESOLUTION {
-            self.points.remove(0);
-        }
-        for (x, _) in self.points.iter_mut() {
-            *x -= 0.5;
+    /// Draw the store `channel` over the main series, labeled with the message key, see `Locale::text`
+    pub fn overlay(&mut self, label: &str, channel: &str) {
+        if self.overlays.iter().all(|overlay| overlay.label != label) {
+            self.overlays.push(Overlay {
+                label: label.to_string(),
+                field: Field::Channel(channel.to_string()),
+            });
         }
+    }
 
-        self.value_latest = value;
-
-        self.points.push((RESOLUTION as f64 / 2.0, value));
-        match self.points.iter().minmax_by_key(|(_, y)| y) {
-            MinMaxResult::MinMax((_, min), (_, max)) => {
-                self.value_min = *min;
-                self.value_max = *max;
-            }
-            MinMaxResult::OneElement((_, el)) => {
-                self.value_min = *el;
-                self.value_max = *el;
-            }
-            _ => {}
+    /// Draw the store `channel` with the share of the series full scale, 0–1, over the main series.
+    ///
+    /// `label` is the message key, see `Locale::text`, and `text` is the latest value itself, formatted.
+    pub fn secondary(&mut self, label: &str, channel: &str, text: String) {
+        match self.secondaries.iter_mut().find(|secondary| secondary.label == label) {
+            Some(secondary) => secondary.text_latest = text,
+            None => self.secondaries.push(Secondary {
+                label: label.to_string(),
+                field: Field::Channel(channel.to_string()),
+                text_latest: text,
+            }),
         }
     }
 
     // Texts and titles
 
     pub fn title(&self) -> &str {
-        match self.chart_type {
-            ChartType::Voltage => "Voltage",
+        let key = match self.chart_type {
+            ChartType::Voltage => "chart.voltage",
             ChartType::EnergyRate => match self.battery_state {
-                State::Charging => "Charging with",
-                State::Discharging => "Discharging with",
-                _ => "Consumption",
+                State::Charging => "chart.charging",
+                State::Discharging => "chart.discharging",
+                _ => "chart.consumption",
             },
-            ChartType::Temperature => "Temperature",
-        }
+            ChartType::Temperature => "chart.temperature",
+            ChartType::StateOfCharge => "chart.state_of_charge",
+            ChartType::CellVoltage => "chart.cell_voltage",
+        };
+
+        self.config.locale().text(key)
     }
 
     /// Current value formatted with proper units
-    pub fn current(&self) -> String {
-        if self.enabled {
-            match self.chart_type {
-                ChartType::Voltage => format!("{:.2} {}", self.value_latest, volt::abbreviation()),
-                ChartType::EnergyRate => format!("{:.2} {}", self.value_latest, watt::abbreviation()),
-                ChartType::Temperature => match self.config.units() {
-                    Units::Human => format!("{:.2} {}", self.value_latest, degree_celsius::abbreviation()),
-                    Units::Si => format!("{:.2} {}", self.value_latest, kelvin::abbreviation()),
-                },
-            }
-        } else {
-            "NOT AVAILABLE".to_string()
+    pub fn current(&self, store: &SampleStore) -> String {
+        if !self.enabled {
+            return self.config.locale().text("chart.not_available").to_string();
+        }
+        let locale = self.config.locale();
+
+        let latest = |field: &Field| self.convert(store.latest_value(field).unwrap_or(0.0));
+        let mut current = self.format(latest(&self.field));
+        for overlay in self.overlays.iter() {
+            current.push_str(&format!(
+                ", {} {}",
+                locale.text(&overlay.label),
+                self.format(latest(&overlay.field))
+            ));
+        }
+        for secondary in self.secondaries.iter() {
+            current.push_str(&format!(
+                ", {} {}",
+                locale.text(&secondary.label),
+                secondary.text_latest
+            ));
+        }
+
+        current
+    }
+
+    fn format(&self, value: f64) -> String {
+        match self.chart_type {
+            ChartType::Voltage => format!("{:.2} {}", value, format::VOLT),
+            ChartType::CellVoltage => format!("{:.3} {}", value, format::VOLT),
+            ChartType::EnergyRate => format!("{:.2} {}", value, format::WATT),
+            ChartType::Temperature => match self.config.units() {
+                Units::Human => format!("{:.2} {}", value, format::DEGREE_CELSIUS),
+                Units::Si => format!("{:.2} {}", value, format::KELVIN),
+            },
+            ChartType::StateOfCharge => format!("{:.0} %", value),
         }
     }
 
     // Data
 
-    pub fn points(&self) -> &[(f64, f64)] {
-        self.points.as_ref()
+    /// Values in the chart units, ex. the state of charge is stored as a ratio, but drawn in percents
+    fn convert(&self, value: f64) -> f64 {
+        match self.chart_type {
+            ChartType::StateOfCharge => value * 100.0,
+            ChartType::Temperature => match self.config.units() {
+                Units::Human => value,
+                Units::Si => value + KELVIN_OFFSET,
+            },
+            _ => value,
+        }
+    }
+
+    /// Chart coordinates of the latest `field` values, the latest sample is at the right edge
+    fn coordinates(&self, store: &SampleStore, field: &Field) -> Vec<(f64, f64)> {
+        store
+            .values(field, RESOLUTION)
+            .into_iter()
+            .map(|(sequence, value)| (x(store, sequence), self.convert(value)))
+            .collect()
+    }
+
+    pub fn points(&self, store: &SampleStore) -> Vec<(f64, f64)> {
+        self.coordinates(store, &self.field)
+    }
+
+    /// Highlight the value at the sample with the given sequence number as an anomaly
+    pub fn mark(&mut self, sequence: u64) {
+        self.markers.push_back(sequence);
+        while self.markers.front().is_some_and(|marker| !visible(sequence, *marker)) {
+            self.markers.pop_front();
+        }
+    }
+
+    pub fn markers(&self, store: &SampleStore) -> Vec<(f64, f64)> {
+        store
+            .values(&self.field, RESOLUTION)
+            .into_iter()
+            .filter(|(sequence, _)| self.markers.contains(sequence))
+            .map(|(sequence, value)| (x(store, sequence), self.convert(value)))
+            .collect()
+    }
+
+    /// Annotate the chart at the sample with the given sequence number, ex. with the power profile change.
+    ///
+    /// `label` is translated when drawn if it is a message key, see `Locale::text`.
+    pub fn annotate(&mut self, label: &str, sequence: u64) {
+        self.annotations.push_back((sequence, label.to_string()));
+        while self
+            .annotations
+            .front()
+            .is_some_and(|(annotated, _)| !visible(sequence, *annotated))
+        {
+            self.annotations.pop_front();
+        }
+    }
+
+    /// Label of the most recent annotation still visible
+    pub fn annotation(&self, store: &SampleStore) -> Option<&str> {
+        self.annotations
+            .back()
+            .filter(|(sequence, _)| visible(store.sequence(), *sequence))
+            .map(|(_, label)| self.config.locale().text(label))
+    }
+
+    /// Vertical lines of all the annotations, spanning the whole Y range
+    pub fn annotation_points(&self, store: &SampleStore) -> Vec<(f64, f64)> {
+        let [lower, upper] = self.y_bounds(store);
+        let step = (upper - lower) / ANNOTATION_RESOLUTION as f64;
+
+        self.annotations
+            .iter()
+            .filter(|(sequence, _)| visible(store.sequence(), *sequence))
+            .flat_map(|(sequence, _)| {
+                let x = x(store, *sequence);
+                (0..=ANNOTATION_RESOLUTION).map(move |i| (x, lower + step * i as f64))
+            })
+            .collect()
+    }
+
+    /// Points of each overlay series
+    pub fn overlay_points(&self, store: &SampleStore) -> Vec<Vec<(f64, f64)>> {
+        self.overlays
+            .iter()
+            .map(|overlay| self.coordinates(store, &overlay.field))
+            .collect()
+    }
+
+    /// Points of each secondary series, with their full scale stretched over the Y range
+    pub fn secondary_points(&self, store: &SampleStore) -> Vec<Vec<(f64, f64)>> {
+        let [lower, upper] = self.y_bounds(store);
+
+        self.secondaries
+            .iter()
+            .map(|secondary| {
+                store
+                    .values(&secondary.field, RESOLUTION)
+                    .into_iter()
+                    .map(|(sequence, fraction)| (x(store, sequence), lower + (upper - lower) * fraction))
+                    .collect()
+            })
+            .collect()
     }
 
     // X scale
@@ -133,40 +299,138 @@ impl ChartData {
 
     pub fn y_title(&self) -> &str {
         match self.chart_type {
-            ChartType::Voltage => volt::abbreviation(),
-            ChartType::EnergyRate => watt::abbreviation(),
+            ChartType::Voltage | ChartType::CellVoltage => format::VOLT,
+            ChartType::EnergyRate => format::WATT,
             ChartType::Temperature => match self.config.units() {
-                Units::Human => degree_celsius::abbreviation(),
-                Units::Si => kelvin::abbreviation(),
+                Units::Human => format::DEGREE_CELSIUS,
+                Units::Si => format::KELVIN,
             },
+            ChartType::StateOfCharge => "%",
         }
     }
 
-    fn y_lower(&self) -> f64 {
-        if self.enabled {
-            let mut value = (self.value_min - 1.0).floor();
-            if value < 0.0 {
-                value = -1.0;
-            }
-            value
-        } else {
-            0.0
+    /// Lowest and highest of the main and overlay series values drawn
+    fn range(&self, store: &SampleStore) -> (f64, f64) {
+        let all = self
+            .overlays
+            .iter()
+            .map(|overlay| &overlay.field)
+            .chain(Some(&self.field))
+            .flat_map(|field| store.values(field, RESOLUTION))
+            .map(|(_, value)| self.convert(value));
+        match all.minmax_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal)) {
+            MinMaxResult::MinMax(min, max) => (min, max),
+            MinMaxResult::OneElement(value) => (value, value),
+            MinMaxResult::NoElements => (100.0, 0.0),
         }
     }
 
-    fn y_upper(&self) -> f64 {
-        if self.enabled {
-            (self.value_max + 1.0).ceil()
-        } else {
-            0.0
+    pub fn y_labels(&self, store: &SampleStore) -> Vec<String> {
+        let [lower, upper] = self.y_bounds(store);
+
+        vec![format!("{:2.0}", lower), format!("{:2.0}", upper)]
+    }
+
+    pub fn y_bounds(&self, store: &SampleStore) -> [f64; 2] {
+        if !self.enabled {
+            return [0.0, 0.0];
         }
+
+        let (min, max) = self.range(store);
+        [(min - 1.0).floor().max(-1.0), (max + 1.0).ceil()]
+    }
+}
+
+/// X coordinate of the sample with the given sequence number, the latest one is at the right edge
+fn x(store: &SampleStore, sequence: u64) -> f64 {
+    (RESOLUTION as f64 - (store.sequence() - sequence) as f64) / 2.0
+}
+
+/// Sample with the `sequence` number still fits the chart when the `latest` one is drawn
+fn visible(latest: u64, sequence: u64) -> bool {
+    latest - sequence < RESOLUTION as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use battery::State;
+
+    use battop_core::history::{Field, Sample, SampleStore};
+
+    use super::{ChartData, ChartType, RESOLUTION};
+    use crate::app::ui::tests::config;
+
+    fn push(store: &mut SampleStore, voltage: f32) {
+        let sequence = store.sequence();
+        store.push(Sample {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(sequence),
+            state: State::Discharging,
+            state_of_charge: 0.5,
+            energy: 25.0,
+            energy_rate: 10.0,
+            voltage,
+            temperature: None,
+            resumed: false,
+        });
     }
 
-    pub fn y_labels(&self) -> Vec<String> {
-        vec![format!("{:2.0}", self.y_lower()), format!("{:2.0}", self.y_upper())]
+    #[test]
+    fn range_covers_the_overlays() {
+        let mut chart = ChartData::new(config(), ChartType::Voltage, Field::Voltage);
+        let mut store = SampleStore::default();
+        push(&mut store, 12.2);
+        assert_eq!(chart.y_bounds(&store), [11.0, 14.0]);
+        push(&mut store, 11.6);
+        assert_eq!(chart.y_bounds(&store), [10.0, 14.0]);
+
+        store.record("voltage.requested", 16.4);
+        chart.overlay("chart.requested", "voltage.requested");
+        assert_eq!(chart.y_bounds(&store), [10.0, 18.0]);
+
+        chart.enabled(false);
+        assert_eq!(chart.y_bounds(&store), [0.0, 0.0]);
     }
 
-    pub fn y_bounds(&self) -> [f64; 2] {
-        [self.y_lower(), self.y_upper()]
+    #[test]
+    fn evicted_values_leave_the_range() {
+        let voltage = ChartData::new(config(), ChartType::Voltage, Field::Voltage);
+        let state_of_charge = ChartData::new(config(), ChartType::StateOfCharge, Field::StateOfCharge);
+        let mut store = SampleStore::default();
+        push(&mut store, 4.0);
+        for _ in 1..RESOLUTION {
+            push(&mut store, 12.0);
+        }
+        assert_eq!(voltage.y_bounds(&store), [3.0, 13.0]);
+        assert_eq!(voltage.points(&store).len(), RESOLUTION);
+
+        // Voltage drop is out of the chart now
+        push(&mut store, 12.0);
+        assert_eq!(voltage.y_bounds(&store), [11.0, 13.0]);
+        assert_eq!(voltage.points(&store)[0], (0.5, 12.0));
+        // State of charge is drawn in percents
+        assert_eq!(state_of_charge.y_bounds(&store), [49.0, 51.0]);
+    }
+
+    #[test]
+    fn markers_and_annotations_are_evicted() {
+        let mut chart = ChartData::new(config(), ChartType::Voltage, Field::Voltage);
+        let mut store = SampleStore::default();
+        push(&mut store, 12.0);
+        chart.mark(store.sequence());
+        chart.annotate("chart.charging_held", store.sequence());
+        assert_eq!(chart.markers(&store), vec![(256.0, 12.0)]);
+        assert_eq!(chart.annotation(&store), Some("charging on hold"));
+
+        for _ in 0..RESOLUTION {
+            push(&mut store, 12.0);
+        }
+        assert_eq!(chart.markers(&store), vec![]);
+        assert_eq!(chart.annotation(&store), None);
+        assert!(chart.annotation_points(&store).is_empty());
+
+        chart.mark(store.sequence());
+        assert_eq!(chart.markers.len(), 1);
     }
 }
@@ -0,0 +1,125 @@
+use battop_core::device::ChargeLimits;
+
+/// Threshold adjustment step, %
+const STEP: u8 = 5;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Field {
+    Start,
+    Stop,
+}
+
+/// Charge thresholds editor, changes are written only after the explicit confirmation
+#[derive(Debug)]
+pub struct LimitsDialog {
+    limits: ChargeLimits,
+    start: Option<u8>,
+    stop: u8,
+    field: Field,
+    confirming: bool,
+    /// Result of the latest write attempt
+    message: Option<String>,
+}
+
+impl LimitsDialog {
+    pub fn new(limits: ChargeLimits) -> LimitsDialog {
+        LimitsDialog {
+            start: limits.start,
+            stop: limits.stop,
+            field: Field::Stop,
+            confirming: false,
+            message: None,
+            limits,
+        }
+    }
+
+    pub fn start(&self) -> Option<u8> {
+        self.start
+    }
+
+    pub fn stop(&self) -> u8 {
+        self.stop
+    }
+
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    pub fn is_confirming(&self) -> bool {
+        self.confirming
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// Switch between the start and stop thresholds, if platform supports both
+    pub fn next_field(&mut self) {
+        if self.start.is_some() {
+            self.field = match self.field {
+                Field::Start => Field::Stop,
+                Field::Stop => Field::Start,
+            };
+        }
+    }
+
+    /// Change the selected threshold, start one is always kept below the stop one
+    pub fn adjust(&mut self, increase: bool) {
+        self.confirming = false;
+        match (self.field, self.start) {
+            (Field::Start, Some(start)) => {
+                let value = if increase {
+                    start.saturating_add(STEP).min(self.stop.saturating_sub(1))
+                } else {
+                    start.saturating_sub(STEP)
+                };
+                self.start = Some(value);
+            }
+            _ => {
+                let min = self.start.map(|start| start + 1).unwrap_or(STEP);
+                let value = if increase {
+                    self.stop.saturating_add(STEP).min(100)
+                } else {
+                    self.stop.saturating_sub(STEP).max(min)
+                };
+                self.stop = value;
+            }
+        }
+    }
+
+    /// First call asks for the confirmation, second one writes the thresholds.
+    ///
+    /// Returns `true` if the dialog is done and can be closed.
+    pub fn confirm(&mut self) -> bool {
+        if self.start == self.limits.start && self.stop == self.limits.stop {
+            return true;
+        }
+        if !self.confirming {
+            self.confirming = true;
+            return false;
+        }
+
+        self.confirming = false;
+        match self.limits.apply(self.start, self.stop) {
+            Ok(()) => {
+                info!("Charge thresholds changed to {:?} - {} %", self.start, self.stop);
+                true
+            }
+            Err(e) => {
+                warn!("Unable to change the charge thresholds: {}", e);
+                self.message = Some(e.to_string());
+                false
+            }
+        }
+    }
+
+    /// Returns `true` if the dialog can be closed, otherwise the confirmation is cancelled only
+    pub fn cancel(&mut self) -> bool {
+        if self.confirming {
+            self.confirming = false;
+            return false;
+        }
+
+        true
+    }
+}
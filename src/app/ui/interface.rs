@@ -1,25 +1,26 @@
-use std::io;
+use std::cmp::Ordering;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::SystemTime;
 
-use termion::input::MouseTerminal;
-use termion::raw::IntoRawMode;
-use termion::screen::AlternateScreen;
-use tui::backend::{Backend, TermionBackend};
+use tui::backend::Backend;
 use tui::Terminal;
 
-use super::{Context, Painter, TabBar, View};
+use battop_core::processes::Attribution;
+use battop_core::stats::{Comparison, WINDOWS};
+
+use super::{Context, LimitsDialog, Page, Painter, TabBar, View};
+use crate::app::events::Event;
 use crate::app::Config;
-use crate::Result;
+use crate::{Error, Result};
+
+/// Runtime forecast power adjustment step, W
+const FORECAST_STEP: f64 = 0.5;
 
 #[allow(clippy::redundant_closure)]
-pub fn init(config: Arc<Config>, views: Vec<View>) -> Result<Interface<impl Backend>> {
+pub fn init<B: Backend>(config: Arc<Config>, backend: B, views: Vec<View>) -> Result<Interface<B>> {
     debug_assert!(!views.is_empty());
 
-    let stdout = io::stdout().into_raw_mode()?;
-    let stdout = MouseTerminal::from(stdout);
-    let stdout = AlternateScreen::from(stdout);
-    let backend = TermionBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     terminal.hide_cursor()?;
 
@@ -31,6 +32,14 @@ pub fn init(config: Arc<Config>, views: Vec<View>) -> Result<Interface<impl Back
         terminal,
         views,
         tabs,
+        page: Page::Overview,
+        window: 0,
+        forecast: None,
+        comparisons: Vec::new(),
+        attribution: None,
+        profile: None,
+        message: None,
+        dialog: None,
     })
 }
 
@@ -41,26 +50,186 @@ pub struct Interface<B: Backend> {
     terminal: Terminal<B>,
     views: Vec<View>,
     tabs: TabBar,
+    page: Page,
+    window: usize,         // Index of the selected statistics window in the `WINDOWS`
+    forecast: Option<f64>, // Power draw for the runtime forecast, W
+    comparisons: Vec<Comparison>,
+    attribution: Option<Attribution>,
+    profile: Option<String>,               // Active platform power profile
+    message: Option<(SystemTime, String)>, // Latest note for the user, shown in the status bar
+    dialog: Option<LimitsDialog>,
 }
 
 impl<B: Backend> Interface<B> {
     pub fn draw(&mut self) -> Result<()> {
+        for (index, view) in self.views.iter().enumerate() {
+            self.tabs.set_connected(index, view.is_connected());
+            self.tabs.set_stale(index, view.is_stale());
+        }
+        let _span = tracing::trace_span!("draw").entered();
         let context = Rc::new(Context {
             tabs: &self.tabs,
+            page: self.page,
+            window: WINDOWS[self.window],
+            forecast: self.forecast(),
+            comparisons: &self.comparisons,
+            attribution: self.attribution.as_ref(),
+            profile: self.profile.as_deref(),
+            message: self.message.as_ref().map(|(time, message)| (*time, message.as_str())),
+            dialog: self.dialog.as_ref(),
             view: &self.views[self.tabs.index()],
         });
-        self.terminal.draw(|frame| {
-            Painter::from_context(context.clone()).draw(frame);
-        })?;
+        self.terminal
+            .draw(|frame| {
+                Painter::from_context(context.clone()).draw(frame);
+            })
+            .map_err(Error::Render)?;
 
         Ok(())
     }
 
+    /// Apply the user input concerning the interface only, returns `false` if the `event` is not one of those
+    pub fn handle(&mut self, event: &Event) -> bool {
+        match event {
+            Event::Cancel => match self.dialog {
+                Some(ref mut dialog) => {
+                    if dialog.cancel() {
+                        self.dialog = None;
+                    }
+                }
+                None => return false,
+            },
+            Event::ChargeLimits => self.open_limits(),
+            Event::Up | Event::Down => {
+                if let Some(ref mut dialog) = self.dialog {
+                    dialog.adjust(*event == Event::Up);
+                }
+            }
+            Event::NextField => {
+                if let Some(ref mut dialog) = self.dialog {
+                    dialog.next_field();
+                }
+            }
+            Event::Confirm => {
+                if self.dialog.as_mut().is_some_and(LimitsDialog::confirm) {
+                    self.dialog = None;
+                }
+            }
+            Event::PreviousTab => self.tabs.previous(),
+            Event::NextTab => self.tabs.next(),
+            Event::Page(page) => self.set_page(*page),
+            Event::NextWindow => self.next_window(),
+            Event::IncreaseForecast => {
+                let watts = self.forecast().unwrap_or(0.0);
+                self.set_forecast(watts + FORECAST_STEP);
+            }
+            Event::DecreaseForecast => {
+                let watts = self.forecast().unwrap_or(0.0);
+                self.set_forecast(watts - FORECAST_STEP);
+            }
+            Event::ForecastMedian | Event::ForecastP90 => {
+                if let Some(summary) = self.views[self.tabs.index()].session_power() {
+                    let watts = match event {
+                        Event::ForecastMedian => summary.p50,
+                        _ => summary.p90,
+                    };
+                    self.set_forecast(watts);
+                }
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    pub fn views(&self) -> &[View] {
+        self.views.as_ref()
+    }
+
     pub fn views_mut(&mut self) -> &mut [View] {
         self.views.as_mut()
     }
 
-    pub fn tabs_mut(&mut self) -> &mut TabBar {
-        &mut self.tabs
+    /// Add the tab for the device connected after the start, at its place in the configured order
+    pub fn add_view(&mut self, view: View) {
+        let index = {
+            let order = self.config.order();
+            self.views
+                .iter()
+                .position(|other| order.compare(view.battery(), other.battery()) == Ordering::Less)
+                .unwrap_or(self.views.len())
+        };
+        self.tabs.insert(index, view.title());
+        self.views.insert(index, view);
+    }
+
+    pub fn set_page(&mut self, page: Page) {
+        if page == Page::Comparison {
+            // Histories are changing slowly, no need to re-read them on each tick
+            self.comparisons = Comparison::all();
+        }
+        self.page = page;
+    }
+
+    pub fn set_attribution(&mut self, attribution: Attribution) {
+        self.attribution = Some(attribution);
+    }
+
+    /// Show the active power profile, charts are annotated if it was changed
+    pub fn set_profile(&mut self, profile: &str) {
+        if self.profile.is_some() {
+            for view in self.views.iter_mut() {
+                view.annotate_profile(profile);
+            }
+        }
+        self.profile = Some(profile.to_string());
+    }
+
+    /// Note shown in the status bar until the next one, ex. the configuration reload result
+    pub fn log<T: Into<String>>(&mut self, message: T) {
+        self.message = Some((SystemTime::now(), message.into()));
+    }
+
+    /// Apply the reloaded configuration to all tabs
+    pub fn set_config(&mut self, config: Arc<Config>) {
+        for (index, view) in self.views.iter_mut().enumerate() {
+            view.set_config(config.clone());
+            // Title might be overridden in the device section
+            self.tabs.set_title(index, view.title());
+        }
+        self.config = config;
+    }
+
+    /// Open the charge thresholds dialog for the selected battery, if its platform allows to change them
+    pub fn open_limits(&mut self) {
+        let view = &self.views[self.tabs.index()];
+        match view.battery().charge_limits() {
+            Some(limits) => self.dialog = Some(LimitsDialog::new(limits)),
+            None => warn!("Charge thresholds of {} can't be changed", view.battery().id()),
+        }
+    }
+
+    pub fn next_window(&mut self) {
+        self.window = (self.window + 1) % WINDOWS.len();
+    }
+
+    /// Power draw used for the runtime forecast for the selected battery.
+    ///
+    /// Median power of the discharging session is used until user picks some other value.
+    pub fn forecast(&self) -> Option<f64> {
+        self.forecast
+            .or_else(|| self.views[self.tabs.index()].session_power().map(|summary| summary.p50))
+    }
+
+    pub fn set_forecast(&mut self, watts: f64) {
+        self.forecast = Some(watts.max(0.0));
+    }
+}
+
+#[cfg(test)]
+impl Interface<tui::backend::TestBackend> {
+    /// Cells drawn so far
+    pub fn buffer(&self) -> &tui::buffer::Buffer {
+        self.terminal.backend().buffer()
     }
 }
@@ -1,13 +1,20 @@
 mod chart;
+mod dialog;
 mod interface;
+mod page;
 mod painter;
 mod tabs;
-mod units;
+mod theme;
 mod view;
 
+#[cfg(test)]
+mod tests;
+
 pub use self::chart::{ChartData, ChartType};
+pub use self::dialog::{Field, LimitsDialog};
 pub use self::interface::{init, Interface};
+pub use self::page::Page;
 pub use self::painter::{Context, Painter};
 pub use self::tabs::TabBar;
-pub use self::units::Units;
+pub use self::theme::{parse_color, Theme};
 pub use self::view::View;
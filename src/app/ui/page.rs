@@ -0,0 +1,16 @@
+/// Content displayed for the selected battery
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Page {
+    /// Current battery state with real-time charts
+    Overview,
+    /// Long-term battery health and degradation trend
+    Health,
+    /// Statistics over the samples collected during this run
+    Statistics,
+    /// Long-term summaries of all batteries battop has history for
+    Comparison,
+    /// Processes most responsible for the power draw
+    Processes,
+    /// Text panels defined by the user scripts
+    Scripts,
+}
@@ -33,14 +33,18 @@
 ///            \------------------ main window -------------------------------------------/
 /// ```
 use std::borrow::Cow;
+use std::fmt;
 use std::ops::Deref;
 use std::rc::Rc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use tui::backend::Backend;
+use tui::buffer::Buffer;
 use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
-use tui::widgets::{Axis, Block, Borders, Chart, Dataset, Gauge, Marker, Paragraph, Row, Table, Tabs, Text, Widget};
+use tui::widgets::{
+    Axis, BarChart, Block, Borders, Chart, Dataset, Gauge, Marker, Paragraph, Row, Table, Tabs, Text, Widget,
+};
 use tui::Frame;
 
 use battery::units::electric_potential::volt;
@@ -49,14 +53,45 @@ use battery::units::power::watt;
 use battery::units::ratio::{percent, ratio};
 use battery::units::thermodynamic_temperature::{degree_celsius, kelvin};
 use battery::units::time::second;
-use battery::units::Unit;
 use battery::State;
 
-use super::{ChartData, TabBar, Units, View};
+use battop_core::format::{self, Units};
+use battop_core::history::{battery_key, SessionKind};
+use battop_core::processes::Attribution;
+use battop_core::stats::{
+    cost, equivalent_cycles, histogram, monthly_charging, Band, Calibration, Comparison, Degradation, Divergence,
+    Efficiency, Rolling, SleepDrain, Summary, Thermal, DAY, MIN_SPAN_DAYS, STRONG_CORRELATION, WEEK,
+};
+
+use super::{ChartData, Field, LimitsDialog, Page, TabBar, View};
+use crate::app::config::Part;
+
+/// Health thresholds with the extrapolated dates shown on the health page
+const HEALTH_THRESHOLDS: [f64; 2] = [80.0, 60.0];
+/// Amount of points used to draw the trend line
+const TREND_RESOLUTION: usize = 128;
+/// Amount of bars in the power distribution histogram
+const HISTOGRAM_BUCKETS: usize = 10;
+/// How many recent months are shown in the charging cost table
+const COST_MONTHS: usize = 6;
+/// How many processes are listed at most
+const PROCESSES_LIMIT: usize = 50;
+/// Difference between the kelvin and the degree Celsius scales
+const KELVIN_OFFSET: f64 = 273.15;
+/// Space above and below the cell voltage lines, V
+const CELL_VOLTAGE_MARGIN: f64 = 0.01;
 
 #[derive(Debug)]
 pub struct Context<'i> {
     pub tabs: &'i TabBar,
+    pub page: Page,
+    pub window: Duration,
+    pub forecast: Option<f64>,
+    pub comparisons: &'i [Comparison],
+    pub attribution: Option<&'i Attribution>,
+    pub profile: Option<&'i str>,
+    pub message: Option<(SystemTime, &'i str)>,
+    pub dialog: Option<&'i LimitsDialog>,
     pub view: &'i View,
 }
 
@@ -75,11 +110,110 @@ impl<'i> Painter<'i> {
                 [
                     Constraint::Length(3), // Tabs
                     Constraint::Min(10),   // Main window
+                    Constraint::Length(if self.profile.is_some() || self.message.is_some() {
+                        1
+                    } else {
+                        0
+                    }), // Status bar
                 ]
                 .as_ref(),
             )
             .split(frame.size());
 
+        self.draw_tabs(&mut frame, main[0]);
+        match self.page {
+            Page::Overview => self.draw_overview(&mut frame, main[1]),
+            Page::Health => self.draw_health(&mut frame, main[1]),
+            Page::Statistics => self.draw_statistics(&mut frame, main[1]),
+            Page::Comparison => self.draw_comparison(&mut frame, main[1]),
+            Page::Processes => self.draw_processes(&mut frame, main[1]),
+            Page::Scripts => self.draw_scripts(&mut frame, main[1]),
+        }
+        self.draw_status_bar(&mut frame, main[2]);
+        if let Some(dialog) = self.dialog {
+            let size = frame.size();
+            self.draw_limits_dialog(dialog, &mut frame, size);
+        }
+    }
+
+    /// Dialog is drawn in the middle of the `area`, over everything else
+    fn draw_limits_dialog<B: Backend>(&self, dialog: &LimitsDialog, frame: &mut Frame<B>, area: Rect) {
+        let width = 52.min(area.width);
+        let height = 10.min(area.height);
+        let area = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let selected = Style::default().fg(Color::Black).bg(Color::Cyan);
+        let field = |name: &str, value: Option<u8>, this: Field| {
+            let (value, style) = match value {
+                Some(value) if dialog.field() == this => (format!(" {:>3} % ", value), selected),
+                Some(value) => (format!(" {:>3} % ", value), Style::default()),
+                None => (format!(" {}", self.text("na")), Style::default()),
+            };
+            vec![
+                Text::Raw(Cow::from(format!("{:<24}", name))),
+                Text::Styled(Cow::from(value), style),
+                Text::Raw(Cow::from("\n")),
+            ]
+        };
+        let mut text = field(self.text("limits.start"), dialog.start(), Field::Start);
+        text.extend(field(self.text("limits.stop"), Some(dialog.stop()), Field::Stop));
+        text.push(Text::Raw(Cow::from("\n")));
+        if let Some(message) = dialog.message() {
+            text.push(Text::Styled(
+                Cow::from(format!("{}\n", message)),
+                Style::default().fg(Color::Red),
+            ));
+        } else if dialog.is_confirming() {
+            text.push(Text::Styled(
+                Cow::from(format!("{}\n", self.text("limits.confirm"))),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        text.push(Text::Styled(
+            Cow::from(self.text("limits.help")),
+            Style::default().fg(Color::DarkGray),
+        ));
+
+        let title = format!(" {} ", self.text("limits.title"));
+        Clear.render(frame, area);
+        Paragraph::new(text.iter())
+            .block(
+                Block::default()
+                    .title(&title)
+                    .title_style(Style::default())
+                    .borders(Borders::ALL),
+            )
+            .wrap(true)
+            .render(frame, area);
+    }
+
+    fn draw_status_bar<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let mut text = Vec::new();
+        if let Some(profile) = self.profile {
+            text.push(Text::Raw(Cow::from(format!(" {} ", self.text("status.profile")))));
+            text.push(Text::Styled(Cow::from(profile), Style::default().fg(Color::Cyan)));
+            text.push(Text::Styled(
+                Cow::from(format!(" {}", self.text("status.profile_switch"))),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        if let Some((time, message)) = self.message {
+            text.push(Text::Styled(
+                Cow::from(format!(" {} ", format::time(time))),
+                Style::default().fg(Color::DarkGray),
+            ));
+            text.push(Text::Styled(Cow::from(message), Style::default().fg(Color::Yellow)));
+        }
+
+        Paragraph::new(text.iter()).render(frame, area);
+    }
+
+    fn draw_overview<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
         // Left column with info and right column with graphs
         let main_columns = Layout::default()
             .direction(Direction::Horizontal)
@@ -90,7 +224,7 @@ impl<'i> Painter<'i> {
                 ]
                 .as_ref(),
             )
-            .split(main[1]);
+            .split(area);
 
         // Percentage bar and information table
         let left_column = Layout::default()
@@ -99,7 +233,7 @@ impl<'i> Painter<'i> {
                 [
                     Constraint::Length(3),  // percentage bar
                     Constraint::Length(10), // common info
-                    Constraint::Length(9),  // energy stuff
+                    Constraint::Length(10), // energy stuff
                     Constraint::Length(5),  // timings
                     Constraint::Min(4),     // environment
                 ]
@@ -107,43 +241,395 @@ impl<'i> Painter<'i> {
             )
             .split(main_columns[0]);
 
-        // Graphs
+        // Graphs, the ones disabled in the configuration file give their room to the rest
+        let view = self.view;
+        let charts = &view.settings().charts;
+        let mut graphs = Vec::with_capacity(3);
+        if charts.voltage {
+            graphs.push(if view.reports_voltage() {
+                view.voltage()
+            } else {
+                view.state_of_charge()
+            });
+        }
+        if charts.energy_rate {
+            graphs.push(view.energy_rate());
+        }
+        if charts.temperature {
+            graphs.push(view.temperature());
+        }
+        let right_column = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Ratio(1, graphs.len().max(1) as u32); graphs.len()])
+            .split(main_columns[1]);
+
+        // Drawing all the things now!
+        self.draw_state_of_charge_bar(frame, left_column[0]);
+        self.draw_common_info(frame, left_column[1]);
+        self.draw_energy_info(frame, left_column[2]);
+        self.draw_timing_info(frame, left_column[3]);
+        self.draw_environment_info(frame, left_column[4]);
+        for (graph, area) in graphs.iter().zip(right_column) {
+            self.draw_chart(graph, frame, area);
+        }
+    }
+
+    fn draw_health<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Length(40), // Information
+                    Constraint::Min(20),    // Degradation graph
+                ]
+                .as_ref(),
+            )
+            .split(area);
+
+        let identity = self.identity_items();
+        let identity_height = if identity.is_empty() {
+            0
+        } else {
+            identity.len() as u16 + 3
+        };
+        let left_column = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(13),              // degradation info
+                    Constraint::Length(identity_height), // pack identity
+                    Constraint::Min(8),                  // capacity comparison
+                ]
+                .as_ref(),
+            )
+            .split(columns[0]);
+
         let right_column = Layout::default()
             .direction(Direction::Vertical)
             .constraints(
                 [
-                    Constraint::Percentage(33), // Voltage
-                    Constraint::Percentage(33), // Consumption
-                    Constraint::Percentage(34), // Temperature
+                    Constraint::Percentage(50), // degradation chart
+                    Constraint::Percentage(50), // temperature vs power
                 ]
                 .as_ref(),
             )
-            .split(main_columns[1]);
+            .split(columns[1]);
 
-        // Drawing all the things now!
-        self.draw_tabs(&mut frame, main[0]);
-        self.draw_state_of_charge_bar(&mut frame, left_column[0]);
-        self.draw_common_info(&mut frame, left_column[1]);
-        self.draw_energy_info(&mut frame, left_column[2]);
-        self.draw_timing_info(&mut frame, left_column[3]);
-        self.draw_environment_info(&mut frame, left_column[4]);
-        self.draw_chart(&self.view.voltage(), &mut frame, right_column[0]);
-        self.draw_chart(&self.view.energy_rate(), &mut frame, right_column[1]);
-        self.draw_chart(&self.view.temperature(), &mut frame, right_column[2]);
+        let degradation = Degradation::new(self.view.history().health().records());
+        self.draw_degradation_info(degradation.as_ref(), frame, left_column[0]);
+        if !identity.is_empty() {
+            let title = format!(" {} ", self.text("health.pack"));
+            let block = Block::default()
+                .title(&title)
+                .title_style(Style::default())
+                .borders(Borders::LEFT | Borders::TOP | Borders::RIGHT);
+            let items = identity
+                .iter()
+                .map(|[key, value]| [key.as_str(), value.as_str()])
+                .collect::<Vec<_>>();
+            self.draw_info_table([self.text("health.identity"), ""], &items, block, frame, left_column[1]);
+        }
+        self.draw_capacity_comparison(frame, left_column[2]);
+        self.draw_degradation_chart(degradation.as_ref(), frame, right_column[0]);
+        if self.view.cells().is_empty() {
+            self.draw_thermal_chart(frame, right_column[1]);
+        } else {
+            let bottom = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                .split(right_column[1]);
+            self.draw_thermal_chart(frame, bottom[0]);
+            self.draw_cells_chart(frame, bottom[1]);
+        }
+    }
+
+    fn draw_statistics<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Length(40), // Statistics
+                    Constraint::Min(20),    // Distribution
+                ]
+                .as_ref(),
+            )
+            .split(area);
+
+        let left_column = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(11), // power summary
+                    Constraint::Length(9),  // charging speed
+                    Constraint::Length(8),  // rolling summaries
+                    Constraint::Min(6),     // charging cost
+                ]
+                .as_ref(),
+            )
+            .split(columns[0]);
+
+        let right_column = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Min(8),    // power distribution
+                    Constraint::Length(8), // load bands
+                    Constraint::Length(8), // runtime forecast
+                ]
+                .as_ref(),
+            )
+            .split(columns[1]);
+
+        // Only the discharging samples are representing the actual power draw, not counting the ones after a suspend
+        let power = self
+            .view
+            .history()
+            .samples()
+            .window(self.window)
+            .filter(|sample| sample.state == State::Discharging && !sample.resumed)
+            .map(|sample| f64::from(sample.energy_rate))
+            .collect::<Vec<_>>();
+        let summary = Summary::new(power.iter().cloned());
+
+        self.draw_power_summary(summary.as_ref(), frame, left_column[0]);
+        self.draw_charging_speed(frame, left_column[1]);
+        self.draw_rolling(frame, left_column[2]);
+        self.draw_charging_cost(frame, left_column[3]);
+        self.draw_power_distribution(&power, summary.as_ref(), frame, right_column[0]);
+        self.draw_load_bands(frame, right_column[1]);
+        self.draw_forecast(frame, right_column[2]);
+    }
+
+    /// Health and discharge rates of all batteries with the persisted history
+    fn draw_comparison<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let title = format!(" {} ", self.text("comparison.title"));
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+
+        let na = self.text("na");
+        let selected = battery_key(self.view.battery());
+        let percents = |value: Option<f64>| match value {
+            Some(value) => format!("{:.2} %", value),
+            None => na.to_string(),
+        };
+        let watts = |value: f64| format!("{:.2} {}", value, format::WATT);
+
+        let rows = self
+            .comparisons
+            .iter()
+            .map(|comparison| {
+                let (since, health, loss) = match comparison.degradation {
+                    Some(ref degradation) => (
+                        format::date(degradation.since()),
+                        percents(degradation.current()),
+                        percents(degradation.yearly_loss()),
+                    ),
+                    None => (na.to_string(), na.to_string(), na.to_string()),
+                };
+                let (p50, p90) = match comparison.discharge {
+                    Some(summary) => (watts(summary.p50), watts(summary.p90)),
+                    None => (na.to_string(), na.to_string()),
+                };
+                let efficiency = self.format_efficiency(comparison.efficiency.as_ref());
+                let style = if comparison.key == selected {
+                    Style::default().modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                let row = [
+                    comparison.key.clone(),
+                    since,
+                    health,
+                    loss,
+                    p50,
+                    p90,
+                    efficiency,
+                    comparison.sessions.to_string(),
+                ];
+                (row, style)
+            })
+            .collect::<Vec<_>>();
+
+        let header = [
+            self.text("comparison.battery"),
+            self.text("comparison.tracked_since"),
+            self.text("comparison.health"),
+            self.text("comparison.loss_per_year"),
+            self.text("comparison.p50"),
+            self.text("comparison.p90"),
+            self.text("comparison.round_trip"),
+            self.text("comparison.sessions"),
+        ];
+        let rows = rows.iter().map(|(row, style)| Row::StyledData(row.iter(), *style));
+
+        Table::new(header.iter(), rows)
+            .header_style(Style::default().modifier(Modifier::BOLD))
+            .block(block)
+            .widths(&[30, 14, 10, 14, 14, 14, 18, 9])
+            .render(frame, area);
+    }
+
+    /// Text panels of the user scripts, evaluated over the samples of the selected battery
+    fn draw_scripts<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let title = format!(" {} ", self.text("scripts.title"));
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+
+        let panels = &self.view.config().scripts().panels;
+        if panels.is_empty() {
+            Paragraph::new([Text::raw(self.text("scripts.empty"))].iter())
+                .block(block)
+                .wrap(true)
+                .render(frame, area);
+            return;
+        }
+
+        let store = self.view.history().samples();
+        let mut text = Vec::with_capacity(panels.len() * 2);
+        for panel in panels.iter() {
+            let value = panel
+                .parts
+                .iter()
+                .map(|part| match part {
+                    Part::Text(text) => text.clone(),
+                    Part::Expr(expr) => match expr.eval(store) {
+                        Some(value) => format!("{:.2}", value),
+                        None => self.text("na").to_string(),
+                    },
+                })
+                .collect::<String>();
+            text.push(Text::styled(
+                format!("{}: ", panel.title),
+                Style::default().modifier(Modifier::BOLD),
+            ));
+            text.push(Text::raw(format!("{}\n", value)));
+        }
+
+        Paragraph::new(text.iter()).block(block).wrap(true).render(frame, area);
+    }
+
+    /// Processes with the biggest share of the CPU time and their estimated power draw
+    fn draw_processes<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let attribution = match self.attribution {
+            Some(attribution) => attribution,
+            None => {
+                let title = format!(" {} ", self.text("processes.title"));
+                let block = Block::default()
+                    .title(&title)
+                    .title_style(Style::default())
+                    .borders(Borders::ALL);
+                let message = if self.view.config().processes() {
+                    self.text("processes.collecting")
+                } else {
+                    self.text("processes.disabled")
+                };
+                Paragraph::new([Text::raw(message)].iter())
+                    .block(block)
+                    .wrap(true)
+                    .render(frame, area);
+                return;
+            }
+        };
+
+        // RAPL measures CPU package only, while battery power includes display and everything else
+        let (source, power) = match attribution.package_power {
+            Some(power) => (self.text("processes.package"), Some(power)),
+            None if self.view.battery().state() == State::Discharging => (
+                self.text("processes.battery"),
+                Some(f64::from(self.view.battery().energy_rate().get::<watt>())),
+            ),
+            None => (self.text("processes.battery"), None),
+        };
+        let title = match power {
+            Some(power) => self.message(
+                "processes.share",
+                &[
+                    ("source", &source),
+                    ("power", &format!("{:.2} {}", power, format::WATT)),
+                ],
+            ),
+            None => self.message("processes.share_unknown", &[("source", &source)]),
+        };
+        let title = format!(" {} ", title);
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+
+        let rows = attribution
+            .processes
+            .iter()
+            .take(PROCESSES_LIMIT)
+            .map(|process| {
+                let power = match power {
+                    Some(power) => format!("{:.2} {}", power * process.share, format::WATT),
+                    None => self.text("na").to_string(),
+                };
+                [
+                    process.pid.to_string(),
+                    process.name.clone(),
+                    format!("{:.1} %", process.share * 100.0),
+                    power,
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let header = [
+            self.text("processes.pid"),
+            self.text("processes.name"),
+            self.text("processes.cpu_share"),
+            self.text("processes.power"),
+        ];
+        let rows = rows.iter().map(|row| Row::Data(row.iter()));
+
+        Table::new(header.iter(), rows)
+            .header_style(Style::default().modifier(Modifier::BOLD))
+            .block(block)
+            .widths(&[8, 24, 10, 12])
+            .render(frame, area);
     }
 
     pub fn draw_tabs<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let titles = self
+            .tabs
+            .titles()
+            .iter()
+            .enumerate()
+            .map(|(index, title)| {
+                if !self.tabs.is_connected(index) {
+                    self.message("tabs.disconnected", &[("title", title)])
+                } else if self.tabs.is_stale(index) {
+                    self.message("tabs.stale", &[("title", title)])
+                } else {
+                    title.clone()
+                }
+            })
+            .collect::<Vec<_>>();
+        // Tabs widget is not able to style the titles separately
+        let highlight = if !self.tabs.is_connected(self.tabs.index()) {
+            Color::DarkGray
+        } else if self.tabs.is_stale(self.tabs.index()) {
+            Color::Yellow
+        } else {
+            Color::White
+        };
+
+        let title = format!(" {} ", self.text("tabs.title"));
         Tabs::default()
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title(" Batteries ") // Note that spaces are intentional in here
+                    .title(&title)
                     .title_style(Style::default()),
             )
-            .titles(self.tabs.titles())
+            .titles(&titles)
             .select(self.tabs.index())
             .style(Style::default().fg(Color::Cyan))
-            .highlight_style(Style::default().fg(Color::White))
+            .highlight_style(Style::default().fg(highlight))
             .render(frame, area);
     }
 
@@ -152,8 +638,9 @@ impl<'i> Painter<'i> {
         let value_label = f64::from(self.view.battery().state_of_charge().get::<percent>());
 
         // create blocks for gauge and text
+        let title = format!(" {} ", self.text("overview.state_of_charge"));
         let gauge_block = Block::default()
-            .title(" State of charge ")
+            .title(&title)
             .title_style(Style::default())
             .borders(Borders::ALL & !Borders::RIGHT);
         let text_block = Block::default().borders(Borders::ALL & !Borders::LEFT);
@@ -191,7 +678,7 @@ impl<'i> Painter<'i> {
             .block(gauge_block)
             .ratio(value)
             .style(Style::default().bg(Color::Black).fg(gauge_color))
-            .label(&"")
+            .label("")
             .render(frame, gauge_area);
         Paragraph::new(text.iter())
             .block(text_block)
@@ -200,12 +687,20 @@ impl<'i> Painter<'i> {
     }
 
     pub fn draw_chart<B: Backend>(&self, data: &ChartData, frame: &mut Frame<B>, area: Rect) {
-        let title = format!(" {} ", data.title());
+        let store = self.view.history().samples();
+        let mut title = match data.source() {
+            Some(source) => format!(" {} ({})", data.title(), source),
+            None => format!(" {}", data.title()),
+        };
+        if let Some(annotation) = data.annotation(store) {
+            title.push_str(&format!(" [{}]", annotation));
+        }
+        title.push(' ');
         let block = Block::default()
             .title(&title)
             .title_style(Style::default())
             .borders(Borders::ALL);
-        let value = data.current();
+        let value = data.current(store);
         // tui automatically hides chart legend if it's height is higher than `chart.height / 3`.
         // Since we have 3 charts already, legend will be invisible for most monitors,
         // so instead writing value as a X axis label
@@ -213,45 +708,106 @@ impl<'i> Painter<'i> {
             .title(&value)
             .style(Style::default().fg(Color::Reset))
             .bounds(data.x_bounds());
-        let y_labels = data.y_labels();
+        let y_labels = data.y_labels(store);
         let y_axis: Axis<String> = Axis::default()
             .title(data.y_title())
             .labels(&y_labels)
-            .bounds(data.y_bounds());
+            .bounds(data.y_bounds(store));
+
+        let annotations = data.annotation_points(store);
+        let overlays = data.overlay_points(store);
+        let secondaries = data.secondary_points(store);
+        let points = data.points(store);
+        let markers = data.markers(store);
+        let theme = &self.view.settings().theme;
+        let mut datasets = overlays
+            .iter()
+            .zip(theme.overlays.iter().cycle())
+            .map(|(points, color)| {
+                Dataset::default()
+                    .marker(Marker::Braille)
+                    .style(Style::default().fg(*color))
+                    .data(points)
+            })
+            .collect::<Vec<_>>();
+        datasets.insert(
+            0,
+            Dataset::default()
+                .marker(Marker::Braille)
+                .style(Style::default().fg(theme.annotations))
+                .data(&annotations),
+        );
+        for (index, (points, color)) in secondaries.iter().zip(theme.secondary.iter().cycle()).enumerate() {
+            datasets.insert(
+                1 + index,
+                Dataset::default()
+                    .marker(Marker::Braille)
+                    .style(Style::default().fg(*color))
+                    .data(points),
+            );
+        }
+        // Main series and anomaly markers are drawn over the overlays and annotations
+        datasets.push(
+            Dataset::default()
+                .marker(Marker::Braille)
+                .style(Style::default().fg(theme.main))
+                .data(&points),
+        );
+        datasets.push(
+            Dataset::default()
+                .marker(Marker::Dot)
+                .style(Style::default().fg(theme.markers))
+                .data(&markers),
+        );
 
         Chart::default()
             .block(block)
             .x_axis(x_axis)
             .y_axis(y_axis)
-            .datasets(&[Dataset::default()
-                .marker(Marker::Braille)
-                .style(Style::default().fg(Color::Green))
-                .data(data.points())])
+            .datasets(&datasets)
             .render(frame, area)
     }
 
     fn draw_common_info<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let title = format!(" {} ", self.text("overview.information"));
         let block = Block::default()
-            .title(" Information ") // Note that spaces are intentional
+            .title(&title)
             .title_style(Style::default())
             .borders(Borders::LEFT | Borders::TOP | Borders::RIGHT);
 
-        let tech = &format!("{}", self.view.battery().technology());
-        let state = &format!("{}", self.view.battery().state());
+        let na = self.text("na");
+        let tech = format::technology(self.view.battery().technology()).unwrap_or(na);
+        let state = &if self.view.battery().charging_held() {
+            self.text("state.on_hold").to_string()
+        } else {
+            self.view.config().locale().name("state", self.view.battery().state())
+        };
         let cycles = &match self.view.battery().cycle_count() {
             Some(cycles) => format!("{}", cycles),
-            None => "N/A".to_string(),
+            None => na.to_string(),
+        };
+        let capacity = match self.view.battery().energy_full_design().get::<watt_hour>() {
+            design if design > 0.0 => design,
+            _ => self.view.battery().energy_full().get::<watt_hour>(),
+        };
+        let equivalent = &match equivalent_cycles(self.view.history().discharged(), f64::from(capacity)) {
+            Some(cycles) => format!("{:.2}", cycles),
+            None => na.to_string(),
         };
 
         let items = vec![
-            ["Vendor", self.view.battery().vendor().unwrap_or("N/A")],
-            ["Model", self.view.battery().model().unwrap_or("N/A")],
-            ["S/N", self.view.battery().serial_number().unwrap_or("N/A")],
-            ["Technology", tech],
-            ["Charge state", state],
-            ["Cycles count", cycles],
+            [self.text("overview.vendor"), self.view.battery().vendor().unwrap_or(na)],
+            [self.text("overview.model"), self.view.battery().model().unwrap_or(na)],
+            [
+                self.text("overview.serial_number"),
+                self.view.battery().serial_number().unwrap_or(na),
+            ],
+            [self.text("overview.technology"), tech],
+            [self.text("overview.charge_state"), state],
+            [self.text("overview.cycles"), cycles],
+            [self.text("overview.equivalent_cycles"), equivalent],
         ];
-        let header = ["Device", ""];
+        let header = [self.text("overview.device"), ""];
 
         self.draw_info_table(header, &items, block, frame, area);
     }
@@ -261,56 +817,66 @@ impl<'i> Painter<'i> {
         let battery = self.view.battery();
         let config = self.view.config();
 
-        let consumption = &format!("{:.2} {}", battery.energy_rate().get::<watt>(), watt::abbreviation());
-        let voltage = &format!("{:.2} {}", battery.voltage().get::<volt>(), volt::abbreviation());
-        let capacity = &format!(
-            "{:.2} {}",
-            battery.state_of_health().get::<percent>(),
-            percent::abbreviation()
-        );
+        let consumption = &format!("{:.2} {}", battery.energy_rate().get::<watt>(), format::WATT);
+        let voltage = &format!("{:.2} {}", battery.voltage().get::<volt>(), format::VOLT);
+        let capacity = &format!("{:.2} {}", battery.state_of_health().get::<percent>(), format::PERCENT);
         let current = &match config.units() {
-            Units::Human => format!(
-                "{:.2} {}",
-                battery.energy().get::<watt_hour>(),
-                watt_hour::abbreviation()
-            ),
-            Units::Si => format!("{:.2} {}", battery.energy().get::<joule>(), joule::abbreviation()),
+            Units::Human => format!("{:.2} {}", battery.energy().get::<watt_hour>(), format::WATT_HOUR),
+            Units::Si => format!("{:.2} {}", battery.energy().get::<joule>(), format::JOULE),
         };
         let last_full = &match config.units() {
-            Units::Human => format!(
-                "{:.2} {}",
-                battery.energy_full().get::<watt_hour>(),
-                watt_hour::abbreviation()
-            ),
-            Units::Si => format!("{:.2} {}", battery.energy_full().get::<joule>(), joule::abbreviation()),
+            Units::Human => format!("{:.2} {}", battery.energy_full().get::<watt_hour>(), format::WATT_HOUR),
+            Units::Si => format!("{:.2} {}", battery.energy_full().get::<joule>(), format::JOULE),
         };
         let full_design = &match config.units() {
             Units::Human => format!(
                 "{:.2} {}",
                 battery.energy_full_design().get::<watt_hour>(),
-                watt_hour::abbreviation()
+                format::WATT_HOUR
             ),
-            Units::Si => format!(
-                "{:.2} {}",
-                battery.energy_full_design().get::<joule>(),
-                joule::abbreviation()
+            Units::Si => format!("{:.2} {}", battery.energy_full_design().get::<joule>(), format::JOULE),
+        };
+        let na = self.text("na");
+        let charging = &match self.view.charging() {
+            Some(ref charging) if charging.weak_charger => self.text("overview.weak_charger").to_string(),
+            Some(charging) => format!(
+                "{} ({:.2}C)",
+                config.locale().name("speed", charging.speed),
+                charging.c_rate
             ),
+            None => na.to_string(),
+        };
+        let capacity_wh = f64::from(battery.energy_full().get::<watt_hour>());
+        let divergence = &match self.view.history().sessions().current() {
+            Some(session) => match Divergence::new(session, capacity_wh) {
+                Some(divergence) => self.message(
+                    "overview.divergence_value",
+                    &[
+                        ("difference", &format!("{:+.1}", divergence.difference())),
+                        ("counted", &format!("{:.1}", divergence.counted * 100.0)),
+                    ],
+                ),
+                None => na.to_string(),
+            },
+            None => na.to_string(),
         };
         let consumption_label = match battery.state() {
-            State::Charging => "Charging with",
-            State::Discharging => "Discharging with",
-            _ => "Consumption",
+            State::Charging => self.text("chart.charging"),
+            State::Discharging => self.text("chart.discharging"),
+            _ => self.text("chart.consumption"),
         };
 
         let items = vec![
             [consumption_label, consumption],
-            ["Voltage", voltage],
-            ["Capacity", capacity],
-            ["Current", current],
-            ["Last full", last_full],
-            ["Full design", full_design],
+            [self.text("overview.voltage"), voltage],
+            [self.text("overview.capacity"), capacity],
+            [self.text("overview.current"), current],
+            [self.text("overview.last_full"), last_full],
+            [self.text("overview.full_design"), full_design],
+            [self.text("overview.charging_speed"), charging],
+            [self.text("overview.divergence"), divergence],
         ];
-        let header = ["Energy", ""];
+        let header = [self.text("overview.energy"), ""];
 
         self.draw_info_table(header, &items, block, frame, area);
     }
@@ -321,16 +887,19 @@ impl<'i> Painter<'i> {
 
         let time_to_full = &match battery.time_to_full() {
             Some(time) => humantime::format_duration(Duration::from_secs(time.get::<second>() as u64)).to_string(),
-            None => "N/A".to_string(),
+            None => self.text("na").to_string(),
         };
 
         let time_to_empty = &match battery.time_to_empty() {
             Some(time) => humantime::format_duration(Duration::from_secs(time.get::<second>() as u64)).to_string(),
-            None => "N/A".to_string(),
+            None => self.text("na").to_string(),
         };
 
-        let items = vec![["Time to full", time_to_full], ["Time to empty", time_to_empty]];
-        let header = ["Time", ""];
+        let items = vec![
+            [self.text("overview.time_to_full"), time_to_full],
+            [self.text("overview.time_to_empty"), time_to_empty],
+        ];
+        let header = [self.text("overview.time"), ""];
 
         self.draw_info_table(header, &items, block, frame, area);
     }
@@ -342,18 +911,791 @@ impl<'i> Painter<'i> {
 
         let temperature = &match battery.temperature() {
             Some(temp) => match config.units() {
-                Units::Human => format!("{:.2} {}", temp.get::<degree_celsius>(), degree_celsius::abbreviation()),
-                Units::Si => format!("{:.2} {}", temp.get::<kelvin>(), kelvin::abbreviation()),
+                Units::Human => format!("{:.2} {}", temp.get::<degree_celsius>(), format::DEGREE_CELSIUS),
+                Units::Si => format!("{:.2} {}", temp.get::<kelvin>(), format::KELVIN),
             },
-            None => "N/A".to_string(),
+            None => self.text("na").to_string(),
         };
 
-        let items = vec![["Temperature", temperature]];
-        let header = ["Environment", ""];
+        let system = self.view.system_temperature().map(|reading| match config.units() {
+            Units::Human => format!("{:.2} {} ({})", reading.celsius, format::DEGREE_CELSIUS, reading.name),
+            Units::Si => format!(
+                "{:.2} {} ({})",
+                f64::from(reading.celsius) + KELVIN_OFFSET,
+                format::KELVIN,
+                reading.name
+            ),
+        });
+
+        let adapter = self.view.adapter().map(|adapter| {
+            let negotiated = match (adapter.voltage, adapter.current) {
+                (Some(voltage), Some(current)) => Some(format!("{:.2} V {:.2} A", voltage, current)),
+                _ => None,
+            };
+            let power = match (adapter.watts, self.view.adapter_insufficient()) {
+                (Some(watts), true) => Some(self.message(
+                    "overview.adapter_too_low",
+                    &[("power", &format!("{:.0} {}", watts, format::WATT))],
+                )),
+                (Some(watts), false) => Some(format!("{:.0} {}", watts, format::WATT)),
+                (None, true) => Some(self.text("overview.too_low").to_string()),
+                (None, false) => None,
+            };
+            (adapter.name.as_str(), negotiated, power)
+        });
+
+        let mut items = vec![[self.text("overview.temperature"), temperature.as_str()]];
+        if let Some(ref system) = system {
+            items.push([self.text("overview.system"), system.as_str()]);
+        }
+        if let Some((name, ref negotiated, ref power)) = adapter {
+            items.push([self.text("overview.adapter"), name]);
+            if let Some(power) = power {
+                items.push([self.text("overview.adapter_power"), power.as_str()]);
+            }
+            if let Some(negotiated) = negotiated {
+                items.push([self.text("overview.negotiated"), negotiated.as_str()]);
+            }
+        }
+        // Whatever else the backend knows about the device, ex. charge thresholds
+        for (name, value) in battery.attributes() {
+            items.push([name.as_str(), value.as_str()]);
+        }
+        let header = [self.text("overview.environment"), ""];
 
         self.draw_info_table(header, &items, block, frame, area);
     }
 
+    /// Pack identity rows, empty if backend knows nothing about it
+    fn identity_items(&self) -> Vec<[String; 2]> {
+        let identity = self.view.battery().identity();
+        let mut items = vec![];
+        if let Some(date) = identity.manufactured {
+            items.push([self.text("health.manufactured").to_string(), date.to_string()]);
+        }
+        if let Some(age) = identity.age() {
+            items.push([self.text("health.age").to_string(), format::age(age)]);
+        }
+        if let Some(ref firmware) = identity.firmware {
+            items.push([self.text("health.firmware").to_string(), firmware.clone()]);
+        }
+        if let Some(ref chemistry) = identity.chemistry {
+            items.push([self.text("health.chemistry").to_string(), chemistry.clone()]);
+        }
+
+        items
+    }
+
+    fn draw_degradation_info<B: Backend>(&self, degradation: Option<&Degradation>, frame: &mut Frame<B>, area: Rect) {
+        let title = format!(" {} ", self.text("health.degradation"));
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::LEFT | Borders::TOP | Borders::RIGHT);
+
+        let na = self.text("na");
+        let mut items = vec![];
+        match degradation {
+            None => items.push([self.text("health.tracked_since").to_string(), na.to_string()]),
+            Some(degradation) => {
+                items.push([
+                    self.text("health.tracked_since").to_string(),
+                    format::date(degradation.since()),
+                ]);
+                items.push([
+                    self.text("health.history").to_string(),
+                    self.message("health.days", &[("days", &degradation.span_days().floor())]),
+                ]);
+                if let Some(current) = degradation.current() {
+                    items.push([self.text("health.health").to_string(), format!("{:.2} %", current)]);
+                }
+                let loss = match degradation.yearly_loss() {
+                    Some(loss) => format!("{:.2} %", loss),
+                    None => self.message("health.loss_unknown", &[("days", &MIN_SPAN_DAYS.round())]),
+                };
+                items.push([self.text("health.loss_per_year").to_string(), loss]);
+                let current = degradation.current().unwrap_or(100.0);
+                for threshold in HEALTH_THRESHOLDS.iter() {
+                    let value = match degradation.projected(*threshold) {
+                        _ if current <= *threshold => self.text("health.reached").to_string(),
+                        Some(date) => format::date(date),
+                        None => na.to_string(),
+                    };
+                    items.push([
+                        self.message("health.threshold", &[("percent", &threshold.round())]),
+                        value,
+                    ]);
+                }
+            }
+        }
+
+        let capacity = f64::from(self.view.battery().energy_full().get::<watt_hour>());
+        match Calibration::new(self.view.history().sessions().records(), capacity) {
+            Some(calibration) => {
+                items.push([
+                    self.text("health.gauge_drift").to_string(),
+                    self.message(
+                        "health.gauge_drift_value",
+                        &[
+                            ("drift", &format!("{:+.1}", calibration.drift * 100.0)),
+                            ("sessions", &calibration.sessions),
+                        ],
+                    ),
+                ]);
+                let status = if calibration.recommended() {
+                    self.text("health.recommended")
+                } else {
+                    self.text("health.not_needed")
+                };
+                items.push([self.text("health.calibration").to_string(), status.to_string()]);
+            }
+            None => items.push([self.text("health.gauge_drift").to_string(), na.to_string()]),
+        }
+        items.push([
+            self.text("health.round_trip").to_string(),
+            self.format_efficiency(Efficiency::new(self.view.history().sessions().records()).as_ref()),
+        ]);
+        let sleep = match SleepDrain::new(self.view.history().sleeps().records()) {
+            Some(drain) => self.message(
+                "health.sleep_drain_value",
+                &[("rate", &format!("{:.2}", drain.rate)), ("sleeps", &drain.count)],
+            ),
+            None => na.to_string(),
+        };
+        items.push([self.text("health.sleep_drain").to_string(), sleep]);
+
+        let items = items
+            .iter()
+            .map(|[key, value]| [key.as_str(), value.as_str()])
+            .collect::<Vec<_>>();
+        self.draw_info_table([self.text("health.health"), ""], &items, block, frame, area);
+    }
+
+    /// Design, last full and current energy side by side, relative to the design energy
+    fn draw_capacity_comparison<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default().borders(Borders::LEFT | Borders::RIGHT | Borders::BOTTOM);
+        let battery = self.view.battery();
+        let units = self.view.config().units();
+
+        let design = battery.energy_full_design().get::<watt_hour>();
+        let of_design = |value: f32| {
+            if design > 0.0 {
+                format!("{:.2} %", value / design * 100.0)
+            } else {
+                self.text("na").to_string()
+            }
+        };
+
+        let mut rows = vec![];
+        for (title, value) in [
+            (self.text("health.design"), design),
+            (self.text("health.last_full"), battery.energy_full().get::<watt_hour>()),
+            (self.text("health.current"), battery.energy().get::<watt_hour>()),
+        ]
+        .iter()
+        {
+            let row = [title.to_string(), format::energy(units, *value), of_design(*value)];
+            rows.push((row, Style::default()));
+        }
+
+        let lost = match self.view.history().health().first() {
+            Some(first) => {
+                let lost = first.energy_full - battery.energy_full().get::<watt_hour>();
+                [
+                    self.message("health.lost_since", &[("date", &format::date(first.timestamp))]),
+                    format::energy(units, lost),
+                    of_design(lost),
+                ]
+            }
+            None => [
+                self.text("health.lost").to_string(),
+                self.text("na").to_string(),
+                "".to_string(),
+            ],
+        };
+        rows.push((lost, Style::default().modifier(Modifier::BOLD)));
+
+        let header = [
+            self.text("health.capacity"),
+            self.text("health.energy"),
+            self.text("health.of_design"),
+        ];
+        let rows = rows.iter().map(|(row, style)| Row::StyledData(row.iter(), *style));
+
+        Table::new(header.iter(), rows)
+            .header_style(Style::default().modifier(Modifier::BOLD))
+            .block(block)
+            .widths(&[16, 10, 9])
+            .render(frame, area);
+    }
+
+    fn draw_degradation_chart<B: Backend>(&self, degradation: Option<&Degradation>, frame: &mut Frame<B>, area: Rect) {
+        let title = format!(" {} ", self.text("health.history_chart"));
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+
+        let points = degradation.map(Degradation::points).unwrap_or(&[]);
+        let x_upper = points.last().map(|(x, _)| x.ceil().max(1.0)).unwrap_or(1.0);
+        let (y_lower, y_upper) = points.iter().fold((100.0f64, 0.0f64), |(lower, upper), (_, y)| {
+            (lower.min(*y), upper.max(*y))
+        });
+        let (y_lower, y_upper) = ((y_lower - 5.0).floor().max(0.0), (y_upper + 1.0).ceil().max(100.0));
+
+        let trend_line = match degradation.and_then(Degradation::trend) {
+            Some(trend) => (0..=TREND_RESOLUTION)
+                .map(|i| {
+                    let x = x_upper * i as f64 / TREND_RESOLUTION as f64;
+                    (x, trend.value_at(x))
+                })
+                .collect::<Vec<_>>(),
+            None => vec![],
+        };
+
+        let x_labels = [String::from("0"), format!("{:.0}", x_upper)];
+        let y_labels = [format!("{:.0}", y_lower), format!("{:.0}", y_upper)];
+        let x_axis = Axis::default()
+            .title(self.text("health.days_axis"))
+            .style(Style::default().fg(Color::Reset))
+            .labels(&x_labels)
+            .bounds([0.0, x_upper]);
+        let y_axis = Axis::default().title("%").labels(&y_labels).bounds([y_lower, y_upper]);
+
+        Chart::default()
+            .block(block)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .datasets(&[
+                Dataset::default()
+                    .marker(Marker::Braille)
+                    .style(Style::default().fg(Color::Yellow))
+                    .data(&trend_line),
+                Dataset::default()
+                    .marker(Marker::Dot)
+                    .style(Style::default().fg(Color::Green))
+                    .data(points),
+            ])
+            .render(frame, area)
+    }
+
+    /// Scatter plot of the battery temperature against the power draw for the retained samples
+    /// Voltages of the separate cells on the same axes, so the imbalance is visible
+    fn draw_cells_chart<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let store = self.view.history().samples();
+        let cells = self.view.cells();
+        let points = cells.iter().map(|cell| cell.points(store)).collect::<Vec<_>>();
+        let latest = points
+            .iter()
+            .filter_map(|points| points.last().map(|(_, y)| *y))
+            .collect::<Vec<_>>();
+        let title = if latest.len() > 1 {
+            let (min, max) = latest
+                .iter()
+                .fold((f64::MAX, f64::MIN), |(min, max), y| (min.min(*y), max.max(*y)));
+            let spread = format!("{:.0}", (max - min) * 1000.0);
+            format!(" {} ", self.message("health.cells_spread", &[("spread", &spread)]))
+        } else {
+            format!(" {} ", self.text("health.cells"))
+        };
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+
+        // Imbalance is a matter of millivolts, so axis is not rounded to the whole volts as the others
+        let (y_lower, y_upper) = points
+            .iter()
+            .flat_map(|points| points.iter())
+            .fold((f64::MAX, f64::MIN), |(lower, upper), (_, y)| {
+                (lower.min(*y), upper.max(*y))
+            });
+        let (y_lower, y_upper) = (y_lower - CELL_VOLTAGE_MARGIN, y_upper + CELL_VOLTAGE_MARGIN);
+        let y_labels = [format!("{:.2}", y_lower), format!("{:.2}", y_upper)];
+        let values = cells
+            .iter()
+            .map(|cell| cell.current(store))
+            .collect::<Vec<_>>()
+            .join(" / ");
+        let x_axis: Axis<String> = Axis::default()
+            .title(&values)
+            .style(Style::default().fg(Color::Reset))
+            .bounds(cells[0].x_bounds());
+        let y_axis = Axis::default()
+            .title(format::VOLT)
+            .labels(&y_labels)
+            .bounds([y_lower, y_upper]);
+
+        let colors = [Color::Green, Color::Cyan, Color::Yellow, Color::Magenta, Color::Blue];
+        let datasets = points
+            .iter()
+            .zip(colors.iter().cycle())
+            .map(|(points, color)| {
+                Dataset::default()
+                    .marker(Marker::Braille)
+                    .style(Style::default().fg(*color))
+                    .data(points)
+            })
+            .collect::<Vec<_>>();
+
+        Chart::default()
+            .block(block)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .datasets(&datasets)
+            .render(frame, area)
+    }
+
+    fn draw_thermal_chart<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let thermal = Thermal::new(self.view.history().samples().iter());
+        let (temperature_unit, offset) = match self.view.config().units() {
+            Units::Human => (format::DEGREE_CELSIUS, KELVIN_OFFSET),
+            Units::Si => (format::KELVIN, 0.0),
+        };
+
+        let title = match (thermal.coefficient(), thermal.trend()) {
+            (Some(r), Some(trend)) => {
+                let verdict = if r >= STRONG_CORRELATION {
+                    self.text("health.heat_tracks_load")
+                } else {
+                    self.text("health.heat_not_explained")
+                };
+                let slope = format!("{:+.2} {}/{}", trend.slope, temperature_unit, format::WATT);
+                let title = self.message(
+                    "health.thermal_value",
+                    &[("r", &format!("{:.2}", r)), ("slope", &slope), ("verdict", &verdict)],
+                );
+                format!(" {} ", title)
+            }
+            _ => format!(" {} ", self.text("health.thermal")),
+        };
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+
+        let points = thermal
+            .points()
+            .iter()
+            .map(|(power, temperature)| (*power, temperature - offset))
+            .collect::<Vec<_>>();
+        let x_upper = points.iter().fold(1.0f64, |upper, (x, _)| upper.max(x.ceil()));
+        let (y_lower, y_upper) = match points.first() {
+            Some((_, y)) => points
+                .iter()
+                .fold((*y, *y), |(lower, upper), (_, y)| (lower.min(*y), upper.max(*y))),
+            None => (0.0, 1.0),
+        };
+        let (y_lower, y_upper) = ((y_lower - 1.0).floor(), (y_upper + 1.0).ceil());
+
+        let trend_line = match thermal.trend() {
+            Some(trend) => (0..=TREND_RESOLUTION)
+                .map(|i| {
+                    let x = x_upper * i as f64 / TREND_RESOLUTION as f64;
+                    (x, trend.value_at(x) - offset)
+                })
+                .filter(|(_, y)| *y >= y_lower && *y <= y_upper)
+                .collect::<Vec<_>>(),
+            None => vec![],
+        };
+
+        let x_labels = [String::from("0"), format!("{:.0}", x_upper)];
+        let y_labels = [format!("{:.0}", y_lower), format!("{:.0}", y_upper)];
+        let x_axis = Axis::default()
+            .title(format::WATT)
+            .style(Style::default().fg(Color::Reset))
+            .labels(&x_labels)
+            .bounds([0.0, x_upper]);
+        let y_axis = Axis::default()
+            .title(temperature_unit)
+            .labels(&y_labels)
+            .bounds([y_lower, y_upper]);
+
+        Chart::default()
+            .block(block)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .datasets(&[
+                Dataset::default()
+                    .marker(Marker::Braille)
+                    .style(Style::default().fg(Color::Yellow))
+                    .data(&trend_line),
+                Dataset::default()
+                    .marker(Marker::Dot)
+                    .style(Style::default().fg(Color::Red))
+                    .data(&points),
+            ])
+            .render(frame, area)
+    }
+
+    fn draw_power_summary<B: Backend>(&self, summary: Option<&Summary>, frame: &mut Frame<B>, area: Rect) {
+        let window = humantime::format_duration(self.window);
+        let title = format!(" {} ", self.message("statistics.power_draw", &[("window", &window)]));
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+
+        let watts = |value: f64| format!("{:.2} {}", value, format::WATT);
+        let items = match summary {
+            Some(summary) => vec![
+                [self.text("statistics.samples").to_string(), summary.count.to_string()],
+                [self.text("statistics.minimum").to_string(), watts(summary.min)],
+                [self.text("statistics.mean").to_string(), watts(summary.mean)],
+                [self.text("statistics.median").to_string(), watts(summary.p50)],
+                ["p90".to_string(), watts(summary.p90)],
+                ["p99".to_string(), watts(summary.p99)],
+                [self.text("statistics.maximum").to_string(), watts(summary.max)],
+            ],
+            None => vec![[
+                self.text("statistics.samples").to_string(),
+                self.text("statistics.not_discharging").to_string(),
+            ]],
+        };
+
+        let items = items
+            .iter()
+            .map(|[key, value]| [key.as_str(), value.as_str()])
+            .collect::<Vec<_>>();
+        self.draw_info_table([self.text("statistics.discharging"), ""], &items, block, frame, area);
+    }
+
+    fn draw_charging_speed<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let title = format!(" {} ", self.text("statistics.charging_speed"));
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+
+        let watts = |value: f64| format!("{:.2} {}", value, format::WATT);
+        let speed = self.text("statistics.speed").to_string();
+        let mut rows = vec![];
+        match self.view.charging() {
+            Some(charging) => {
+                let name = self.view.config().locale().name("speed", charging.speed);
+                rows.push(([speed, name], Style::default()));
+                rows.push((
+                    [self.text("statistics.power").to_string(), watts(charging.power)],
+                    Style::default(),
+                ));
+                rows.push((
+                    [
+                        self.text("statistics.c_rate").to_string(),
+                        format!("{:.2}C", charging.c_rate),
+                    ],
+                    Style::default(),
+                ));
+                let accepted = charging
+                    .accepted
+                    .map(watts)
+                    .unwrap_or_else(|| self.text("na").to_string());
+                rows.push((
+                    [self.text("statistics.accepted").to_string(), accepted],
+                    Style::default(),
+                ));
+                if charging.weak_charger {
+                    rows.push((
+                        [
+                            self.text("statistics.warning").to_string(),
+                            self.text("statistics.weak_charger").to_string(),
+                        ],
+                        Style::default().fg(Color::Red).modifier(Modifier::BOLD),
+                    ));
+                }
+            }
+            None => rows.push((
+                [speed, self.text("statistics.not_charging").to_string()],
+                Style::default(),
+            )),
+        }
+
+        let header = [self.text("statistics.charging"), ""];
+        let rows = rows.iter().map(|(row, style)| Row::StyledData(row.iter(), *style));
+
+        Table::new(header.iter(), rows)
+            .header_style(Style::default().modifier(Modifier::BOLD))
+            .block(block)
+            .widths(&[17, 17])
+            .render(frame, area);
+    }
+
+    /// Energy consumed and time on battery over the last day and week, including the session in progress
+    fn draw_rolling<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let title = format!(" {} ", self.text("statistics.rolling"));
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+
+        let units = self.view.config().units();
+        let sessions = self.view.history().sessions();
+        let capacity = f64::from(self.view.battery().energy_full().get::<watt_hour>());
+        let now = SystemTime::now();
+        let rolling = |period| {
+            Rolling::new(
+                sessions.records().iter().chain(sessions.current()),
+                now,
+                period,
+                capacity,
+            )
+        };
+        let (day, week) = (rolling(DAY), rolling(WEEK));
+
+        let cycles = |rolling: &Rolling| match rolling.cycles {
+            Some(cycles) => format!("{:.2}", cycles),
+            None => self.text("na").to_string(),
+        };
+        let hours = |rolling: &Rolling| {
+            let hours = format!("{:.1}", rolling.on_battery.as_secs() as f64 / 3600.0);
+            self.message("statistics.hours", &[("hours", &hours)])
+        };
+        let rows = [
+            [
+                self.text("statistics.discharged").to_string(),
+                format::energy(units, day.discharged as f32),
+                format::energy(units, week.discharged as f32),
+            ],
+            [
+                self.text("statistics.charged").to_string(),
+                format::energy(units, day.charged as f32),
+                format::energy(units, week.charged as f32),
+            ],
+            [self.text("statistics.cycles").to_string(), cycles(&day), cycles(&week)],
+            [
+                self.text("statistics.on_battery").to_string(),
+                hours(&day),
+                hours(&week),
+            ],
+        ];
+
+        let header = [
+            self.text("statistics.total"),
+            self.text("statistics.day"),
+            self.text("statistics.week"),
+        ];
+        let rows = rows.iter().map(|row| Row::Data(row.iter()));
+
+        Table::new(header.iter(), rows)
+            .header_style(Style::default().modifier(Modifier::BOLD))
+            .block(block)
+            .widths(&[13, 12, 12])
+            .render(frame, area);
+    }
+
+    fn draw_charging_cost<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let title = format!(" {} ", self.text("statistics.charging_cost"));
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+        let config = self.view.config();
+        let sessions = self.view.history().sessions();
+
+        let format_cost = |watt_hours: f64| match config.price() {
+            Some(price) => format!("{:.2} {}", cost(watt_hours, price), config.currency())
+                .trim_end()
+                .to_string(),
+            None => self.text("na").to_string(),
+        };
+        let format_row = |title: String, watt_hours: f64| {
+            [
+                title,
+                format!("{:.2} {}", watt_hours / 1000.0, format::KILOWATT_HOUR),
+                format_cost(watt_hours),
+            ]
+        };
+
+        let mut rows = vec![];
+        match sessions.current() {
+            Some(session) if session.kind == SessionKind::Charging => rows.push(format_row(
+                self.text("statistics.this_session").to_string(),
+                f64::from(session.energy),
+            )),
+            _ => rows.push([
+                self.text("statistics.this_session").to_string(),
+                self.text("statistics.not_charging").to_string(),
+                "".to_string(),
+            ]),
+        }
+
+        let months = monthly_charging(sessions.records().iter().chain(sessions.current()));
+        for (month, watt_hours) in months.into_iter().rev().take(COST_MONTHS) {
+            rows.push(format_row(month, watt_hours));
+        }
+
+        let header = [
+            self.text("statistics.charged"),
+            self.text("health.energy"),
+            self.text("statistics.cost"),
+        ];
+        let rows = rows.iter().map(|row| Row::Data(row.iter()));
+
+        Table::new(header.iter(), rows)
+            .header_style(Style::default().modifier(Modifier::BOLD))
+            .block(block)
+            .widths(&[13, 12, 9])
+            .render(frame, area);
+    }
+
+    fn draw_power_distribution<B: Backend>(
+        &self,
+        power: &[f64],
+        summary: Option<&Summary>,
+        frame: &mut Frame<B>,
+        area: Rect,
+    ) {
+        let title = format!(" {} ", self.text("statistics.distribution"));
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+
+        let max = summary.map(|summary| summary.max.ceil()).unwrap_or(0.0);
+        let counts = histogram(power, HISTOGRAM_BUCKETS, max);
+        let labels = (1..=HISTOGRAM_BUCKETS)
+            .map(|i| format!("{:.0}{}", max * i as f64 / HISTOGRAM_BUCKETS as f64, format::WATT))
+            .collect::<Vec<_>>();
+        let data = labels.iter().map(String::as_str).zip(counts).collect::<Vec<_>>();
+        let bar_width = (block.inner(area).width / HISTOGRAM_BUCKETS as u16)
+            .saturating_sub(1)
+            .max(1);
+
+        BarChart::default()
+            .block(block)
+            .data(&data)
+            .bar_width(bar_width)
+            .style(Style::default().fg(Color::Green))
+            .value_style(Style::default().fg(Color::Black).bg(Color::Green))
+            .render(frame, area);
+    }
+
+    /// Time and energy spent in each load band during the current or the latest discharging session
+    fn draw_load_bands<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let history = self.view.history();
+        let bands = &self.view.settings().bands;
+        let session = self.view.discharging_session();
+
+        let title = match session {
+            Some(session) => format!(
+                " {} ",
+                self.message("statistics.load_since", &[("time", &format::time(session.start))])
+            ),
+            None => format!(" {} ", self.text("statistics.load")),
+        };
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+
+        let usage = session.map(|session| bands.usage(history.samples().range(session.start, session.end)));
+        let total = usage
+            .iter()
+            .flat_map(|usage| usage.iter())
+            .fold(Duration::from_secs(0), |total, usage| total + usage.time);
+
+        let rows = Band::all()
+            .iter()
+            .enumerate()
+            .map(|(idx, band)| match usage {
+                Some(usage) => {
+                    let share = if total.as_secs() > 0 {
+                        usage[idx].time.as_secs() as f64 / total.as_secs() as f64 * 100.0
+                    } else {
+                        0.0
+                    };
+                    [
+                        self.view.config().locale().name("band", band),
+                        bands.range(*band),
+                        humantime::format_duration(Duration::from_secs(usage[idx].time.as_secs())).to_string(),
+                        format!("{:.1} %", share),
+                        format!("{:.2} {}", usage[idx].energy, format::WATT_HOUR),
+                    ]
+                }
+                None => [
+                    self.view.config().locale().name("band", band),
+                    bands.range(*band),
+                    self.text("na").to_string(),
+                    "".to_string(),
+                    "".to_string(),
+                ],
+            })
+            .collect::<Vec<_>>();
+
+        let header = [
+            self.text("statistics.band"),
+            self.text("statistics.power"),
+            self.text("statistics.time"),
+            self.text("statistics.share"),
+            self.text("health.energy"),
+        ];
+        let rows = rows.iter().map(|row| Row::Data(row.iter()));
+
+        Table::new(header.iter(), rows)
+            .header_style(Style::default().modifier(Modifier::BOLD))
+            .block(block)
+            .widths(&[6, 10, 16, 8, 12])
+            .render(frame, area);
+    }
+
+    /// What-if calculator: how long battery will last with some specific power draw
+    fn draw_forecast<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let title = format!(" {} ", self.text("statistics.what_if"));
+        let block = Block::default()
+            .title(&title)
+            .title_style(Style::default())
+            .borders(Borders::ALL);
+
+        let energy = f64::from(self.view.battery().energy().get::<watt_hour>());
+        let watts = |value: f64| format!("{:.2} {}", value, format::WATT);
+        let runtime = |power: f64| {
+            if power > 0.0 {
+                let seconds = energy / power * 3600.0;
+                humantime::format_duration(Duration::from_secs(seconds as u64)).to_string()
+            } else {
+                self.text("na").to_string()
+            }
+        };
+
+        let forecast = self.text("statistics.forecast").to_string();
+        let mut rows = vec![];
+        match self.forecast {
+            Some(power) => rows.push([forecast, watts(power), runtime(power)]),
+            None => rows.push([forecast, self.text("na").to_string(), "".to_string()]),
+        }
+        match self.view.session_power() {
+            Some(summary) => {
+                rows.push([
+                    self.text("statistics.session_p50").to_string(),
+                    watts(summary.p50),
+                    runtime(summary.p50),
+                ]);
+                rows.push([
+                    self.text("statistics.session_p90").to_string(),
+                    watts(summary.p90),
+                    runtime(summary.p90),
+                ]);
+            }
+            None => rows.push([
+                self.text("statistics.session").to_string(),
+                self.text("na").to_string(),
+                "".to_string(),
+            ]),
+        }
+
+        let header = [
+            self.text("statistics.power_draw_title"),
+            "",
+            self.text("statistics.runtime"),
+        ];
+        let rows = rows.iter().map(|row| Row::Data(row.iter()));
+
+        Table::new(header.iter(), rows)
+            .header_style(Style::default().modifier(Modifier::BOLD))
+            .block(block)
+            .widths(&[16, 12, 24])
+            .render(frame, area);
+    }
+
     fn draw_info_table<B: Backend>(
         &self,
         header: [&str; 2],
@@ -380,6 +1722,28 @@ impl<'i> Painter<'i> {
             .widths(&[17, 17])
             .render(frame, area);
     }
+
+    /// Message of the configured locale, see `Locale::text`
+    fn text<'a>(&'a self, key: &'a str) -> &'a str {
+        self.view.config().locale().text(key)
+    }
+
+    /// Message of the configured locale with the placeholders replaced, see `Locale::message`
+    fn message(&self, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+        self.view.config().locale().message(key, args)
+    }
+
+    /// Format round-trip efficiency, marking the packs which are becoming less efficient
+    fn format_efficiency(&self, efficiency: Option<&Efficiency>) -> String {
+        match efficiency {
+            Some(efficiency) if efficiency.dropping() => {
+                let value = format!("{:.1} %", efficiency.recent * 100.0);
+                self.message("health.dropping", &[("efficiency", &value)])
+            }
+            Some(efficiency) => format!("{:.1} %", efficiency.recent * 100.0),
+            None => self.text("na").to_string(),
+        }
+    }
 }
 
 impl<'i> Deref for Painter<'i> {
@@ -389,3 +1753,16 @@ impl<'i> Deref for Painter<'i> {
         &self.0
     }
 }
+
+/// Resets the area, so the widgets drawn over the other ones are not mixed with them
+struct Clear;
+
+impl Widget for Clear {
+    fn draw(&mut self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                buf.get_mut(x, y).reset();
+            }
+        }
+    }
+}
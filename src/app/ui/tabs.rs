@@ -1,12 +1,17 @@
 #[derive(Debug, Default)]
 pub struct TabBar {
     titles: Vec<String>,
+    disconnected: Vec<bool>,
+    /// Connected, but the latest refreshes had failed
+    stale: Vec<bool>,
     index: usize, // Currently selected tab, 0 by default
 }
 
 impl TabBar {
     pub fn new(titles: Vec<String>) -> TabBar {
         TabBar {
+            disconnected: vec![false; titles.len()],
+            stale: vec![false; titles.len()],
             titles,
             index: 0,
         }
@@ -28,7 +33,55 @@ impl TabBar {
         }
     }
 
+    /// Selected tab stays the same, even if it is moved by the insertion
+    pub fn insert(&mut self, index: usize, title: String) {
+        if index <= self.index && !self.titles.is_empty() {
+            self.index += 1;
+        }
+        self.titles.insert(index, title);
+        self.disconnected.insert(index, false);
+        self.stale.insert(index, false);
+    }
+
+    pub fn set_title(&mut self, index: usize, title: String) {
+        self.titles[index] = title;
+    }
+
+    pub fn set_connected(&mut self, index: usize, connected: bool) {
+        self.disconnected[index] = !connected;
+    }
+
+    pub fn is_connected(&self, index: usize) -> bool {
+        !self.disconnected[index]
+    }
+
+    pub fn set_stale(&mut self, index: usize, stale: bool) {
+        self.stale[index] = stale;
+    }
+
+    pub fn is_stale(&self, index: usize) -> bool {
+        self.stale[index]
+    }
+
     pub fn titles(&self) -> &[String] {
         self.titles.as_ref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TabBar;
+
+    #[test]
+    fn insert_keeps_selection() {
+        let mut tabs = TabBar::new(vec!["BAT0".into(), "BAT1".into()]);
+        tabs.next();
+        tabs.insert(0, "UPS".into());
+        assert_eq!(tabs.titles(), ["UPS", "BAT0", "BAT1"]);
+        assert_eq!(tabs.index(), 2);
+
+        tabs.insert(3, "Mouse".into());
+        assert_eq!(tabs.index(), 2);
+        assert!(tabs.is_connected(3));
+    }
+}
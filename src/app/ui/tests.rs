@@ -0,0 +1,161 @@
+//! Snapshot tests of the interface: scripted batteries are drawn into the test backend after
+//! the key presses, and the screen text is compared with the `snapshots/<name>.txt` golden files.
+//! Pages showing the current date, like the health one, are not covered.
+//!
+//! Missing or different golden files are failing the test; run the tests with the
+//! `BATTOP_UPDATE_SNAPSHOTS` environment variable set to write all of them once the interface
+//! was changed on purpose, and review the difference before committing it.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tui::backend::TestBackend;
+
+use battop_core::device::{PowerSource, Reading, Scripted};
+
+use super::{init, Interface, View};
+use crate::app::events::key_event;
+use crate::app::terminal::Key;
+use crate::app::Config;
+
+const WIDTH: u16 = 100;
+const HEIGHT: u16 = 30;
+
+fn snapshots() -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "src", "app", "ui", "snapshots"]
+        .iter()
+        .collect()
+}
+
+/// Configuration of the snapshots directory, with the scripts of its `scripts` subdirectory
+pub fn config() -> Arc<Config> {
+    let path = snapshots().join("config.toml");
+    let args = vec![
+        "battop".into(),
+        "--config".into(),
+        path.into_os_string(),
+        "--locale".into(),
+        "en".into(),
+        "--no-history".into(),
+    ];
+
+    Arc::new(Config::load_from(args).expect("Snapshot configuration is valid"))
+}
+
+/// View of the scripted battery, updated with all the `steps` but the first one, read on enumeration
+fn view(config: &Arc<Config>, model: &str, steps: Vec<Reading>) -> View {
+    let count = steps.len();
+    let mut source = Scripted::new(model, 50.0, steps.into_iter().map(Some).collect());
+    let mut device = source.devices().expect("Scripted battery").remove(0);
+    let mut view = View::new(config.clone(), device.clone());
+    for _ in 1..count {
+        source.refresh(&mut device).expect("Scripted step");
+        view.update(device.clone()).expect("View is updated");
+    }
+
+    view
+}
+
+/// Discharging with the power slowly rising, one step per percent
+fn discharging() -> Vec<Reading> {
+    (0..20)
+        .map(|i| Reading::discharging(0.8 - i as f32 / 100.0, 8.0 + i as f32 / 4.0))
+        .collect()
+}
+
+fn interface(views: Vec<View>, keys: &[Key]) -> Interface<TestBackend> {
+    let config = config();
+    let bindings = config.key_bindings().to_vec();
+    let mut interface = init(config, TestBackend::new(WIDTH, HEIGHT), views).expect("Interface is created");
+    for key in keys {
+        let event = key_event(&bindings, *key).expect("Key is bound");
+        assert!(interface.handle(&event), "{:?} is not handled by the interface", event);
+    }
+    interface.draw().expect("Interface is drawn");
+
+    interface
+}
+
+/// Screen text, with the trailing whitespace trimmed
+fn screen(interface: &Interface<TestBackend>) -> String {
+    let buffer = interface.buffer();
+    let mut screen = String::new();
+    for line in buffer.content().chunks(buffer.area().width as usize) {
+        let line = line.iter().map(|cell| cell.symbol.as_str()).collect::<String>();
+        screen.push_str(line.trim_end());
+        screen.push('\n');
+    }
+
+    screen
+}
+
+/// Snapshots are written only when asked to, so a missing one fails instead of passing silently
+fn assert_snapshot(name: &str, interface: &Interface<TestBackend>) {
+    let actual = screen(interface);
+    let path = snapshots().join(format!("{}.txt", name));
+    if env::var_os("BATTOP_UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, actual).expect("Snapshot is written");
+        return;
+    }
+
+    match fs::read_to_string(&path) {
+        Ok(expected) => assert!(
+            expected == actual,
+            "Snapshot `{}` differs, expected:\n{}\nactual:\n{}",
+            name,
+            expected,
+            actual
+        ),
+        Err(e) => panic!(
+            "missing snapshot `{}` ({}: {}), set BATTOP_UPDATE_SNAPSHOTS to write it:\n{}",
+            name,
+            path.display(),
+            e,
+            actual
+        ),
+    }
+}
+
+#[test]
+fn overview() {
+    let config = config();
+    let interface = interface(vec![view(&config, "Scripted battery", discharging())], &[]);
+
+    assert_snapshot("overview", &interface);
+}
+
+#[test]
+fn overview_charging() {
+    let config = config();
+    let steps = (0..10)
+        .map(|i| Reading::charging(0.3 + i as f32 / 100.0, 30.0 - i as f32))
+        .collect();
+    let interface = interface(vec![view(&config, "Scripted battery", steps)], &[]);
+
+    assert_snapshot("overview_charging", &interface);
+}
+
+#[test]
+fn second_tab() {
+    let config = config();
+    let views = vec![
+        view(&config, "Scripted battery", discharging()),
+        view(&config, "Spare battery", vec![Reading::discharging(0.5, 4.0); 5]),
+    ];
+    let interface = interface(views, &[Key::Right]);
+
+    assert_snapshot("second_tab", &interface);
+}
+
+#[test]
+fn scripts() {
+    let config = config();
+    let interface = interface(
+        vec![view(&config, "Scripted battery", discharging())],
+        &[Key::Char('6')],
+    );
+
+    assert_snapshot("scripts", &interface);
+}
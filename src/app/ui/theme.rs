@@ -0,0 +1,57 @@
+use tui::style::Color;
+
+/// Colors of the chart series, configurable in the `[theme]` section of the configuration file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Battery own values
+    pub main: Color,
+    /// Series in the same units, ex. CPU package power, used in turn
+    pub overlays: Vec<Color>,
+    /// Series in their own units, ex. display brightness, used in turn
+    pub secondary: Vec<Color>,
+    pub annotations: Color,
+    /// Anomalies, like the voltage sags
+    pub markers: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            main: Color::Green,
+            overlays: vec![Color::Cyan, Color::Yellow, Color::Magenta],
+            secondary: vec![Color::Gray, Color::LightRed],
+            annotations: Color::Blue,
+            markers: Color::Red,
+        }
+    }
+}
+
+/// Terminal color name, ex. `lightred`, or the `#rrggbb` value
+pub fn parse_color(name: &str) -> Option<Color> {
+    let color = match name.to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        rgb if rgb.len() == 7 && rgb.starts_with('#') => {
+            let channel = |index: usize| u8::from_str_radix(&rgb[index..index + 2], 16).ok();
+            Color::Rgb(channel(1)?, channel(3)?, channel(5)?)
+        }
+        _ => return None,
+    };
+
+    Some(color)
+}
@@ -1,61 +1,283 @@
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use battery::units;
+use battery::State;
 
-use super::{ChartData, ChartType, Units};
-use crate::app::Config;
+use battop_core::adapter::Adapter;
+use battop_core::device::{Device, DeviceKind};
+use battop_core::history::{Field, History, Session, SessionKind};
+use battop_core::sensors::Reading;
+use battop_core::stats::{Charging, Summary, VoltageModel};
+
+use super::{ChartData, ChartType};
+use crate::app::alert;
+use crate::app::config::{Config, DeviceSettings};
 use crate::Result;
 
+/// Charging power is averaged over this period to smooth the noise
+const CHARGING_POWER_WINDOW: Duration = Duration::from_secs(60);
+/// Temperature drawn on the chart, battery own or the system one, °C
+const TEMPERATURE: &str = "temperature";
+/// Minimal delay between the repeated voltage sag alerts
+const SAG_ALERT_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// Minimal delay between the repeated insufficient adapter alerts
+const ADAPTER_ALERT_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// Battery discharging slower than that while on adapter is just a measurement noise, W
+const ADAPTER_DEFICIT_THRESHOLD: f32 = 0.5;
+
 /// View is a content of one separate tab - information about one specific battery
 #[derive(Debug)]
 pub struct View {
     config: Arc<Config>,
-    battery: battery::Battery,
+    battery: Device,
+    history: History,
     voltage: ChartData,
     energy_rate: ChartData,
     temperature: ChartData,
+    state_of_charge: ChartData,
+    cells: Vec<ChartData>,
+    sag_alerted: Option<Instant>,
+    adapter_alerted: Option<Instant>,
+    charging_held: bool,
+    connected: bool,
+    /// Latest refreshes had failed, values shown are the last known ones
+    stale: bool,
 }
 
 impl View {
-    pub fn new(config: Arc<Config>, battery: battery::Battery) -> View {
+    pub fn new(config: Arc<Config>, battery: Device) -> View {
+        let mut history = History::open(&battery, config.history_enabled(), *config.delay(), config.retention());
+        history.observe(&battery);
+
+        let charging_held = battery.charging_held();
+
         View {
             config: config.clone(),
             battery,
-            voltage: ChartData::new(config.clone(), ChartType::Voltage),
-            energy_rate: ChartData::new(config.clone(), ChartType::EnergyRate),
-            temperature: ChartData::new(config, ChartType::Temperature),
+            history,
+            voltage: ChartData::new(config.clone(), ChartType::Voltage, Field::Voltage),
+            energy_rate: ChartData::new(config.clone(), ChartType::EnergyRate, Field::EnergyRate),
+            temperature: ChartData::new(
+                config.clone(),
+                ChartType::Temperature,
+                Field::Channel(TEMPERATURE.to_string()),
+            ),
+            state_of_charge: ChartData::new(config, ChartType::StateOfCharge, Field::StateOfCharge),
+            cells: Vec::new(),
+            sag_alerted: None,
+            adapter_alerted: None,
+            charging_held,
+            connected: true,
+            stale: false,
         }
     }
 
-    /// Update internal state, but do not re-draw it
-    pub fn update(&mut self, manager: &mut battery::Manager) -> Result<()> {
-        manager.refresh(&mut self.battery)?;
+    /// Update internal state with the refreshed device, but do not re-draw it.
+    pub fn update(&mut self, battery: Device) -> Result<()> {
+        if !self.connected {
+            return Ok(());
+        }
+        self.battery = battery;
+        self.stale = false;
+        self.history.observe(&self.battery);
+        let sequence = self.history.samples().sequence();
+        // Taken on the collection thread along with the refresh, as some of them are slow
+        let system = self.battery.system().cloned().unwrap_or_default();
 
-        self.voltage
-            .push(self.battery.voltage().get::<units::electric_potential::volt>());
         *self.voltage.battery_state() = self.battery.state();
+        if let Some(voltage) = self.battery.charger().and_then(|charger| charger.desired_voltage) {
+            self.history.record("voltage.requested", f64::from(voltage));
+            self.voltage.overlay("chart.requested", "voltage.requested");
+        }
+        self.detect_sag();
 
-        self.energy_rate
-            .push(self.battery.energy_rate().get::<units::power::watt>());
         *self.energy_rate.battery_state() = self.battery.state();
+        if let Some(power) = system.power {
+            self.history.record("power.cpu", power.package);
+            self.energy_rate.overlay("chart.cpu", "power.cpu");
+            if let Some(gpu) = power.gpu {
+                self.history.record("power.gpu", gpu);
+                self.energy_rate.overlay("chart.gpu", "power.gpu");
+            }
+        }
 
-        if let Some(temp) = self.battery.temperature() {
-            let value = match self.config.units() {
-                Units::Human => temp.get::<units::thermodynamic_temperature::degree_celsius>(),
-                Units::Si => temp.get::<units::thermodynamic_temperature::kelvin>(),
+        // Adapter and display are powered by this machine only
+        if self.battery.kind() == DeviceKind::Battery && self.battery.host().is_none() {
+            if let Some(level) = system.brightness {
+                self.history.record("brightness", f64::from(level) / 100.0);
+                self.energy_rate
+                    .secondary("chart.brightness", "brightness", format!("{:.0} %", level));
+            }
+            // Charge current is relative to what the battery asks for
+            if let Some(charger) = self.battery.charger() {
+                if let (Some(current), Some(desired)) = (charger.charge_current, charger.desired_current) {
+                    if desired > 0.0 {
+                        self.history.record("charge_current", f64::from(current / desired));
+                        self.energy_rate.secondary(
+                            "chart.charge_current",
+                            "charge_current",
+                            format!("{:.2} A", current),
+                        );
+                    }
+                }
+            }
+            if let Some(reading) = system.cpu {
+                self.history.record("cpu", reading.fraction());
+                self.energy_rate.secondary("chart.cpu", "cpu", reading.to_string());
+            }
+            self.detect_insufficient_adapter();
+        }
+
+        *self.state_of_charge.battery_state() = self.battery.state();
+        // Explains why the level is parked, and when it started to move again
+        if self.battery.charging_held() != self.charging_held {
+            self.charging_held = self.battery.charging_held();
+            let label = if self.charging_held {
+                "chart.charging_held"
+            } else {
+                "chart.charging_resumed"
             };
-            self.temperature.push(value);
+            self.state_of_charge.annotate(label, sequence);
+        }
+
+        let cell_voltages = self.battery.cell_voltages();
+        if self.cells.len() != cell_voltages.len() {
+            self.cells = (0..cell_voltages.len())
+                .map(|index| {
+                    let channel = Field::Channel(format!("cell.{}", index));
+                    ChartData::new(self.config.clone(), ChartType::CellVoltage, channel)
+                })
+                .collect();
+        }
+        for (index, (chart, voltage)) in self.cells.iter_mut().zip(cell_voltages).enumerate() {
+            self.history.record(&format!("cell.{}", index), f64::from(*voltage));
+            *chart.battery_state() = self.battery.state();
+        }
+
+        let celsius = match (self.battery.temperature(), &system.temperature) {
+            (Some(temp), _) => Some(temp.get::<units::thermodynamic_temperature::degree_celsius>()),
+            (None, Some(reading)) => Some(reading.celsius),
+            (None, None) => None,
+        };
+        if let Some(celsius) = celsius {
+            self.history.record(TEMPERATURE, f64::from(celsius));
+            for reading in system.sensors.iter() {
+                let channel = format!("{}.{}", TEMPERATURE, reading.name);
+                self.history.record(&channel, f64::from(reading.celsius));
+                self.temperature.overlay(&reading.name, &channel);
+            }
             *self.temperature.battery_state() = self.battery.state();
+            self.temperature
+                .set_source(system.temperature.as_ref().map(|reading| reading.name.clone()));
             self.temperature.enabled(true);
         } else {
             self.temperature.enabled(false);
         }
 
+        // Derived series are the last ones, so they can refer all the channels recorded above
+        let config = self.config.clone();
+        for series in config.scripts().series.iter() {
+            if let Some(value) = series.expr.eval(self.history.samples()) {
+                self.history.record(&series.channel, value);
+                let chart = match series.chart {
+                    ChartType::Voltage => &mut self.voltage,
+                    ChartType::Temperature => &mut self.temperature,
+                    ChartType::StateOfCharge => &mut self.state_of_charge,
+                    _ => &mut self.energy_rate,
+                };
+                chart.overlay(&series.label, &series.channel);
+            }
+        }
+
         Ok(())
     }
 
-    /// Return view title used in a tab header
+    /// Compare the latest voltage with the one expected from the previous samples
+    fn detect_sag(&mut self) {
+        let samples = self.history.samples();
+        // Sags are looked for under load only, so the model is not fitted while charging
+        let latest = match samples.latest() {
+            Some(latest) if latest.state == State::Discharging => *latest,
+            _ => return,
+        };
+        let model = match VoltageModel::fit(samples.iter().rev().skip(1)) {
+            Some(model) => model,
+            None => return,
+        };
+        if !model.is_sag(&latest) {
+            return;
+        }
+
+        let expected = model.expected(&latest);
+        warn!(
+            "Voltage sag detected: {:.2} V at {:.2} W, expected {:.2} V",
+            latest.voltage, latest.energy_rate, expected
+        );
+        self.voltage.mark(self.history.samples().sequence());
+
+        match self.sag_alerted {
+            Some(alerted) if alerted.elapsed() < SAG_ALERT_INTERVAL => {}
+            _ => {
+                self.sag_alerted = Some(Instant::now());
+                alert::notify(
+                    &self.config,
+                    "voltage-sag",
+                    &self.title(),
+                    &[
+                        ("VOLTAGE", format!("{:.2}", latest.voltage)),
+                        ("EXPECTED_VOLTAGE", format!("{:.2}", expected)),
+                        ("POWER", format!("{:.2}", latest.energy_rate)),
+                    ],
+                );
+            }
+        }
+    }
+
+    /// Battery should not be discharging while adapter is connected, unless the adapter is too weak for the load
+    fn detect_insufficient_adapter(&mut self) {
+        if !self.adapter_insufficient() {
+            return;
+        }
+
+        let deficit = self.battery.energy_rate().get::<units::power::watt>();
+        match self.adapter_alerted {
+            Some(alerted) if alerted.elapsed() < ADAPTER_ALERT_INTERVAL => {}
+            _ => {
+                warn!("Battery is discharging with {:.2} W while on adapter", deficit);
+                self.adapter_alerted = Some(Instant::now());
+                let watts = self
+                    .adapter()
+                    .and_then(|adapter| adapter.watts)
+                    .map(|watts| format!("{:.2}", watts))
+                    .unwrap_or_default();
+                alert::notify(
+                    &self.config,
+                    "adapter-insufficient",
+                    &self.title(),
+                    &[("POWER", format!("{:.2}", deficit)), ("ADAPTER_POWER", watts)],
+                );
+            }
+        }
+    }
+
+    /// Return view title used in a tab header, prefixed with the host name if there are several of them
     pub fn title(&self) -> String {
+        match self.battery.host() {
+            Some(host) => format!("{}: {}", host, self.device_title()),
+            None => self.device_title(),
+        }
+    }
+
+    fn device_title(&self) -> String {
+        if let Some(nickname) = self.config.nickname(&self.battery) {
+            return nickname.to_string();
+        }
+
+        if let Some(ref title) = self.settings().title {
+            return title.clone();
+        }
+
         if let Some(model) = self.battery.model() {
             trace!("View is going to use battery model as a tab title: {}", model);
             return model.to_string();
@@ -71,14 +293,119 @@ impl View {
             return sn.to_string();
         }
 
-        warn!("View is unable to determine proper tab title, falling back to unknown");
-        "Unknown battery".to_string()
+        warn!("View is unable to determine proper tab title, falling back to the device kind");
+        self.config
+            .locale()
+            .message("tabs.unknown", &[("kind", &self.battery.kind())])
     }
 
-    pub fn battery(&self) -> &battery::Battery {
+    pub fn battery(&self) -> &Device {
         &self.battery
     }
 
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// Device failed to refresh, but it is still retried; cleared by the next successful `update`
+    pub fn degrade(&mut self) {
+        self.stale = true;
+    }
+
+    /// Device which can't be refreshed anymore, its last values are kept until it is back, see `reconnect`
+    pub fn disconnect(&mut self) {
+        self.connected = false;
+    }
+
+    /// Resume updates with the device found again after it was disconnected
+    pub fn reconnect(&mut self, battery: Device) {
+        self.battery = battery;
+        self.connected = true;
+        self.stale = false;
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Charging speed assessment, if battery is charging right now
+    pub fn charging(&self) -> Option<Charging> {
+        if self.battery.state() != State::Charging {
+            return None;
+        }
+
+        let (sum, count) = self
+            .history
+            .samples()
+            .window(CHARGING_POWER_WINDOW)
+            .filter(|sample| sample.state == State::Charging && !sample.resumed)
+            .fold((0.0, 0), |(sum, count), sample| {
+                (sum + f64::from(sample.energy_rate), count + 1)
+            });
+        if count == 0 {
+            return None;
+        }
+
+        Charging::new(
+            sum / f64::from(count),
+            f64::from(self.battery.energy_full().get::<units::energy::watt_hour>()),
+            self.battery.state_of_charge().get::<units::ratio::ratio>(),
+            self.history.sessions().records(),
+        )
+    }
+
+    /// Current discharging session or the latest finished one
+    pub fn discharging_session(&self) -> Option<&Session> {
+        let sessions = self.history.sessions();
+
+        sessions
+            .current()
+            .into_iter()
+            .chain(sessions.records().iter().rev())
+            .find(|session| session.kind == SessionKind::Discharging)
+    }
+
+    /// Power draw statistics for the `discharging_session`, based on the samples collected during this run.
+    ///
+    /// Energy rate read right after the wake-up is averaged by the firmware over the suspend as well,
+    /// so the `resumed` samples are left out, the same as from the session energy.
+    pub fn session_power(&self) -> Option<Summary> {
+        let session = self.discharging_session()?;
+        let power = self
+            .history
+            .samples()
+            .range(session.start, session.end)
+            .filter(|sample| sample.state == State::Discharging && !sample.resumed)
+            .map(|sample| f64::from(sample.energy_rate));
+
+        Summary::new(power)
+    }
+
+    /// Apply the reloaded configuration, collected values are kept
+    pub fn set_config(&mut self, config: Arc<Config>) {
+        let charts = self.cells.iter_mut().chain(vec![
+            &mut self.voltage,
+            &mut self.energy_rate,
+            &mut self.temperature,
+            &mut self.state_of_charge,
+        ]);
+        for chart in charts {
+            chart.set_config(config.clone());
+        }
+        self.config = config;
+    }
+
+    /// Mark the power profile change on the consumption chart, profile is affecting this machine only
+    pub fn annotate_profile(&mut self, profile: &str) {
+        if self.battery.host().is_none() {
+            self.energy_rate.annotate(profile, self.history.samples().sequence());
+        }
+    }
+
     pub fn voltage(&self) -> &ChartData {
         &self.voltage
     }
@@ -91,7 +418,43 @@ impl View {
         &self.temperature
     }
 
+    pub fn state_of_charge(&self) -> &ChartData {
+        &self.state_of_charge
+    }
+
+    /// Power adapter the system is running from, for the local batteries only
+    pub fn adapter(&self) -> Option<&Adapter> {
+        self.battery.system().and_then(|system| system.adapter.as_ref())
+    }
+
+    /// Adapter is connected, but it can't sustain the system draw, so the battery makes up the difference
+    pub fn adapter_insufficient(&self) -> bool {
+        self.adapter().is_some()
+            && self.battery.state() == State::Discharging
+            && self.battery.energy_rate().get::<units::power::watt>() > ADAPTER_DEFICIT_THRESHOLD
+    }
+
+    /// System sensor temperature, used if battery is not reporting its own
+    pub fn system_temperature(&self) -> Option<&Reading> {
+        self.battery.system().and_then(|system| system.temperature.as_ref())
+    }
+
+    /// Voltage charts of the separate cells, empty if backend does not know them
+    pub fn cells(&self) -> &[ChartData] {
+        self.cells.as_ref()
+    }
+
+    /// Peripherals are reporting the charge percentage only, so voltage chart would be empty
+    pub fn reports_voltage(&self) -> bool {
+        self.battery.capabilities().voltage && self.battery.voltage().get::<units::electric_potential::volt>() > 0.0
+    }
+
     pub fn config(&self) -> &Config {
         &self.config
     }
+
+    /// Settings for this device, including its own section of the configuration file
+    pub fn settings(&self) -> &DeviceSettings {
+        self.config.settings(&self.battery)
+    }
 }
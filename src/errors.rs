@@ -14,7 +14,45 @@ pub enum Error {
     Io(io::Error),
     Channel(mpsc::RecvError),
     Logger(log::SetLoggerError),
+    /// `--trace-file` writer can't be installed
+    Tracing(tracing::subscriber::SetGlobalDefaultError),
     ParseError,
+    /// Invalid configuration file, with the details
+    Config(String),
+    /// Failure limited to one device, referred by its id
+    Backend(String, Box<Error>),
+    /// Terminal can't be drawn to anymore
+    Render(io::Error),
+    /// Part of battop left out at the build time, by its cargo feature name
+    Disabled(String),
+}
+
+/// What should be done after the error, see `Error::recovery`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Recovery {
+    /// Restore the terminal and quit
+    Exit,
+    /// Keep the device last values and mark its view as stale, other views are not affected
+    Degrade,
+    /// Show the error in the status line and carry on
+    Report,
+}
+
+impl Error {
+    /// Recovery policy of the subsystem the error came from
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            Error::UserExit
+            | Error::NoBatteries
+            | Error::Channel(_)
+            | Error::Logger(_)
+            | Error::Tracing(_)
+            | Error::Render(_)
+            | Error::Disabled(_) => Recovery::Exit,
+            Error::Backend(..) => Recovery::Degrade,
+            Error::Battery(_) | Error::Io(_) | Error::ParseError | Error::Config(_) => Recovery::Report,
+        }
+    }
 }
 
 impl error::Error for Error {
@@ -24,6 +62,9 @@ impl error::Error for Error {
             Error::Io(e) => Some(e),
             Error::Channel(e) => Some(e),
             Error::Logger(e) => Some(e),
+            Error::Tracing(e) => Some(e),
+            Error::Backend(_, e) => Some(e.as_ref()),
+            Error::Render(e) => Some(e),
             _ => None,
         }
     }
@@ -35,10 +76,27 @@ impl fmt::Display for Error {
             Error::UserExit => f.write_str("User-requested exit"),
             Error::NoBatteries => f.write_str("Unable to find any batteries installed"),
             Error::ParseError => f.write_str("Unable to parse value"),
+            Error::Config(message) => write!(f, "Invalid configuration: {}", message),
             Error::Battery(e) => fmt::Display::fmt(e, f),
             Error::Io(e) => fmt::Display::fmt(e, f),
             Error::Channel(e) => fmt::Display::fmt(e, f),
             Error::Logger(e) => fmt::Display::fmt(e, f),
+            Error::Tracing(e) => fmt::Display::fmt(e, f),
+            Error::Backend(id, e) => write!(f, "Unable to refresh {}: {}", id, e),
+            Error::Render(e) => write!(f, "Unable to draw the interface: {}", e),
+            Error::Disabled(feature) => write!(f, "battop was built without the `{}` feature", feature),
+        }
+    }
+}
+
+impl From<battop_core::Error> for Error {
+    fn from(e: battop_core::Error) -> Self {
+        match e {
+            battop_core::Error::NoBatteries => Error::NoBatteries,
+            battop_core::Error::Battery(e) => Error::Battery(e),
+            battop_core::Error::Io(e) => Error::Io(e),
+            battop_core::Error::ParseError => Error::ParseError,
+            battop_core::Error::Disabled(feature) => Error::Disabled(feature),
         }
     }
 }
@@ -66,3 +124,9 @@ impl From<log::SetLoggerError> for Error {
         Error::Logger(e)
     }
 }
+
+impl From<tracing::subscriber::SetGlobalDefaultError> for Error {
+    fn from(e: tracing::subscriber::SetGlobalDefaultError) -> Self {
+        Error::Tracing(e)
+    }
+}
@@ -0,0 +1,85 @@
+//! Log records go to the stderr at the `--verbose` level; with the `--trace-file` given,
+//! all battop records are passed to the `tracing` subscriber, which fmt layer writes them there
+//! along with the spans of the enumeration, refreshes, drawing and history writes,
+//! timestamped and tagged with the thread name. Spans are written once closed, with the time they took.
+
+use std::fs::OpenOptions;
+use std::sync::Mutex;
+
+use log::{LevelFilter, Log, Metadata, Record};
+use stderrlog::StdErrLog;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+
+use crate::app::config::Config;
+use crate::Result;
+
+pub struct Logger {
+    stderr: StdErrLog,
+    /// `--trace-file` subscriber is installed
+    trace: bool,
+}
+
+impl Logger {
+    pub fn init(config: &Config) -> Result<()> {
+        let mut stderr = stderrlog::new();
+        stderr
+            .module("battop")
+            .module("battop_core")
+            .verbosity(config.verbosity())
+            .timestamp(stderrlog::Timestamp::Second);
+
+        let trace = match config.trace_file() {
+            Some(path) => {
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                let layer = tracing_subscriber::fmt::layer()
+                    .with_writer(Mutex::new(file))
+                    .with_ansi(false)
+                    .with_thread_names(true)
+                    .with_span_events(FmtSpan::CLOSE);
+                tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))?;
+                true
+            }
+            None => false,
+        };
+        let level = if trace {
+            LevelFilter::Trace
+        } else {
+            // Same mapping as the `StdErrLog` has
+            match config.verbosity() {
+                0 => LevelFilter::Error,
+                1 => LevelFilter::Warn,
+                2 => LevelFilter::Info,
+                3 => LevelFilter::Debug,
+                _ => LevelFilter::Trace,
+            }
+        };
+
+        log::set_boxed_logger(Box::new(Logger {
+            stderr,
+            trace,
+        }))?;
+        log::set_max_level(level);
+
+        Ok(())
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // `battop` prefix covers the `battop_core` records as well
+        (self.trace && metadata.target().starts_with("battop")) || self.stderr.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.stderr.log(record);
+
+        if self.trace && record.target().starts_with("battop") {
+            let _ = tracing_log::format_trace(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+    }
+}
@@ -1,26 +1,45 @@
-#[macro_use]
-extern crate structopt;
+// Headless builds are leaving most of the configuration unread
+#![cfg_attr(not(feature = "ui"), allow(dead_code))]
+
 #[macro_use]
 extern crate log;
 
+use std::process;
 use std::sync::Arc;
 
-use structopt::StructOpt;
-
 mod app;
 mod errors;
+mod logger;
 
-pub use self::errors::{Error, Result};
+pub use self::errors::{Error, Recovery, Result};
 
-fn main() -> Result<()> {
-    let config = Arc::new(app::config::Config::from_args());
-    stderrlog::new()
-        .module(module_path!())
-        .verbosity(config.verbosity())
-        .timestamp(stderrlog::Timestamp::Second)
-        .init()?;
+fn main() {
+    // Returned error would be printed with its `Debug` form, which hides the configuration diagnostics layout
+    if let Err(e) = start() {
+        eprintln!("battop: {}", e);
+        process::exit(1);
+    }
+}
+
+fn start() -> Result<()> {
+    let config = Arc::new(app::config::Config::load()?);
+    logger::Logger::init(&config)?;
 
     trace!("Starting with {:?}", &config);
+    if let Some(path) = config.path() {
+        info!("Using configuration file {}", path.display());
+    }
+    match config.command() {
+        Some(app::Command::Report) => return app::print_report(&config),
+        Some(app::Command::Daemon) => return app::run_daemon(&config),
+        None => {}
+    }
+
+    run(config)
+}
+
+#[cfg(feature = "ui")]
+fn run(config: Arc<app::Config>) -> Result<()> {
     let mut app = app::init(config)?;
 
     match app.run() {
@@ -35,3 +54,8 @@ fn main() -> Result<()> {
         }
     }
 }
+
+#[cfg(not(feature = "ui"))]
+fn run(_config: Arc<app::Config>) -> Result<()> {
+    Err(Error::Disabled("ui".to_string()))
+}